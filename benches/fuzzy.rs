@@ -0,0 +1,75 @@
+//! Fuzzy matcher timings: `cargo bench --bench fuzzy`
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use rust_text_editor::fuzzy::{fuzzy_match, fuzzy_rank};
+
+/// Paths shaped like a mid-sized project
+fn sample_paths() -> Vec<String> {
+    let dirs = [
+        "src",
+        "src/editor",
+        "src/lsp/protocol",
+        "tests/fixtures",
+        "docs/guide",
+    ];
+    let names = [
+        "main",
+        "buffer_view",
+        "renderTarget",
+        "key_map",
+        "undoHistory",
+        "lib",
+    ];
+    let extensions = ["rs", "md", "toml", "json"];
+
+    let mut paths = vec![];
+    for i in 0..500 {
+        for dir in dirs {
+            let name = names[i % names.len()];
+            let extension = extensions[i % extensions.len()];
+            paths.push(format!("{}/{}_{}.{}", dir, name, i, extension));
+        }
+    }
+    paths
+}
+
+fn bench(name: &str, iterations: u32, mut f: impl FnMut()) {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let per_iteration = start.elapsed() / iterations;
+    println!("{:<32} {:>12?} per iteration", name, per_iteration);
+}
+
+fn main() {
+    let paths = sample_paths();
+    let long_line = "let value = compute_something(argument_one, argument_two); ".repeat(40);
+
+    bench("match short pattern", 100_000, || {
+        black_box(fuzzy_match(
+            black_box("bv"),
+            black_box("src/editor/buffer_view_12.rs"),
+        ));
+    });
+    bench("match long pattern", 100_000, || {
+        black_box(fuzzy_match(
+            black_box("edtrbufvw12rs"),
+            black_box("src/editor/buffer_view_12.rs"),
+        ));
+    });
+    bench("reject", 100_000, || {
+        black_box(fuzzy_match(
+            black_box("xyz"),
+            black_box("src/editor/buffer_view_12.rs"),
+        ));
+    });
+    bench("match long line", 10_000, || {
+        black_box(fuzzy_match(black_box("cmptargtwo"), black_box(&long_line)));
+    });
+    bench(&format!("rank {} paths", paths.len()), 100, || {
+        black_box(fuzzy_rank(black_box("edbufrs"), &paths));
+    });
+}
@@ -0,0 +1,89 @@
+//! Renderer timings over big synthetic buffers: `cargo bench --bench render
+//! --features bench`
+
+use std::hint::black_box;
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rust_text_editor::Editor;
+
+const SCREEN_ROWS: usize = 50;
+const SCREEN_COLUMNS: usize = 160;
+
+/// C source with tabs, comments and strings, `lines` lines long
+fn code(lines: usize) -> String {
+    let mut text = String::new();
+    for i in 0..lines {
+        match i % 8 {
+            0 => text.push_str(&format!("/* block {} of the\n", i)),
+            1 => text.push_str(" * synthetic source */\n"),
+            2 => text.push_str(&format!("static int value_{}(int argument)\n", i)),
+            3 => text.push_str("{\n"),
+            4 => text.push_str("\tchar *name = \"a string\twith a tab\";\n"),
+            5 => text.push_str("\t\treturn argument * 2; // doubled\n"),
+            6 => text.push_str("}\n"),
+            _ => text.push('\n'),
+        }
+    }
+    text
+}
+
+/// Lines mixing wide chars, control chars and tabs
+fn mixed(lines: usize) -> String {
+    "漢字とかな\tmixed\x07 with control\x1b chars and ünïcödé\n".repeat(lines)
+}
+
+/// Lines of about `width` chars of code
+fn long_lines(lines: usize, width: usize) -> String {
+    let line = "x = call(argument, \"string\"); /* comment */\t";
+    let mut text = line.repeat(width / line.len());
+    text.push('\n');
+    text.repeat(lines)
+}
+
+/// Writes `text` to a temporary file named `name` and opens it
+fn open(name: &str, text: &str) -> (Editor, PathBuf) {
+    let path = std::env::temp_dir().join(format!("kilo-bench-{}-{}", std::process::id(), name));
+    std::fs::write(&path, text).expect("writing the benchmark file");
+    let mut editor = Editor::new_headless(SCREEN_ROWS, SCREEN_COLUMNS);
+    editor.editor_switch_file(&path.to_string_lossy());
+    (editor, path)
+}
+
+fn render(c: &mut Criterion) {
+    // Group name, file and contents
+    let buffers = [
+        ("100k lines of C", "code.c", code(100_000)),
+        ("100k lines of wide chars", "mixed.txt", mixed(100_000)),
+        ("2k lines of 4k chars", "long.c", long_lines(2_000, 4_000)),
+    ];
+
+    for (name, file, text) in buffers {
+        let mut group = c.benchmark_group(name);
+        // Rendering every row of a big buffer takes a while
+        group.sample_size(10);
+        let (mut editor, path) = open(file, &text);
+        group.bench_function("render, all rows", |b| {
+            b.iter(|| black_box(editor.bench_render_rows()))
+        });
+
+        // Halfway down, with the syntax state above worked out
+        editor.editor_goto(text.lines().count() / 2, 0);
+        editor.bench_settle_syntax();
+        group.bench_function("draw_rows", |b| {
+            b.iter(|| black_box(editor.bench_draw_rows()))
+        });
+        group.bench_function("full frame", |b| {
+            b.iter(|| {
+                let frame = editor.editor_frame();
+                black_box(editor.editor_frame_output(&frame))
+            })
+        });
+        group.finish();
+
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+criterion_group!(benches, render);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+/* C API of the editor core, see src/ffi.rs. Build the library with
+ *   cargo rustc --lib --release --features ffi --crate-type cdylib
+ */
+#ifndef KILO_H
+#define KILO_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/* Key codes for kilo_editor_keys, other keys are sent as their byte value
+ * (e.g. 0x13 for Ctrl-S, 13 for Enter) or Unicode code point */
+#define KILO_KEY_ESCAPE 27
+#define KILO_KEY_BACKSPACE 127
+#define KILO_KEY_ARROW_LEFT 1000
+#define KILO_KEY_ARROW_RIGHT 1001
+#define KILO_KEY_ARROW_UP 1002
+#define KILO_KEY_ARROW_DOWN 1003
+#define KILO_KEY_PAGE_UP 1004
+#define KILO_KEY_PAGE_DOWN 1005
+#define KILO_KEY_HOME 1006
+#define KILO_KEY_END 1007
+#define KILO_KEY_DELETE 1008
+#define KILO_KEY_ALT_ARROW_UP 1009
+#define KILO_KEY_ALT_ARROW_DOWN 1010
+#define KILO_KEY_ALT_N 1011
+#define KILO_KEY_ALT_SHIFT_N 1012
+
+typedef struct KiloEditor KiloEditor;
+
+KiloEditor *kilo_editor_new(uint32_t rows, uint32_t columns);
+void kilo_editor_free(KiloEditor *editor);
+
+bool kilo_editor_open(KiloEditor *editor, const char *path);
+bool kilo_editor_save(KiloEditor *editor);
+void kilo_editor_insert_text(KiloEditor *editor, const char *text);
+void kilo_editor_keys(KiloEditor *editor, const uint32_t *keys, size_t count);
+bool kilo_editor_should_quit(KiloEditor *editor);
+
+void kilo_editor_resize(KiloEditor *editor, uint32_t rows, uint32_t columns);
+void kilo_editor_render(KiloEditor *editor, uint32_t *cells, uint32_t rows, uint32_t columns,
+                        uint32_t *cursor_row, uint32_t *cursor_column);
+
+char *kilo_editor_contents(KiloEditor *editor);
+void kilo_string_free(char *s);
+
+#endif
@@ -0,0 +1,132 @@
+//! Abbreviations, defined with `abbrev TRIGGER EXPANSION` (usually in
+//! ~/.kilorc): typing a non-word char or Enter right after a trigger replaces
+//! it with the expansion, so `abbrev teh the` fixes a typo as it is made and
+//! `abbrev #!py #!/usr/bin/env python3` saves typing. Backspace right after an
+//! expansion brings back the trigger.
+
+use crate::completion::is_word_char;
+use crate::hooks::InputHook;
+use crate::overlay::OverlayLine;
+use crate::*;
+
+/// Whether `trigger` ends `before_cursor` as a whole word: after whitespace
+/// or the start of the line, or after punctuation for one starting like a word
+fn ends_with_trigger(before_cursor: &str, trigger: &str) -> bool {
+    let before = match before_cursor.strip_suffix(trigger) {
+        Some(before) => before,
+        None => return false,
+    };
+    match before.chars().next_back() {
+        None => true,
+        Some(c) if c.is_whitespace() => true,
+        Some(c) => trigger.starts_with(is_word_char) && !is_word_char(c),
+    }
+}
+
+#[derive(Default)]
+pub struct Abbreviations {
+    /// Row and column right after the last expansion and the char typed
+    /// there, the length of the expansion and the trigger it replaced
+    last: Option<(usize, usize, usize, String)>,
+}
+
+impl InputHook for Abbreviations {
+    fn on_key(&mut self, editor: &mut Editor, key: Key) -> bool {
+        let last = self.last.take();
+        if editor.abbreviations.is_empty() || editor.buffer.cursor_y >= editor.get_num_rows() {
+            return false;
+        }
+
+        if key == Key::Backspace {
+            return match last {
+                Some((row, column, len, trigger))
+                    if (row, column) == (editor.buffer.cursor_y, editor.buffer.cursor_x) =>
+                {
+                    // The typed char stays, only the expansion goes
+                    let typed = editor.buffer.rows[row].chars[..column]
+                        .chars()
+                        .next_back()
+                        .map_or(0, char::len_utf8);
+                    editor.buffer.cursor_x -= typed;
+                    editor.editor_replace_before_cursor(len, &trigger);
+                    editor.buffer.cursor_x += typed;
+                    true
+                }
+                _ => false,
+            };
+        }
+
+        let typed = match key {
+            Key::Char(c) if !is_word_char(c) => Some(c),
+            Key::Enter => None,
+            _ => return false,
+        };
+        let row = &editor.buffer.rows[editor.buffer.cursor_y].chars;
+        let before_cursor = &row[..floor_char_boundary(row, editor.buffer.cursor_x)];
+        // The longest trigger wins, as `#!py3` over `py3`
+        let (trigger, expansion) = match editor
+            .abbreviations
+            .iter()
+            .filter(|(trigger, _)| ends_with_trigger(before_cursor, trigger))
+            .max_by_key(|(trigger, _)| trigger.len())
+        {
+            Some((trigger, expansion)) => (trigger.clone(), expansion.clone()),
+            None => return false,
+        };
+
+        editor.editor_replace_before_cursor(trigger.len(), &expansion);
+        // The key itself is handled as usual
+        if let Some(c) = typed {
+            self.last = Some((
+                editor.buffer.cursor_y,
+                editor.buffer.cursor_x + c.len_utf8(),
+                expansion.len(),
+                trigger,
+            ));
+        }
+        false
+    }
+}
+
+impl Editor {
+    /// `abbrev TRIGGER EXPANSION` defines an abbreviation, `abbrev TRIGGER`
+    /// shows one and `abbrev` lists them
+    pub fn editor_abbreviate(&mut self, args: &str) {
+        match args.split_once(char::is_whitespace) {
+            Some((trigger, expansion)) => {
+                self.abbreviations
+                    .insert(trigger.to_string(), expansion.trim_start().to_string());
+            }
+            None if args.is_empty() => {
+                let mut triggers: Vec<&String> = self.abbreviations.keys().collect();
+                triggers.sort();
+                let lines: Vec<OverlayLine> = triggers
+                    .iter()
+                    .map(|trigger| {
+                        let expansion = &self.abbreviations[*trigger];
+                        OverlayLine::new(format!("{} -> {}", trigger, expansion))
+                    })
+                    .collect();
+                if lines.is_empty() {
+                    self.editor_set_status_message("No abbreviations");
+                } else {
+                    self.editor_show_overlay("Abbreviations", &lines);
+                }
+            }
+            None => match self.abbreviations.get(args) {
+                Some(expansion) => {
+                    let message = format!("{} -> {}", args, expansion);
+                    self.editor_set_status_message(&message);
+                }
+                None => self.editor_set_status_message(&format!("No abbreviation {}", args)),
+            },
+        }
+    }
+
+    /// `unabbrev TRIGGER` removes an abbreviation
+    pub fn editor_unabbreviate(&mut self, trigger: &str) {
+        if self.abbreviations.remove(trigger).is_none() {
+            self.editor_set_status_message(&format!("No abbreviation {}", trigger));
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! Entry points for the render benchmarks in benches/render.rs, for parts of
+//! the renderer that aren't public. Built with the `bench` feature.
+
+use std::thread;
+use std::time::Duration;
+
+use crate::*;
+
+impl Editor {
+    /// Renders every row whole, returns the render columns
+    pub fn bench_render_rows(&self) -> usize {
+        self.buffer
+            .rows
+            .iter()
+            .map(|row| {
+                row.render(self.options.tab_stop, 0, usize::MAX)
+                    .chars()
+                    .count()
+            })
+            .sum()
+    }
+
+    /// Draws the text rows of the screen, returns how many were drawn
+    pub fn bench_draw_rows(&mut self) -> usize {
+        self.editor_scroll();
+        let mut lines = Vec::with_capacity(self.screen_num_rows);
+        self.editor_draw_rows(&mut lines);
+        lines.len()
+    }
+
+    /// Works out the syntax state up to the rows on screen, waiting for the
+    /// worker, so the frames timed after it are fully highlighted
+    pub fn bench_settle_syntax(&mut self) {
+        self.editor_update_syntax_state(self.buffer.row_offset + self.screen_num_rows);
+        while self.editor_syntax_working() {
+            thread::sleep(Duration::from_millis(1));
+            self.editor_syntax_poll();
+        }
+    }
+}
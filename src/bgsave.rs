@@ -0,0 +1,228 @@
+//! Saving big buffers on a worker thread. A buffer over `asyncsave`
+//! megabytes (16 by default, 0 never) is normalized, encoded and written in
+//! the background with its progress in the status bar, and editing goes on
+//! meanwhile. Edits made before the save finishes stay unsaved, so none are
+//! lost. Switching buffers, opening another file or quitting waits for the
+//! save.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use crate::checksum::verify_written;
+use crate::normalize::{save_text, Normalization, UneditedChanges};
+use crate::*;
+
+/// What a save on a worker thread starts from
+pub enum SaveSource {
+    /// The bytes of the file, from hex mode
+    Bytes(Vec<u8>),
+    /// The rows, made into the file's bytes on the worker
+    Rows {
+        rows: Vec<String>,
+        normalization: Normalization,
+        how: SaveText,
+        format: FileFormat,
+    },
+}
+
+/// What the worker sends back
+enum SaveResult {
+    /// The bytes it wrote, how the write went and whether they read back the
+    /// same
+    Written(Vec<u8>, io::Result<()>, Option<String>),
+    /// Normalizing would change unedited lines, nothing was written
+    Unedited(UneditedChanges),
+    /// The rows can't be encoded, nothing was written
+    Unencodable(String),
+}
+
+/// What a worker that went away without a word leaves
+fn interrupted() -> SaveResult {
+    SaveResult::Written(
+        vec![],
+        Err(io::Error::other("the save was interrupted")),
+        None,
+    )
+}
+
+/// A save running in the background
+pub struct Saving {
+    filename: String,
+    /// `dirty` when the rows were taken
+    dirty: usize,
+    /// Bytes to write, 0 while they are being made
+    total: Arc<AtomicUsize>,
+    written: Arc<AtomicUsize>,
+    result: Receiver<SaveResult>,
+}
+
+impl Editor {
+    /// Whether a save of the buffer goes to a worker thread
+    pub fn editor_saves_in_background(&self) -> bool {
+        let len = self
+            .editor_hex_len()
+            .unwrap_or_else(|| self.buffer.rows.iter().map(|row| row.len() + 1).sum());
+        !self.headless
+            && self.options.async_save > 0
+            && len > self.options.async_save.saturating_mul(MEGABYTE)
+    }
+
+    /// What a background save of the buffer, made into text `how`, starts from
+    pub fn editor_save_source(&self, how: SaveText) -> SaveSource {
+        match self.editor_hex_bytes() {
+            Some(bytes) => SaveSource::Bytes(bytes),
+            None => SaveSource::Rows {
+                rows: self
+                    .buffer
+                    .rows
+                    .iter()
+                    .map(|row| row.chars.clone())
+                    .collect(),
+                normalization: Normalization::new(&self.options),
+                how,
+                format: self.buffer.file_format,
+            },
+        }
+    }
+
+    /// Starts making the bytes of `source` and writing them to `filename` on
+    /// a worker thread
+    pub(crate) fn editor_start_save(
+        &mut self,
+        filename: &str,
+        source: SaveSource,
+    ) -> Result<(), SaveError> {
+        let write = self
+            .editor_file_write(filename)
+            .map_err(|error| SaveError::Failed(describe_io_error(&error)))?;
+
+        let total = Arc::new(AtomicUsize::new(0));
+        let written = Arc::new(AtomicUsize::new(0));
+        let (sender, result) = mpsc::channel();
+        let (worker_total, progress) = (Arc::clone(&total), Arc::clone(&written));
+        let path = filename.to_string();
+        thread::spawn(move || {
+            let bytes = match source {
+                SaveSource::Bytes(bytes) => bytes,
+                SaveSource::Rows {
+                    rows,
+                    normalization,
+                    how,
+                    format,
+                } => {
+                    let text = match save_text(rows.join("\n"), &path, &normalization, how) {
+                        Ok(text) => text,
+                        Err(changes) => {
+                            let _ = sender.send(SaveResult::Unedited(changes));
+                            return;
+                        }
+                    };
+                    match format.encode(&text) {
+                        Ok(bytes) => bytes,
+                        Err(error) => {
+                            let _ = sender.send(SaveResult::Unencodable(error));
+                            return;
+                        }
+                    }
+                }
+            };
+            worker_total.store(bytes.len(), Ordering::Relaxed);
+            let outcome = write.write(
+                &bytes,
+                &|done| progress.store(done, Ordering::Relaxed),
+                &mut |_, _| progress.store(0, Ordering::Relaxed),
+            );
+            let verified = match outcome {
+                Ok(()) => verify_written(&path, &bytes),
+                Err(_) => None,
+            };
+            let _ = sender.send(SaveResult::Written(bytes, outcome, verified));
+        });
+
+        self.saving = Some(Saving {
+            filename: filename.to_string(),
+            dirty: self.buffer.dirty,
+            total,
+            written,
+            result,
+        });
+        self.editor_set_status_message(&format!("Saving {} in the background", filename));
+        Ok(())
+    }
+
+    /// Finishes the save if the worker is done. True if it was.
+    pub fn editor_save_poll(&mut self) -> bool {
+        let result = match self.saving.as_ref().map(|saving| saving.result.try_recv()) {
+            Some(Ok(result)) => result,
+            Some(Err(TryRecvError::Disconnected)) => interrupted(),
+            // Redrawn anyway so the progress moves
+            Some(Err(TryRecvError::Empty)) => return true,
+            None => return false,
+        };
+        self.editor_save_done(result);
+        true
+    }
+
+    /// Waits for a save running in the background to finish, and for the one
+    /// it is started again as if the user was asked about normalizing
+    pub fn editor_finish_save(&mut self) {
+        while let Some(saving) = &self.saving {
+            let result = saving.result.recv().unwrap_or_else(|_| interrupted());
+            self.editor_save_done(result);
+        }
+    }
+
+    fn editor_save_done(&mut self, result: SaveResult) {
+        if let Err(error) = self.editor_finish_saving(result) {
+            self.editor_set_status_message(&error.to_string());
+        }
+    }
+
+    fn editor_finish_saving(&mut self, result: SaveResult) -> Result<(), SaveError> {
+        let saving = match self.saving.take() {
+            Some(saving) => saving,
+            None => return Ok(()),
+        };
+        let (bytes, outcome, verified) = match result {
+            SaveResult::Written(bytes, outcome, verified) => (bytes, outcome, verified),
+            SaveResult::Unedited(changes) => {
+                // Saved again as answered, from the rows as they are now
+                let how = self
+                    .editor_ask_normalize(&changes)
+                    .ok_or(SaveError::Refused("Save aborted"))?;
+                let source = self.editor_save_source(how);
+                return self.editor_start_save(&saving.filename, source);
+            }
+            SaveResult::Unencodable(error) => return Err(SaveError::Failed(error)),
+        };
+        let edits_since = self.buffer.dirty.saturating_sub(saving.dirty);
+        let elevated = match outcome {
+            Ok(()) => false,
+            Err(error) if error.kind() == ErrorKind::PermissionDenied => {
+                self.editor_save_elevated(&saving.filename, &bytes, &error)?;
+                true
+            }
+            Err(error) => return Err(SaveError::Failed(describe_io_error(&error))),
+        };
+        if let Some(error) = verified {
+            return Err(SaveError::Failed(error));
+        }
+        self.editor_saved(&bytes, elevated, edits_since);
+        Ok(())
+    }
+
+    /// Status bar tag while a save is running
+    pub fn editor_save_status(&self) -> Option<String> {
+        self.saving
+            .as_ref()
+            .map(|saving| match saving.total.load(Ordering::Relaxed) {
+                0 => "[saving]".to_string(),
+                total => {
+                    let written = saving.written.load(Ordering::Relaxed);
+                    format!("[saving {}%]", written * 100 / total)
+                }
+            })
+    }
+}
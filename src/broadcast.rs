@@ -0,0 +1,373 @@
+//! Read-only screen broadcast: with `broadcast unix PATH` or `broadcast tcp
+//! [IP:]PORT` every frame is also sent to viewers attached to the socket.
+//! TCP listens on 127.0.0.1 unless given an IP. A viewer first sends the
+//! token shown when the broadcast starts, then watches in their own
+//! terminal, e.g. with `{ echo TOKEN; cat; } | nc 127.0.0.1 PORT`. Nothing
+//! else viewers send is read.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::*;
+
+/// How often the listener checks for new viewers
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(200);
+/// Frames queued per viewer, a viewer further behind skips frames
+const VIEWER_QUEUE: usize = 4;
+/// How long a viewer has to send the token
+const TOKEN_TIMEOUT: Duration = Duration::from_secs(10);
+/// A viewer not taking a frame for this long is disconnected
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+impl Listener {
+    fn accept(&self) -> io::Result<Connection> {
+        let connection = match self {
+            Listener::Tcp(listener) => Connection::Tcp(listener.accept()?.0),
+            Listener::Unix(listener) => Connection::Unix(listener.accept()?.0),
+        };
+        connection.prepare()?;
+        Ok(connection)
+    }
+}
+
+/// A viewer's socket
+enum Connection {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl Connection {
+    fn prepare(&self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => {
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(TOKEN_TIMEOUT))?;
+                stream.set_write_timeout(Some(WRITE_TIMEOUT))
+            }
+            Connection::Unix(stream) => {
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(TOKEN_TIMEOUT))?;
+                stream.set_write_timeout(Some(WRITE_TIMEOUT))
+            }
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Connection> {
+        Ok(match self {
+            Connection::Tcp(stream) => Connection::Tcp(stream.try_clone()?),
+            Connection::Unix(stream) => Connection::Unix(stream.try_clone()?),
+        })
+    }
+
+    /// Ends a read or write the viewer's thread is blocked in
+    fn shutdown(&self) {
+        let _ = match self {
+            Connection::Tcp(stream) => stream.shutdown(Shutdown::Both),
+            Connection::Unix(stream) => stream.shutdown(Shutdown::Both),
+        };
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.read(buf),
+            Connection::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.write(buf),
+            Connection::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.flush(),
+            Connection::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Viewers {
+    /// Frame queues of the viewers that sent the token
+    senders: Vec<SyncSender<Arc<String>>>,
+    /// Every accepted connection with its thread, shut down and joined when
+    /// the broadcast stops
+    connections: Vec<(Connection, JoinHandle<()>)>,
+    /// Latest frame, sent to new viewers right away
+    last_frame: Arc<String>,
+    token: String,
+    /// Set once stopping, so a viewer accepted meanwhile is turned away
+    stopped: bool,
+}
+
+#[derive(Default)]
+pub struct Broadcast {
+    /// Shared with the listener thread, None while not broadcasting
+    viewers: Option<Arc<Mutex<Viewers>>>,
+    address: String,
+    /// Unix socket to remove when the broadcast stops
+    socket_path: Option<PathBuf>,
+}
+
+/// Accepts viewers until the broadcast is stopped
+fn run_listener(listener: Listener, viewers: Weak<Mutex<Viewers>>) {
+    loop {
+        let viewers = match viewers.upgrade() {
+            Some(viewers) => viewers,
+            None => return,
+        };
+        match listener.accept() {
+            Ok(connection) => {
+                let Ok(shared) = connection.try_clone() else {
+                    continue;
+                };
+                if let Ok(mut locked) = viewers.lock() {
+                    if locked.stopped {
+                        return;
+                    }
+                    locked
+                        .connections
+                        .retain(|(_, thread)| !thread.is_finished());
+                    let weak = Arc::downgrade(&viewers);
+                    let thread = thread::spawn(move || run_viewer(connection, weak));
+                    locked.connections.push((shared, thread));
+                }
+            }
+            Err(_) => {
+                drop(viewers);
+                thread::sleep(ACCEPT_INTERVAL);
+            }
+        }
+    }
+}
+
+/// Waits for the token, then writes frames to one viewer until it
+/// disconnects, falls behind by more than `WRITE_TIMEOUT` or the broadcast
+/// stops
+fn run_viewer(mut connection: Connection, viewers: Weak<Mutex<Viewers>>) {
+    let Ok(reader) = connection.try_clone() else {
+        return;
+    };
+    let mut line = String::new();
+    if BufReader::new(reader).read_line(&mut line).is_err() {
+        return;
+    }
+    let frames = {
+        let Some(viewers) = viewers.upgrade() else {
+            return;
+        };
+        let Ok(mut viewers) = viewers.lock() else {
+            return;
+        };
+        if line.trim() != viewers.token {
+            let _ = connection.write_all(b"Wrong token\r\n");
+            return;
+        }
+        let (sender, receiver) = sync_channel(VIEWER_QUEUE);
+        let _ = sender.try_send(viewers.last_frame.clone());
+        viewers.senders.push(sender);
+        receiver
+    };
+    write_frames(connection, frames);
+}
+
+fn write_frames(mut connection: Connection, frames: Receiver<Arc<String>>) {
+    // The viewer's terminal may show anything before the first frame
+    if connection.write_all(b"\x1b[2J").is_err() {
+        return;
+    }
+    for frame in frames {
+        if connection.write_all(frame.as_bytes()).is_err() || connection.flush().is_err() {
+            return;
+        }
+    }
+}
+
+/// Random hex token viewers have to send
+fn new_token() -> io::Result<String> {
+    let mut bytes = [0; 8];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// A TCP address, on the loopback interface when only a port is given so
+/// listening on other interfaces takes naming one
+fn tcp_address(address: &str) -> io::Result<SocketAddr> {
+    let port = address.strip_prefix(':').unwrap_or(address);
+    if let Ok(port) = port.parse::<u16>() {
+        return Ok(SocketAddr::from(([127, 0, 0, 1], port)));
+    }
+    address
+        .parse()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "expected PORT or IP:PORT"))
+}
+
+fn bind(kind: &str, address: &str) -> io::Result<(Listener, String, Option<PathBuf>)> {
+    match kind {
+        "tcp" => {
+            let address = tcp_address(address)?;
+            let listener = TcpListener::bind(address)?;
+            listener.set_nonblocking(true)?;
+            Ok((Listener::Tcp(listener), address.to_string(), None))
+        }
+        _ => {
+            // A socket left behind by a session that didn't stop cleanly
+            let path = PathBuf::from(address);
+            if std::fs::metadata(&path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+                std::fs::remove_file(&path)?;
+            }
+            let listener = UnixListener::bind(&path)?;
+            listener.set_nonblocking(true)?;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+            Ok((Listener::Unix(listener), address.to_string(), Some(path)))
+        }
+    }
+}
+
+impl Editor {
+    /// `broadcast unix PATH`, `broadcast tcp [IP:]PORT` or `broadcast off`;
+    /// without arguments shows where the screen is broadcast and the token
+    pub fn editor_broadcast_command(&mut self, args: &str) {
+        let (kind, address) = args.split_once(' ').unwrap_or((args, ""));
+        let address = address.trim();
+        match (kind, address) {
+            ("", _) => {
+                let token = self
+                    .broadcast
+                    .viewers
+                    .as_ref()
+                    .and_then(|viewers| Some(viewers.lock().ok()?.token.clone()));
+                let message = match (self.editor_broadcast_status(), token) {
+                    (Some(status), Some(token)) => format!(
+                        "Broadcasting on {} with token {} {}",
+                        self.broadcast.address, token, status
+                    ),
+                    _ => "Not broadcasting (broadcast unix PATH | tcp [IP:]PORT)".to_string(),
+                };
+                self.editor_set_status_message(&message);
+            }
+            ("off", _) => {
+                self.editor_broadcast_stop();
+                self.editor_set_status_message("Broadcast stopped");
+            }
+            ("unix" | "tcp", address) if !address.is_empty() => {
+                self.editor_broadcast_stop();
+                match new_token().and_then(|token| Ok((token, bind(kind, address)?))) {
+                    Ok((token, (listener, address, socket_path))) => {
+                        let viewers = Arc::new(Mutex::new(Viewers {
+                            token: token.clone(),
+                            ..Viewers::default()
+                        }));
+                        let weak = Arc::downgrade(&viewers);
+                        thread::spawn(move || run_listener(listener, weak));
+                        self.editor_set_status_message(&format!(
+                            "Broadcasting read-only on {}, viewers send the token {} first",
+                            address, token
+                        ));
+                        self.broadcast = Broadcast {
+                            viewers: Some(viewers),
+                            address,
+                            socket_path,
+                        };
+                    }
+                    Err(error) => self.editor_set_error_message(&format!(
+                        "Could not listen on {}: {}",
+                        address, error
+                    )),
+                }
+            }
+            _ => {
+                self.editor_set_error_message("Usage: broadcast [unix PATH | tcp [IP:]PORT | off]")
+            }
+        }
+    }
+
+    /// Disconnects all viewers, waits for their threads and stops listening
+    pub fn editor_broadcast_stop(&mut self) {
+        if let Some(path) = self.broadcast.socket_path.take() {
+            let _ = std::fs::remove_file(path);
+        }
+        // Dropping the last handle ends the listener thread
+        let viewers = match std::mem::take(&mut self.broadcast).viewers {
+            Some(viewers) => viewers,
+            None => return,
+        };
+        let connections = match viewers.lock() {
+            Ok(mut viewers) => {
+                viewers.stopped = true;
+                viewers.senders.clear();
+                std::mem::take(&mut viewers.connections)
+            }
+            Err(_) => return,
+        };
+        for (connection, thread) in connections {
+            connection.shutdown();
+            let _ = thread.join();
+        }
+    }
+
+    /// Sends `frame` to the attached viewers, always in full so it doesn't
+    /// depend on the frames a viewer skipped
+    pub fn editor_broadcast_frame(&mut self, frame: &Frame) {
+        let viewers = match &self.broadcast.viewers {
+            Some(viewers) => viewers,
+            None => return,
+        };
+        let output = Arc::new(frame_to_ansi(frame, self.editor_color_depth()));
+        if let Ok(mut viewers) = viewers.lock() {
+            viewers.last_frame = output.clone();
+            viewers.senders.retain(|sender| {
+                !matches!(
+                    sender.try_send(output.clone()),
+                    Err(TrySendError::Disconnected(_))
+                )
+            });
+        }
+    }
+
+    /// Status bar tag while broadcasting
+    pub fn editor_broadcast_status(&self) -> Option<String> {
+        let viewers = self.broadcast.viewers.as_ref()?.lock().ok()?;
+        Some(match viewers.senders.len() {
+            1 => "[broadcast: 1 viewer]".to_string(),
+            count => format!("[broadcast: {} viewers]", count),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listens_on_loopback_for_a_port() {
+        let loopback = SocketAddr::from(([127, 0, 0, 1], 7000));
+        assert_eq!(tcp_address("7000").unwrap(), loopback);
+        assert_eq!(tcp_address(":7000").unwrap(), loopback);
+        assert_eq!(
+            tcp_address("0.0.0.0:7000").unwrap(),
+            SocketAddr::from(([0, 0, 0, 0], 7000))
+        );
+        assert!(tcp_address("somewhere").is_err());
+    }
+}
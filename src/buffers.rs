@@ -0,0 +1,278 @@
+//! Several files open at once, one buffer each, like vi's argument list:
+//! `kilo a.rs b.rs` opens both, `next` and `prev` cycle through them and
+//! `args` lists them. The shown buffer is the editor's `buffer`, the others
+//! wait in `buffers`. `wa` (Ctrl-Alt-S) saves all of them, and
+//! quitting with unsaved changes in any asks whether to save them all,
+//! discard them or stay.
+
+use std::mem;
+use std::path::Path;
+
+use crate::overlay::OverlayLine;
+use crate::*;
+
+impl Editor {
+    /// Exchanges the shown buffer with the one in slot `index`
+    fn editor_swap_buffer(&mut self, index: usize) {
+        mem::swap(&mut self.buffer, &mut self.buffers[index]);
+    }
+
+    /// Shows buffer `index`, keeping the current one as it is
+    pub fn editor_select_buffer(&mut self, index: usize) {
+        if index == self.current_buffer || index >= self.buffers.len() {
+            return;
+        }
+        self.editor_finish_save();
+        let lsp_started = self.buffer.lsp.is_some();
+        // The slot of the shown buffer is empty until it's put back
+        self.editor_swap_buffer(self.current_buffer);
+        self.current_buffer = index;
+        self.editor_swap_buffer(index);
+
+        self.buffer.mark = None;
+        self.editor_spell_read_modeline();
+        // Each buffer has a server of its own once one was started
+        if lsp_started && self.buffer.lsp.is_none() && self.buffer.filename.is_some() {
+            self.editor_lsp_start();
+        }
+        self.editor_invalidate_screen();
+    }
+
+    /// Buffer `index`, shown or not
+    fn editor_buffer(&self, index: usize) -> &Buffer {
+        if index == self.current_buffer {
+            &self.buffer
+        } else {
+            &self.buffers[index]
+        }
+    }
+
+    fn editor_buffer_mut(&mut self, index: usize) -> &mut Buffer {
+        if index == self.current_buffer {
+            &mut self.buffer
+        } else {
+            &mut self.buffers[index]
+        }
+    }
+
+    /// Rows of buffer `index`, shown or not
+    pub(crate) fn editor_buffer_rows_mut(&mut self, index: usize) -> &mut Vec<EditorRow> {
+        &mut self.editor_buffer_mut(index).rows
+    }
+
+    /// Every buffer, the shown one included
+    pub(crate) fn editor_all_buffers_mut(&mut self) -> impl Iterator<Item = &mut Buffer> {
+        let current = self.current_buffer;
+        self.buffers
+            .iter_mut()
+            .enumerate()
+            .filter(move |&(index, _)| index != current)
+            .map(|(_, buffer)| buffer)
+            .chain(std::iter::once(&mut self.buffer))
+    }
+
+    /// Opens `filename` in a new buffer after the others and shows it
+    pub fn editor_add_buffer(&mut self, filename: &str) {
+        self.buffers.push(Buffer::default());
+        self.editor_select_buffer(self.buffers.len() - 1);
+        self.editor_open(filename);
+    }
+
+    /// Index of the buffer showing `filename`
+    pub fn editor_find_buffer(&self, filename: &str) -> Option<usize> {
+        let wanted = Path::new(filename).canonicalize().ok();
+        let same = |name: &Option<String>| match name {
+            Some(name) if name == filename => true,
+            Some(name) => wanted.is_some() && Path::new(name).canonicalize().ok() == wanted,
+            None => false,
+        };
+        (0..self.buffers.len()).find(|&index| same(&self.editor_buffer(index).filename))
+    }
+
+    /// Whether any buffer, shown or not, has unsaved changes
+    pub fn editor_any_buffer_dirty(&self) -> bool {
+        (0..self.buffers.len()).any(|index| self.editor_buffer(index).dirty != 0)
+    }
+
+    /// File name and unsaved change count of buffer `index`
+    fn editor_buffer_state(&self, index: usize) -> (&Option<String>, usize) {
+        let buffer = self.editor_buffer(index);
+        (&buffer.filename, buffer.dirty)
+    }
+
+    /// Names of the buffers with unsaved changes
+    fn editor_dirty_buffer_names(&self) -> Vec<String> {
+        (0..self.buffers.len())
+            .filter_map(|index| {
+                let (filename, dirty) = self.editor_buffer_state(index);
+                (dirty != 0).then(|| filename.as_deref().unwrap_or("[No Name]").to_string())
+            })
+            .collect()
+    }
+
+    /// `wa`, Ctrl-Alt-S: saves every buffer with unsaved changes and a name,
+    /// carrying on past the ones that fail, and sums up how it went. True if
+    /// none is left unsaved.
+    pub fn editor_save_all(&mut self) -> bool {
+        let shown = self.current_buffer;
+        let mut saved = 0;
+        let mut failures = vec![];
+        for index in 0..self.buffers.len() {
+            match self.editor_buffer_state(index) {
+                (Some(_), dirty) if dirty != 0 => {}
+                _ => continue,
+            }
+            self.editor_select_buffer(index);
+            match self.editor_write_buffer(false) {
+                Ok(()) => saved += 1,
+                Err(SaveError::Refused(message)) => failures.push(message.to_string()),
+                Err(SaveError::Failed(reason)) => failures.push(reason),
+            }
+        }
+        self.editor_select_buffer(shown);
+
+        let unnamed = (0..self.buffers.len())
+            .filter(|&index| matches!(self.editor_buffer_state(index), (None, dirty) if dirty != 0))
+            .count();
+        let mut summary = format!(
+            "{} buffer{} saved",
+            saved,
+            if saved == 1 { "" } else { "s" }
+        );
+        if !failures.is_empty() {
+            summary.push_str(&format!(
+                ", {} failed: {}",
+                failures.len(),
+                failures.join("; ")
+            ));
+        }
+        if unnamed > 0 {
+            summary.push_str(&format!(", {} without a name", unnamed));
+        }
+        self.editor_set_status_message(&summary);
+        failures.is_empty() && unnamed == 0
+    }
+
+    /// Asks what to do with unsaved changes before quitting, listing the
+    /// buffers that have them. True if the editor may quit.
+    pub fn editor_confirm_quit(&mut self) -> bool {
+        let names = self.editor_dirty_buffer_names();
+        self.editor_set_status_message(&format!(
+            "Unsaved changes in {}. Save all (s), discard all (d) or cancel (ESC)?",
+            names.join(", ")
+        ));
+        loop {
+            self.editor_refresh_screen();
+            match self.editor_read_key() {
+                Key::Char('s' | 'S') => return self.editor_save_all(),
+                Key::Char('d' | 'D') => return true,
+                Key::Escape | Key::Char('c' | 'C') | Key::Ctrl('c') => {
+                    self.editor_set_status_message("Quit cancelled");
+                    return false;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Turns relative file names of all buffers into absolute ones
+    pub fn editor_make_filenames_absolute(&mut self) {
+        let filenames = self
+            .editor_all_buffers_mut()
+            .map(|buffer| &mut buffer.filename);
+        for filename in filenames.flatten() {
+            *filename = absolute_path(Path::new(filename.as_str()))
+                .to_string_lossy()
+                .into_owned();
+        }
+    }
+
+    /// `--readonly`: keeps every buffer from being saved until reopened rw
+    pub fn editor_set_all_read_only(&mut self) {
+        for buffer in self.editor_all_buffers_mut() {
+            buffer.file_format.read_only = true;
+        }
+    }
+
+    /// File name and cursor position of each buffer with a name, and the
+    /// index among them of the shown one
+    pub fn editor_buffer_positions(&self) -> (Vec<(String, usize, usize)>, usize) {
+        let mut positions = vec![];
+        let mut shown = 0;
+        for index in 0..self.buffers.len() {
+            if index == self.current_buffer {
+                shown = positions.len();
+            }
+            let buffer = self.editor_buffer(index);
+            if let Some(filename) = &buffer.filename {
+                positions.push((filename.clone(), buffer.cursor_y, buffer.cursor_x));
+            }
+        }
+        (positions, shown)
+    }
+
+    /// Deletes the recovery files of all buffers, on quitting
+    pub fn editor_remove_all_recovery(&mut self) {
+        for index in 0..self.buffers.len() {
+            if index != self.current_buffer {
+                mem::swap(&mut self.buffer.recovery, &mut self.buffers[index].recovery);
+                self.editor_remove_recovery();
+                mem::swap(&mut self.buffer.recovery, &mut self.buffers[index].recovery);
+            }
+        }
+        self.editor_remove_recovery();
+    }
+
+    /// Status bar tag with the position in the argument list, e.g. `[2/3]`
+    pub fn editor_buffers_status(&self) -> Option<String> {
+        (self.buffers.len() > 1)
+            .then(|| format!("[{}/{}]", self.current_buffer + 1, self.buffers.len()))
+    }
+
+    /// `next` and `prev`: shows the following or preceding buffer, wrapping
+    /// around at the ends
+    pub fn editor_cycle_buffer(&mut self, forward: bool) {
+        let count = self.buffers.len();
+        if count < 2 {
+            self.editor_set_status_message("Only one buffer open");
+            return;
+        }
+        let index = if forward {
+            (self.current_buffer + 1) % count
+        } else {
+            (self.current_buffer + count - 1) % count
+        };
+        self.editor_select_buffer(index);
+        self.editor_set_status_message(&format!(
+            "{} {}",
+            self.buffer.filename.as_deref().unwrap_or("[No Name]"),
+            self.editor_buffers_status().unwrap_or_default()
+        ));
+    }
+
+    /// `args`: lists the open buffers, Enter shows one
+    pub fn editor_list_buffers(&mut self) {
+        let lines: Vec<OverlayLine> = (0..self.buffers.len())
+            .map(|index| {
+                let (filename, dirty) = self.editor_buffer_state(index);
+                let line = format!(
+                    "{} {:3} {}{}",
+                    if index == self.current_buffer {
+                        '%'
+                    } else {
+                        ' '
+                    },
+                    index + 1,
+                    filename
+                        .as_deref()
+                        .map_or("[No Name]".to_string(), display_path),
+                    if dirty != 0 { " (modified)" } else { "" }
+                );
+                OverlayLine::new(line)
+            })
+            .collect();
+        if let Some(selected) = self.editor_show_overlay("Buffers", &lines) {
+            self.editor_select_buffer(selected);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Case conversion: `upper`, `lower`, `togglecase` and `titlecase` change the
+//! selection, or the word under the cursor without one, in a single undo
+//! step. The selection stays over the converted text.
+
+use crate::completion::is_word_char;
+use crate::*;
+
+#[derive(Clone, Copy)]
+pub enum Case {
+    Upper,
+    Lower,
+    Toggle,
+    Title,
+}
+
+/// `text` converted to `case`. Title case starts each word with a capital
+/// and lowers the rest; an apostrophe inside a word, as in "don't", doesn't
+/// start a new one.
+fn convert(text: &str, case: Case) -> String {
+    match case {
+        Case::Upper => text.to_uppercase(),
+        Case::Lower => text.to_lowercase(),
+        Case::Toggle => text
+            .chars()
+            .map(|c| {
+                if c.is_uppercase() {
+                    c.to_lowercase().to_string()
+                } else {
+                    c.to_uppercase().to_string()
+                }
+            })
+            .collect(),
+        Case::Title => {
+            let mut converted = String::with_capacity(text.len());
+            let mut in_word = false;
+            for c in text.chars() {
+                if !in_word {
+                    converted.extend(c.to_uppercase());
+                } else {
+                    converted.extend(c.to_lowercase());
+                }
+                in_word = c.is_alphanumeric() || (c == '\'' && in_word);
+            }
+            converted
+        }
+    }
+}
+
+impl Editor {
+    /// Byte range of the word under or right before the cursor
+    fn editor_word_at_cursor(&self) -> Option<(usize, usize)> {
+        let chars = &self.buffer.rows.get(self.buffer.cursor_y)?.chars;
+        let start = chars[..self.buffer.cursor_x]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_word_char(c))
+            .last()
+            .map_or(self.buffer.cursor_x, |(index, _)| index);
+        let end = chars[self.buffer.cursor_x..]
+            .char_indices()
+            .find(|&(_, c)| !is_word_char(c))
+            .map_or(chars.len(), |(index, _)| self.buffer.cursor_x + index);
+        (start < end).then_some((start, end))
+    }
+
+    /// `upper`, `lower`, `togglecase`, `titlecase`: changes the case of the
+    /// selection or the word under the cursor
+    pub fn editor_change_case(&mut self, case: Case) {
+        let selected = self.editor_selection().filter(|(start, end)| start != end);
+        let ((start_x, start_y), (end_x, end_y)) = match selected {
+            Some(selection) => selection,
+            None => match self.editor_word_at_cursor() {
+                Some((start, end)) => ((start, self.buffer.cursor_y), (end, self.buffer.cursor_y)),
+                None => {
+                    self.editor_set_status_message("No selection or word at the cursor");
+                    return;
+                }
+            },
+        };
+        // A selection may end past the last row
+        let last_row = match self.get_num_rows().checked_sub(1) {
+            Some(last_row) if start_y <= last_row => last_row,
+            _ => return,
+        };
+        let (end_x, end_y) = if end_y > last_row {
+            (self.buffer.rows[last_row].len(), last_row)
+        } else {
+            (end_x.min(self.buffer.rows[end_y].len()), end_y)
+        };
+
+        let text: Vec<&str> = (start_y..=end_y)
+            .map(|row| {
+                let chars = self.buffer.rows[row].chars.as_str();
+                let from = if row == start_y { start_x } else { 0 };
+                let to = if row == end_y { end_x } else { chars.len() };
+                &chars[from..to]
+            })
+            .collect();
+        let converted = convert(&text.join("\n"), case);
+        let prefix = &self.buffer.rows[start_y].chars[..start_x];
+        let suffix = &self.buffer.rows[end_y].chars[end_x..];
+        let new_text = format!("{}{}{}", prefix, converted, suffix);
+        let new_rows: Vec<String> = new_text.split('\n').map(str::to_string).collect();
+        // Converting can change the length, as with ß to SS
+        let last = new_rows.len() - 1;
+        let new_end_x = new_rows[last].len() - suffix.len();
+
+        let (cursor_x, cursor_y) = (self.buffer.cursor_x, self.buffer.cursor_y);
+        let had_selection = selected.is_some();
+        let cursor_at_end = (cursor_y, cursor_x) >= (end_y, end_x);
+        self.editor_replace_rows(start_y, end_y - start_y + 1, new_rows);
+        if had_selection {
+            let start = (start_x, start_y);
+            let end = (new_end_x, start_y + last);
+            let (mark, cursor) = if cursor_at_end {
+                (start, end)
+            } else {
+                (end, start)
+            };
+            self.buffer.mark = Some(mark);
+            (self.buffer.cursor_x, self.buffer.cursor_y) = cursor;
+        } else {
+            self.buffer.cursor_x = floor_char_boundary(&self.buffer.rows[cursor_y].chars, cursor_x);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Screen recording (`--record-cast FILE`): everything written to the
+//! terminal is logged with its timing in the asciinema v2 format, one event
+//! per frame, so `asciinema play FILE` shows exactly what was drawn.
+
+use std::cell::Cell;
+use std::fs::File;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::json::Json;
+use crate::*;
+
+pub struct CastRecorder {
+    file: File,
+    start: Instant,
+    /// Columns and rows last recorded, a resize event is logged on a change
+    size: Cell<(usize, usize)>,
+}
+
+impl CastRecorder {
+    fn write_event(&self, code: &str, data: &str) {
+        let time = (self.start.elapsed().as_secs_f64() * 1e6).round() / 1e6;
+        let event = Json::Array(vec![
+            Json::Number(time),
+            Json::string(code),
+            Json::string(data),
+        ]);
+        // A failed write loses the event but shouldn't stop the editing
+        let _ = writeln!(&self.file, "{}", event);
+    }
+}
+
+impl Editor {
+    fn editor_screen_size(&self) -> (usize, usize) {
+        (
+            self.screen_num_columns,
+            self.screen_num_rows + KILO_MESSAGE_BAR_HEIGHT,
+        )
+    }
+
+    /// Starts recording the screen to `path`, replacing the file. Exits if it
+    /// can't be written.
+    pub fn editor_start_cast(&mut self, path: &str) {
+        let file = match File::create(path) {
+            Ok(file) => file,
+            Err(error) => return self.die(&format!("Could not record to {}: {}", path, error)),
+        };
+        let (width, height) = self.editor_screen_size();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let term = std::env::var("TERM").unwrap_or_default();
+        let header = Json::object(vec![
+            ("version", Json::Number(2.0)),
+            ("width", Json::Number(width as f64)),
+            ("height", Json::Number(height as f64)),
+            ("timestamp", Json::Number(timestamp as f64)),
+            ("env", Json::object(vec![("TERM", Json::string(&term))])),
+        ]);
+        if let Err(error) = writeln!(&file, "{}", header) {
+            return self.die(&format!("Could not record to {}: {}", path, error));
+        }
+        self.cast = Some(CastRecorder {
+            file,
+            start: Instant::now(),
+            size: Cell::new((width, height)),
+        });
+    }
+
+    /// Logs `output` written to the terminal, after a resize event if the
+    /// screen changed size
+    pub fn editor_record_cast(&self, output: &str) {
+        let cast = match &self.cast {
+            Some(cast) => cast,
+            None => return,
+        };
+        let size = self.editor_screen_size();
+        if cast.size.replace(size) != size {
+            cast.write_event("r", &format!("{}x{}", size.0, size.1));
+        }
+        cast.write_event("o", output);
+    }
+}
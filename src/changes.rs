@@ -0,0 +1,116 @@
+//! Change list: recent edit positions, walked with `g;` (older) and `g,` (newer)
+
+use crate::overlay::OverlayLine;
+use crate::*;
+
+const CHANGE_LIST_LIMIT: usize = 100;
+
+#[derive(Default)]
+pub struct ChangeList {
+    /// (cursor_x, cursor_y) of each change, oldest first
+    positions: Vec<(usize, usize)>,
+    /// Position in `positions` while walking the list, None after a new change
+    current: Option<usize>,
+}
+
+impl ChangeList {
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.current = None;
+    }
+
+    /// Changes on the same row as the newest entry replace it, so typing a
+    /// line only leaves one entry
+    fn record(&mut self, position: (usize, usize)) {
+        self.current = None;
+        match self.positions.last_mut() {
+            Some(last) if last.1 == position.1 => *last = position,
+            _ => {
+                self.positions.push(position);
+                if self.positions.len() > CHANGE_LIST_LIMIT {
+                    self.positions.remove(0);
+                }
+            }
+        }
+    }
+}
+
+impl Editor {
+    pub fn editor_record_change(&mut self) {
+        self.buffer
+            .changes
+            .record((self.buffer.cursor_x, self.buffer.cursor_y));
+    }
+
+    /// `g;` when `older`, `g,` otherwise
+    pub fn editor_jump_to_change(&mut self, older: bool) {
+        let count = self.buffer.changes.positions.len();
+        if count == 0 {
+            self.editor_set_status_message("Change list is empty");
+            return;
+        }
+
+        let index = match (self.buffer.changes.current, older) {
+            // The first g; goes to the newest change, unless the cursor is already there
+            (None, true) => {
+                let (x, y) = self.buffer.changes.positions[count - 1];
+                if (x, y) == (self.buffer.cursor_x, self.buffer.cursor_y) && count > 1 {
+                    count - 2
+                } else {
+                    count - 1
+                }
+            }
+            (None, false) => {
+                self.editor_set_status_message("At end of change list");
+                return;
+            }
+            (Some(0), true) => {
+                self.editor_set_status_message("At start of change list");
+                return;
+            }
+            (Some(index), true) => index - 1,
+            (Some(index), false) if index + 1 >= count => {
+                self.editor_set_status_message("At end of change list");
+                return;
+            }
+            (Some(index), false) => index + 1,
+        };
+
+        self.buffer.changes.current = Some(index);
+        let (x, y) = self.buffer.changes.positions[index];
+        self.editor_goto(y, x);
+        self.editor_set_status_message(&format!("Change {} of {}", index + 1, count));
+    }
+
+    /// `changes`: lists recent change positions, newest first
+    pub fn editor_change_list(&mut self) {
+        if self.buffer.changes.positions.is_empty() {
+            self.editor_set_status_message("Change list is empty");
+            return;
+        }
+
+        let lines: Vec<OverlayLine> = self
+            .buffer
+            .changes
+            .positions
+            .iter()
+            .rev()
+            .map(|&(x, y)| {
+                let text = self
+                    .buffer
+                    .rows
+                    .get(y)
+                    .map(|row| row.chars.trim())
+                    .unwrap_or("");
+                OverlayLine::new(format!("{:>5}:{:<4} {}", y + 1, x + 1, text))
+            })
+            .collect();
+
+        if let Some(selected) = self.editor_show_overlay("Changes", &lines) {
+            let index = self.buffer.changes.positions.len() - 1 - selected;
+            self.buffer.changes.current = Some(index);
+            let (x, y) = self.buffer.changes.positions[index];
+            self.editor_goto(y, x);
+        }
+    }
+}
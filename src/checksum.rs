@@ -0,0 +1,158 @@
+//! SHA-256 of the buffer and of the file on disk: `checksum` shows both, and
+//! every save reads the file back to check it hashes to what was written
+
+use crate::*;
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+fn compress(state: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+    for (i, word) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for i in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let choice = (e & f) ^ (!e & g);
+        let temp1 = h
+            .wrapping_add(s1)
+            .wrapping_add(choice)
+            .wrapping_add(ROUND_CONSTANTS[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let majority = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(majority);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(temp1);
+        d = c;
+        c = b;
+        b = a;
+        a = temp1.wrapping_add(temp2);
+    }
+    for (value, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *value = value.wrapping_add(add);
+    }
+}
+
+/// SHA-256 of `data` as lowercase hex
+pub fn sha256_hex(data: &[u8]) -> String {
+    let mut state = INITIAL_STATE;
+    let mut chunks = data.chunks_exact(64);
+    for block in &mut chunks {
+        compress(&mut state, block);
+    }
+
+    // Padding: a 1 bit, zeros, then the length in bits as a 64 bit number
+    let mut tail = chunks.remainder().to_vec();
+    tail.push(0x80);
+    while tail.len() % 64 != 56 {
+        tail.push(0);
+    }
+    tail.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+    for block in tail.chunks(64) {
+        compress(&mut state, block);
+    }
+
+    state.iter().map(|word| format!("{:08x}", word)).collect()
+}
+
+/// Reads `filename` back after saving and checks it hashes like `written`.
+/// Returns an error message on a mismatch.
+pub fn verify_written(filename: &str, written: &[u8]) -> Option<String> {
+    match std::fs::read(filename) {
+        Ok(contents) => {
+            let expected = sha256_hex(written);
+            let actual = sha256_hex(&contents);
+            (expected != actual).then(|| {
+                format!(
+                    "verification failed, {} has SHA-256 {}, expected {}",
+                    filename, actual, expected
+                )
+            })
+        }
+        Err(error) => Some(format!(
+            "verification failed, could not read {} back: {}",
+            filename, error
+        )),
+    }
+}
+
+impl Editor {
+    /// `checksum`: shows the SHA-256 of the buffer and of the file on disk
+    pub fn editor_checksum(&mut self) {
+        // Hashed as it would be saved, to compare with the file
+        let text = self.editor_rows_to_string();
+        let buffer = match self.buffer.file_format.encode(&text) {
+            Ok(bytes) => sha256_hex(&bytes),
+            Err(_) => sha256_hex(text.as_bytes()),
+        };
+        let disk = match self.buffer.filename.as_ref().map(std::fs::read) {
+            Some(Ok(contents)) => sha256_hex(&contents),
+            Some(Err(error)) => error.to_string(),
+            None => "no file".to_string(),
+        };
+        let message = if buffer == disk {
+            format!("SHA-256 {} (buffer and file match)", buffer)
+        } else {
+            format!("SHA-256 buffer {} file {}", buffer, disk)
+        };
+        self.editor_set_status_message(&message);
+    }
+
+    /// Reads the file just saved back and checks it hashes like `written`.
+    /// Returns an error message on a mismatch.
+    pub fn editor_verify_save(&self, written: &[u8]) -> Option<String> {
+        verify_written(self.buffer.filename.as_ref()?, written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // FIPS 180-2 appendix B examples and the empty message
+    #[test]
+    fn hashes_the_standard_test_vectors() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(
+            sha256_hex(&[b'a'; 1_000_000]),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Command line arguments. `--help` and `--version` print and exit before
+//! the terminal is touched, as do mistakes in the arguments.
+
+use std::path::PathBuf;
+
+use crate::*;
+
+const USAGE: &str = "\
+Usage: kilo [OPTIONS] [FILE]...
+
+Opens each FILE in a buffer of its own, or piped text with `-` or no FILE.
+
+Options:
+  -R, --readonly          Open the files read-only
+      --tabstop N         Columns between tab stops (default 8)
+      --config PATH       Read settings from PATH instead of the config file
+      --session           Open the files of the last session again
+      --line N            Start on line N of the first file
+  -f, --follow            Follow the first file as it grows, like tail -f
+      --script COMMANDS   Run `;` separated commands on each FILE and save it
+      --cat               With --script, print the result instead of saving
+      --record-cast PATH  Record the session as an asciicast to PATH
+  -h, --help              Print this help and exit
+  -V, --version           Print the version and exit
+      --                  Treat the arguments after it as file names
+
+Environment:
+  KILO_CONFIG             Config file to read instead of ~/.kilorc
+  KILO_THEME              Theme file, or the name of one in the themes directory
+  COLUMNS, LINES          Screen size when the terminal doesn't report one
+";
+
+/// What the command line asks for
+#[derive(Default)]
+pub struct Args {
+    pub filenames: Vec<String>,
+    /// `-` was given, piped text is opened even with file names
+    pub read_stdin: bool,
+    pub readonly: bool,
+    pub tab_stop: Option<usize>,
+    pub config: Option<PathBuf>,
+    pub session: bool,
+    /// Line to start on, counted from 1
+    pub line: Option<usize>,
+    pub follow: bool,
+    pub cast: Option<String>,
+    pub script: Option<Script>,
+}
+
+/// Reports a mistake in the arguments and exits
+fn usage_error(message: &str) -> ! {
+    eprintln!("Error: {}", message);
+    eprintln!("Try 'kilo --help' for more information.");
+    process::exit(2);
+}
+
+/// The number `value` of option `name`, at least 1
+fn parse_count(name: &str, value: &str) -> usize {
+    match value.parse() {
+        Ok(count) if count > 0 => count,
+        _ => usage_error(&format!("{} needs a number above 0, not '{}'", name, value)),
+    }
+}
+
+impl Args {
+    /// Parses the arguments after the program name. Options taking a value
+    /// accept it as the next argument or after `=`.
+    pub fn parse(arguments: impl IntoIterator<Item = String>) -> Args {
+        let mut args = Args::default();
+        let mut arguments = arguments.into_iter();
+        while let Some(argument) = arguments.next() {
+            if !argument.starts_with('-') || argument == "-" {
+                match argument.as_str() {
+                    "-" => args.read_stdin = true,
+                    _ => args.filenames.push(argument),
+                }
+                continue;
+            }
+            if argument == "--" {
+                args.filenames.extend(arguments.by_ref());
+                break;
+            }
+
+            let (name, inline) = match argument.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+                _ => (argument.as_str(), None),
+            };
+            let takes_value = matches!(
+                name,
+                "--tabstop" | "--config" | "--line" | "--script" | "--record-cast"
+            );
+            let value = match (takes_value, inline) {
+                (true, Some(value)) => value,
+                (true, None) => arguments
+                    .next()
+                    .unwrap_or_else(|| usage_error(&format!("{} needs an argument", name))),
+                (false, Some(_)) => usage_error(&format!("{} doesn't take an argument", name)),
+                (false, None) => String::new(),
+            };
+
+            match name {
+                "-h" | "--help" => {
+                    print!("{}", USAGE);
+                    process::exit(0);
+                }
+                "-V" | "--version" => {
+                    println!("kilo {}", KILO_VERSION);
+                    process::exit(0);
+                }
+                "-R" | "--readonly" => args.readonly = true,
+                "--tabstop" => args.tab_stop = Some(parse_count(name, &value)),
+                "--config" => args.config = Some(PathBuf::from(value)),
+                "--session" => args.session = true,
+                "--line" => args.line = Some(parse_count(name, &value)),
+                "-f" | "--follow" => args.follow = true,
+                "--script" => args.script.get_or_insert_with(Script::default).push(&value),
+                "--cat" => args.script.get_or_insert_with(Script::default).cat = true,
+                "--record-cast" => args.cast = Some(value),
+                _ => usage_error(&format!("unknown option {}", name)),
+            }
+        }
+
+        if args.session && !args.filenames.is_empty() {
+            usage_error("--session opens the files of the last session, not named ones");
+        }
+        if let Some(config) = &args.config {
+            if !config.is_file() {
+                usage_error(&format!("no config file {}", config.display()));
+            }
+        }
+        args
+    }
+}
+
+impl Editor {
+    /// Loads the theme named by $KILO_THEME, the config file (the one given
+    /// with `--config` if any) and then the options given on the command line
+    /// over them. Batch mode draws nothing, so it skips the theme.
+    pub fn editor_configure(&mut self, args: &Args) {
+        if let Some(theme) = std::env::var("KILO_THEME")
+            .ok()
+            .filter(|theme| !theme.is_empty() && !self.headless)
+        {
+            self.editor_load_theme(&theme);
+        }
+        self.editor_load_config(args.config.as_deref());
+        if let Some(tab_stop) = args.tab_stop {
+            self.options.tab_stop = tab_stop;
+            self.editor_options_changed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(arguments: &[&str]) -> Args {
+        Args::parse(arguments.iter().map(|argument| argument.to_string()))
+    }
+
+    #[test]
+    fn parses_files_and_flags() {
+        let args = parse(&[
+            "-R",
+            "a.txt",
+            "--tabstop",
+            "4",
+            "-",
+            "--line=12",
+            "-f",
+            "b.txt",
+        ]);
+        assert_eq!(args.filenames, ["a.txt", "b.txt"]);
+        assert!(args.read_stdin && args.readonly && args.follow);
+        assert_eq!(args.tab_stop, Some(4));
+        assert_eq!(args.line, Some(12));
+        assert!(!args.session && args.script.is_none());
+    }
+
+    #[test]
+    fn takes_everything_after_double_dash_as_files() {
+        let args = parse(&["--readonly", "--", "--help", "-"]);
+        assert_eq!(args.filenames, ["--help", "-"]);
+        assert!(args.readonly && !args.read_stdin);
+    }
+
+    #[test]
+    fn collects_script_commands() {
+        let args = parse(&[
+            "--script",
+            "strip; retab 4",
+            r"--script=replace a\;b c",
+            "--cat",
+        ]);
+        let script = args.script.unwrap();
+        assert_eq!(script.commands, ["strip", " retab 4", "replace a;b c"]);
+        assert!(script.cat);
+    }
+}
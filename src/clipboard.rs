@@ -0,0 +1,178 @@
+//! Copy and paste, exporting copies to the system clipboard with OSC 52
+
+use std::path::PathBuf;
+
+use crate::storage::create_private_temp;
+use crate::*;
+
+/// Encoded payloads above this size are not sent with OSC 52, many terminals
+/// silently drop anything larger
+const OSC52_MAX_BYTES: usize = 100_000;
+
+const BASE64_TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+
+        encoded.push(BASE64_TABLE[(n >> 18) as usize & 63] as char);
+        encoded.push(BASE64_TABLE[(n >> 12) as usize & 63] as char);
+        encoded.push(if chunk.len() > 1 {
+            BASE64_TABLE[(n >> 6) as usize & 63] as char
+        } else {
+            '='
+        });
+        encoded.push(if chunk.len() > 2 {
+            BASE64_TABLE[n as usize & 63] as char
+        } else {
+            '='
+        });
+    }
+    encoded
+}
+
+impl Editor {
+    /// Text covered by the selection
+    pub fn editor_selected_text(&self) -> Option<String> {
+        let ((start_x, start_y), (end_x, end_y)) = self.editor_selection()?;
+        let mut text = String::new();
+
+        for y in start_y..=end_y.min(self.get_num_rows().saturating_sub(1)) {
+            let chars = &self.buffer.rows[y].chars;
+            let from = if y == start_y {
+                start_x.min(chars.len())
+            } else {
+                0
+            };
+            let to = if y == end_y {
+                end_x.min(chars.len())
+            } else {
+                chars.len()
+            };
+
+            text.push_str(&chars[from..to.max(from)]);
+            if y != end_y {
+                text.push('\n');
+            }
+        }
+
+        Some(text)
+    }
+
+    pub fn editor_copy(&mut self) {
+        let text = match self.editor_selected_text() {
+            Some(text) => text,
+            None => {
+                self.editor_set_status_message("No selection to copy (Ctrl-Space sets the mark)");
+                return;
+            }
+        };
+
+        self.buffer.mark = None;
+        self.clipboard = text;
+        self.editor_export_clipboard();
+    }
+
+    /// Shift-Delete: copies the selection and deletes it
+    pub fn editor_cut(&mut self) {
+        let ((start_x, start_y), (end_x, end_y)) = match self.editor_selection() {
+            Some(selection) if self.editor_selected_text().is_some() => selection,
+            _ => {
+                self.editor_set_status_message("No selection to cut (Ctrl-Space sets the mark)");
+                return;
+            }
+        };
+        self.editor_copy();
+
+        let num_rows = self.get_num_rows();
+        if start_y >= num_rows {
+            return;
+        }
+        let end_y = end_y.min(num_rows - 1);
+        let first = &self.buffer.rows[start_y].chars;
+        let last = &self.buffer.rows[end_y].chars;
+        let joined = format!(
+            "{}{}",
+            &first[..start_x.min(first.len())],
+            &last[end_x.min(last.len())..]
+        );
+        self.editor_replace_rows(start_y, end_y - start_y + 1, vec![joined]);
+        self.editor_goto(start_y, start_x);
+    }
+
+    /// Sends the clipboard to the terminal, falling back to a temporary file
+    /// when it is too large for OSC 52
+    pub fn editor_export_clipboard(&mut self) {
+        let encoded = base64_encode(self.clipboard.as_bytes());
+        if encoded.len() <= OSC52_MAX_BYTES {
+            self.write_to_stdout(&format!("\x1b]52;c;{}\x07", encoded));
+            self.flush_stdout();
+            self.editor_set_status_message(&format!(
+                "Copied {} bytes to clipboard",
+                self.clipboard.len()
+            ));
+            return;
+        }
+
+        match write_clipboard_file(&self.clipboard) {
+            Ok(path) => self.editor_set_status_message(&format!(
+                "Copied {} bytes (too large for terminal clipboard, saved to {})",
+                self.clipboard.len(),
+                path.display()
+            )),
+            Err(error) => self.editor_set_status_message(&format!(
+                "Copied {} bytes internally, clipboard export failed: {}",
+                self.clipboard.len(),
+                error
+            )),
+        }
+    }
+
+    pub fn editor_paste(&mut self) {
+        if self.clipboard.is_empty() {
+            self.editor_set_status_message("Clipboard is empty");
+            return;
+        }
+
+        let text = self.clipboard.clone();
+        self.editor_insert_text(&text);
+    }
+
+    /// Inserts `text` (which may span several lines) at the cursor as one undo step
+    pub fn editor_insert_text(&mut self, text: &str) {
+        let (before, after) = match self.buffer.rows.get(self.buffer.cursor_y) {
+            Some(row) => {
+                let at = floor_char_boundary(&row.chars, self.buffer.cursor_x);
+                (row.chars[..at].to_string(), row.chars[at..].to_string())
+            }
+            None => (String::new(), String::new()),
+        };
+
+        let mut new_rows: Vec<String> = text.split('\n').map(str::to_string).collect();
+        let last = new_rows.len() - 1;
+        let cursor_x = if last == 0 {
+            before.len() + new_rows[last].len()
+        } else {
+            new_rows[last].len()
+        };
+        new_rows[0].insert_str(0, &before);
+        new_rows[last].push_str(&after);
+
+        self.editor_replace_rows(self.buffer.cursor_y, 1, new_rows);
+        self.buffer.cursor_y += last;
+        self.buffer.cursor_x = cursor_x;
+    }
+}
+
+/// Writes `contents` to a file only we can read, returning where it is
+fn write_clipboard_file(contents: &str) -> io::Result<PathBuf> {
+    let (path, mut file) = create_private_temp(&format!("clipboard-{}.txt", process::id()))?;
+    file.write_all(contents.as_bytes())?;
+    Ok(path)
+}
@@ -0,0 +1,237 @@
+//! Command prompt (Ctrl-P) and command dispatch
+
+use crate::*;
+
+/// Commands with a short description, listed by the command palette
+pub const COMMANDS: &[(&str, &str)] = &[
+    (
+        "abbrev",
+        "Define or list abbreviations expanded as you type",
+    ),
+    (
+        "align",
+        "Align the selected rows on a separator, -a on every one",
+    ),
+    ("args", "List the open files"),
+    ("blame", "Show git blame for the file"),
+    ("broadcast", "Share the screen read-only over a socket"),
+    (
+        "cd",
+        "Change the working directory, to the file's by default",
+    ),
+    ("changes", "List recent change positions"),
+    ("checksum", "Show the SHA-256 of the buffer and the file"),
+    ("color", "Show or set the theme colors"),
+    ("commands", "Command palette"),
+    (
+        "commentjump",
+        "Jump between the opener and closer of a block comment",
+    ),
+    ("completion", "Set completion sources for a filetype"),
+    ("copen", "List quickfix entries"),
+    ("cnext", "Jump to the next quickfix entry"),
+    ("cprev", "Jump to the previous quickfix entry"),
+    ("copy", "Copy the selection"),
+    ("delete", "Delete a range of lines, e.g. .,+5d or 'a,'bd x"),
+    ("diff", "Show changes against the saved file"),
+    ("difftool", "Compare with an external diff tool"),
+    ("digraphs", "List the digraphs for Ctrl-K"),
+    ("files", "Open a file by fuzzy name"),
+    ("filter", "Only show rows matching a pattern"),
+    ("fmnext", "Jump to the next front matter key"),
+    ("fmprev", "Jump to the previous front matter key"),
+    ("follow", "Keep reading lines appended to the file"),
+    ("format", "Format the buffer"),
+    ("formatter", "Set the formatter for a filetype"),
+    ("frontmatter", "Fold or unfold the front matter"),
+    ("g;", "Jump to an older change"),
+    ("g,", "Jump to a newer change"),
+    ("grep", "Search all files of the project"),
+    ("help", "Show the key bindings and commands"),
+    ("hex", "Switch hex mode on or off"),
+    ("lower", "Lowercase the selection or the word"),
+    ("lsp", "Start or stop the language server"),
+    ("lspserver", "Set the language server for a filetype"),
+    ("make", "Run the build command"),
+    ("mark", "Set a named mark for ranges"),
+    ("memory", "Show memory use or compact the buffer"),
+    ("next", "Show the next open file"),
+    ("nextmod", "Jump to the next modified line"),
+    ("paste", "Paste the clipboard"),
+    ("prev", "Show the previous open file"),
+    ("preview", "Show or hide the Markdown preview"),
+    ("prevmod", "Jump to the previous modified line"),
+    ("put", "Insert the lines of a register below the cursor"),
+    ("pwd", "Show the working directory"),
+    ("recent", "Open a recently used file"),
+    ("recover", "Recover unsaved changes from crashed sessions"),
+    ("redo", "Redo the last undone change"),
+    ("reindent", "Reindent the buffer"),
+    (
+        "reopen",
+        "Read the file again with another encoding or line endings",
+    ),
+    ("replace", "Replace every occurrence of a string"),
+    ("retab", "Expand tabs to spaces"),
+    ("reverse", "Reverse the order of the selected rows"),
+    ("set", "Show or change options"),
+    ("snippet", "Define a completion snippet"),
+    (
+        "sort",
+        "Sort the selected rows, -n numeric, -r reverse, -u unique",
+    ),
+    ("source", "Run a Rhai script"),
+    ("spell", "Toggle spell checking"),
+    ("strip", "Remove trailing whitespace"),
+    ("symbols", "Jump to a symbol in the project"),
+    ("syntax", "List the language definitions"),
+    ("spellgood", "Add a word to the personal dictionary"),
+    ("spellgood!", "Add a word to the project dictionary"),
+    (
+        "spelllang",
+        "Set the spell check languages of the file or a file type",
+    ),
+    (
+        "titlecase",
+        "Capitalize the words of the selection or the word",
+    ),
+    ("togglecase", "Swap the case of the selection or the word"),
+    ("unabbrev", "Remove an abbreviation"),
+    ("undo", "Undo the last change"),
+    ("unicode", "Insert a char by code point or digraph"),
+    ("uniq", "Remove repeated rows from the selection"),
+    ("upper", "Uppercase the selection or the word"),
+    ("usage", "Show usage statistics"),
+    ("wa", "Save all open files"),
+    ("wc", "Count lines, words and characters"),
+    ("yank", "Copy a range of lines, e.g. .,+5y or %y a"),
+    ("zen", "Toggle distraction free mode"),
+    ("zz", "Scroll the cursor line to the middle"),
+    ("zt", "Scroll the cursor line to the top"),
+    ("zb", "Scroll the cursor line to the bottom"),
+    ("*", "Search forward for the word under the cursor"),
+    ("#", "Search backward for the word under the cursor"),
+    ("!", "Run a shell command"),
+    ("r!", "Insert the output of a shell command"),
+    ("|", "Filter the selection through a shell command"),
+];
+
+impl Editor {
+    pub fn editor_command_prompt(&mut self) {
+        if let Some(command) =
+            self.editor_prompt("Command: %s (ESC to cancel)", HistoryKind::Command)
+        {
+            self.editor_record_command_usage(command.trim());
+            self.editor_execute_command(command.trim());
+        }
+    }
+
+    pub fn editor_execute_command(&mut self, command: &str) {
+        if let Some(shell_command) = command.strip_prefix("r!") {
+            return self.editor_shell_read(shell_command.trim());
+        } else if let Some(shell_command) = command.strip_prefix('!') {
+            return self.editor_shell_command(shell_command.trim());
+        } else if let Some(shell_command) = command.strip_prefix('|') {
+            return self.editor_shell_filter(shell_command.trim());
+        } else if self.editor_line_command(command) {
+            return;
+        }
+
+        let (name, args) = match command.split_once(char::is_whitespace) {
+            Some((name, args)) => (name, args.trim()),
+            None => (command, ""),
+        };
+
+        match name {
+            "diff" => self.editor_diff_saved(),
+            "difftool" => self.editor_difftool(args),
+            "reindent" => self.editor_reindent(),
+            "reopen" => self.editor_reopen(args),
+            "replace" => self.editor_replace_all(args),
+            "retab" => self.editor_retab(args),
+            "strip" => self.editor_strip_whitespace(),
+            "undo" => self.editor_undo(),
+            "redo" => self.editor_redo(),
+            "set" => self.editor_set_option(args),
+            "copy" => self.editor_copy(),
+            "paste" => self.editor_paste(),
+            "put" => self.editor_put(args),
+            "mark" => self.editor_set_named_mark(args),
+            "memory" => self.editor_memory_command(args),
+            "blame" => self.editor_git_blame(),
+            "broadcast" => self.editor_broadcast_command(args),
+            "sort" => self.editor_sort(args),
+            "unicode" => self.editor_insert_unicode(args),
+            "digraphs" => self.editor_list_digraphs(),
+            "abbrev" => self.editor_abbreviate(args),
+            "unabbrev" => self.editor_unabbreviate(args),
+            "reverse" => self.editor_reverse_lines(),
+            "uniq" => self.editor_unique_lines(),
+            "align" => self.editor_align(args),
+            "filter" => self.editor_filter(args),
+            "frontmatter" => self.editor_toggle_front_matter(),
+            "fmnext" => self.editor_front_matter_key(true),
+            "fmprev" => self.editor_front_matter_key(false),
+            "grep" => self.editor_grep(args),
+            "help" => self.editor_help(),
+            "hex" => self.editor_toggle_hex(),
+            "follow" => self.editor_toggle_follow(),
+            "zen" => self.editor_toggle_zen(),
+            "make" => self.editor_make(args),
+            "copen" => self.editor_quickfix_list(),
+            "cnext" => self.editor_quickfix_next(true),
+            "cprev" => self.editor_quickfix_next(false),
+            "format" => self.editor_format(),
+            "formatter" => self.editor_set_formatter(args),
+            "changes" => self.editor_change_list(),
+            "checksum" => self.editor_checksum(),
+            "color" => self.editor_color_command(args),
+            "commentjump" => self.editor_jump_block_comment(),
+            "g;" => self.editor_jump_to_change(true),
+            "g," => self.editor_jump_to_change(false),
+            "next" => self.editor_cycle_buffer(true),
+            "prev" => self.editor_cycle_buffer(false),
+            "args" => self.editor_list_buffers(),
+            "preview" => self.editor_toggle_preview(),
+            "nextmod" => self.editor_goto_changed_line(true),
+            "prevmod" => self.editor_goto_changed_line(false),
+            "spell" => self.editor_toggle_spell(),
+            "spellgood" => self.editor_spell_add_word(args, false),
+            "spellgood!" => self.editor_spell_add_word(args, true),
+            "spelllang" => self.editor_spell_language_command(args),
+            "recent" => self.editor_open_recent(),
+            "recover" => self.editor_recover(false),
+            "wa" => {
+                self.editor_save_all();
+            }
+            "wc" => self.editor_word_count(),
+            "cd" => self.editor_change_directory(args),
+            "pwd" => self.editor_print_directory(),
+            "usage" => self.editor_usage(args),
+            "completion" => self.editor_completion_command(args),
+            "snippet" => self.editor_define_snippet(args),
+            "files" => self.editor_find_file(),
+            "commands" => self.editor_command_palette(),
+            "symbols" => self.editor_find_symbol(),
+            "syntax" => self.editor_list_syntaxes(),
+            "*" => self.editor_search_word(true),
+            "#" => self.editor_search_word(false),
+            "zz" | "zt" | "zb" => self.editor_position_cursor_line(name),
+            "lsp" => self.editor_lsp_command(args),
+            "upper" => self.editor_change_case(Case::Upper),
+            "lower" => self.editor_change_case(Case::Lower),
+            "togglecase" => self.editor_change_case(Case::Toggle),
+            "titlecase" => self.editor_change_case(Case::Title),
+            "lspserver" => self.editor_set_lsp_server(args),
+            #[cfg(feature = "scripting")]
+            "source" => self.editor_source_script(args),
+            #[cfg(not(feature = "scripting"))]
+            "source" => self.editor_set_status_message(
+                "Built without scripting support (cargo build --features scripting)",
+            ),
+            #[cfg(feature = "scripting")]
+            _ if self.editor_script_command(name, args) => {}
+            _ => self.editor_set_error_message(&format!("Unknown command: {}", name)),
+        }
+    }
+}
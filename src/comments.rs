@@ -0,0 +1,187 @@
+//! Block comments (`set autocomment`): typing the opener of one, `/*` or
+//! `<!--` depending on the file type, inserts its closer after the cursor,
+//! Backspace right after removes both again and Enter inside a `/* */`
+//! comment continues it with a ` * ` line. `commentjump` moves between the
+//! opener and the closer of the comment the cursor is in.
+
+use crate::hooks::InputHook;
+use crate::syntax::syntax_for;
+use crate::*;
+
+/// Opener and closer of block comments in files with this extension
+pub fn block_comment(extension: &str) -> Option<(&'static str, &'static str)> {
+    let (opener, closer) = syntax_for(extension)?.block_comment.as_ref()?;
+    Some((opener, closer))
+}
+
+#[derive(Default)]
+pub struct BlockComments {
+    /// Row and column of the cursor right after a closer was inserted
+    last: Option<(usize, usize)>,
+}
+
+impl InputHook for BlockComments {
+    fn on_key(&mut self, editor: &mut Editor, key: Key) -> bool {
+        let last = self.last.take();
+        let extension = editor.editor_file_extension().unwrap_or_default();
+        let (opener, closer) = match block_comment(&extension) {
+            Some(delimiters) if editor.options.auto_comment => delimiters,
+            _ => return false,
+        };
+        if editor.buffer.cursor_y >= editor.get_num_rows() || editor.buffer.mark.is_some() {
+            return false;
+        }
+        let row = &editor.buffer.rows[editor.buffer.cursor_y].chars;
+        let (before, after) = row.split_at(floor_char_boundary(row, editor.buffer.cursor_x));
+
+        match key {
+            Key::Char(c) => {
+                let typed = format!("{}{}", before, c);
+                if !typed.ends_with(opener) || after.starts_with(closer) {
+                    return false;
+                }
+                editor.editor_replace_before_cursor(0, &format!("{}{}", c, closer));
+                editor.buffer.cursor_x -= closer.len();
+                self.last = Some((editor.buffer.cursor_y, editor.buffer.cursor_x));
+                true
+            }
+            Key::Backspace => match last {
+                Some((row, column))
+                    if (row, column) == (editor.buffer.cursor_y, editor.buffer.cursor_x)
+                        && after.starts_with(closer) =>
+                {
+                    // The opener loses its last char, the closer goes entirely
+                    let mut line = editor.buffer.rows[row].chars.clone();
+                    line.replace_range(column - 1..column + closer.len(), "");
+                    editor.editor_replace_rows(row, 1, vec![line]);
+                    editor.buffer.cursor_x = column - 1;
+                    true
+                }
+                _ => false,
+            },
+            Key::Enter if opener == "/*" => editor.editor_continue_block_comment(),
+            _ => false,
+        }
+    }
+}
+
+impl Editor {
+    /// Position of the opener of the block comment at `row`, `column`, None if
+    /// it isn't in one. Delimiters inside strings aren't told apart.
+    fn editor_enclosing_block_comment(&self, row: usize, column: usize) -> Option<(usize, usize)> {
+        let extension = self.editor_file_extension().unwrap_or_default();
+        let (opener, closer) = block_comment(&extension)?;
+        let mut end = Some(column);
+        for row in (0..=row.min(self.get_num_rows().checked_sub(1)?)).rev() {
+            let chars = &self.buffer.rows[row].chars;
+            let text = &chars[..end.take().unwrap_or(chars.len()).min(chars.len())];
+            match (text.rfind(opener), text.rfind(closer)) {
+                // A closer overlapping the opener, as in `/*/`, doesn't close it
+                (Some(open), Some(close)) if close >= open + opener.len() => return None,
+                (Some(open), _) => return Some((row, open)),
+                (None, Some(_)) => return None,
+                (None, None) => {}
+            }
+        }
+        None
+    }
+
+    /// Position of the closer of the block comment opened at `row`, `column`
+    fn editor_block_comment_closer(&self, row: usize, column: usize) -> Option<(usize, usize)> {
+        let extension = self.editor_file_extension().unwrap_or_default();
+        let (opener, closer) = block_comment(&extension)?;
+        let mut start = column + opener.len();
+        for row in row..self.get_num_rows() {
+            let chars = &self.buffer.rows[row].chars;
+            if let Some(at) = chars.get(start..).and_then(|rest| rest.find(closer)) {
+                return Some((row, start + at));
+            }
+            start = 0;
+        }
+        None
+    }
+
+    /// Enter inside a `/* */` comment: starts the new line with ` * `, and
+    /// between a just typed `/*` and `*/` opens the comment up over three
+    /// lines. False if the cursor isn't in a comment.
+    fn editor_continue_block_comment(&mut self) -> bool {
+        let (row, column) = (self.buffer.cursor_y, self.buffer.cursor_x);
+        let (open_row, open_column) = match self.editor_enclosing_block_comment(row, column) {
+            Some(opener) => opener,
+            None => return false,
+        };
+        let chars = self.buffer.rows[row].chars.clone();
+        let (before, after) = chars.split_at(column);
+
+        // Stars line up under the first one of the opener
+        let indent: String = self.buffer.rows[open_row].chars[..open_column]
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .collect();
+        let prefix = format!("{} * ", indent);
+        let after = after.trim_start();
+        let new_rows = if after.starts_with("*/") && open_row == row {
+            let closing = format!("{} {}", indent, after);
+            vec![before.trim_end().to_string(), prefix.clone(), closing]
+        } else if after.starts_with("*/") {
+            vec![
+                before.trim_end().to_string(),
+                format!("{} {}", indent, after),
+            ]
+        } else {
+            vec![
+                before.trim_end().to_string(),
+                format!("{}{}", prefix, after),
+            ]
+        };
+
+        let cursor_x = if new_rows.len() == 3 || !after.starts_with("*/") {
+            prefix.len()
+        } else {
+            indent.len() + 1
+        };
+        self.editor_replace_rows(row, 1, new_rows);
+        self.editor_goto(row + 1, cursor_x);
+        true
+    }
+
+    /// `commentjump`: moves from inside a block comment or its opener to its
+    /// closer, and from the closer back to the opener
+    pub fn editor_jump_block_comment(&mut self) {
+        let extension = self.editor_file_extension().unwrap_or_default();
+        let (opener, closer) = match block_comment(&extension) {
+            Some(delimiters) => delimiters,
+            None => {
+                self.editor_set_status_message("No block comments in this file type");
+                return;
+            }
+        };
+        let (row, column) = (self.buffer.cursor_y, self.buffer.cursor_x);
+        let chars = match self.buffer.rows.get(row) {
+            Some(row) => &row.chars,
+            None => return,
+        };
+
+        // On the closer: back to the opener
+        let closer_start = (column.saturating_sub(closer.len() - 1)..=column)
+            .find(|&at| chars.get(at..).is_some_and(|rest| rest.starts_with(closer)));
+        if let Some(at) = closer_start {
+            if let Some((open_row, open_column)) = self.editor_enclosing_block_comment(row, at) {
+                self.editor_goto(open_row, open_column);
+                return;
+            }
+        }
+
+        // On the opener or inside: on to the closer
+        let opener_start = (column.saturating_sub(opener.len() - 1)..=column)
+            .find(|&at| chars.get(at..).is_some_and(|rest| rest.starts_with(opener)));
+        let opened = opener_start
+            .map(|at| (row, at))
+            .or_else(|| self.editor_enclosing_block_comment(row, column));
+        match opened.and_then(|(row, column)| self.editor_block_comment_closer(row, column)) {
+            Some((close_row, close_column)) => self.editor_goto(close_row, close_column),
+            None if opened.is_some() => self.editor_set_status_message("Comment is not closed"),
+            None => self.editor_set_status_message("Not in a block comment"),
+        }
+    }
+}
@@ -0,0 +1,702 @@
+//! Completion popup (Ctrl-O) fed by pluggable sources.
+//!
+//! A source implements `CompletionSource` and returns candidates for the text
+//! before the cursor; the popup ranks, filters and inserts them. Sources are
+//! chosen per filetype with `completion EXTENSION SOURCE,...`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::frame::Frame;
+use crate::fuzzy::{fuzzy_match, highlight_matches, FuzzyMatch};
+use crate::*;
+
+/// Candidates shown in the popup at once
+const POPUP_HEIGHT: usize = 10;
+/// Priority of language server completions, which come in after the others
+const LSP_PRIORITY: i64 = 3;
+
+pub struct Candidate {
+    /// Shown in the popup and matched against what was typed
+    pub label: String,
+    /// Inserted in place of the typed prefix
+    pub text: String,
+    /// Shown after the label, e.g. a type or the source name
+    pub detail: String,
+    /// Bytes before the cursor the candidate replaces
+    pub prefix_len: usize,
+    /// Open the popup again once it is inserted, as after a directory
+    pub reopen: bool,
+}
+
+pub trait CompletionSource {
+    /// Name used by the `completion` command
+    fn name(&self) -> &'static str;
+
+    /// Whether the source is used for files with `extension` unless the
+    /// `completion` command says otherwise
+    fn enabled_by_default(&self, _extension: &str) -> bool {
+        true
+    }
+
+    /// Breaks ties between equally good matches, higher first
+    fn priority(&self) -> i64 {
+        0
+    }
+
+    /// Candidates for `before_cursor`, the text on the cursor row before the cursor
+    fn candidates(&mut self, editor: &mut Editor, before_cursor: &str) -> Vec<Candidate>;
+}
+
+pub struct Completion {
+    sources: Vec<Box<dyn CompletionSource>>,
+    /// Source names per file extension, set with `completion`
+    filetypes: HashMap<String, Vec<String>>,
+    /// Trigger to body, set with `snippet`
+    pub snippets: HashMap<String, String>,
+    popup: Option<Popup>,
+}
+
+/// What the popup shows while it is open
+struct Popup {
+    /// Label, detail and the label's matched chars
+    items: Vec<(String, String, Vec<usize>)>,
+    selected: usize,
+    /// Chars between the start of the typed prefix and the cursor
+    prefix_chars: usize,
+}
+
+impl Default for Completion {
+    fn default() -> Self {
+        Completion {
+            sources: vec![
+                Box::new(LspSource),
+                Box::new(SnippetSource),
+                Box::new(BufferWordSource),
+                Box::new(ProjectWordSource),
+                Box::new(PathSource),
+                Box::new(DictionarySource),
+            ],
+            filetypes: HashMap::new(),
+            snippets: HashMap::new(),
+            popup: None,
+        }
+    }
+}
+
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Identifier being typed at the end of `before_cursor`
+pub fn word_prefix(before_cursor: &str) -> &str {
+    let start = before_cursor
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_word_char(c))
+        .last()
+        .map_or(before_cursor.len(), |(i, _)| i);
+    &before_cursor[start..]
+}
+
+/// Ranks `candidates` against the text before the cursor, with `typed` bytes
+/// added since they were asked for, best first, dropping those that don't
+/// match and duplicates
+fn rank(
+    candidates: &[(Candidate, i64)],
+    before_cursor: &str,
+    typed: usize,
+) -> Vec<(usize, FuzzyMatch)> {
+    let mut scored: Vec<(FuzzyMatch, i64, usize)> = candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (candidate, priority))| {
+            let start = before_cursor
+                .len()
+                .checked_sub(candidate.prefix_len + typed)?;
+            let query = before_cursor.get(start..)?;
+            if candidate.label == query {
+                return None;
+            }
+            let matched = fuzzy_match(query, &candidate.label)?;
+            Some((matched, *priority, i))
+        })
+        .collect();
+    // Shorter labels are closer to what was typed
+    scored.sort_by(|a, b| {
+        let (a_label, b_label) = (&candidates[a.2].0.label, &candidates[b.2].0.label);
+        b.0.score
+            .cmp(&a.0.score)
+            .then(b.1.cmp(&a.1))
+            .then(a_label.len().cmp(&b_label.len()))
+            .then(a_label.cmp(b_label))
+    });
+
+    let mut seen = std::collections::HashSet::new();
+    scored
+        .into_iter()
+        .filter(|(_, _, i)| seen.insert((&candidates[*i].0.label, &candidates[*i].0.text)))
+        .map(|(matched, _, i)| (i, matched))
+        .collect()
+}
+
+/// Words in the buffer
+struct BufferWordSource;
+
+impl CompletionSource for BufferWordSource {
+    fn name(&self) -> &'static str {
+        "words"
+    }
+
+    fn priority(&self) -> i64 {
+        1
+    }
+
+    fn candidates(&mut self, editor: &mut Editor, before_cursor: &str) -> Vec<Candidate> {
+        let prefix = word_prefix(before_cursor);
+        if prefix.is_empty() {
+            return vec![];
+        }
+
+        let mut words: Vec<&str> = editor
+            .buffer
+            .rows
+            .iter()
+            .flat_map(|row| row.chars.split(|c: char| !is_word_char(c)))
+            .filter(|word| word.chars().count() > 2)
+            .collect();
+        words.sort_unstable();
+        words.dedup();
+        words
+            .into_iter()
+            .map(|word| Candidate {
+                label: word.to_string(),
+                text: word.to_string(),
+                detail: String::new(),
+                prefix_len: prefix.len(),
+                reopen: false,
+            })
+            .collect()
+    }
+}
+
+/// Words from the other files of the project, the most frequent first
+struct ProjectWordSource;
+
+impl CompletionSource for ProjectWordSource {
+    fn name(&self) -> &'static str {
+        "project"
+    }
+
+    fn candidates(&mut self, editor: &mut Editor, before_cursor: &str) -> Vec<Candidate> {
+        let prefix = word_prefix(before_cursor);
+        let first = match prefix.chars().next() {
+            Some(first) if editor.options.project_index => first,
+            _ => return vec![],
+        };
+        editor
+            .index
+            .words(first, 500)
+            .into_iter()
+            .map(|(word, _)| Candidate {
+                label: word.clone(),
+                text: word,
+                detail: "project".to_string(),
+                prefix_len: prefix.len(),
+                reopen: false,
+            })
+            .collect()
+    }
+}
+
+/// The directory `dir` names, with a leading `~/`, `$NAME/` or `${NAME}/`
+/// expanded as the shell would
+fn expand_dir(dir: &str) -> Option<PathBuf> {
+    if let Some(rest) = dir.strip_prefix("~/") {
+        return Some(Path::new(&std::env::var_os("HOME")?).join(rest));
+    }
+    if let Some(rest) = dir.strip_prefix('$') {
+        let (name, rest) = match rest.strip_prefix('{') {
+            Some(braced) => braced.split_once('}')?,
+            None => rest.split_at(rest.find(|c: char| !is_word_char(c))?),
+        };
+        let rest = rest.strip_prefix('/')?;
+        return Some(Path::new(&std::env::var_os(name)?).join(rest));
+    }
+    Some(Path::new(dir).to_path_buf())
+}
+
+/// Files and directories, once the text before the cursor looks like a path.
+/// Picking a directory lists what is in it next.
+struct PathSource;
+
+impl CompletionSource for PathSource {
+    fn name(&self) -> &'static str {
+        "paths"
+    }
+
+    fn candidates(&mut self, _editor: &mut Editor, before_cursor: &str) -> Vec<Candidate> {
+        let start = before_cursor
+            .rfind(|c: char| c.is_whitespace() || "\"'`()<>=,".contains(c))
+            .map_or(0, |i| i + 1);
+        let path = &before_cursor[start..];
+        let (dir, name) = match path.rfind('/') {
+            Some(slash) => (&path[..=slash], &path[slash + 1..]),
+            None => return vec![],
+        };
+
+        let dir_path = match expand_dir(dir) {
+            Some(dir_path) => dir_path,
+            None => return vec![],
+        };
+        let entries = match std::fs::read_dir(&dir_path) {
+            Ok(entries) => entries,
+            Err(_) => return vec![],
+        };
+
+        entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let mut label = entry.file_name().to_str()?.to_string();
+                // Hidden files only when asked for
+                if label.starts_with('.') && !name.starts_with('.') {
+                    return None;
+                }
+                let is_dir = entry.file_type().ok()?.is_dir();
+                if is_dir {
+                    label.push('/');
+                }
+                Some(Candidate {
+                    text: label.clone(),
+                    label,
+                    detail: if is_dir { "dir" } else { "file" }.to_string(),
+                    prefix_len: name.len(),
+                    reopen: is_dir,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Snippets defined with `snippet TRIGGER BODY`
+struct SnippetSource;
+
+impl CompletionSource for SnippetSource {
+    fn name(&self) -> &'static str {
+        "snippets"
+    }
+
+    fn priority(&self) -> i64 {
+        2
+    }
+
+    fn candidates(&mut self, editor: &mut Editor, before_cursor: &str) -> Vec<Candidate> {
+        let prefix = word_prefix(before_cursor);
+        editor
+            .completion
+            .snippets
+            .iter()
+            .map(|(trigger, body)| Candidate {
+                label: trigger.clone(),
+                text: body.clone(),
+                detail: "snippet".to_string(),
+                prefix_len: prefix.len(),
+                reopen: false,
+            })
+            .collect()
+    }
+}
+
+/// Completions from the running language server. The server is only asked
+/// here, its answer joins the popup once it comes in.
+struct LspSource;
+
+impl CompletionSource for LspSource {
+    fn name(&self) -> &'static str {
+        "lsp"
+    }
+
+    fn priority(&self) -> i64 {
+        LSP_PRIORITY
+    }
+
+    fn candidates(&mut self, editor: &mut Editor, _before_cursor: &str) -> Vec<Candidate> {
+        editor.editor_lsp_request_completions();
+        vec![]
+    }
+}
+
+/// Words from the spell checking word list, for prose
+struct DictionarySource;
+
+impl CompletionSource for DictionarySource {
+    fn name(&self) -> &'static str {
+        "dictionary"
+    }
+
+    fn enabled_by_default(&self, extension: &str) -> bool {
+        spell::comment_marker(extension).is_none()
+    }
+
+    fn candidates(&mut self, editor: &mut Editor, before_cursor: &str) -> Vec<Candidate> {
+        let prefix = word_prefix(before_cursor);
+        // The word list is large, only look at words sharing a prefix
+        if prefix.chars().count() < 2 {
+            return vec![];
+        }
+        editor
+            .editor_dictionary_words(prefix)
+            .into_iter()
+            .map(|word| Candidate {
+                label: word.clone(),
+                text: word,
+                detail: "dict".to_string(),
+                prefix_len: prefix.len(),
+                reopen: false,
+            })
+            .collect()
+    }
+}
+
+/// Turns `\n` and `\t` in a snippet definition into newlines and tabs
+fn unescape_snippet(body: &str) -> String {
+    let mut result = String::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                result.push('\n');
+                chars.next();
+            }
+            ('\\', Some('t')) => {
+                result.push('\t');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                result.push('\\');
+                chars.next();
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+impl Editor {
+    /// Adds a source after the built-in ones
+    pub fn editor_add_completion_source(&mut self, source: Box<dyn CompletionSource>) {
+        self.completion.sources.push(source);
+    }
+
+    /// Whether source `name` is used for the current file
+    fn editor_completion_source_enabled(&self, source: &dyn CompletionSource) -> bool {
+        let extension = self.editor_file_extension().unwrap_or_default();
+        match self.completion.filetypes.get(&extension) {
+            Some(names) => names.iter().any(|name| name == source.name()),
+            None => source.enabled_by_default(&extension),
+        }
+    }
+
+    fn editor_text_before_cursor(&self) -> String {
+        match self.buffer.rows.get(self.buffer.cursor_y) {
+            Some(row) => {
+                row.chars[..floor_char_boundary(&row.chars, self.buffer.cursor_x)].to_string()
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Replaces the `len` bytes before the cursor with `text` as one undo step
+    pub fn editor_replace_before_cursor(&mut self, len: usize, text: &str) {
+        let row = match self.buffer.rows.get(self.buffer.cursor_y) {
+            Some(row) => &row.chars,
+            None => return,
+        };
+        let at = floor_char_boundary(row, self.buffer.cursor_x);
+        let start = floor_char_boundary(row, at.saturating_sub(len));
+        let (before, after) = (row[..start].to_string(), row[at..].to_string());
+
+        let mut new_rows: Vec<String> = text.split('\n').map(str::to_string).collect();
+        let last = new_rows.len() - 1;
+        let cursor_x = if last == 0 {
+            before.len() + new_rows[last].len()
+        } else {
+            new_rows[last].len()
+        };
+        new_rows[0].insert_str(0, &before);
+        new_rows[last].push_str(&after);
+
+        self.editor_replace_rows(self.buffer.cursor_y, 1, new_rows);
+        self.buffer.cursor_y += last;
+        self.buffer.cursor_x = cursor_x;
+    }
+
+    /// Ctrl-O: completes the word before the cursor from the enabled sources.
+    /// Typing narrows the list, Up/Down select, Tab/Enter insert, Escape closes.
+    pub fn editor_complete(&mut self) {
+        let before_cursor = self.editor_text_before_cursor();
+
+        // Sources get the editor, so they are moved out while they run
+        let mut sources = std::mem::take(&mut self.completion.sources);
+        let mut candidates: Vec<(Candidate, i64)> = vec![];
+        for source in sources.iter_mut() {
+            if self.editor_completion_source_enabled(source.as_ref()) {
+                let priority = source.priority();
+                candidates.extend(
+                    source
+                        .candidates(self, &before_cursor)
+                        .into_iter()
+                        .map(|candidate| (candidate, priority)),
+                );
+            }
+        }
+        sources.append(&mut self.completion.sources);
+        self.completion.sources = sources;
+
+        let start_x = self.buffer.cursor_x;
+        let lsp_prefix_len = word_prefix(&before_cursor).len();
+        let mut selected = 0;
+        loop {
+            if let Some(items) = self.editor_lsp_take_completions() {
+                candidates.extend(items.into_iter().map(|(label, text, detail)| {
+                    let candidate = Candidate {
+                        label,
+                        text,
+                        detail,
+                        prefix_len: lsp_prefix_len,
+                        reopen: false,
+                    };
+                    (candidate, LSP_PRIORITY)
+                }));
+            }
+            let before_cursor = self.editor_text_before_cursor();
+            let typed = self.buffer.cursor_x - start_x;
+            let ranked = rank(&candidates, &before_cursor, typed);
+            if ranked.is_empty() {
+                self.completion.popup = None;
+                if !self.editor_lsp_completion_pending() {
+                    self.editor_set_status_message("No completions");
+                    return;
+                }
+                self.editor_set_status_message("Waiting for the language server...");
+                self.editor_refresh_screen();
+                match self.editor_read_completion_key() {
+                    None => continue,
+                    Some(key) => {
+                        self.editor_set_status_message("");
+                        self.pending_keys.push_front(key);
+                        break;
+                    }
+                }
+            }
+            selected = selected.min(ranked.len() - 1);
+
+            let longest_prefix = ranked
+                .iter()
+                .map(|&(i, _)| candidates[i].0.prefix_len + typed)
+                .max()
+                .unwrap_or(0);
+            let prefix_start = floor_char_boundary(
+                &before_cursor,
+                before_cursor.len().saturating_sub(longest_prefix),
+            );
+            self.completion.popup = Some(Popup {
+                items: ranked
+                    .iter()
+                    .map(|(i, matched)| {
+                        let candidate = &candidates[*i].0;
+                        (
+                            candidate.label.clone(),
+                            candidate.detail.clone(),
+                            matched.positions.clone(),
+                        )
+                    })
+                    .collect(),
+                selected,
+                prefix_chars: before_cursor[prefix_start..].chars().count(),
+            });
+            self.editor_refresh_screen();
+
+            let key = match self.editor_read_completion_key() {
+                Some(key) => key,
+                None => continue,
+            };
+            match key {
+                Key::Up => selected = selected.checked_sub(1).unwrap_or(ranked.len() - 1),
+                Key::Down => selected = (selected + 1) % ranked.len(),
+                Key::Tab | Key::Enter => {
+                    let candidate = &candidates[ranked[selected].0].0;
+                    let text = candidate.text.clone();
+                    let prefix_len = candidate.prefix_len + self.buffer.cursor_x - start_x;
+                    let reopen = candidate.reopen;
+                    self.editor_replace_before_cursor(prefix_len, &text);
+                    self.completion.popup = None;
+                    self.editor_lsp_cancel_completions();
+                    if reopen {
+                        self.editor_complete();
+                    }
+                    return;
+                }
+                Key::Escape => break,
+                Key::Backspace if self.buffer.cursor_x > start_x => {
+                    self.editor_delete_char();
+                }
+                // Other chars narrow the list too while a candidate has them,
+                // like the dot of a file name
+                Key::Char(c)
+                    if is_word_char(c)
+                        || (c != '/'
+                            && ranked
+                                .iter()
+                                .any(|&(i, _)| candidates[i].0.label.contains(c))) =>
+                {
+                    self.editor_insert_char(c);
+                }
+                _ => {
+                    // Any other key closes the popup and does what it usually does
+                    self.pending_keys.push_front(key);
+                    break;
+                }
+            }
+        }
+        self.completion.popup = None;
+        self.editor_lsp_cancel_completions();
+    }
+
+    /// Reads a key for the popup, or None if completions from the language
+    /// server came in while waiting for one
+    fn editor_read_completion_key(&mut self) -> Option<Key> {
+        while !self.headless
+            && self.pending_keys.is_empty()
+            && self.input.is_empty()
+            && self.editor_lsp_completion_pending()
+        {
+            if !self.editor_fill_input(IDLE_INTERVAL) {
+                self.editor_idle();
+                if !self.editor_lsp_completion_pending() {
+                    return None;
+                }
+            }
+        }
+        Some(self.editor_read_key())
+    }
+
+    /// Draws the completion popup below the cursor (above it near the bottom)
+    pub fn editor_draw_completion_popup(&self, frame: &mut Frame) {
+        let popup = match &self.completion.popup {
+            Some(popup) => popup,
+            None => return,
+        };
+        let (cursor_y, cursor_x) = match frame.cursor {
+            Some(cursor) => cursor,
+            None => return,
+        };
+
+        let offset = popup.selected.saturating_sub(POPUP_HEIGHT - 1);
+        let items = &popup.items[offset..popup.items.len().min(offset + POPUP_HEIGHT)];
+        let label_width = items
+            .iter()
+            .map(|(label, _, _)| label.chars().count())
+            .max();
+        let detail_width = items
+            .iter()
+            .map(|(_, detail, _)| detail.chars().count())
+            .max();
+        let label_width = label_width.unwrap_or(0);
+        let width = (label_width + detail_width.unwrap_or(0) + 3).min(self.screen_num_columns);
+
+        let top = if cursor_y + items.len() < self.screen_num_rows {
+            cursor_y + 1
+        } else {
+            cursor_y.saturating_sub(items.len())
+        };
+        let column = cursor_x
+            .saturating_sub(popup.prefix_chars)
+            .min(self.screen_num_columns.saturating_sub(width));
+
+        for (i, (label, detail, positions)) in items.iter().enumerate() {
+            let style = if offset + i == popup.selected {
+                CellStyle {
+                    inverse: true,
+                    ..CellStyle::default()
+                }
+            } else {
+                CellStyle {
+                    bg: Some(self.theme.completion),
+                    ..CellStyle::default()
+                }
+            };
+            let text = format!(" {:<label_width$} {} ", label, detail);
+            let text: String = format!("{:<width$}", text).chars().take(width).collect();
+            // Matched positions are shifted by the leading space
+            let positions: Vec<usize> = positions.iter().map(|position| position + 1).collect();
+            let styles = highlight_matches(text.chars().count(), &positions, style);
+            if top + i >= self.screen_num_rows {
+                break;
+            }
+            frame.lines[top + i].overlay(column, &text, &styles);
+        }
+    }
+
+    /// `completion [EXTENSION SOURCE,...]` sets the sources for a filetype,
+    /// without arguments shows the ones used for the current file
+    pub fn editor_completion_command(&mut self, args: &str) {
+        match args.split_once(char::is_whitespace) {
+            Some((extension, names)) => {
+                let names: Vec<String> = names
+                    .split(',')
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty())
+                    .collect();
+                if let Some(unknown) = names.iter().find(|name| {
+                    !self
+                        .completion
+                        .sources
+                        .iter()
+                        .any(|source| source.name() == *name)
+                }) {
+                    let message = format!("Unknown completion source: {}", unknown);
+                    self.editor_set_error_message(&message);
+                    return;
+                }
+                self.completion
+                    .filetypes
+                    .insert(extension.trim_start_matches('.').to_string(), names);
+            }
+            None if args.is_empty() => {
+                let enabled: Vec<&str> = self
+                    .completion
+                    .sources
+                    .iter()
+                    .filter(|source| self.editor_completion_source_enabled(source.as_ref()))
+                    .map(|source| source.name())
+                    .collect();
+                let all: Vec<&str> = self
+                    .completion
+                    .sources
+                    .iter()
+                    .map(|source| source.name())
+                    .collect();
+                self.editor_set_status_message(&format!(
+                    "Completion sources: {} (available: {})",
+                    enabled.join(","),
+                    all.join(",")
+                ));
+            }
+            None => self.editor_set_error_message("Usage: completion EXTENSION SOURCE,..."),
+        }
+    }
+
+    /// `snippet TRIGGER BODY` defines a snippet, `\n` and `\t` in the body
+    /// stand for a newline and a tab
+    pub fn editor_define_snippet(&mut self, args: &str) {
+        match args.split_once(char::is_whitespace) {
+            Some((trigger, body)) => {
+                self.completion
+                    .snippets
+                    .insert(trigger.to_string(), unescape_snippet(body.trim_start()));
+            }
+            None => self.editor_set_error_message("Usage: snippet TRIGGER BODY"),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! Config file: one command per line, run at startup
+
+use std::path::{Path, PathBuf};
+
+use crate::storage::config_dir;
+use crate::*;
+
+/// $KILO_CONFIG if set, else ~/.kilorc if there is one, else kilorc in the
+/// config directory
+fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("KILO_CONFIG").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let home = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".kilorc"));
+    match home {
+        Some(path) if path.exists() => Some(path),
+        _ => config_dir().map(|dir| dir.join("kilorc")),
+    }
+}
+
+impl Editor {
+    /// Runs every command in the config file, or in `path`, lines starting
+    /// with # are comments
+    pub fn editor_load_config(&mut self, path: Option<&Path>) {
+        let path = path.map(Path::to_path_buf).or_else(config_path);
+        let contents = match path.map(std::fs::read_to_string) {
+            Some(Ok(contents)) => contents,
+            _ => return,
+        };
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.editor_execute_command(line);
+        }
+    }
+}
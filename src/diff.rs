@@ -0,0 +1,421 @@
+//! Line based diffing (Myers' O(ND) algorithm, in linear space)
+
+use crate::overlay::OverlayLine;
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOp {
+    /// Line present in both, (old index, new index)
+    Equal(usize, usize),
+    /// Line only in old, old index
+    Delete(usize),
+    /// Line only in new, new index
+    Insert(usize),
+}
+
+pub fn diff_lines<S: AsRef<str>, T: AsRef<str>>(old: &[S], new: &[T]) -> Vec<DiffOp> {
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    diff_range(old, new, 0, 0, &mut ops);
+    ops
+}
+
+/// Appends the edit script turning `old` into `new`, slices starting at
+/// `old_start` and `new_start` of the whole files, to `ops`. Splits the
+/// changed middle at a snake of the shortest script and recurses, which
+/// takes memory linear in the input rather than in the number of changes.
+fn diff_range<S: AsRef<str>, T: AsRef<str>>(
+    old: &[S],
+    new: &[T],
+    old_start: usize,
+    new_start: usize,
+    ops: &mut Vec<DiffOp>,
+) {
+    // Strip the common prefix and suffix so the search only covers the changed middle
+    let mut prefix = 0;
+    while prefix < old.len() && prefix < new.len() && old[prefix].as_ref() == new[prefix].as_ref() {
+        prefix += 1;
+    }
+
+    let mut suffix = 0;
+    while suffix < old.len() - prefix
+        && suffix < new.len() - prefix
+        && old[old.len() - 1 - suffix].as_ref() == new[new.len() - 1 - suffix].as_ref()
+    {
+        suffix += 1;
+    }
+
+    ops.extend((0..prefix).map(|i| DiffOp::Equal(old_start + i, new_start + i)));
+
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+    let (old_mid_start, new_mid_start) = (old_start + prefix, new_start + prefix);
+    if old_mid.is_empty() {
+        ops.extend((0..new_mid.len()).map(|i| DiffOp::Insert(new_mid_start + i)));
+    } else if new_mid.is_empty() {
+        ops.extend((0..old_mid.len()).map(|i| DiffOp::Delete(old_mid_start + i)));
+    } else {
+        let (x, y, u, v) = middle_snake(old_mid, new_mid);
+        diff_range(
+            &old_mid[..x],
+            &new_mid[..y],
+            old_mid_start,
+            new_mid_start,
+            ops,
+        );
+        ops.extend((0..u - x).map(|i| DiffOp::Equal(old_mid_start + x + i, new_mid_start + y + i)));
+        diff_range(
+            &old_mid[u..],
+            &new_mid[v..],
+            old_mid_start + u,
+            new_mid_start + v,
+            ops,
+        );
+    }
+
+    ops.extend((0..suffix).map(|i| {
+        DiffOp::Equal(
+            old_start + old.len() - suffix + i,
+            new_start + new.len() - suffix + i,
+        )
+    }));
+}
+
+/// The middle snake of a shortest edit script between `old` and `new`
+/// (Myers' linear space refinement), as its start `(x, y)` and end `(u, v)`.
+/// Searches from both ends at once until the paths meet.
+fn middle_snake<S: AsRef<str>, T: AsRef<str>>(
+    old: &[S],
+    new: &[T],
+) -> (usize, usize, usize, usize) {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    // Furthest x on each diagonal k = x - y, going forward from the start and
+    // backward from the end (counted from the end)
+    let mut forward = vec![0isize; 2 * offset as usize + 1];
+    let mut backward = vec![0isize; 2 * offset as usize + 1];
+    let at = |k: isize| (k + offset) as usize;
+
+    for d in 0..=max {
+        let mut k = -d;
+        while k <= d {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (start_x, start_y) = (x, y);
+            while x < n && y < m && old[x as usize].as_ref() == new[y as usize].as_ref() {
+                x += 1;
+                y += 1;
+            }
+            forward[at(k)] = x;
+            // The backward paths on this diagonal went d - 1 steps
+            let back_k = delta - k;
+            if odd && (-(d - 1)..=d - 1).contains(&back_k) && x + backward[at(back_k)] >= n {
+                return (start_x as usize, start_y as usize, x as usize, y as usize);
+            }
+            k += 2;
+        }
+
+        let mut k = -d;
+        while k <= d {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (start_x, start_y) = (x, y);
+            while x < n
+                && y < m
+                && old[(n - 1 - x) as usize].as_ref() == new[(m - 1 - y) as usize].as_ref()
+            {
+                x += 1;
+                y += 1;
+            }
+            backward[at(k)] = x;
+            let forward_k = delta - k;
+            if !odd && (-d..=d).contains(&forward_k) && x + forward[at(forward_k)] >= n {
+                return (
+                    (n - x) as usize,
+                    (m - y) as usize,
+                    (n - start_x) as usize,
+                    (m - start_y) as usize,
+                );
+            }
+            k += 2;
+        }
+    }
+    unreachable!("the forward and backward paths always meet")
+}
+
+/// Formats the edit script as unified diff hunks (without the file header)
+pub fn unified_hunks<S: AsRef<str>, T: AsRef<str>>(
+    old: &[S],
+    new: &[T],
+    ops: &[DiffOp],
+    context: usize,
+) -> Vec<String> {
+    let mut lines = vec![];
+
+    let changed: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut i = 0;
+    while i < changed.len() {
+        // Group changes whose context windows overlap into one hunk
+        let start = changed[i].saturating_sub(context);
+        let mut end = changed[i];
+        while i + 1 < changed.len() && changed[i + 1] <= end + 2 * context + 1 {
+            i += 1;
+            end = changed[i];
+        }
+        let end = (end + context + 1).min(ops.len());
+        i += 1;
+
+        let (mut old_start, mut new_start) = (None, None);
+        let (mut old_count, mut new_count) = (0, 0);
+        let mut body = vec![];
+        for op in &ops[start..end] {
+            match *op {
+                DiffOp::Equal(o, n) => {
+                    old_start.get_or_insert(o);
+                    new_start.get_or_insert(n);
+                    old_count += 1;
+                    new_count += 1;
+                    body.push(format!(" {}", old[o].as_ref()));
+                }
+                DiffOp::Delete(o) => {
+                    old_start.get_or_insert(o);
+                    old_count += 1;
+                    body.push(format!("-{}", old[o].as_ref()));
+                }
+                DiffOp::Insert(n) => {
+                    new_start.get_or_insert(n);
+                    new_count += 1;
+                    body.push(format!("+{}", new[n].as_ref()));
+                }
+            }
+        }
+
+        let old_start = old_start.unwrap_or_else(|| hunk_anchor(ops, start, true));
+        let new_start = new_start.unwrap_or_else(|| hunk_anchor(ops, start, false));
+        lines.push(format!(
+            "@@ -{} +{} @@",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        lines.append(&mut body);
+    }
+
+    lines
+}
+
+/// Position in the old (or new) file an empty side of a hunk is attached to
+fn hunk_anchor(ops: &[DiffOp], before: usize, old_side: bool) -> usize {
+    let mut count = 0;
+    for op in &ops[..before] {
+        match op {
+            DiffOp::Equal(..) => count += 1,
+            DiffOp::Delete(_) if old_side => count += 1,
+            DiffOp::Insert(_) if !old_side => count += 1,
+            _ => {}
+        }
+    }
+    count
+}
+
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        // An empty range refers to the line before the hunk
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+impl Editor {
+    /// Shows a unified diff of the on-disk file against the buffer
+    pub fn editor_diff_saved(&mut self) {
+        let filename = match &self.buffer.filename {
+            Some(filename) => filename.clone(),
+            None => {
+                self.editor_set_status_message("No file name, nothing to diff against");
+                return;
+            }
+        };
+
+        // Read as the buffer was, in its encoding and line endings
+        let format = self.buffer.file_format;
+        let saved = match std::fs::read(&filename) {
+            Ok(bytes) => match format.decode(&bytes) {
+                Ok(contents) => format.split_lines(&contents),
+                Err(error) => {
+                    self.editor_set_error_message(&format!("Error reading file: {}", error));
+                    return;
+                }
+            },
+            Err(error) if error.kind() == ErrorKind::NotFound => vec![],
+            Err(error) => {
+                self.editor_set_error_message(&format!("Error reading file: {}", error));
+                return;
+            }
+        };
+        let current: Vec<&str> = self
+            .buffer
+            .rows
+            .iter()
+            .map(|row| row.chars.as_str())
+            .collect();
+
+        let ops = diff_lines(&saved, &current);
+        let hunks = unified_hunks(&saved, &current, &ops, 3);
+        if hunks.is_empty() {
+            self.editor_set_status_message("No changes since last save");
+            return;
+        }
+
+        let mut lines = vec![
+            OverlayLine::new(format!("--- {} (saved)", filename)),
+            OverlayLine::new(format!("+++ {} (buffer)", filename)),
+        ];
+        for hunk_line in hunks {
+            lines.push(match hunk_line.as_bytes()[0] {
+                b'+' => OverlayLine::colored(hunk_line, Color::GREEN),
+                b'-' => OverlayLine::colored(hunk_line, Color::RED),
+                b'@' => OverlayLine::colored(hunk_line, Color::CYAN),
+                _ => OverlayLine::new(hunk_line),
+            });
+        }
+
+        self.editor_show_overlay(&format!("Diff: {}", filename), &lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Length of the longest common subsequence, the Equal ops of a shortest
+    /// edit script
+    fn lcs_len(old: &[&str], new: &[&str]) -> usize {
+        let mut table = vec![vec![0; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                table[i][j] = if old[i] == new[j] {
+                    table[i + 1][j + 1] + 1
+                } else {
+                    table[i + 1][j].max(table[i][j + 1])
+                };
+            }
+        }
+        table[0][0]
+    }
+
+    /// Checks that `ops` walks both sides in order and keeps as many lines
+    /// as possible
+    fn check(old: &[&str], new: &[&str]) {
+        let ops = diff_lines(old, new);
+        let (mut o, mut n, mut equal) = (0, 0, 0);
+        for op in &ops {
+            match *op {
+                DiffOp::Equal(i, j) => {
+                    assert_eq!((i, j), (o, n), "{:?} -> {:?}", old, new);
+                    assert_eq!(old[i], new[j]);
+                    (o, n, equal) = (o + 1, n + 1, equal + 1);
+                }
+                DiffOp::Delete(i) => {
+                    assert_eq!(i, o);
+                    o += 1;
+                }
+                DiffOp::Insert(j) => {
+                    assert_eq!(j, n);
+                    n += 1;
+                }
+            }
+        }
+        assert_eq!((o, n), (old.len(), new.len()));
+        assert_eq!(equal, lcs_len(old, new), "{:?} -> {:?}", old, new);
+    }
+
+    #[test]
+    fn finds_a_shortest_script() {
+        check(&[], &[]);
+        check(&["a"], &[]);
+        check(&[], &["a", "b"]);
+        check(&["a", "b", "c"], &["a", "x", "c"]);
+        check(
+            &["a", "b", "c", "a", "b", "b", "a"],
+            &["c", "b", "a", "b", "a", "c"],
+        );
+
+        // Small random files over few distinct lines, so they share many
+        let mut seed = 12345u32;
+        let mut next = |bound: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) % bound
+        };
+        let words = ["a", "b", "c", "d"];
+        for _ in 0..500 {
+            let old: Vec<&str> = (0..next(12)).map(|_| words[next(4) as usize]).collect();
+            let new: Vec<&str> = (0..next(12)).map(|_| words[next(4) as usize]).collect();
+            check(&old, &new);
+        }
+    }
+
+    #[test]
+    fn diffs_many_changed_lines() {
+        let old: Vec<String> = (0..5000).map(|i| format!("line {}", i)).collect();
+        let new: Vec<String> = (0..5000)
+            .map(|i| match i % 5 {
+                0 => format!("changed {}", i),
+                _ => format!("line {}", i),
+            })
+            .collect();
+        let ops = diff_lines(&old, &new);
+        let equal = ops
+            .iter()
+            .filter(|op| matches!(op, DiffOp::Equal(..)))
+            .count();
+        assert_eq!(equal, 4000);
+        assert_eq!(ops.len(), 6000);
+    }
+
+    #[test]
+    fn formats_unified_hunks() {
+        let old = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+        let new = ["a", "B", "c", "d", "e", "f", "g", "h", "i", "j", "k"];
+        let ops = diff_lines(&old, &new);
+        assert_eq!(
+            unified_hunks(&old, &new, &ops, 1),
+            [
+                "@@ -1,3 +1,3 @@",
+                " a",
+                "-b",
+                "+B",
+                " c",
+                "@@ -10 +10,2 @@",
+                " j",
+                "+k",
+            ]
+        );
+        // Close changes share a hunk, an empty side points before it
+        let ops = diff_lines(&old, &new);
+        assert_eq!(unified_hunks(&old, &new, &ops, 5).len(), 13);
+        let ops = diff_lines(&["x"], &[] as &[&str]);
+        assert_eq!(
+            unified_hunks(&["x"], &[] as &[&str], &ops, 3),
+            ["@@ -1 +0,0 @@", "-x"]
+        );
+        assert!(unified_hunks(&old, &old, &diff_lines(&old, &old), 3).is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+//! Comparing the buffer in an external diff/merge tool
+
+use std::path::{Path, PathBuf};
+
+use crate::git::git_head_contents;
+use crate::shell::shell_quote;
+use crate::storage::create_private_temp;
+use crate::*;
+
+/// Writes `contents` to a temporary file only we can read, named after
+/// `filename` so the tool can detect its type
+fn write_temp(filename: &str, label: &str, contents: &str) -> io::Result<PathBuf> {
+    let name = Path::new(filename)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "buffer".to_string());
+    let (path, mut file) = create_private_temp(&format!("{}-{}-{}", process::id(), label, name))?;
+    file.write_all(contents.as_bytes())?;
+    Ok(path)
+}
+
+/// Builds the tool command line, `{old}` and `{new}` are replaced if present,
+/// otherwise both paths are appended
+fn tool_command(tool: &str, old: &Path, new: &Path) -> String {
+    let old = shell_quote(&old.to_string_lossy());
+    let new = shell_quote(&new.to_string_lossy());
+    if tool.contains("{old}") || tool.contains("{new}") {
+        tool.replace("{old}", &old).replace("{new}", &new)
+    } else {
+        format!("{} {} {}", tool, old, new)
+    }
+}
+
+impl Editor {
+    /// `difftool [head|disk]`: opens the buffer in the configured diff tool
+    /// against HEAD (default) or the saved file. Changes made to the buffer
+    /// side in the tool are loaded back as one undo step.
+    pub fn editor_difftool(&mut self, args: &str) {
+        let filename = match &self.buffer.filename {
+            Some(filename) => filename.clone(),
+            None => {
+                self.editor_set_status_message("No file name, nothing to diff against");
+                return;
+            }
+        };
+
+        let (label, base) = match args {
+            "" | "head" => ("HEAD", git_head_contents(&filename)),
+            "disk" => ("saved", std::fs::read_to_string(&filename).ok()),
+            _ => {
+                self.editor_set_error_message("Usage: difftool [head|disk]");
+                return;
+            }
+        };
+        let base = match base {
+            Some(base) => base,
+            None => {
+                self.editor_set_status_message(&format!("No {} version of {}", label, filename));
+                return;
+            }
+        };
+
+        let buffer = self.editor_rows_to_string();
+        let written = write_temp(&filename, label, &base).and_then(|base_path| {
+            match write_temp(&filename, "buffer", &buffer) {
+                Ok(buffer_path) => Ok((base_path, buffer_path)),
+                Err(error) => {
+                    let _ = std::fs::remove_file(&base_path);
+                    Err(error)
+                }
+            }
+        });
+        let (base_path, buffer_path) = match written {
+            Ok(paths) => paths,
+            Err(error) => {
+                let message = format!("Could not write temporary file: {}", error);
+                self.editor_set_error_message(&message);
+                return;
+            }
+        };
+
+        let command = tool_command(&self.options.diff_tool, &base_path, &buffer_path);
+        let status = self.editor_run_interactive(&command);
+        let edited = std::fs::read_to_string(&buffer_path);
+        let _ = std::fs::remove_file(&base_path);
+        let _ = std::fs::remove_file(&buffer_path);
+
+        match (status, edited) {
+            (None, _) => {}
+            (Some(_), Ok(edited)) if edited != buffer => {
+                let count = self.get_num_rows();
+                self.editor_replace_rows(0, count, split_lines(&edited));
+                self.editor_set_status_message("Buffer updated from diff tool");
+            }
+            (Some(status), Ok(_)) if !status.success() => {
+                self.editor_set_status_message(&format!("'{}' exited with {}", command, status))
+            }
+            (Some(_), Ok(_)) => self.editor_set_status_message("No changes from diff tool"),
+            (Some(_), Err(error)) => {
+                self.editor_set_error_message(&format!("Could not read back buffer: {}", error))
+            }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Typing chars that aren't on the keyboard: Ctrl-K or `unicode` asks for a
+//! code point (`U+00E9`, `0xe9`) or a two char digraph in the style of RFC
+//! 1345 and Vim (`e'` for é, `Eu` for €, `a*` for α) and inserts the char.
+//! `digraphs` lists them.
+
+use crate::history::HistoryKind;
+use crate::overlay::OverlayLine;
+use crate::*;
+
+/// Digraphs and the chars they stand for
+const DIGRAPHS: &[(&str, char)] = &[
+    // Accents: ' acute, ! grave, > circumflex, : diaeresis, ? tilde, , cedilla
+    ("a'", 'á'),
+    ("e'", 'é'),
+    ("i'", 'í'),
+    ("o'", 'ó'),
+    ("u'", 'ú'),
+    ("y'", 'ý'),
+    ("A'", 'Á'),
+    ("E'", 'É'),
+    ("I'", 'Í'),
+    ("O'", 'Ó'),
+    ("U'", 'Ú'),
+    ("a!", 'à'),
+    ("e!", 'è'),
+    ("i!", 'ì'),
+    ("o!", 'ò'),
+    ("u!", 'ù'),
+    ("A!", 'À'),
+    ("E!", 'È'),
+    ("I!", 'Ì'),
+    ("O!", 'Ò'),
+    ("U!", 'Ù'),
+    ("a>", 'â'),
+    ("e>", 'ê'),
+    ("i>", 'î'),
+    ("o>", 'ô'),
+    ("u>", 'û'),
+    ("A>", 'Â'),
+    ("E>", 'Ê'),
+    ("I>", 'Î'),
+    ("O>", 'Ô'),
+    ("U>", 'Û'),
+    ("a:", 'ä'),
+    ("e:", 'ë'),
+    ("i:", 'ï'),
+    ("o:", 'ö'),
+    ("u:", 'ü'),
+    ("y:", 'ÿ'),
+    ("A:", 'Ä'),
+    ("E:", 'Ë'),
+    ("I:", 'Ï'),
+    ("O:", 'Ö'),
+    ("U:", 'Ü'),
+    ("a?", 'ã'),
+    ("n?", 'ñ'),
+    ("o?", 'õ'),
+    ("A?", 'Ã'),
+    ("N?", 'Ñ'),
+    ("O?", 'Õ'),
+    ("c,", 'ç'),
+    ("C,", 'Ç'),
+    ("aa", 'å'),
+    ("AA", 'Å'),
+    ("ae", 'æ'),
+    ("AE", 'Æ'),
+    ("o/", 'ø'),
+    ("O/", 'Ø'),
+    ("oe", 'œ'),
+    ("OE", 'Œ'),
+    ("ss", 'ß'),
+    // Punctuation
+    ("!I", '¡'),
+    ("?I", '¿'),
+    ("<<", '«'),
+    (">>", '»'),
+    ("'6", '‘'),
+    ("'9", '’'),
+    ("\"6", '“'),
+    ("\"9", '”'),
+    ("-N", '–'),
+    ("-M", '—'),
+    (",.", '…'),
+    ("NS", '\u{a0}'),
+    ("SE", '§'),
+    ("PI", '¶'),
+    // Currency and signs
+    ("Eu", '€'),
+    ("Pd", '£'),
+    ("Ye", '¥'),
+    ("Ct", '¢'),
+    ("Co", '©'),
+    ("Rg", '®'),
+    ("TM", '™'),
+    ("DG", '°'),
+    ("My", 'µ'),
+    ("OK", '✓'),
+    ("XX", '✗'),
+    // Maths
+    ("+-", '±'),
+    ("*X", '×'),
+    ("-:", '÷'),
+    ("!=", '≠'),
+    ("=<", '≤'),
+    (">=", '≥'),
+    ("?2", '≈'),
+    ("00", '∞'),
+    ("RT", '√'),
+    ("12", '½'),
+    ("14", '¼'),
+    ("34", '¾'),
+    ("1S", '¹'),
+    ("2S", '²'),
+    ("3S", '³'),
+    // Arrows
+    ("<-", '←'),
+    ("->", '→'),
+    ("-!", '↑'),
+    ("-v", '↓'),
+    ("=>", '⇒'),
+    ("<=", '⇐'),
+    // Greek
+    ("a*", 'α'),
+    ("b*", 'β'),
+    ("g*", 'γ'),
+    ("d*", 'δ'),
+    ("e*", 'ε'),
+    ("l*", 'λ'),
+    ("m*", 'μ'),
+    ("p*", 'π'),
+    ("s*", 'σ'),
+    ("t*", 'τ'),
+    ("f*", 'φ'),
+    ("w*", 'ω'),
+    ("D*", 'Δ'),
+    ("S*", 'Σ'),
+    ("P*", 'Π'),
+    ("W*", 'Ω'),
+];
+
+/// The char `input` names: a code point as `U+XXXX`, `0xXXXX` or `\u{XXXX}`,
+/// a digraph, or a single char as it is
+fn parse_char(input: &str) -> Option<char> {
+    let hex = input
+        .strip_prefix("U+")
+        .or_else(|| input.strip_prefix("u+"))
+        .or_else(|| input.strip_prefix("0x"))
+        .or_else(|| {
+            input
+                .strip_prefix("\\u{")
+                .and_then(|hex| hex.strip_suffix('}'))
+        });
+    if let Some(hex) = hex {
+        return u32::from_str_radix(hex, 16).ok().and_then(char::from_u32);
+    }
+    if let Some(&(_, c)) = DIGRAPHS.iter().find(|(digraph, _)| *digraph == input) {
+        return Some(c);
+    }
+    let mut chars = input.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+impl Editor {
+    /// Ctrl-K / `unicode [CODE]`: inserts the char with a code point or
+    /// digraph, asking for it without one
+    pub fn editor_insert_unicode(&mut self, args: &str) {
+        let input = if args.is_empty() {
+            match self.editor_prompt(
+                "Char: %s (U+00E9, e' or another digraph, ESC to cancel)",
+                HistoryKind::Char,
+            ) {
+                Some(input) => input,
+                None => return,
+            }
+        } else {
+            args.to_string()
+        };
+        let input = input.trim();
+        if input.is_empty() {
+            return;
+        }
+
+        match parse_char(input) {
+            Some('\n') => self.editor_insert_newline(),
+            Some(c) if self.overwrite => self.editor_overwrite_char(c),
+            Some(c) => self.editor_insert_char(c),
+            None => self.editor_set_status_message(&format!(
+                "Not a code point or digraph: {} (see digraphs)",
+                input
+            )),
+        }
+    }
+
+    /// `digraphs`: lists the digraphs
+    pub fn editor_list_digraphs(&mut self) {
+        let lines: Vec<OverlayLine> = DIGRAPHS
+            .chunks(5)
+            .map(|chunk| {
+                let cells: Vec<String> = chunk
+                    .iter()
+                    .map(|(digraph, c)| format!("{:<3}{} U+{:04X}", digraph, c, *c as u32))
+                    .collect();
+                OverlayLine::new(cells.join("   "))
+            })
+            .collect();
+        self.editor_show_overlay("Digraphs (Ctrl-K)", &lines);
+    }
+}
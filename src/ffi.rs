@@ -0,0 +1,191 @@
+//! C ABI for embedding the editor core, enabled with the `ffi` feature.
+//!
+//! Build a shared library with
+//! `cargo rustc --lib --release --features ffi --crate-type cdylib`
+//! and include `include/kilo.h`. An embedded editor never touches the
+//! terminal: the host injects key codes and reads back a grid of characters.
+
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use crate::*;
+
+/// Borrows the editor behind a handle, None for a null pointer
+///
+/// # Safety
+/// `editor` must be null or a live pointer returned by `kilo_editor_new`.
+unsafe fn editor_mut<'a>(editor: *mut Editor) -> Option<&'a mut Editor> {
+    editor.as_mut()
+}
+
+unsafe fn str_arg<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok()
+}
+
+/// Creates an editor with an empty buffer for a screen of `rows` x `columns`
+#[no_mangle]
+pub extern "C" fn kilo_editor_new(rows: u32, columns: u32) -> *mut Editor {
+    Box::into_raw(Box::new(Editor::new_headless(
+        rows as usize,
+        columns as usize,
+    )))
+}
+
+/// # Safety
+/// `editor` must come from `kilo_editor_new` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn kilo_editor_free(editor: *mut Editor) {
+    if !editor.is_null() {
+        drop(Box::from_raw(editor));
+    }
+}
+
+/// Replaces the buffer with the file at `path` (an empty buffer if it doesn't
+/// exist). Returns false if the file can't be read.
+///
+/// # Safety
+/// `editor` must come from `kilo_editor_new`, `path` must be a C string.
+#[no_mangle]
+pub unsafe extern "C" fn kilo_editor_open(editor: *mut Editor, path: *const c_char) -> bool {
+    let (editor, path) = match (editor_mut(editor), str_arg(path)) {
+        (Some(editor), Some(path)) => (editor, path),
+        _ => return false,
+    };
+    if let Err(error) = std::fs::read_to_string(path) {
+        if error.kind() != ErrorKind::NotFound {
+            return false;
+        }
+    }
+
+    editor.editor_reset_buffer();
+    editor.editor_open(path);
+    true
+}
+
+/// Saves the buffer to its file. Returns false if it has no file name or the
+/// write failed (the message bar says why).
+///
+/// # Safety
+/// `editor` must come from `kilo_editor_new`.
+#[no_mangle]
+pub unsafe extern "C" fn kilo_editor_save(editor: *mut Editor) -> bool {
+    match editor_mut(editor) {
+        Some(editor) if editor.buffer.filename.is_some() => {
+            editor.editor_save();
+            editor.buffer.dirty == 0
+        }
+        _ => false,
+    }
+}
+
+/// Inserts UTF-8 `text` at the cursor as one undo step
+///
+/// # Safety
+/// `editor` must come from `kilo_editor_new`, `text` must be a C string.
+#[no_mangle]
+pub unsafe extern "C" fn kilo_editor_insert_text(editor: *mut Editor, text: *const c_char) {
+    if let (Some(editor), Some(text)) = (editor_mut(editor), str_arg(text)) {
+        editor.editor_insert_text(text);
+    }
+}
+
+/// Handles a sequence of key codes (bytes, Unicode code points or the
+/// KILO_KEY_* constants), codes that aren't keys are ignored.
+/// Keys following one that opens a prompt are typed into that prompt; a
+/// prompt still open after the last key is cancelled.
+///
+/// # Safety
+/// `editor` must come from `kilo_editor_new`, `keys` must point to `count` keys.
+#[no_mangle]
+pub unsafe extern "C" fn kilo_editor_keys(editor: *mut Editor, keys: *const u32, count: usize) {
+    if let Some(editor) = editor_mut(editor) {
+        if !keys.is_null() {
+            let keys = std::slice::from_raw_parts(keys, count);
+            editor.editor_handle_keys(keys.iter().filter_map(|&key| Key::from_code(key)));
+        }
+    }
+}
+
+/// Whether the quit key was pressed; the host decides what to do about it
+///
+/// # Safety
+/// `editor` must come from `kilo_editor_new`.
+#[no_mangle]
+pub unsafe extern "C" fn kilo_editor_should_quit(editor: *mut Editor) -> bool {
+    editor_mut(editor).is_some_and(|editor| editor.quit_requested)
+}
+
+/// # Safety
+/// `editor` must come from `kilo_editor_new`.
+#[no_mangle]
+pub unsafe extern "C" fn kilo_editor_resize(editor: *mut Editor, rows: u32, columns: u32) {
+    if let Some(editor) = editor_mut(editor) {
+        editor.editor_resize(rows as usize, columns as usize);
+    }
+}
+
+/// Renders the screen into `cells`, `rows` x `columns` Unicode code points in
+/// row-major order, padded with spaces. The cell after a wide char is 0. Stores the cursor position in
+/// `cursor_row`/`cursor_column` when they are not null.
+///
+/// # Safety
+/// `editor` must come from `kilo_editor_new`, `cells` must have room for
+/// `rows * columns` values.
+#[no_mangle]
+pub unsafe extern "C" fn kilo_editor_render(
+    editor: *mut Editor,
+    cells: *mut u32,
+    rows: u32,
+    columns: u32,
+    cursor_row: *mut u32,
+    cursor_column: *mut u32,
+) {
+    let editor = match editor_mut(editor) {
+        Some(editor) if !cells.is_null() => editor,
+        _ => return,
+    };
+    let (rows, columns) = (rows as usize, columns as usize);
+    let cells = std::slice::from_raw_parts_mut(cells, rows * columns);
+    cells.fill(' ' as u32);
+
+    let frame = editor.editor_frame();
+    for (y, line) in frame.lines.iter().take(rows).enumerate() {
+        for (x, c) in line.text().chars().take(columns).enumerate() {
+            cells[y * columns + x] = c as u32;
+        }
+    }
+
+    let (screen_y, screen_x) = frame.cursor.unwrap_or_default();
+    if !cursor_row.is_null() {
+        *cursor_row = screen_y as u32;
+    }
+    if !cursor_column.is_null() {
+        *cursor_column = screen_x as u32;
+    }
+}
+
+/// Buffer contents as a newly allocated C string, free it with `kilo_string_free`
+///
+/// # Safety
+/// `editor` must come from `kilo_editor_new`.
+#[no_mangle]
+pub unsafe extern "C" fn kilo_editor_contents(editor: *mut Editor) -> *mut c_char {
+    match editor_mut(editor) {
+        Some(editor) => CString::new(editor.editor_rows_to_string().replace('\0', ""))
+            .map(CString::into_raw)
+            .unwrap_or(ptr::null_mut()),
+        None => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `s` must come from `kilo_editor_contents` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn kilo_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
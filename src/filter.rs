@@ -0,0 +1,150 @@
+//! Line filter view: only rows matching a pattern (plus context) are shown,
+//! edits still go straight to the buffer
+
+use crate::*;
+
+pub struct LineFilter {
+    pattern: String,
+    /// Match case only if the pattern contains an uppercase letter
+    ignore_case: bool,
+    context: usize,
+}
+
+impl LineFilter {
+    fn matches(&self, line: &str) -> bool {
+        if self.ignore_case {
+            line.to_lowercase().contains(&self.pattern)
+        } else {
+            line.contains(&self.pattern)
+        }
+    }
+}
+
+impl Editor {
+    pub fn editor_row_visible(&self, file_row: usize) -> bool {
+        if self.editor_front_matter_hidden(file_row) {
+            return false;
+        }
+        let filter = match &self.buffer.filter {
+            Some(filter) => filter,
+            None => return true,
+        };
+        if file_row >= self.get_num_rows() {
+            return true;
+        }
+
+        let first = file_row.saturating_sub(filter.context);
+        let last = (file_row + filter.context).min(self.get_num_rows() - 1);
+        (first..=last).any(|row| filter.matches(&self.buffer.rows[row].chars))
+    }
+
+    /// Closest visible row above or below `file_row`
+    pub fn editor_next_visible_row(&self, file_row: usize, up: bool) -> Option<usize> {
+        if up {
+            (0..file_row)
+                .rev()
+                .find(|&row| self.editor_row_visible(row))
+        } else {
+            (file_row + 1..=self.get_num_rows()).find(|&row| self.editor_row_visible(row))
+        }
+    }
+
+    /// Moves the cursor off a row the filter hides, to the closest visible
+    /// row below it or else above it
+    pub fn editor_snap_to_visible_row(&mut self) {
+        if self.editor_row_visible(self.buffer.cursor_y) {
+            return;
+        }
+        self.buffer.cursor_y = self
+            .editor_next_visible_row(self.buffer.cursor_y, false)
+            .or_else(|| self.editor_next_visible_row(self.buffer.cursor_y, true))
+            .unwrap_or(0);
+        self.buffer.cursor_x = 0;
+    }
+
+    /// `filter [-C N] PATTERN` narrows the view, `filter` alone clears it
+    pub fn editor_filter(&mut self, args: &str) {
+        let mut context = 0;
+        let mut pattern = args;
+        if let Some(rest) = args.strip_prefix("-C") {
+            let rest = rest.trim_start();
+            let (number, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+            match number.parse() {
+                Ok(number) => context = number,
+                Err(_) => {
+                    self.editor_set_error_message(&format!("Invalid context: {}", number));
+                    return;
+                }
+            }
+            pattern = rest.trim();
+        }
+
+        if pattern.is_empty() {
+            self.buffer.filter = None;
+            self.editor_set_status_message("Filter cleared");
+            return;
+        }
+
+        let ignore_case = !pattern.chars().any(char::is_uppercase);
+        self.buffer.filter = Some(LineFilter {
+            pattern: if ignore_case {
+                pattern.to_lowercase()
+            } else {
+                pattern.to_string()
+            },
+            ignore_case,
+            context,
+        });
+
+        let matching = (0..self.get_num_rows())
+            .filter(|&row| self.editor_row_visible(row))
+            .count();
+        self.editor_snap_to_visible_row();
+        self.editor_set_status_message(&format!(
+            "Showing {} of {} lines (filter with no pattern to clear)",
+            matching,
+            self.get_num_rows()
+        ));
+    }
+
+    /// Status bar tag for the active filter
+    pub fn editor_filter_status(&self) -> Option<String> {
+        self.buffer
+            .filter
+            .as_ref()
+            .map(|filter| format!("[filter: {}]", filter.pattern))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_text(editor: &mut Editor, text: &str) {
+        editor.editor_handle_keys(text.chars().map(|c| match c {
+            '\n' => Key::Enter,
+            c => Key::Char(c),
+        }));
+    }
+
+    #[test]
+    fn keeps_the_cursor_on_a_shown_row_after_an_edit() {
+        let mut editor = Editor::new_headless(24, 80);
+        type_text(&mut editor, "foo\nbar\nfoo2");
+        editor.editor_execute_command("filter foo");
+        assert_eq!(editor.buffer.cursor_y, 2);
+
+        // Deleting the f hides the row being edited
+        editor.editor_handle_keys([Key::Home, Key::Delete]);
+        assert_eq!(editor.buffer.rows[2].chars, "oo2");
+        assert!(editor.editor_row_visible(editor.buffer.cursor_y));
+        editor.editor_frame();
+    }
+
+    #[test]
+    fn refuses_a_bad_context() {
+        let mut editor = Editor::new_headless(24, 80);
+        editor.editor_execute_command("filter -C x foo");
+        assert!(editor.status_is_error && editor.buffer.filter.is_none());
+    }
+}
@@ -0,0 +1,169 @@
+//! Following a growing file like `less +F`: `kilo -f app.log` or `follow`
+//! keeps reading what is appended to the file into new rows at the end. With
+//! the cursor on the last line the view stays at the bottom; moving up pauses
+//! that so earlier lines can be read or edited, and going back to the last
+//! line picks it up again. Only whole lines are read, and a file that shrinks
+//! (truncated or rotated) is read again from the start.
+
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+
+use crate::reopen::LineEndings;
+use crate::*;
+
+pub struct Follow {
+    /// Bytes of the file that are in the buffer
+    offset: u64,
+}
+
+impl Editor {
+    /// Whether the cursor is on the last line, where appended lines keep it
+    fn editor_follow_pinned(&self) -> bool {
+        self.buffer.cursor_y + 1 >= self.get_num_rows()
+    }
+
+    /// `follow`: starts or stops following the file
+    pub fn editor_toggle_follow(&mut self) {
+        if self.buffer.follow.take().is_some() {
+            self.editor_set_status_message("Stopped following the file");
+            return;
+        }
+        let filename = match &self.buffer.filename {
+            Some(filename) => filename.clone(),
+            None => {
+                self.editor_set_status_message("No file to follow");
+                return;
+            }
+        };
+        if self.buffer.hex.is_some()
+            || !matches!(
+                self.buffer.file_format.encoding,
+                Encoding::Utf8 | Encoding::Latin1
+            )
+        {
+            self.editor_set_status_message("Only UTF-8 and Latin-1 text files can be followed");
+            return;
+        }
+        // Lines written since the file was read come in as appended ones
+        let offset = match self.editor_disk_size() {
+            Some(size) => size,
+            None => {
+                self.editor_set_status_message(&format!("{} doesn't exist yet", filename));
+                return;
+            }
+        };
+        self.buffer.follow = Some(Follow { offset });
+        self.editor_goto(self.get_num_rows().saturating_sub(1), 0);
+        self.editor_set_status_message("Following the file, move up to pause");
+    }
+
+    /// Appends the lines added to the followed file since the last read.
+    /// True if there were any.
+    pub fn editor_follow_poll(&mut self) -> bool {
+        let (filename, offset) = match (&self.buffer.filename, &self.buffer.follow) {
+            (Some(filename), Some(follow)) if self.buffer.hex.is_none() => {
+                (filename.clone(), follow.offset)
+            }
+            _ => return false,
+        };
+        let size = match std::fs::metadata(&filename) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return false,
+        };
+        if size == offset {
+            return false;
+        }
+        if size < offset {
+            self.editor_follow_restart(&filename);
+            return true;
+        }
+
+        let mut bytes = vec![];
+        let read = File::open(&filename).and_then(|mut file| {
+            file.seek(SeekFrom::Start(offset))?;
+            file.take(size - offset).read_to_end(&mut bytes)
+        });
+        if read.is_err() {
+            return false;
+        }
+        // A line still being written waits for its end
+        let newline = match self.buffer.file_format.line_endings {
+            LineEndings::Cr => b'\r',
+            _ => b'\n',
+        };
+        let end = match bytes.iter().rposition(|&byte| byte == newline) {
+            Some(at) => at + 1,
+            None => return false,
+        };
+        let text = match self.buffer.file_format.decode(&bytes[..end]) {
+            Ok(text) => text,
+            Err(_) => return false,
+        };
+        if let Some(follow) = self.buffer.follow.as_mut() {
+            follow.offset += end as u64;
+        }
+
+        let pinned = self.editor_follow_pinned();
+        let mut lines = self.buffer.file_format.split_lines(&text).into_iter();
+        // The first piece finishes the last row
+        match (self.buffer.rows.last_mut(), lines.next()) {
+            (Some(row), Some(rest)) => {
+                row.chars.push_str(&rest);
+                row.chars_changed();
+            }
+            (None, Some(first)) => self.buffer.rows.push(EditorRow::new(first)),
+            _ => {}
+        }
+        self.buffer.rows.extend(lines.map(EditorRow::new));
+        self.revision += 1;
+        if self.buffer.dirty == 0 {
+            self.editor_mark_saved();
+        }
+        self.editor_record_disk_stamp();
+        if pinned {
+            self.editor_goto(self.get_num_rows().saturating_sub(1), 0);
+        }
+        true
+    }
+
+    /// The buffer was written to the followed file: what is on disk now is
+    /// in the rows, so following goes on from its end
+    pub fn editor_follow_saved(&mut self) {
+        let size = self.editor_disk_size();
+        if let (Some(follow), Some(size)) = (self.buffer.follow.as_mut(), size) {
+            follow.offset = size;
+        }
+    }
+
+    /// The followed file shrank: reads it again if there are no edits to
+    /// lose, otherwise goes on from its new end
+    fn editor_follow_restart(&mut self, filename: &str) {
+        if self.buffer.dirty == 0 {
+            let pinned = self.editor_follow_pinned();
+            self.editor_read_again(filename, self.buffer.file_format);
+            self.buffer.row_offset = 0;
+            if pinned {
+                self.editor_goto(self.get_num_rows().saturating_sub(1), 0);
+            }
+            self.editor_set_status_message(&format!("{} was truncated, read again", filename));
+        } else {
+            self.editor_record_disk_stamp();
+            self.editor_set_status_message(&format!(
+                "{} was truncated, following from its new end",
+                filename
+            ));
+        }
+        let offset = self.editor_disk_size().unwrap_or(0);
+        self.buffer.follow = Some(Follow { offset });
+    }
+
+    /// Status bar tag while following
+    pub fn editor_follow_status(&self) -> Option<String> {
+        self.buffer.follow.as_ref()?;
+        Some(if self.editor_follow_pinned() {
+            "[follow]".to_string()
+        } else {
+            "[follow paused]".to_string()
+        })
+    }
+}
@@ -0,0 +1,131 @@
+//! Formatting the buffer through an external, per filetype formatter, also
+//! before each save with `set formatonsave`
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::hooks::EditorHook;
+use crate::shell::shell_quote;
+use crate::*;
+
+/// Formatter command per file extension, set with `formatter` in the config
+pub type Formatters = HashMap<String, String>;
+
+/// Formatters used when the config file doesn't set one for the filetype
+fn default_formatter(extension: &str) -> Option<&'static str> {
+    match extension {
+        "rs" => Some("rustfmt --edition 2021"),
+        "c" | "h" | "cc" | "cpp" | "hpp" => Some("clang-format"),
+        "js" | "jsx" | "ts" | "tsx" | "json" | "css" | "html" | "md" | "yaml" | "yml" => {
+            Some("prettier --stdin-filepath {file}")
+        }
+        "go" => Some("gofmt"),
+        "py" => Some("black -q -"),
+        _ => None,
+    }
+}
+
+/// Formats the whole buffer before it's saved if `formatonsave` is set
+pub struct FormatOnSave;
+
+impl EditorHook for FormatOnSave {
+    fn on_save_pre(&mut self, editor: &mut Editor) {
+        if !editor.options.format_on_save || editor.editor_formatter().is_none() {
+            return;
+        }
+        let mark = editor.buffer.mark.take();
+        editor.editor_format();
+        editor.buffer.mark = mark;
+    }
+}
+
+impl Editor {
+    pub fn editor_file_extension(&self) -> Option<String> {
+        let filename = self.buffer.filename.as_deref()?;
+        Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_string)
+    }
+
+    /// `formatter EXTENSION COMMAND` sets the formatter for a filetype,
+    /// `{file}` in the command is replaced by the quoted file name
+    pub fn editor_set_formatter(&mut self, args: &str) {
+        match args.split_once(char::is_whitespace) {
+            Some((extension, command)) => {
+                self.formatters.insert(
+                    extension.trim_start_matches('.').to_string(),
+                    command.trim().to_string(),
+                );
+            }
+            None => self.editor_set_error_message("Usage: formatter EXTENSION COMMAND"),
+        }
+    }
+
+    /// Formatter command for the open file, None if its type has none
+    fn editor_formatter(&self) -> Option<String> {
+        let extension = self.editor_file_extension().unwrap_or_default();
+        self.formatters
+            .get(&extension)
+            .map(String::as_str)
+            .or_else(|| default_formatter(&extension))
+            .map(|command| {
+                let filename = self.buffer.filename.as_deref().unwrap_or("");
+                command.replace("{file}", &shell_quote(filename))
+            })
+    }
+
+    /// Formats the selected rows (or the whole buffer) as one undoable edit
+    pub fn editor_format(&mut self) {
+        let command = match self.editor_formatter() {
+            Some(command) => command,
+            None => {
+                self.editor_set_error_message(&format!(
+                    "No formatter for '{}' files (formatter EXTENSION COMMAND)",
+                    self.editor_file_extension().unwrap_or_default()
+                ));
+                return;
+            }
+        };
+
+        let (start, count) = self.editor_selected_rows();
+        let cursor_y = self.buffer.cursor_y;
+        if self.editor_pipe_rows(start, count, &command).is_some() {
+            self.buffer.cursor_y = cursor_y.min(self.get_num_rows().saturating_sub(1));
+            if let Some(row) = self.buffer.rows.get(self.buffer.cursor_y) {
+                self.buffer.cursor_x = floor_char_boundary(&row.chars, self.buffer.cursor_x);
+            }
+            self.editor_set_status_message(&format!("Formatted with {}", command));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_the_file_name() {
+        let mut editor = Editor::new_headless(24, 80);
+        editor.buffer.filename = Some("it's; rm -rf ~.md".to_string());
+        assert_eq!(
+            editor.editor_formatter().unwrap(),
+            r"prettier --stdin-filepath 'it'\''s; rm -rf ~.md'"
+        );
+    }
+
+    #[test]
+    fn formats_through_the_command() {
+        let mut editor = Editor::new_headless(24, 80);
+        editor.buffer.filename = Some("notes.txt".to_string());
+        editor.editor_execute_command("formatter txt sed 's/^ *//'");
+        editor.editor_handle_keys(" éé".chars().map(Key::Char).chain([Key::Left]));
+        editor.editor_format();
+        assert_eq!(editor.buffer.rows[0].chars, "éé");
+        // The cursor was inside the second é once the space went
+        assert_eq!(editor.buffer.cursor_x, 2);
+        editor.editor_handle_keys([Key::Char('x')]);
+        editor.editor_frame();
+        assert_eq!(editor.buffer.rows[0].chars, "éxé");
+    }
+}
@@ -0,0 +1,443 @@
+//! Screen contents as a structured frame: lines of styled spans and a cursor.
+//!
+//! The core builds a `Frame` for every redraw and the terminal backend turns it
+//! into escape sequences. Other front ends (enable the `frontend` feature) can
+//! draw the same frames with `Editor::editor_frame`.
+//!
+//! Each char of a line is one screen cell, except that a wide char (CJK,
+//! emoji) takes two: the char and a `WIDE_FILLER` after it.
+
+pub use crate::style::{CellStyle, Color, ColorDepth};
+
+/// Cell taking the second column of the wide char before it, drawn as nothing
+pub const WIDE_FILLER: char = '\0';
+
+/// Chars drawn over the one before them: combining marks, zero width
+/// spaces and joiners, direction marks and variation selectors
+const ZERO_WIDTH: &[(u32, u32)] = &[
+    (0x0300, 0x036f),
+    (0x0483, 0x0489),
+    (0x0591, 0x05bd),
+    (0x05bf, 0x05bf),
+    (0x05c1, 0x05c2),
+    (0x05c4, 0x05c5),
+    (0x05c7, 0x05c7),
+    (0x0610, 0x061a),
+    (0x064b, 0x065f),
+    (0x0670, 0x0670),
+    (0x06d6, 0x06dc),
+    (0x06df, 0x06e4),
+    (0x06e7, 0x06e8),
+    (0x06ea, 0x06ed),
+    (0x0711, 0x0711),
+    (0x0730, 0x074a),
+    (0x07a6, 0x07b0),
+    (0x07eb, 0x07f3),
+    (0x0816, 0x082d),
+    (0x0859, 0x085b),
+    (0x0898, 0x089f),
+    (0x08ca, 0x0902),
+    (0x093a, 0x093a),
+    (0x093c, 0x093c),
+    (0x0941, 0x0948),
+    (0x094d, 0x094d),
+    (0x0951, 0x0957),
+    (0x0962, 0x0963),
+    (0x0981, 0x0981),
+    (0x09bc, 0x09bc),
+    (0x09c1, 0x09c4),
+    (0x09cd, 0x09cd),
+    (0x09e2, 0x09e3),
+    (0x0a01, 0x0a02),
+    (0x0a3c, 0x0a3c),
+    (0x0a41, 0x0a51),
+    (0x0a70, 0x0a71),
+    (0x0a75, 0x0a75),
+    (0x0a81, 0x0a82),
+    (0x0abc, 0x0abc),
+    (0x0ac1, 0x0ac8),
+    (0x0acd, 0x0acd),
+    (0x0ae2, 0x0ae3),
+    (0x0b01, 0x0b01),
+    (0x0b3c, 0x0b3c),
+    (0x0b3f, 0x0b3f),
+    (0x0b41, 0x0b44),
+    (0x0b4d, 0x0b4d),
+    (0x0b82, 0x0b82),
+    (0x0bc0, 0x0bc0),
+    (0x0bcd, 0x0bcd),
+    (0x0c3e, 0x0c40),
+    (0x0c46, 0x0c56),
+    (0x0cbc, 0x0cbc),
+    (0x0ccc, 0x0ccd),
+    (0x0d41, 0x0d44),
+    (0x0d4d, 0x0d4d),
+    (0x0dca, 0x0dca),
+    (0x0dd2, 0x0dd6),
+    (0x0e31, 0x0e31),
+    (0x0e34, 0x0e3a),
+    (0x0e47, 0x0e4e),
+    (0x0eb1, 0x0eb1),
+    (0x0eb4, 0x0ebc),
+    (0x0ec8, 0x0ece),
+    (0x0f18, 0x0f19),
+    (0x0f35, 0x0f35),
+    (0x0f37, 0x0f37),
+    (0x0f39, 0x0f39),
+    (0x0f71, 0x0f7e),
+    (0x0f80, 0x0f84),
+    (0x0f86, 0x0f87),
+    (0x0f8d, 0x0fbc),
+    (0x102d, 0x1030),
+    (0x1032, 0x1037),
+    (0x1039, 0x103a),
+    (0x1160, 0x11ff),
+    (0x135d, 0x135f),
+    (0x17b4, 0x17b5),
+    (0x17b7, 0x17bd),
+    (0x17c6, 0x17c6),
+    (0x17c9, 0x17d3),
+    (0x180b, 0x180f),
+    (0x1ab0, 0x1aff),
+    (0x1dc0, 0x1dff),
+    (0x200b, 0x200f),
+    (0x202a, 0x202e),
+    (0x2060, 0x2064),
+    (0x20d0, 0x20f0),
+    (0x2cef, 0x2cf1),
+    (0x2de0, 0x2dff),
+    (0x302a, 0x302d),
+    (0x3099, 0x309a),
+    (0xa66f, 0xa672),
+    (0xa674, 0xa67d),
+    (0xa69e, 0xa69f),
+    (0xa6f0, 0xa6f1),
+    (0xa8e0, 0xa8f1),
+    (0xfb1e, 0xfb1e),
+    (0xfe00, 0xfe0f),
+    (0xfe20, 0xfe2f),
+    (0xfeff, 0xfeff),
+    (0x101fd, 0x101fd),
+    (0x1d167, 0x1d169),
+    (0x1d17b, 0x1d182),
+    (0x1d185, 0x1d18b),
+    (0x1d1aa, 0x1d1ad),
+    (0xe0000, 0xe007f),
+    (0xe0100, 0xe01ef),
+];
+
+/// East Asian wide and fullwidth chars and emoji shown as pictures
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115f),
+    (0x231a, 0x231b),
+    (0x2329, 0x232a),
+    (0x23e9, 0x23ec),
+    (0x23f0, 0x23f0),
+    (0x23f3, 0x23f3),
+    (0x25fd, 0x25fe),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267f, 0x267f),
+    (0x2693, 0x2693),
+    (0x26a1, 0x26a1),
+    (0x26aa, 0x26ab),
+    (0x26bd, 0x26be),
+    (0x26c4, 0x26c5),
+    (0x26ce, 0x26ce),
+    (0x26d4, 0x26d4),
+    (0x26ea, 0x26ea),
+    (0x26f2, 0x26f3),
+    (0x26f5, 0x26f5),
+    (0x26fa, 0x26fa),
+    (0x26fd, 0x26fd),
+    (0x2705, 0x2705),
+    (0x270a, 0x270b),
+    (0x2728, 0x2728),
+    (0x274c, 0x274c),
+    (0x274e, 0x274e),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27b0, 0x27b0),
+    (0x27bf, 0x27bf),
+    (0x2b1b, 0x2b1c),
+    (0x2b50, 0x2b50),
+    (0x2b55, 0x2b55),
+    (0x2e80, 0x3029),
+    (0x302e, 0x303e),
+    (0x3041, 0x3098),
+    (0x309b, 0x33ff),
+    (0x3400, 0x4dbf),
+    (0x4e00, 0x9fff),
+    (0xa000, 0xa4cf),
+    (0xa960, 0xa97f),
+    (0xac00, 0xd7a3),
+    (0xf900, 0xfaff),
+    (0xfe10, 0xfe19),
+    (0xfe30, 0xfe6f),
+    (0xff00, 0xff60),
+    (0xffe0, 0xffe6),
+    (0x16fe0, 0x16fe4),
+    (0x16ff0, 0x16ff1),
+    (0x17000, 0x18cd5),
+    (0x18d00, 0x18d08),
+    (0x1aff0, 0x1b2fb),
+    (0x1f004, 0x1f004),
+    (0x1f0cf, 0x1f0cf),
+    (0x1f18e, 0x1f18e),
+    (0x1f191, 0x1f19a),
+    (0x1f200, 0x1f202),
+    (0x1f210, 0x1f23b),
+    (0x1f240, 0x1f248),
+    (0x1f250, 0x1f251),
+    (0x1f260, 0x1f265),
+    (0x1f300, 0x1f64f),
+    (0x1f680, 0x1f6ff),
+    (0x1f7e0, 0x1f7eb),
+    (0x1f7f0, 0x1f7f0),
+    (0x1f900, 0x1f9ff),
+    (0x1fa70, 0x1faff),
+    (0x20000, 0x2fffd),
+    (0x30000, 0x3fffd),
+];
+
+/// Whether `c` is in one of the sorted `ranges`
+fn in_ranges(c: char, ranges: &[(u32, u32)]) -> bool {
+    let c = c as u32;
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                std::cmp::Ordering::Less
+            } else if start > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Columns `c` takes on a terminal: 2 for East Asian wide and fullwidth
+/// chars and emoji, 0 for combining marks and other chars drawn over the one
+/// before them, 1 for the rest
+pub fn char_width(c: char) -> usize {
+    if (c as u32) < 0x300 {
+        1
+    } else if in_ranges(c, ZERO_WIDTH) {
+        0
+    } else if in_ranges(c, WIDE) {
+        2
+    } else {
+        1
+    }
+}
+
+/// `text` as cells, cut to at most `width` columns without splitting a wide
+/// char
+pub fn to_cells(text: &str, width: usize) -> String {
+    let mut cells = String::with_capacity(text.len());
+    let mut columns = 0;
+    for c in text.chars() {
+        let c_width = char_width(c);
+        // A cell holds one char, marks on top of it are left out
+        if c_width == 0 {
+            continue;
+        }
+        if columns + c_width > width {
+            break;
+        }
+        cells.push(c);
+        if c_width == 2 {
+            cells.push(WIDE_FILLER);
+        }
+        columns += c_width;
+    }
+    cells
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: CellStyle,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameLine {
+    pub spans: Vec<Span>,
+}
+
+impl FrameLine {
+    pub fn push(&mut self, text: &str) {
+        self.push_styled(text, CellStyle::default());
+    }
+
+    /// Appends `text`, merging it into the last span if the style matches
+    pub fn push_styled(&mut self, text: &str, style: CellStyle) {
+        if text.is_empty() {
+            return;
+        }
+        match self.spans.last_mut() {
+            Some(last) if last.style == style => last.text.push_str(text),
+            _ => self.spans.push(Span {
+                text: text.to_string(),
+                style,
+            }),
+        }
+    }
+
+    /// Appends `text` with one style per char
+    pub fn push_cells(&mut self, text: &str, styles: &[CellStyle]) {
+        for (i, c) in text.chars().enumerate() {
+            let style = styles.get(i).copied().unwrap_or_default();
+            self.push_styled(c.encode_utf8(&mut [0; 4]), style);
+        }
+    }
+
+    /// Draws `text` over the line starting at char `column` with one style per
+    /// char, padding the line with spaces
+    pub fn overlay(&mut self, column: usize, text: &str, styles: &[CellStyle]) {
+        let mut cells: Vec<(char, CellStyle)> = self
+            .spans
+            .iter()
+            .flat_map(|span| span.text.chars().map(move |c| (c, span.style)))
+            .collect();
+        let end = column + text.chars().count();
+        if cells.len() < end {
+            cells.resize(end, (' ', CellStyle::default()));
+        }
+        for (i, c) in text.chars().enumerate() {
+            cells[column + i] = (c, styles.get(i).copied().unwrap_or_default());
+        }
+
+        self.spans.clear();
+        for (c, style) in cells {
+            self.push_styled(c.encode_utf8(&mut [0; 4]), style);
+        }
+    }
+
+    /// The line without styling
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    /// One entry per screen row, top to bottom
+    pub lines: Vec<FrameLine>,
+    /// Cursor position as (row, column), None when it is hidden
+    pub cursor: Option<(usize, usize)>,
+}
+
+/// Terminal backend: escape sequences that draw `frame` over the whole screen
+pub fn frame_to_ansi(frame: &Frame, depth: ColorDepth) -> String {
+    // Hide the cursor while drawing, starting at the top of the screen
+    let mut out = String::from("\x1b[?25l\x1b[H");
+
+    for (y, line) in frame.lines.iter().enumerate() {
+        push_line(&mut out, line, depth);
+        if y + 1 < frame.lines.len() {
+            out.push_str("\r\n");
+        }
+    }
+
+    push_cursor(&mut out, frame);
+    out
+}
+
+/// Like `frame_to_ansi` but only draws the lines that differ from `previous`,
+/// which must have as many lines
+pub fn frame_to_ansi_changes(previous: &Frame, frame: &Frame, depth: ColorDepth) -> String {
+    let mut out = String::from("\x1b[?25l");
+
+    for (y, line) in frame.lines.iter().enumerate() {
+        if previous.lines.get(y) != Some(line) {
+            out.push_str(&format!("\x1b[{};1H", y + 1));
+            push_line(&mut out, line, depth);
+        }
+    }
+
+    push_cursor(&mut out, frame);
+    out
+}
+
+/// Draws `line` from the terminal cursor with colors brought down to `depth` and clears the rest of the row
+fn push_line(out: &mut String, line: &FrameLine, depth: ColorDepth) {
+    let cells: Vec<char> = line
+        .spans
+        .iter()
+        .flat_map(|span| span.text.chars())
+        .collect();
+    let mut index = 0;
+    for span in &line.spans {
+        // Half of a wide char left after the other half was drawn over, as by
+        // a popup, shows as a space
+        let mut text = String::with_capacity(span.text.len());
+        for c in span.text.chars() {
+            let wide_before = index > 0 && char_width(cells[index - 1]) == 2;
+            let filler_after = cells.get(index + 1) == Some(&WIDE_FILLER);
+            match c {
+                WIDE_FILLER if wide_before => {}
+                WIDE_FILLER => text.push(' '),
+                c if char_width(c) == 2 && !filler_after => text.push(' '),
+                c => text.push(c),
+            }
+            index += 1;
+        }
+
+        if span.style == CellStyle::default() {
+            out.push_str(&text);
+        } else {
+            out.push_str(&span.style.sgr(depth));
+            out.push_str(&text);
+            out.push_str("\x1b[m");
+        }
+    }
+    out.push_str("\x1b[K");
+}
+
+fn push_cursor(out: &mut String, frame: &Frame) {
+    if let Some((row, column)) = frame.cursor {
+        out.push_str(&format!("\x1b[{};{}H", row + 1, column + 1));
+        out.push_str("\x1b[?25h");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_chars() {
+        assert_eq!(char_width('a'), 1);
+        assert_eq!(char_width('é'), 1);
+        assert_eq!(char_width('中'), 2);
+        assert_eq!(char_width('한'), 2);
+        assert_eq!(char_width('Ａ'), 2);
+        // Emoji, transport and the newer symbols
+        assert_eq!(char_width('😀'), 2);
+        assert_eq!(char_width('🚀'), 2);
+        assert_eq!(char_width('🥰'), 2);
+        assert_eq!(char_width('🪐'), 2);
+        assert_eq!(char_width('⌚'), 2);
+        // Combining acute, zero width joiner and space, variation selector
+        assert_eq!(char_width('\u{301}'), 0);
+        assert_eq!(char_width('\u{200d}'), 0);
+        assert_eq!(char_width('\u{200b}'), 0);
+        assert_eq!(char_width('\u{fe0f}'), 0);
+    }
+
+    #[test]
+    fn keeps_the_ranges_sorted() {
+        for ranges in [ZERO_WIDTH, WIDE] {
+            assert!(ranges.iter().all(|&(start, end)| start <= end));
+            assert!(ranges.windows(2).all(|pair| pair[0].1 < pair[1].0));
+        }
+    }
+
+    #[test]
+    fn leaves_marks_out_of_cells() {
+        assert_eq!(to_cells("e\u{301}x", 10), "ex");
+        assert_eq!(to_cells("中a", 2), format!("中{}", WIDE_FILLER));
+    }
+}
@@ -0,0 +1,211 @@
+//! Front matter of Markdown files: the YAML (`---`) or TOML (`+++`) block at
+//! the top is highlighted and folded when the file is opened. `frontmatter`
+//! opens or closes the fold, `fmnext` / `fmprev` jump between its keys.
+
+use crate::*;
+
+/// Longest front matter block looked for
+const MAX_FRONT_MATTER_ROWS: usize = 500;
+
+const KEY_COLOR: Color = Color::CYAN;
+const STRING_COLOR: Color = Color::GREEN;
+const LITERAL_COLOR: Color = Color::MAGENTA;
+const TABLE_COLOR: Color = Color::MAGENTA;
+const COMMENT_COLOR: Color = Color::BRIGHT_BLACK;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FrontMatterKind {
+    Yaml,
+    Toml,
+}
+
+/// Byte range of the key defined on `line`, if any
+fn key_range(kind: FrontMatterKind, line: &str) -> Option<(usize, usize)> {
+    let indent = line.len() - line.trim_start().len();
+    let mut start = indent;
+    if kind == FrontMatterKind::Yaml && line[start..].starts_with("- ") {
+        start += 2;
+    }
+    if line[start..].starts_with('#') || line[start..].starts_with('[') {
+        return None;
+    }
+
+    let end = match kind {
+        FrontMatterKind::Yaml => {
+            let colon = line[start..].find(':')? + start;
+            let after = &line[colon + 1..];
+            if !(after.is_empty() || after.starts_with(' ')) {
+                return None;
+            }
+            colon
+        }
+        FrontMatterKind::Toml => line[start..].find('=')? + start,
+    };
+    let key = line[start..end].trim_end();
+    let valid = !key.is_empty() && (!key.contains(' ') || key.starts_with('"'));
+    valid.then_some((start, start + key.len()))
+}
+
+/// Byte ranges and colors of the syntax on one front matter line
+fn highlight_line(kind: FrontMatterKind, line: &str) -> Vec<(usize, usize, Color)> {
+    let mut spans = vec![];
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+
+    if trimmed.starts_with('#') {
+        return vec![(indent, line.len(), COMMENT_COLOR)];
+    }
+    if kind == FrontMatterKind::Toml && trimmed.starts_with('[') {
+        return vec![(indent, line.len(), TABLE_COLOR)];
+    }
+
+    let mut value_start = indent;
+    if let Some((start, end)) = key_range(kind, line) {
+        spans.push((start, end, KEY_COLOR));
+        value_start = end + 1;
+    } else if kind == FrontMatterKind::Yaml && trimmed.starts_with("- ") {
+        value_start = indent + 2;
+    }
+
+    // The value: a quoted string, or a literal, up to a comment
+    let value = &line[value_start..];
+    let offset = value_start + (value.len() - value.trim_start().len());
+    let value = value.trim_start();
+    if let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') {
+        let end = value[1..].find(quote).map_or(value.len(), |at| at + 2);
+        spans.push((offset, offset + end, STRING_COLOR));
+        if let Some(comment) = value[end..].find('#') {
+            spans.push((offset + end + comment, line.len(), COMMENT_COLOR));
+        }
+    } else {
+        let comment = value.find(" #").map(|at| at + 1);
+        let literal = value[..comment.unwrap_or(value.len())].trim_end();
+        let is_literal = literal.parse::<f64>().is_ok()
+            || matches!(literal, "true" | "false" | "null" | "yes" | "no" | "~");
+        if is_literal {
+            spans.push((offset, offset + literal.len(), LITERAL_COLOR));
+        }
+        if let Some(comment) = comment {
+            spans.push((offset + comment, line.len(), COMMENT_COLOR));
+        }
+    }
+    spans
+}
+
+impl Editor {
+    /// The front matter of a Markdown file as its kind and closing row,
+    /// looked for again only once the rows change
+    pub fn editor_front_matter(&self) -> Option<(FrontMatterKind, usize)> {
+        let filename = self.buffer.filename.as_deref()?;
+        if !(filename.ends_with(".md") || filename.ends_with(".markdown")) {
+            return None;
+        }
+        self.front_matter
+            .get(self.editor_rows_key(), || self.editor_find_front_matter())
+    }
+
+    fn editor_find_front_matter(&self) -> Option<(FrontMatterKind, usize)> {
+        let (kind, delimiter) = match self.buffer.rows.first()?.chars.trim_end() {
+            "---" => (FrontMatterKind::Yaml, "---"),
+            "+++" => (FrontMatterKind::Toml, "+++"),
+            _ => return None,
+        };
+        let end = self
+            .buffer
+            .rows
+            .iter()
+            .enumerate()
+            .skip(1)
+            .take(MAX_FRONT_MATTER_ROWS)
+            .find(|(_, row)| row.chars.trim_end() == delimiter)?
+            .0;
+        Some((kind, end))
+    }
+
+    /// Rows hidden by the closed front matter fold: all but the opening line
+    pub fn editor_front_matter_hidden(&self, file_row: usize) -> bool {
+        self.buffer.front_matter_folded
+            && file_row > 0
+            && self
+                .editor_front_matter()
+                .is_some_and(|(_, end)| file_row <= end)
+    }
+
+    /// Whether the front matter fold is closed
+    pub fn editor_front_matter_fold_closed(&self) -> bool {
+        self.buffer.front_matter_folded && self.editor_front_matter().is_some()
+    }
+
+    /// Text drawn after the opening line of the closed fold
+    pub fn editor_front_matter_fold_summary(&self, file_row: usize) -> Option<String> {
+        if file_row != 0 || !self.buffer.front_matter_folded {
+            return None;
+        }
+        let (kind, end) = self.editor_front_matter()?;
+        let name = match kind {
+            FrontMatterKind::Yaml => "YAML",
+            FrontMatterKind::Toml => "TOML",
+        };
+        Some(format!(" [{} front matter, {} lines]", name, end + 1))
+    }
+
+    /// Syntax colors of `file_row` as byte ranges of its chars
+    pub fn editor_front_matter_highlights(&self, file_row: usize) -> Vec<(usize, usize, Color)> {
+        let (kind, end) = match self.editor_front_matter() {
+            Some(front_matter) => front_matter,
+            None => return vec![],
+        };
+        let line = &self.buffer.rows[file_row].chars;
+        if file_row == 0 || file_row == end {
+            vec![(0, line.len(), COMMENT_COLOR)]
+        } else if file_row < end {
+            highlight_line(kind, line)
+        } else {
+            vec![]
+        }
+    }
+
+    /// `frontmatter`: opens or closes the front matter fold
+    pub fn editor_toggle_front_matter(&mut self) {
+        if self.editor_front_matter().is_none() {
+            self.editor_set_status_message("No front matter in this file");
+            return;
+        }
+        self.buffer.front_matter_folded = !self.buffer.front_matter_folded;
+        if self.editor_front_matter_hidden(self.buffer.cursor_y) {
+            self.buffer.cursor_y = 0;
+            self.buffer.cursor_x = 0;
+        }
+    }
+
+    /// `fmnext` / `fmprev`: jumps to the next or previous front matter key,
+    /// opening the fold
+    pub fn editor_front_matter_key(&mut self, forward: bool) {
+        let (kind, end) = match self.editor_front_matter() {
+            Some(front_matter) => front_matter,
+            None => {
+                self.editor_set_status_message("No front matter in this file");
+                return;
+            }
+        };
+        let has_key = |row: &usize| key_range(kind, &self.buffer.rows[*row].chars).is_some();
+        let found = if forward {
+            (self.buffer.cursor_y + 1..end).find(has_key)
+        } else {
+            (1..self.buffer.cursor_y.min(end)).rev().find(has_key)
+        };
+
+        match found {
+            Some(row) => {
+                self.buffer.front_matter_folded = false;
+                let (start, _) = key_range(kind, &self.buffer.rows[row].chars).unwrap_or_default();
+                self.editor_goto(row, start);
+            }
+            None => self.editor_set_status_message(if forward {
+                "No more front matter keys below"
+            } else {
+                "No more front matter keys above"
+            }),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Fuzzy matching shared by the pickers and completion.
+//!
+//! Scores are computed fzf-style: a Smith-Waterman like alignment of the
+//! pattern in the text where every matched char scores, gaps cost, and
+//! matches at word starts, after path separators, at camelCase humps and in
+//! consecutive runs earn bonuses. Matching is case-insensitive unless the
+//! pattern contains an uppercase letter.
+
+use crate::style::{CellStyle, Color};
+
+const SCORE_MATCH: i64 = 16;
+const SCORE_GAP_START: i64 = -3;
+const SCORE_GAP_EXTENSION: i64 = -1;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_PATH_SEPARATOR: i64 = 9;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+/// The first pattern char's bonus counts this many times
+const BONUS_FIRST_CHAR_MULTIPLIER: i64 = 2;
+/// Texts longer than this (in chars) are matched greedily, the full alignment
+/// needs pattern length x text length memory
+const MAX_ALIGNED_LEN: usize = 1024;
+
+const NONE: i64 = i64::MIN / 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Char indices of the matched chars in the text
+    pub positions: Vec<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Lower,
+    Upper,
+    Digit,
+    Separator,
+    Other,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_lowercase() {
+        CharClass::Lower
+    } else if c.is_uppercase() {
+        CharClass::Upper
+    } else if c.is_numeric() {
+        CharClass::Digit
+    } else if c == '/' || c == '\\' {
+        CharClass::Separator
+    } else {
+        CharClass::Other
+    }
+}
+
+/// Bonus for a match on a char of class `class` following one of class `previous`
+fn position_bonus(previous: CharClass, class: CharClass) -> i64 {
+    match (previous, class) {
+        (CharClass::Separator, CharClass::Lower | CharClass::Upper | CharClass::Digit) => {
+            BONUS_PATH_SEPARATOR
+        }
+        (CharClass::Other, CharClass::Lower | CharClass::Upper | CharClass::Digit) => {
+            BONUS_BOUNDARY
+        }
+        (CharClass::Lower, CharClass::Upper) => BONUS_CAMEL,
+        (CharClass::Lower | CharClass::Upper, CharClass::Digit) => BONUS_CAMEL,
+        _ => 0,
+    }
+}
+
+fn chars_match(pattern: char, text: char, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        pattern == text
+    } else {
+        pattern == text || text.to_lowercase().eq(pattern.to_lowercase())
+    }
+}
+
+/// Bonus of each char in `text`; the start of the text counts as a boundary
+fn bonuses(text: &[char]) -> Vec<i64> {
+    let mut previous = CharClass::Other;
+    text.iter()
+        .map(|&c| {
+            let class = char_class(c);
+            let bonus = position_bonus(previous, class);
+            previous = class;
+            bonus
+        })
+        .collect()
+}
+
+/// Matches `pattern` as a subsequence of `text`. None if some pattern char
+/// can't be found; an empty pattern matches everything with score 0.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+    if pattern.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: vec![],
+        });
+    }
+    let case_sensitive = pattern.iter().any(|c| c.is_uppercase());
+    let text: Vec<char> = text.chars().collect();
+
+    // Cheap rejection before the alignment, also the fallback for long texts
+    let mut greedy = Vec::with_capacity(pattern.len());
+    let mut next = 0;
+    for &p in &pattern {
+        let found = (next..text.len()).find(|&j| chars_match(p, text[j], case_sensitive))?;
+        greedy.push(found);
+        next = found + 1;
+    }
+
+    let bonus = bonuses(&text);
+    if text.len() > MAX_ALIGNED_LEN {
+        return Some(FuzzyMatch {
+            score: score_positions(&greedy, &bonus),
+            positions: greedy,
+        });
+    }
+
+    // Only the part of the text between the first possible match of the first
+    // char and the last possible match of the last char can be aligned
+    let first = greedy[0];
+    let last = (0..text.len())
+        .rev()
+        .find(|&j| chars_match(pattern[pattern.len() - 1], text[j], case_sensitive))?;
+    let width = last - first + 1;
+
+    // score[i][j]: best score of pattern[..=i] with pattern[i] matched at first + j,
+    // from[i][j]: where pattern[i - 1] was matched on that path
+    let mut score = vec![vec![NONE; width]; pattern.len()];
+    let mut from = vec![vec![0; width]; pattern.len()];
+    // Length of the run of consecutive matches ending at (i, j)
+    let mut run = vec![vec![0; width]; pattern.len()];
+
+    for (i, &p) in pattern.iter().enumerate() {
+        // Best score[i - 1][k] + gap penalty for k < j - 1, and its k
+        let mut gap: (i64, usize) = (NONE, 0);
+        for j in 0..width {
+            if i > 0 && j >= 2 {
+                let k = j - 2;
+                let extended = gap.0 + SCORE_GAP_EXTENSION;
+                let started = score[i - 1][k] + SCORE_GAP_START;
+                gap = if started >= extended {
+                    (started, k)
+                } else {
+                    (extended, gap.1)
+                };
+            }
+            if !chars_match(p, text[first + j], case_sensitive) {
+                continue;
+            }
+
+            let char_bonus = bonus[first + j];
+            if i == 0 {
+                score[i][j] = SCORE_MATCH + char_bonus * BONUS_FIRST_CHAR_MULTIPLIER;
+                run[i][j] = 1;
+                continue;
+            }
+
+            if gap.0 > NONE {
+                score[i][j] = gap.0 + SCORE_MATCH + char_bonus;
+                from[i][j] = gap.1;
+                run[i][j] = 1;
+            }
+            if j > 0 && score[i - 1][j - 1] > NONE {
+                // A run keeps the bonus of the boundary it started at
+                let run_start = first + j - run[i - 1][j - 1];
+                let consecutive_bonus = char_bonus.max(BONUS_CONSECUTIVE).max(bonus[run_start]);
+                let consecutive = score[i - 1][j - 1] + SCORE_MATCH + consecutive_bonus;
+                if consecutive >= score[i][j] {
+                    score[i][j] = consecutive;
+                    from[i][j] = j - 1;
+                    run[i][j] = run[i - 1][j - 1] + 1;
+                }
+            }
+        }
+    }
+
+    let end = pattern.len() - 1;
+    let (mut j, best) = score[end]
+        .iter()
+        .enumerate()
+        .filter(|(_, &s)| s > NONE)
+        .max_by_key(|&(j, &s)| (s, std::cmp::Reverse(j)))
+        .map(|(j, &s)| (j, s))?;
+
+    let mut positions = vec![0; pattern.len()];
+    for i in (0..pattern.len()).rev() {
+        positions[i] = first + j;
+        if i > 0 {
+            j = from[i][j];
+        }
+    }
+
+    Some(FuzzyMatch {
+        score: best,
+        positions,
+    })
+}
+
+/// Score of a match at `positions` without alignment, for long texts
+fn score_positions(positions: &[usize], bonus: &[i64]) -> i64 {
+    let mut score = 0;
+    for (i, &position) in positions.iter().enumerate() {
+        score += SCORE_MATCH + bonus[position];
+        if i > 0 {
+            let gap = position - positions[i - 1] - 1;
+            if gap == 0 {
+                score += BONUS_CONSECUTIVE;
+            } else {
+                score += SCORE_GAP_START + SCORE_GAP_EXTENSION * (gap as i64 - 1);
+            }
+        }
+    }
+    score
+}
+
+/// Indices of the `items` matching `pattern` with their matches, best first.
+/// Equal scores prefer matches starting earlier, then shorter items, then the
+/// original order.
+pub fn fuzzy_rank<S: AsRef<str>>(pattern: &str, items: &[S]) -> Vec<(usize, FuzzyMatch)> {
+    let mut matches: Vec<(usize, FuzzyMatch)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| Some((i, fuzzy_match(pattern, item.as_ref())?)))
+        .collect();
+    matches.sort_by(|(a, a_match), (b, b_match)| {
+        b_match
+            .score
+            .cmp(&a_match.score)
+            .then(a_match.positions.first().cmp(&b_match.positions.first()))
+            .then(items[*a].as_ref().len().cmp(&items[*b].as_ref().len()))
+            .then(a.cmp(b))
+    });
+    matches
+}
+
+/// Styles for `len` chars drawn in `base`, with the matched `positions` picked out
+pub fn highlight_matches(len: usize, positions: &[usize], base: CellStyle) -> Vec<CellStyle> {
+    let mut styles = vec![base; len];
+    for &position in positions {
+        if let Some(style) = styles.get_mut(position) {
+            style.bold = true;
+            style.fg = Some(Color::YELLOW);
+        }
+    }
+    styles
+}
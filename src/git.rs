@@ -0,0 +1,340 @@
+//! Git integration: gutter signs against the index
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::diff::{diff_lines, DiffOp};
+use crate::hooks::EditorHook;
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GutterSign {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl GutterSign {
+    pub fn symbol(&self) -> char {
+        match self {
+            GutterSign::Added => '+',
+            GutterSign::Modified => '~',
+            GutterSign::Deleted => '-',
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            GutterSign::Added => Color::GREEN,
+            GutterSign::Modified => Color::YELLOW,
+            GutterSign::Deleted => Color::RED,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GitGutter {
+    /// Contents of the file in the git index, None if not tracked
+    index_lines: Option<Vec<String>>,
+    signs: Vec<Option<GutterSign>>,
+    /// Rows the signs were last computed for
+    computed_at: Option<RowsKey>,
+}
+
+/// Reads the index version of the file again whenever it's opened or saved
+pub struct GitRefresh;
+
+impl EditorHook for GitRefresh {
+    fn on_open(&mut self, editor: &mut Editor) {
+        editor.editor_git_refresh();
+    }
+
+    fn on_save_post(&mut self, editor: &mut Editor) {
+        editor.editor_git_refresh();
+    }
+}
+
+impl GitGutter {
+    pub fn is_active(&self) -> bool {
+        self.index_lines.is_some()
+    }
+
+    pub fn sign(&self, row: usize) -> Option<GutterSign> {
+        self.signs.get(row).copied().flatten()
+    }
+}
+
+/// Runs git in the directory containing `filename`
+fn git_command(filename: &str) -> Command {
+    let path = Path::new(filename);
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null());
+    command
+}
+
+/// Contents of `filename` as staged in the git index
+fn git_index_contents(filename: &str) -> Option<String> {
+    git_show(filename, "")
+}
+
+/// Contents of `filename` in the HEAD commit
+pub fn git_head_contents(filename: &str) -> Option<String> {
+    git_show(filename, "HEAD")
+}
+
+/// Contents of `filename` at `revision`, or in the index if it is empty
+fn git_show(filename: &str, revision: &str) -> Option<String> {
+    let name = Path::new(filename).file_name()?.to_str()?;
+    let output = git_command(filename)
+        .arg("show")
+        .arg(format!("{}:./{}", revision, name))
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Per row signs for `current` compared to `base`
+pub fn compute_signs<S: AsRef<str>, T: AsRef<str>>(
+    base: &[S],
+    current: &[T],
+) -> Vec<Option<GutterSign>> {
+    let mut signs = vec![None; current.len()];
+    let last_row = current.len().saturating_sub(1);
+    let ops = diff_lines(base, current);
+
+    let mut i = 0;
+    while i < ops.len() {
+        if let DiffOp::Equal(..) = ops[i] {
+            i += 1;
+            continue;
+        }
+
+        // Collect a run of changes
+        let mut deleted = 0;
+        let mut inserted = vec![];
+        while i < ops.len() {
+            match ops[i] {
+                DiffOp::Delete(_) => deleted += 1,
+                DiffOp::Insert(n) => inserted.push(n),
+                DiffOp::Equal(_, n) => {
+                    if inserted.is_empty() && deleted > 0 && !signs.is_empty() {
+                        // Pure deletion, mark the line following it
+                        signs[n.min(last_row)] = Some(GutterSign::Deleted);
+                    }
+                    break;
+                }
+            }
+            i += 1;
+        }
+
+        if i == ops.len() && inserted.is_empty() && deleted > 0 && !signs.is_empty() {
+            signs[last_row] = Some(GutterSign::Deleted);
+        }
+
+        for (idx, row) in inserted.into_iter().enumerate() {
+            signs[row] = Some(if idx < deleted {
+                GutterSign::Modified
+            } else {
+                GutterSign::Added
+            });
+        }
+    }
+
+    signs
+}
+
+impl Editor {
+    /// Re-reads the file from the git index, called on open and save
+    pub fn editor_git_refresh(&mut self) {
+        self.buffer.git_gutter.index_lines = self
+            .buffer
+            .filename
+            .as_deref()
+            .and_then(git_index_contents)
+            .map(|contents| split_lines(&contents));
+        self.buffer.git_gutter.computed_at = None;
+        self.editor_update_git_signs();
+    }
+
+    /// Recomputes gutter signs if the buffer changed. Returns true if they were updated.
+    pub fn editor_update_git_signs(&mut self) -> bool {
+        if self.buffer.git_gutter.computed_at == Some(self.editor_rows_key()) {
+            return false;
+        }
+
+        let current: Vec<&str> = self
+            .buffer
+            .rows
+            .iter()
+            .map(|row| row.chars.as_str())
+            .collect();
+        self.buffer.git_gutter.signs = match &self.buffer.git_gutter.index_lines {
+            Some(index_lines) => compute_signs(index_lines, &current),
+            None => vec![],
+        };
+        self.buffer.git_gutter.computed_at = Some(self.editor_rows_key());
+
+        true
+    }
+}
+
+/// Formats a unix timestamp as YYYY-MM-DD (UTC)
+pub fn format_date(timestamp: i64) -> String {
+    // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let days = timestamp.div_euclid(86400);
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+impl Editor {
+    /// Shows who last changed the cursor line in the message bar
+    pub fn editor_git_blame(&mut self) {
+        let filename = match &self.buffer.filename {
+            Some(filename) => filename.clone(),
+            None => {
+                self.editor_set_status_message("Blame needs a file name");
+                return;
+            }
+        };
+        if self.buffer.cursor_y >= self.get_num_rows() {
+            self.editor_set_status_message("No line to blame");
+            return;
+        }
+        let name = match Path::new(&filename)
+            .file_name()
+            .and_then(|name| name.to_str())
+        {
+            Some(name) => name.to_string(),
+            None => return,
+        };
+
+        // Blame the buffer contents so unsaved edits keep line numbers in sync
+        let line = self.buffer.cursor_y + 1;
+        let contents = self.editor_rows_to_string();
+        let child = git_command(&filename)
+            .args(["blame", "--porcelain", "--contents", "-"])
+            .arg("-L")
+            .arg(format!("{},{}", line, line))
+            .arg("--")
+            .arg(&name)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn();
+
+        let output = child.and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(contents.as_bytes())?;
+            }
+            child.wait_with_output()
+        });
+
+        let output = match output {
+            Ok(output) if output.status.success() => output,
+            Ok(_) => {
+                self.editor_set_error_message("git blame failed (is the file tracked?)");
+                return;
+            }
+            Err(error) => {
+                self.editor_set_error_message(&format!("Could not run git: {}", error));
+                return;
+            }
+        };
+
+        let porcelain = String::from_utf8_lossy(&output.stdout);
+        let mut lines = porcelain.lines();
+        let hash = lines
+            .next()
+            .and_then(|header| header.split_whitespace().next())
+            .unwrap_or("");
+
+        if hash.chars().all(|c| c == '0') {
+            self.editor_set_status_message(&format!("{}: Not committed yet", line));
+            return;
+        }
+
+        let (mut author, mut date, mut summary) = ("", String::new(), "");
+        for field in lines {
+            if let Some(value) = field.strip_prefix("author ") {
+                author = value;
+            } else if let Some(value) = field.strip_prefix("author-time ") {
+                date = value.parse().map(format_date).unwrap_or_default();
+            } else if let Some(value) = field.strip_prefix("summary ") {
+                summary = value;
+            }
+        }
+
+        self.editor_set_status_message(&format!(
+            "{} {} {}: {}",
+            &hash[..hash.len().min(8)],
+            author,
+            date,
+            summary
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_rows_changed_from_the_index() {
+        let base = ["a", "b", "c", "d"];
+        let signs = compute_signs(&base, &["a", "B", "c", "d", "e"]);
+        assert_eq!(
+            signs,
+            [
+                None,
+                Some(GutterSign::Modified),
+                None,
+                None,
+                Some(GutterSign::Added)
+            ]
+        );
+        // A deletion is shown on the line after it, or the last line at the end
+        assert_eq!(
+            compute_signs(&base, &["a", "c", "d"]),
+            [None, Some(GutterSign::Deleted), None]
+        );
+        assert_eq!(
+            compute_signs(&base, &["a", "b"]),
+            [None, Some(GutterSign::Deleted)]
+        );
+    }
+
+    #[test]
+    fn draws_signs_in_the_gutter() {
+        let mut editor = Editor::new_headless(24, 80);
+        editor.options.change_marks = false;
+        editor.editor_replace_rows(0, 0, ["a", "b"].map(String::from).to_vec());
+        editor.buffer.git_gutter.index_lines = Some(vec!["a".to_string(), "b".to_string()]);
+        editor.editor_handle_keys([Key::Down, Key::Char('x')]);
+        editor.editor_idle();
+
+        let frame = editor.editor_frame();
+        assert_eq!(frame.lines[0].text(), "  a");
+        assert_eq!(frame.lines[1].text(), "~ xb");
+    }
+}
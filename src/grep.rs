@@ -0,0 +1,201 @@
+//! Project search: `grep PATTERN` searches every file of the project on a
+//! worker thread. Matches are appended to a results buffer and the quickfix
+//! list as they are found, one `file:line:column: text` row each; Enter on a
+//! row opens the match. `grep` alone shows the results buffer again.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread;
+
+use crate::hooks::InputHook;
+use crate::index::project_files;
+use crate::quickfix::{parse_diagnostics, Quickfix, QuickfixEntry};
+use crate::search::find_matches;
+use crate::workdir::absolute_path;
+use crate::*;
+
+/// The search stops after this many matches
+const MAX_MATCHES: usize = 10_000;
+
+#[derive(Default)]
+pub struct Grep {
+    pattern: String,
+    /// Matches from the worker, None once it finished
+    results: Option<Receiver<QuickfixEntry>>,
+    /// Working directory of the search, the rows name files relative to it
+    directory: PathBuf,
+    /// Slot of the results buffer, made by the first search
+    buffer: Option<usize>,
+}
+
+/// Enter on a row of the results buffer opens the match
+pub struct GrepResultKeys;
+
+impl InputHook for GrepResultKeys {
+    fn on_key(&mut self, editor: &mut Editor, key: Key) -> bool {
+        key == Key::Enter && editor.editor_showing_grep_results() && editor.editor_grep_open_row()
+    }
+}
+
+/// Worker: sends the matches of `pattern` in `files` until done or nobody is
+/// listening any more
+fn run_grep(pattern: String, files: Vec<String>, results: Sender<QuickfixEntry>) {
+    let mut count = 0;
+    for file in files {
+        // Binary and unreadable files are skipped
+        let contents = match std::fs::read_to_string(&file) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+        for (line, text) in contents.lines().enumerate() {
+            for (start, _) in find_matches(text, &pattern) {
+                // Absolute, a `cd` while it runs would break relative names
+                let entry = QuickfixEntry {
+                    file: absolute_path(Path::new(&file))
+                        .to_string_lossy()
+                        .into_owned(),
+                    line: line + 1,
+                    column: start + 1,
+                    message: text.trim().to_string(),
+                };
+                count += 1;
+                if results.send(entry).is_err() || count >= MAX_MATCHES {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl Editor {
+    /// `grep PATTERN`: searches the project into the results buffer
+    pub fn editor_grep(&mut self, pattern: &str) {
+        if pattern.is_empty() {
+            match self.grep.buffer {
+                Some(buffer) => self.editor_select_buffer(buffer),
+                None => self.editor_set_error_message("Usage: grep PATTERN"),
+            }
+            return;
+        }
+
+        let (sender, receiver) = channel();
+        let files = project_files();
+        let worker_pattern = pattern.to_string();
+        thread::spawn(move || run_grep(worker_pattern, files, sender));
+
+        // Replacing the receiver stops an earlier search still running
+        self.grep.pattern = pattern.to_string();
+        self.grep.results = Some(receiver);
+        self.grep.directory = std::env::current_dir().unwrap_or_default();
+        self.quickfix = Quickfix::default();
+
+        match self.grep.buffer {
+            Some(buffer) => self.editor_select_buffer(buffer),
+            None => {
+                self.buffers.push(Buffer::default());
+                let buffer = self.buffers.len() - 1;
+                self.editor_select_buffer(buffer);
+                self.grep.buffer = Some(buffer);
+            }
+        }
+        // Not a file, nothing to save
+        self.buffer.file_format.read_only = true;
+        self.buffer.rows.clear();
+        self.revision += 1;
+        self.buffer.undo.clear();
+        self.buffer.dirty = 0;
+        self.editor_goto(0, 0);
+        self.editor_set_status_message(&format!("grep {}: searching...", pattern));
+        self.editor_grep_poll();
+    }
+
+    /// Whether the shown buffer is the results buffer
+    pub fn editor_showing_grep_results(&self) -> bool {
+        self.grep.buffer == Some(self.current_buffer)
+    }
+
+    /// Appends the matches found since the last call to the quickfix list and
+    /// the results buffer, true if there were any or the search finished
+    pub fn editor_grep_poll(&mut self) -> bool {
+        let results = match &self.grep.results {
+            Some(results) => results,
+            None => return false,
+        };
+        let mut found = vec![];
+        let mut finished = false;
+        loop {
+            match results.try_recv() {
+                Ok(entry) => found.push(entry),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+
+        let directory = &self.grep.directory;
+        let rows: Vec<EditorRow> = found
+            .iter()
+            .map(|entry| {
+                let path = Path::new(&entry.file);
+                let file = path.strip_prefix(directory).unwrap_or(path);
+                let text = format!(
+                    "{}:{}:{}: {}",
+                    file.display(),
+                    entry.line,
+                    entry.column,
+                    entry.message
+                );
+                EditorRow::new(text)
+            })
+            .collect();
+        if let Some(buffer) = self.grep.buffer {
+            self.editor_buffer_rows_mut(buffer).extend(rows);
+            self.revision += 1;
+        }
+        let changed = finished || !found.is_empty();
+        for entry in found {
+            self.quickfix.push(entry);
+        }
+        if finished {
+            self.grep.results = None;
+            self.editor_set_status_message(&format!(
+                "grep {}: {} matches",
+                self.grep.pattern,
+                self.quickfix.len()
+            ));
+        }
+        changed
+    }
+
+    /// Status bar tag while a search is running
+    pub fn editor_grep_status(&self) -> Option<String> {
+        self.grep
+            .results
+            .as_ref()
+            .map(|_| format!("[grep: {} matches]", self.quickfix.len()))
+    }
+
+    /// Opens the match on the cursor's row of the results buffer, in a buffer
+    /// of its own. False if the row isn't a match.
+    fn editor_grep_open_row(&mut self) -> bool {
+        let entry = match self
+            .buffer
+            .rows
+            .get(self.buffer.cursor_y)
+            .and_then(|row| parse_diagnostics(&row.chars).pop())
+        {
+            Some(entry) => entry,
+            None => return false,
+        };
+        let file = self.grep.directory.join(&entry.file);
+        let message = entry.message.clone();
+        if !self.editor_switch_file(&file.to_string_lossy()) {
+            return true;
+        }
+        self.editor_goto(entry.line.saturating_sub(1), entry.column.saturating_sub(1));
+        self.editor_set_status_message(&message);
+        true
+    }
+}
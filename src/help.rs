@@ -0,0 +1,38 @@
+//! F1 / `help`: the key bindings and commands in a scrollable overlay. Enter
+//! on a command starts typing it at the command prompt.
+
+use crate::command::COMMANDS;
+use crate::keymap::KEYMAP;
+use crate::overlay::OverlayLine;
+use crate::style::Color;
+use crate::*;
+
+impl Editor {
+    /// Shows the key bindings and commands
+    pub fn editor_help(&mut self) {
+        let mut lines = vec![OverlayLine::colored("Keys".to_string(), Color::CYAN)];
+        for binding in KEYMAP {
+            lines.push(OverlayLine::new(format!(
+                "  {:<22} {}",
+                binding.label, binding.description
+            )));
+        }
+        lines.push(OverlayLine::new(String::new()));
+        lines.push(OverlayLine::colored(
+            "Commands (Ctrl-P, Enter here to type one)".to_string(),
+            Color::CYAN,
+        ));
+        let first_command = lines.len();
+        for (name, description) in COMMANDS {
+            lines.push(OverlayLine::new(format!("  {:<22} {}", name, description)));
+        }
+
+        let selected = self.editor_show_overlay("Help", &lines);
+        if let Some((name, _)) = selected
+            .and_then(|selected| selected.checked_sub(first_command))
+            .and_then(|index| COMMANDS.get(index))
+        {
+            self.editor_prompt_command(name);
+        }
+    }
+}
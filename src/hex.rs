@@ -0,0 +1,286 @@
+//! Hex mode for binary files: files with NUL bytes open as rows of offset,
+//! hex bytes and their ASCII, and `hex` switches any file in and out of it.
+//! Bytes are overwritten in place, a nibble at a time in the hex column or a
+//! char at a time in the ASCII one (Tab moves between them), and saved as
+//! they are. The bytes take the place of the rows while the mode is on.
+
+use crate::hooks::InputHook;
+use crate::*;
+
+const OFFSET_COLOR: Color = Color::BRIGHT_BLACK;
+/// Width of the offset column with the space after it
+const OFFSET_WIDTH: usize = 10;
+/// Files with a NUL byte this close to the start are binary
+const BINARY_CHECK_LENGTH: usize = 8000;
+
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_CHECK_LENGTH)].contains(&0)
+}
+
+pub struct HexView {
+    bytes: Vec<u8>,
+    /// Cursor in nibbles: byte `cursor / 2`, its high nibble when even
+    cursor: usize,
+    /// First line shown
+    offset: usize,
+    /// Typing goes to the ASCII column instead of the hex one
+    ascii: bool,
+}
+
+impl HexView {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        HexView {
+            bytes,
+            cursor: 0,
+            offset: 0,
+            ascii: false,
+        }
+    }
+
+    /// Moves the cursor by `nibbles`, staying within the bytes
+    fn move_cursor(&mut self, nibbles: isize) {
+        let last = (self.bytes.len() * 2).saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(nibbles).min(last);
+        if self.ascii {
+            self.cursor -= self.cursor % 2;
+        }
+    }
+}
+
+/// Bytes shown per line: as many of 16, 8 and 4 as fit in `columns`, each
+/// taking three columns for its hex and one for its char
+fn bytes_per_line(columns: usize) -> usize {
+    [16, 8, 4]
+        .into_iter()
+        .find(|&count| OFFSET_WIDTH + count * 4 + 2 <= columns)
+        .unwrap_or(4)
+}
+
+/// Keys of hex mode. Only saving, quitting and the command prompt get
+/// through to the editor, the rest would edit the rows that aren't shown.
+#[derive(Default)]
+pub struct HexKeys;
+
+impl InputHook for HexKeys {
+    fn on_key(&mut self, editor: &mut Editor, key: Key) -> bool {
+        let per_line = bytes_per_line(editor.screen_num_columns) as isize;
+        let page = editor.screen_num_rows as isize * per_line * 2;
+        let hex = match editor.buffer.hex.as_mut() {
+            Some(hex) => hex,
+            None => return false,
+        };
+        let step = if hex.ascii { 2 } else { 1 };
+        let column = (hex.cursor / 2) as isize % per_line;
+        match key {
+            Key::Ctrl('s' | 'q' | 'p') => return false,
+            Key::Left | Key::Backspace => hex.move_cursor(-step),
+            Key::Right => hex.move_cursor(step),
+            Key::Up => hex.move_cursor(-per_line * 2),
+            Key::Down => hex.move_cursor(per_line * 2),
+            Key::PageUp => hex.move_cursor(-page),
+            Key::PageDown => hex.move_cursor(page),
+            Key::Home => hex.move_cursor(-column * 2 - (hex.cursor % 2) as isize),
+            Key::End => hex.move_cursor((per_line - 1 - column) * 2),
+            Key::Tab => {
+                hex.ascii = !hex.ascii;
+                hex.move_cursor(0);
+            }
+            Key::Char(_) if hex.bytes.is_empty() => {
+                editor.editor_set_status_message("Empty file, there are no bytes to change");
+            }
+            Key::Char(c) if hex.ascii && c.is_ascii() && !c.is_ascii_control() => {
+                hex.bytes[hex.cursor / 2] = c as u8;
+                hex.move_cursor(2);
+                editor.buffer.dirty += 1;
+            }
+            Key::Char(c) if !hex.ascii && c.is_ascii_hexdigit() => {
+                let nibble = c.to_digit(16).unwrap_or(0) as u8;
+                let byte = &mut hex.bytes[hex.cursor / 2];
+                *byte = if hex.cursor % 2 == 0 {
+                    (*byte & 0x0f) | nibble << 4
+                } else {
+                    (*byte & 0xf0) | nibble
+                };
+                hex.move_cursor(1);
+                editor.buffer.dirty += 1;
+            }
+            Key::Char(_) if hex.ascii => {
+                editor.editor_set_status_message("Only ASCII chars can be typed here");
+            }
+            Key::Char(_) => {
+                editor.editor_set_status_message("Type hex digits, Tab to type chars");
+            }
+            _ => {}
+        }
+        true
+    }
+}
+
+impl Editor {
+    /// Keeps the cursor line of hex mode on the screen
+    pub fn editor_hex_scroll(&mut self) {
+        let per_line = bytes_per_line(self.screen_num_columns);
+        let rows = self.screen_num_rows.max(1);
+        if let Some(hex) = self.buffer.hex.as_mut() {
+            let line = hex.cursor / 2 / per_line;
+            if line < hex.offset {
+                hex.offset = line;
+            } else if line >= hex.offset + rows {
+                hex.offset = line + 1 - rows;
+            }
+        }
+    }
+
+    /// Draws the hex rows instead of the text, false if hex mode is off
+    pub fn editor_draw_hex(&self, lines: &mut Vec<FrameLine>) -> bool {
+        let hex = match &self.buffer.hex {
+            Some(hex) => hex,
+            None => return false,
+        };
+        let per_line = bytes_per_line(self.screen_num_columns);
+        let margin = " ".repeat(self.left_margin() + self.gutter_width());
+        let cursor_byte = hex.cursor / 2;
+        // The cursor's byte is picked out in the column the cursor isn't in
+        let picked = CellStyle {
+            inverse: true,
+            ..CellStyle::default()
+        };
+        let unprintable = CellStyle {
+            dim: true,
+            ..CellStyle::default()
+        };
+
+        for y in 0..self.screen_num_rows {
+            let mut line = FrameLine::default();
+            let start = (hex.offset + y) * per_line;
+            if start >= hex.bytes.len() && !(start == 0 && y == 0) {
+                line.push("~");
+                lines.push(line);
+                continue;
+            }
+            line.push(&margin);
+            line.push_styled(
+                &format!("{:08x}  ", start),
+                CellStyle {
+                    fg: Some(OFFSET_COLOR),
+                    ..CellStyle::default()
+                },
+            );
+            let bytes = &hex.bytes[start..(start + per_line).min(hex.bytes.len())];
+            for (i, &byte) in bytes.iter().enumerate() {
+                let style = match byte {
+                    _ if hex.ascii && start + i == cursor_byte => picked,
+                    0 => unprintable,
+                    _ => CellStyle::default(),
+                };
+                line.push_styled(&format!("{:02x}", byte), style);
+                line.push(" ");
+            }
+            line.push(&"   ".repeat(per_line - bytes.len()));
+            line.push("|");
+            for (i, &byte) in bytes.iter().enumerate() {
+                let printable = byte.is_ascii() && !byte.is_ascii_control();
+                let style = match printable {
+                    _ if !hex.ascii && start + i == cursor_byte => picked,
+                    true => CellStyle::default(),
+                    false => unprintable,
+                };
+                let c = if printable { byte as char } else { '.' };
+                line.push_styled(c.encode_utf8(&mut [0; 4]), style);
+            }
+            line.push("|");
+            lines.push(line);
+        }
+        true
+    }
+
+    /// Where the cursor goes on the screen in hex mode, left of the gutter
+    pub fn editor_hex_cursor_position(&self) -> Option<(usize, usize)> {
+        let hex = self.buffer.hex.as_ref()?;
+        let per_line = bytes_per_line(self.screen_num_columns);
+        let byte = hex.cursor / 2;
+        let column = byte % per_line;
+        let x = if hex.ascii {
+            OFFSET_WIDTH + per_line * 3 + 1 + column
+        } else {
+            OFFSET_WIDTH + column * 3 + hex.cursor % 2
+        };
+        Some(((byte / per_line).saturating_sub(hex.offset), x))
+    }
+
+    /// Status bar tag in hex mode: the cursor's offset and the file size
+    pub fn editor_hex_status(&self) -> Option<String> {
+        let hex = self.buffer.hex.as_ref()?;
+        Some(format!(
+            "[hex 0x{:x}/0x{:x}]",
+            hex.cursor / 2,
+            hex.bytes.len()
+        ))
+    }
+
+    /// Size of the file in hex mode
+    pub fn editor_hex_len(&self) -> Option<usize> {
+        Some(self.buffer.hex.as_ref()?.bytes.len())
+    }
+
+    /// The bytes to save in hex mode
+    pub fn editor_hex_bytes(&self) -> Option<Vec<u8>> {
+        Some(self.buffer.hex.as_ref()?.bytes.clone())
+    }
+
+    /// `hex`: switches to hex mode, or back to the text if the bytes can be
+    /// read as text
+    pub fn editor_toggle_hex(&mut self) {
+        match self.buffer.hex.take() {
+            Some(hex) => {
+                let text = match self.buffer.file_format.decode(&hex.bytes) {
+                    Ok(text) => text,
+                    Err(error) => {
+                        self.buffer.hex = Some(hex);
+                        self.editor_set_status_message(&format!(
+                            "Staying in hex mode, the bytes are {}",
+                            error
+                        ));
+                        return;
+                    }
+                };
+                let lines = self.buffer.file_format.split_lines(&text);
+                self.buffer.rows = lines.into_iter().map(EditorRow::new).collect();
+                self.revision += 1;
+                let byte = hex.cursor / 2;
+                let row = hex.bytes[..byte].iter().filter(|&&b| b == b'\n').count();
+                self.editor_goto(row, 0);
+            }
+            None => {
+                let bytes = match self
+                    .buffer
+                    .file_format
+                    .encode(&self.editor_rows_to_string())
+                {
+                    Ok(bytes) => bytes,
+                    Err(error) => {
+                        self.editor_set_status_message(&error);
+                        return;
+                    }
+                };
+                // The cursor goes to the first byte of its line
+                let line_start: usize = self.buffer.rows
+                    [..self.buffer.cursor_y.min(self.get_num_rows())]
+                    .iter()
+                    .map(|row| row.len() + 1)
+                    .sum();
+                let mut hex = HexView::new(bytes);
+                hex.move_cursor((line_start * 2) as isize);
+                self.buffer.hex = Some(hex);
+                self.buffer.rows.clear();
+                self.revision += 1;
+                self.buffer.cursor_x = 0;
+                self.buffer.cursor_y = 0;
+                self.buffer.mark = None;
+            }
+        }
+        // Undo steps refer to rows as they were before
+        self.buffer.undo.clear();
+        self.editor_invalidate_screen();
+    }
+}
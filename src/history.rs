@@ -0,0 +1,159 @@
+//! Prompt history: search queries, commands and file names entered in the
+//! prompts, browsed with Up/Down and kept across sessions in the state
+//! directory
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::storage::{read_atomic, state_dir, write_atomic};
+
+const HISTORY_HEADER: &str = "kilo-history 1";
+/// Entries kept per prompt
+const HISTORY_SIZE: usize = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HistoryKind {
+    Search,
+    Command,
+    File,
+    Char,
+}
+
+impl HistoryKind {
+    fn name(self) -> &'static str {
+        match self {
+            HistoryKind::Search => "search",
+            HistoryKind::Command => "command",
+            HistoryKind::File => "file",
+            HistoryKind::Char => "char",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "search" => Some(HistoryKind::Search),
+            "command" => Some(HistoryKind::Command),
+            "file" => Some(HistoryKind::File),
+            "char" => Some(HistoryKind::Char),
+            _ => None,
+        }
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("history"))
+}
+
+/// Parses the history file: `KIND ENTRY` lines after the header, oldest first
+fn read_history_file(path: &Path) -> HashMap<HistoryKind, Vec<String>> {
+    let mut history: HashMap<HistoryKind, Vec<String>> = HashMap::new();
+    let contents = match read_atomic(path, HISTORY_HEADER) {
+        Some(contents) => contents,
+        None => return history,
+    };
+
+    for line in contents.lines() {
+        if let Some((kind, entry)) = line.split_once(' ') {
+            if let Some(kind) = HistoryKind::from_name(kind) {
+                history.entry(kind).or_default().push(entry.to_string());
+            }
+        }
+    }
+    history
+}
+
+fn write_history_file(
+    path: &Path,
+    history: &HashMap<HistoryKind, Vec<String>>,
+) -> std::io::Result<()> {
+    let mut contents = format!("{}\n", HISTORY_HEADER);
+    for kind in [
+        HistoryKind::Search,
+        HistoryKind::Command,
+        HistoryKind::File,
+        HistoryKind::Char,
+    ] {
+        for entry in history.get(&kind).into_iter().flatten() {
+            contents.push_str(&format!("{} {}\n", kind.name(), entry));
+        }
+    }
+    write_atomic(path, contents)
+}
+
+/// Entries of one prompt's history, oldest first
+fn history_entries(kind: HistoryKind) -> Vec<String> {
+    history_path()
+        .map(|path| read_history_file(&path).remove(&kind).unwrap_or_default())
+        .unwrap_or_default()
+}
+
+/// Adds `entry` as the newest of its prompt's history. The file is read again
+/// first so entries added by other sessions are kept.
+pub fn add_history_entry(kind: HistoryKind, entry: &str) {
+    let entry = entry.replace(['\n', '\r'], " ");
+    if entry.trim().is_empty() {
+        return;
+    }
+    let path = match history_path() {
+        Some(path) => path,
+        None => return,
+    };
+
+    let mut history = read_history_file(&path);
+    let entries = history.entry(kind).or_default();
+    entries.retain(|existing| *existing != entry);
+    entries.push(entry);
+    if entries.len() > HISTORY_SIZE {
+        entries.drain(..entries.len() - HISTORY_SIZE);
+    }
+    // History is a convenience, a failed write shouldn't interrupt editing
+    let _ = write_history_file(&path, &history);
+}
+
+/// Up/Down browsing through a prompt's history. Only entries starting with
+/// the text typed before browsing are shown.
+pub struct HistoryBrowser {
+    entries: Vec<String>,
+    /// Index in `entries` of the entry shown, None while showing the typed text
+    index: Option<usize>,
+    typed: String,
+}
+
+impl HistoryBrowser {
+    pub fn new(kind: HistoryKind) -> Self {
+        HistoryBrowser {
+            entries: history_entries(kind),
+            index: None,
+            typed: String::new(),
+        }
+    }
+
+    /// Text to show after Up (`older`) or Down with `current` in the prompt,
+    /// None if there is no entry further that way
+    pub fn step(&mut self, older: bool, current: &str) -> Option<String> {
+        if self.index.is_none() {
+            self.typed = current.to_string();
+        }
+        let matches =
+            |i: &usize| self.entries[*i].starts_with(&self.typed) && self.entries[*i] != current;
+
+        if older {
+            let end = self.index.unwrap_or(self.entries.len());
+            let found = (0..end).rev().find(matches)?;
+            self.index = Some(found);
+            Some(self.entries[found].clone())
+        } else {
+            let start = self.index? + 1;
+            match (start..self.entries.len()).find(matches) {
+                Some(found) => {
+                    self.index = Some(found);
+                    Some(self.entries[found].clone())
+                }
+                None => {
+                    self.index = None;
+                    Some(self.typed.clone())
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Hooks: extensions the editor calls into. Input hooks see each key typed
+//! into the buffer before the editor handles it, and may handle it
+//! themselves. Editor hooks are told about opening and saving files, typed
+//! chars and prompts or overlays opening; features such as the git gutter and
+//! format on save attach to those instead of being called directly.
+
+use crate::*;
+
+pub trait InputHook {
+    /// Called with each key before it is handled, returns true if the hook
+    /// handled it. Hooks after one that handled a key don't see it.
+    fn on_key(&mut self, editor: &mut Editor, key: Key) -> bool;
+}
+
+/// What has the keyboard: the buffer, a message bar prompt or an overlay
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    Edit,
+    Prompt,
+    Overlay,
+}
+
+/// Receives editor events, all of them do nothing unless implemented
+pub trait EditorHook {
+    /// A file was read into the buffer
+    fn on_open(&mut self, _editor: &mut Editor) {}
+    /// The buffer is about to be saved, changes made here are saved with it
+    fn on_save_pre(&mut self, _editor: &mut Editor) {}
+    /// The buffer was saved
+    fn on_save_post(&mut self, _editor: &mut Editor) {}
+    /// `c` was typed into the buffer, the cursor is right after it
+    fn on_char_insert(&mut self, _editor: &mut Editor, _c: char) {}
+    /// The keyboard went to `mode`
+    fn on_mode_change(&mut self, _editor: &mut Editor, _mode: Mode) {}
+}
+
+impl Editor {
+    /// Offers `key` to the input hooks in order, true if one handled it
+    pub fn editor_run_input_hooks(&mut self, key: Key) -> bool {
+        // Hooks get the editor mutably, so they are moved out while they run
+        let mut hooks = std::mem::take(&mut self.input_hooks);
+        let handled = hooks.iter_mut().any(|hook| hook.on_key(self, key));
+        self.input_hooks = hooks;
+        handled
+    }
+
+    /// Adds a hook called after the ones already there
+    pub fn editor_add_hook(&mut self, hook: Box<dyn EditorHook>) {
+        self.editor_hooks.push(hook);
+    }
+
+    /// Calls `event` on every editor hook. Events the hooks cause themselves
+    /// while they run aren't passed on.
+    pub fn editor_run_hooks(&mut self, mut event: impl FnMut(&mut dyn EditorHook, &mut Editor)) {
+        let mut hooks = std::mem::take(&mut self.editor_hooks);
+        for hook in hooks.iter_mut() {
+            event(hook.as_mut(), self);
+        }
+        // Hooks added while they ran go after the others
+        hooks.append(&mut self.editor_hooks);
+        self.editor_hooks = hooks;
+    }
+
+    /// Gives the keyboard to `mode`, returning the mode it had
+    pub fn editor_set_mode(&mut self, mode: Mode) -> Mode {
+        let previous = std::mem::replace(&mut self.mode, mode);
+        if previous != mode {
+            self.editor_run_hooks(|hook, editor| hook.on_mode_change(editor, mode));
+        }
+        previous
+    }
+}
@@ -0,0 +1,246 @@
+//! Brace based indent engine and the reindent command
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::*;
+
+/// Returns the indentation unit used by the buffer: a tab if most indented
+/// lines start with one, otherwise spaces matching the smallest indent seen
+pub fn detect_indent_unit(lines: &[&str]) -> String {
+    let mut tabs = 0;
+    let mut spaces = 0;
+    let mut smallest_space_indent = usize::MAX;
+    for line in lines {
+        if line.starts_with('\t') {
+            tabs += 1;
+        } else if line.starts_with(' ') && !line.trim().is_empty() {
+            spaces += 1;
+            let width = line.len() - line.trim_start_matches(' ').len();
+            smallest_space_indent = smallest_space_indent.min(width);
+        }
+    }
+
+    if tabs > spaces {
+        "\t".to_string()
+    } else if spaces > 0 && smallest_space_indent <= 8 {
+        " ".repeat(smallest_space_indent)
+    } else {
+        " ".repeat(4)
+    }
+}
+
+/// Extensions of the languages whose nesting the braces show. Others, like
+/// Python or YAML, nest by indentation that reindenting can't work out.
+const BRACE_EXTENSIONS: &[&str] = &[
+    "c", "h", "cc", "cpp", "hpp", "cs", "css", "scss", "go", "java", "js", "jsx", "ts", "tsx",
+    "json", "kt", "rs", "swift", "zig",
+];
+
+/// Skips a char literal after its opening quote, leaving a Rust lifetime
+/// (`'a`) as it is
+fn skip_char_literal(chars: &mut Peekable<Chars>) {
+    let mut ahead = chars.clone();
+    match ahead.next() {
+        Some('\\') => {
+            ahead.next();
+            if ahead.any(|c| c == '\'') {
+                *chars = ahead;
+            }
+        }
+        Some(_) if ahead.next() == Some('\'') => *chars = ahead,
+        _ => {}
+    }
+}
+
+/// Net change in nesting depth over `line`, and how many closers it starts
+/// with. Braces in strings, chars and comments don't count; `in_comment`
+/// carries a block comment on from one line to the next.
+fn brace_delta(line: &str, in_comment: &mut bool) -> (isize, usize) {
+    let mut delta = 0;
+    let mut leading_closers = 0;
+    let mut leading = true;
+    let mut in_string: Option<char> = None;
+    let mut chars = line.trim().chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if *in_comment {
+            if c == '*' && chars.next_if_eq(&'/').is_some() {
+                *in_comment = false;
+            }
+            continue;
+        }
+        if let Some(quote) = in_string {
+            if c == '\\' {
+                chars.next();
+            } else if c == quote {
+                in_string = None;
+            }
+            continue;
+        }
+
+        match c {
+            '"' | '`' => in_string = Some(c),
+            '\'' => skip_char_literal(&mut chars),
+            '/' if chars.peek() == Some(&'/') => break,
+            '/' if chars.next_if_eq(&'*').is_some() => {
+                *in_comment = true;
+                continue;
+            }
+            '#' if delta == 0 && leading => break,
+            '{' | '(' | '[' => delta += 1,
+            '}' | ')' | ']' => {
+                delta -= 1;
+                if leading {
+                    leading_closers += 1;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        leading = false;
+    }
+
+    (delta, leading_closers)
+}
+
+/// Computes new indentation for `lines[start..start + count]`, taking the
+/// nesting depth from the lines before the range into account
+pub fn reindent_lines(lines: &[&str], start: usize, count: usize, unit: &str) -> Vec<String> {
+    let mut depth: isize = 0;
+    let mut in_comment = false;
+    for line in &lines[..start] {
+        depth = (depth + brace_delta(line, &mut in_comment).0).max(0);
+    }
+
+    let mut result = Vec::with_capacity(count);
+    for line in &lines[start..start + count] {
+        let text = line.trim_start();
+        // The inside of a block comment keeps its own layout
+        let commented = in_comment;
+        let (delta, leading_closers) = brace_delta(line, &mut in_comment);
+
+        if text.is_empty() {
+            result.push(String::new());
+        } else if commented {
+            result.push(line.to_string());
+        } else {
+            let level = (depth - leading_closers as isize).max(0) as usize;
+            result.push(format!("{}{}", unit.repeat(level), text));
+        }
+
+        depth = (depth + delta).max(0);
+    }
+
+    result
+}
+
+impl Editor {
+    /// Reindents the selected rows (or the whole buffer) after confirming
+    pub fn editor_reindent(&mut self) {
+        let extension = self.editor_file_extension().unwrap_or_default();
+        if !BRACE_EXTENSIONS.contains(&extension.as_str()) {
+            self.editor_set_error_message(&format!(
+                "Reindent only knows brace languages, not '{}' files",
+                extension
+            ));
+            return;
+        }
+        let (start, count) = self.editor_selected_rows();
+        if count == 0 {
+            self.editor_set_status_message("Nothing to reindent");
+            return;
+        }
+
+        let lines: Vec<&str> = self
+            .buffer
+            .rows
+            .iter()
+            .map(|row| row.chars.as_str())
+            .collect();
+        let unit = detect_indent_unit(&lines);
+        let new_lines = reindent_lines(&lines, start, count, &unit);
+
+        let changed = new_lines
+            .iter()
+            .zip(&lines[start..start + count])
+            .filter(|(new, old)| new.as_str() != **old)
+            .count();
+        if changed == 0 {
+            self.editor_set_status_message("Indentation already consistent");
+            return;
+        }
+
+        if !self.editor_confirm(&format!(
+            "Reindent will change {} lines. Apply? (y/n)",
+            changed
+        )) {
+            self.editor_set_status_message("Reindent cancelled");
+            return;
+        }
+
+        self.editor_replace_rows(start, count, new_lines);
+        self.buffer.mark = None;
+        self.editor_set_status_message(&format!("Reindented {} lines", changed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_braces_in_strings_chars_and_comments() {
+        let lines = [
+            "fn main() {",
+            "let open = '{';",
+            "let s = \"}}\"; // }",
+            "/* {",
+            "   keeps its layout }",
+            "*/",
+            "f::<'a>(x, '\\'');",
+            "if x {  ",
+            "y",
+            "}",
+            "}",
+        ];
+        assert_eq!(
+            reindent_lines(&lines, 0, lines.len(), "    "),
+            [
+                "fn main() {",
+                "    let open = '{';",
+                "    let s = \"}}\"; // }",
+                "    /* {",
+                "   keeps its layout }",
+                "*/",
+                "    f::<'a>(x, '\\'');",
+                "    if x {  ",
+                "        y",
+                "    }",
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn reindents_brace_languages_only() {
+        let mut editor = Editor::new_headless(24, 80);
+        let lines = ["def f():", "    return 1"].map(String::from).to_vec();
+        editor.editor_replace_rows(0, 0, lines);
+        editor.buffer.filename = Some("f.py".to_string());
+        editor.editor_reindent();
+        assert!(editor.status_is_error);
+        assert_eq!(editor.buffer.rows[1].chars, "    return 1");
+
+        editor.buffer.filename = Some("f.rs".to_string());
+        editor.editor_replace_rows(
+            0,
+            2,
+            vec!["fn f() {".to_string(), "1".to_string(), "}".to_string()],
+        );
+        editor.pending_keys.push_back(Key::Char('y'));
+        editor.editor_reindent();
+        assert!(!editor.status_is_error);
+        assert_eq!(editor.buffer.rows[1].chars, "    1");
+    }
+}
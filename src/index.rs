@@ -0,0 +1,612 @@
+//! Background index of the project under the working directory: file paths,
+//! symbols (from ctags when installed, else a keyword scan) and word counts.
+//!
+//! The index is off by default (`set index`). It is built on a worker thread
+//! the first time it is needed and saved in the cache directory. A file the
+//! editor reads or writes is indexed again right away; the whole project is
+//! rescanned for other changes when the index is used after a while, re-reading
+//! only the files whose modification time changed. ctags runs once per batch
+//! of files, not once per file.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use crate::storage::{cache_dir, read_atomic, write_atomic};
+use crate::workdir::absolute_path;
+use crate::*;
+
+const INDEX_HEADER: &str = "kilo-index 1";
+/// The project is scanned again when the index is used this long after the
+/// last scan
+const RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+/// Files passed to one ctags run
+const CTAGS_BATCH: usize = 200;
+/// Files listed outside a git repository
+const MAX_FILES: usize = 50_000;
+/// Larger files are listed but not read
+const MAX_INDEXED_SIZE: u64 = 1 << 20;
+
+#[derive(Clone)]
+pub struct Symbol {
+    pub name: String,
+    /// e.g. "function", "struct"
+    pub kind: String,
+    /// Zero based
+    pub line: usize,
+}
+
+#[derive(Default, Clone)]
+struct FileEntry {
+    /// Seconds since the epoch, files are re-read when it changes
+    modified: u64,
+    symbols: Vec<Symbol>,
+    words: HashMap<String, usize>,
+}
+
+#[derive(Default)]
+pub struct IndexData {
+    files: HashMap<String, FileEntry>,
+    /// Set once the first scan finished
+    ready: bool,
+}
+
+/// Handle to the index shared with the worker thread, None until started.
+/// Dropping it stops the worker, even in the middle of a scan.
+#[derive(Default)]
+pub struct ProjectIndex {
+    data: Option<Arc<Mutex<IndexData>>>,
+    /// What the worker should look at again
+    requests: Option<Sender<Refresh>>,
+    rescanned_at: Option<Instant>,
+    stop: Arc<AtomicBool>,
+}
+
+/// Asks the worker to bring part of the index up to date
+enum Refresh {
+    /// Every file of the project
+    Project,
+    /// One file, relative to the working directory
+    File(String),
+}
+
+impl Drop for ProjectIndex {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Files under the working directory that git doesn't ignore, None outside a
+/// git repository
+fn git_files() -> Option<Vec<String>> {
+    let output = Command::new("git")
+        .args(["ls-files", "--cached", "--others", "--exclude-standard"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Files under `dir`, skipping hidden files and directories
+fn walk_files(dir: &Path, files: &mut Vec<String>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        if files.len() >= MAX_FILES {
+            return;
+        }
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => walk_files(&path, files),
+            Ok(_) => {
+                let path = path.strip_prefix("./").unwrap_or(&path);
+                files.push(path.to_string_lossy().into_owned());
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Files of the project, respecting .gitignore inside a git repository
+pub fn project_files() -> Vec<String> {
+    git_files().unwrap_or_else(|| {
+        let mut files = vec![];
+        walk_files(Path::new("."), &mut files);
+        files
+    })
+}
+
+fn index_path() -> Option<PathBuf> {
+    let cache = cache_dir()?;
+    let cwd = std::env::current_dir().ok()?;
+    let name: String = cwd
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c == '/' { '%' } else { c })
+        .collect();
+    Some(cache.join("index").join(name))
+}
+
+/// Symbol kinds recognized by the keyword scan, by the keyword before the name
+fn keyword_kind(keyword: &str) -> Option<&'static str> {
+    match keyword {
+        "fn" | "def" | "func" | "function" => Some("function"),
+        "struct" => Some("struct"),
+        "enum" => Some("enum"),
+        "trait" | "interface" => Some("interface"),
+        "class" => Some("class"),
+        "type" => Some("type"),
+        "mod" | "module" | "namespace" => Some("module"),
+        "const" | "static" => Some("constant"),
+        "macro_rules!" => Some("macro"),
+        _ => None,
+    }
+}
+
+/// Definitions found by looking for `keyword name` at the start of lines
+/// (after visibility and other modifiers)
+fn scan_symbols(contents: &str) -> Vec<Symbol> {
+    let modifiers = [
+        "pub",
+        "pub(crate)",
+        "async",
+        "unsafe",
+        "export",
+        "default",
+        "static",
+        "extern",
+    ];
+    let mut symbols = vec![];
+    for (line, text) in contents.lines().enumerate() {
+        let mut words = text
+            .split(|c: char| c.is_whitespace() || c == '(' || c == ':' || c == '<' || c == '{')
+            .filter(|word| !word.is_empty())
+            .skip_while(|word| modifiers.contains(word));
+        let (keyword, name) = match (words.next(), words.next()) {
+            (Some(keyword), Some(name)) => (keyword, name),
+            _ => continue,
+        };
+        let kind = match keyword_kind(keyword) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let name: String = name
+            .chars()
+            .take_while(|&c| c.is_alphanumeric() || c == '_')
+            .collect();
+        if !name.is_empty() {
+            symbols.push(Symbol {
+                name,
+                kind: kind.to_string(),
+                line,
+            });
+        }
+    }
+    symbols
+}
+
+/// Definitions reported by ctags by file, None if ctags failed
+fn ctags_symbols(paths: &[&str]) -> Option<HashMap<String, Vec<Symbol>>> {
+    let output = Command::new("ctags")
+        .args(["-f", "-", "--fields=Kn"])
+        .args(paths)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // name<TAB>file<TAB>address;"<TAB>kind<TAB>line:N
+    let mut symbols: HashMap<String, Vec<Symbol>> = HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let mut fields = line.split('\t');
+        let (name, file) = match (fields.next(), fields.next()) {
+            (Some(name), Some(file)) => (name, file),
+            _ => continue,
+        };
+        let mut kind = String::new();
+        let mut number = None;
+        for field in fields.skip(1) {
+            match field.strip_prefix("line:") {
+                Some(line) => number = line.parse::<usize>().ok(),
+                None if !field.contains(':') => kind = field.to_string(),
+                None => {}
+            }
+        }
+        if let Some(number) = number {
+            symbols.entry(file.to_string()).or_default().push(Symbol {
+                name: name.to_string(),
+                kind,
+                line: number.saturating_sub(1),
+            });
+        }
+    }
+    Some(symbols)
+}
+
+fn count_words(contents: &str) -> HashMap<String, usize> {
+    let mut words = HashMap::new();
+    for word in contents.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        if word.chars().count() > 2 && !word.starts_with(|c: char| c.is_ascii_digit()) {
+            *words.entry(word.to_string()).or_default() += 1;
+        }
+    }
+    words
+}
+
+/// Reads `path`, taking its symbols from `symbols` if ctags ran on it
+fn index_file(path: &str, modified: u64, symbols: Option<Vec<Symbol>>) -> FileEntry {
+    let mut entry = FileEntry {
+        modified,
+        ..FileEntry::default()
+    };
+    if std::fs::metadata(path).map_or(true, |meta| meta.len() > MAX_INDEXED_SIZE) {
+        return entry;
+    }
+    // Binary files have NUL bytes near the start
+    let contents = match std::fs::read(path) {
+        Ok(bytes) if !bytes[..bytes.len().min(8000)].contains(&0) => {
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+        _ => return entry,
+    };
+
+    entry.symbols = symbols.unwrap_or_else(|| scan_symbols(&contents));
+    entry.words = count_words(&contents);
+    entry
+}
+
+fn modified_time(path: &str) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// Reads `stale` files with their modification times into `files`, a batch at
+/// a time. Gives up half way once `stop` is set.
+fn index_files(
+    files: &mut HashMap<String, FileEntry>,
+    stale: &[(String, u64)],
+    use_ctags: bool,
+    stop: &AtomicBool,
+) {
+    for batch in stale.chunks(CTAGS_BATCH) {
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        // A name starting with '-' would be taken for an option
+        let mut tags = match use_ctags {
+            true => ctags_symbols(
+                &batch
+                    .iter()
+                    .map(|(path, _)| path.as_str())
+                    .filter(|path| !path.starts_with('-'))
+                    .collect::<Vec<_>>(),
+            ),
+            false => None,
+        };
+        for (path, modified) in batch {
+            let symbols = match &mut tags {
+                Some(tags) if !path.starts_with('-') => Some(tags.remove(path).unwrap_or_default()),
+                _ => None,
+            };
+            files.insert(path.clone(), index_file(path, *modified, symbols));
+        }
+    }
+}
+
+/// Indexes `path` again if it is part of the index and changed on disk.
+/// Returns true if it did.
+fn refresh_file(files: &mut HashMap<String, FileEntry>, path: &str, use_ctags: bool) -> bool {
+    let modified = match (files.get(path), modified_time(path)) {
+        (Some(entry), Some(modified)) if entry.modified != modified => modified,
+        _ => return false,
+    };
+    index_files(
+        files,
+        &[(path.to_string(), modified)],
+        use_ctags,
+        &AtomicBool::new(false),
+    );
+    true
+}
+
+/// Brings `files` up to date with the disk. Returns true if anything changed.
+/// Gives up half way once `stop` is set.
+fn refresh(files: &mut HashMap<String, FileEntry>, use_ctags: bool, stop: &AtomicBool) -> bool {
+    let listed = project_files();
+    let mut changed = false;
+
+    let before = files.len();
+    let current: std::collections::HashSet<&String> = listed.iter().collect();
+    files.retain(|path, _| current.contains(path));
+    changed |= files.len() != before;
+
+    let stale: Vec<(String, u64)> = listed
+        .into_iter()
+        .filter_map(|path| {
+            let modified = modified_time(&path)?;
+            let fresh = files
+                .get(&path)
+                .is_some_and(|entry| entry.modified == modified);
+            (!fresh).then_some((path, modified))
+        })
+        .collect();
+    index_files(files, &stale, use_ctags, stop);
+    changed || !stale.is_empty()
+}
+
+fn write_index(path: &Path, files: &HashMap<String, FileEntry>) -> io::Result<()> {
+    let mut out = format!("{}\n", INDEX_HEADER);
+    for (file, entry) in files {
+        out.push_str(&format!("file {} {}\n", entry.modified, file));
+        for symbol in &entry.symbols {
+            out.push_str(&format!(
+                "sym {} {} {}\n",
+                symbol.line, symbol.kind, symbol.name
+            ));
+        }
+        for (word, count) in &entry.words {
+            out.push_str(&format!("word {} {}\n", count, word));
+        }
+    }
+    write_atomic(path, out)
+}
+
+fn read_index(path: &Path) -> HashMap<String, FileEntry> {
+    let mut files: HashMap<String, FileEntry> = HashMap::new();
+    let contents = match read_atomic(path, INDEX_HEADER) {
+        Some(contents) => contents,
+        None => return files,
+    };
+
+    // `file MODIFIED PATH` starts the entry its `sym LINE KIND NAME` and
+    // `word COUNT WORD` lines belong to
+    let mut current: Option<&mut FileEntry> = None;
+    for line in contents.lines().skip(1) {
+        let (tag, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let mut parts = rest.splitn(if tag == "sym" { 3 } else { 2 }, ' ');
+        match (tag, parts.next(), parts.next(), parts.next()) {
+            ("file", Some(modified), Some(name), None) => {
+                let entry = files.entry(name.to_string()).or_default();
+                entry.modified = modified.parse().unwrap_or(0);
+                current = Some(entry);
+            }
+            ("sym", Some(number), Some(kind), Some(name)) => {
+                if let (Some(entry), Ok(number)) = (current.as_deref_mut(), number.parse()) {
+                    entry.symbols.push(Symbol {
+                        name: name.to_string(),
+                        kind: kind.to_string(),
+                        line: number,
+                    });
+                }
+            }
+            ("word", Some(count), Some(word), None) => {
+                if let (Some(entry), Ok(count)) = (current.as_deref_mut(), count.parse()) {
+                    entry.words.insert(word.to_string(), count);
+                }
+            }
+            _ => {}
+        }
+    }
+    files
+}
+
+/// Worker: loads the saved index, then refreshes it on each request until the
+/// editor is gone or `stop` is set
+fn run_indexer(data: Weak<Mutex<IndexData>>, requests: Receiver<Refresh>, stop: Arc<AtomicBool>) {
+    let use_ctags = Command::new("ctags")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    let path = index_path();
+    let mut files = path.as_deref().map(read_index).unwrap_or_default();
+    let mut project = true;
+    let mut changed_files: Vec<String> = vec![];
+
+    loop {
+        let mut changed = false;
+        if project {
+            changed = refresh(&mut files, use_ctags, &stop);
+        }
+        for file in changed_files.drain(..) {
+            changed |= refresh_file(&mut files, &file, use_ctags);
+        }
+        // After a `cd` the working directory is another project's
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        if changed {
+            if let Some(path) = &path {
+                let _ = write_index(path, &files);
+            }
+        }
+
+        // Publish a copy so the editor never waits on a scan
+        let data = match data.upgrade() {
+            Some(data) => data,
+            None => return,
+        };
+        if let Ok(mut data) = data.lock() {
+            if changed || !data.ready {
+                data.files = files.clone();
+                data.ready = true;
+            }
+        }
+        drop(data);
+
+        // Wait for the next request, taking the ones queued behind it too
+        let first = match requests.recv() {
+            Ok(first) => first,
+            Err(_) => return,
+        };
+        project = false;
+        for request in std::iter::once(first).chain(requests.try_iter()) {
+            match request {
+                Refresh::Project => project = true,
+                Refresh::File(file) => changed_files.push(file),
+            }
+        }
+    }
+}
+
+impl ProjectIndex {
+    /// Starts the worker if it isn't running yet, or asks it to rescan the
+    /// project if the last scan was a while ago
+    fn start(&mut self) -> Arc<Mutex<IndexData>> {
+        if let Some(data) = &self.data {
+            if self
+                .rescanned_at
+                .is_some_and(|rescanned| rescanned.elapsed() >= RESCAN_INTERVAL)
+            {
+                self.rescanned_at = Some(Instant::now());
+                if let Some(requests) = &self.requests {
+                    let _ = requests.send(Refresh::Project);
+                }
+            }
+            return data.clone();
+        }
+
+        let data = Arc::new(Mutex::new(IndexData::default()));
+        let weak = Arc::downgrade(&data);
+        let (sender, receiver) = channel();
+        let stop = self.stop.clone();
+        thread::spawn(move || run_indexer(weak, receiver, stop));
+        self.data = Some(data.clone());
+        self.requests = Some(sender);
+        self.rescanned_at = Some(Instant::now());
+        data
+    }
+
+    /// Has the worker index `filename` again, after the editor read or wrote
+    /// it. Nothing if the index isn't running.
+    pub fn file_changed(&mut self, filename: &str) {
+        let (requests, cwd) = match (&self.requests, std::env::current_dir()) {
+            (Some(requests), Ok(cwd)) => (requests, cwd),
+            _ => return,
+        };
+        if let Ok(relative) = absolute_path(Path::new(filename)).strip_prefix(&cwd) {
+            let _ = requests.send(Refresh::File(relative.to_string_lossy().into_owned()));
+        }
+    }
+
+    /// Runs `f` on the index once the first scan finished, None before that
+    fn with_data<T>(&mut self, f: impl FnOnce(&IndexData) -> T) -> Option<T> {
+        let data = self.start();
+        let data = data.lock().ok()?;
+        data.ready.then(|| f(&data))
+    }
+
+    /// Indexed file paths, sorted
+    pub fn files(&mut self) -> Option<Vec<String>> {
+        self.with_data(|data| {
+            let mut files: Vec<String> = data.files.keys().cloned().collect();
+            files.sort();
+            files
+        })
+    }
+
+    /// Every symbol as (file, symbol)
+    pub fn symbols(&mut self) -> Option<Vec<(String, Symbol)>> {
+        self.with_data(|data| {
+            let mut symbols: Vec<(String, Symbol)> = data
+                .files
+                .iter()
+                .flat_map(|(path, entry)| {
+                    entry
+                        .symbols
+                        .iter()
+                        .map(move |symbol| (path.clone(), symbol.clone()))
+                })
+                .collect();
+            symbols.sort_by(|a, b| (&a.0, a.1.line).cmp(&(&b.0, b.1.line)));
+            symbols
+        })
+    }
+
+    /// Words across the project starting with `first` (ignoring case), most
+    /// frequent first
+    pub fn words(&mut self, first: char, limit: usize) -> Vec<(String, usize)> {
+        let lower: String = first.to_lowercase().collect();
+        self.with_data(|data| {
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            for entry in data.files.values() {
+                for (word, count) in &entry.words {
+                    if word.to_lowercase().starts_with(&lower) {
+                        *counts.entry(word).or_default() += count;
+                    }
+                }
+            }
+            let mut words: Vec<(String, usize)> = counts
+                .into_iter()
+                .map(|(word, count)| (word.to_string(), count))
+                .collect();
+            words.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            words.truncate(limit);
+            words
+        })
+        .unwrap_or_default()
+    }
+}
+
+impl Editor {
+    /// `symbols`: jumps to a definition anywhere in the project
+    pub fn editor_find_symbol(&mut self) {
+        if !self.options.project_index {
+            self.editor_set_status_message("Project index is off (set index)");
+            return;
+        }
+        let symbols = match self.index.symbols() {
+            Some(symbols) => symbols,
+            None => {
+                self.editor_set_status_message("Indexing the project, try again in a moment");
+                return;
+            }
+        };
+        if symbols.is_empty() {
+            self.editor_set_status_message("No symbols found");
+            return;
+        }
+
+        let items: Vec<String> = symbols
+            .iter()
+            .map(|(file, symbol)| {
+                format!(
+                    "{:<32} {:<10} {}:{}",
+                    symbol.name,
+                    symbol.kind,
+                    file,
+                    symbol.line + 1
+                )
+            })
+            .collect();
+        let title = format!("Symbols ({})", symbols.len());
+        if let Some(selected) = self.editor_fuzzy_pick(&title, &items) {
+            let (file, symbol) = &symbols[selected];
+            self.editor_jump_to_location(file, symbol.line, 0);
+        }
+    }
+}
@@ -0,0 +1,412 @@
+//! Keyboard input: `Key` events and the parser turning the bytes a terminal
+//! sends into them. Understands UTF-8, CSI sequences with xterm modifiers
+//! (`\x1b[1;5C` is Ctrl-Right, as also sent by tmux), the rxvt forms of
+//! modified keys (`\x1b[a`, `\x1b[3$`, `\x1b[7^`), the linux console's F1-F5
+//! (`\x1b[[A`), SS3 sequences and Alt+key sent as an ESC prefix. Chars with
+//! modifiers are read from xterm's modifyOtherKeys (`\x1b[27;6;70~`) and the
+//! `CSI u` form (`\x1b[102;6u`), the only way Ctrl-Shift with a letter gets
+//! told apart from Ctrl. Unrecognized input reads as Escape.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    /// Ctrl with a letter or one of ` \]^_`, Ctrl-Space is `Ctrl(' ')`
+    Ctrl(char),
+    Alt(char),
+    /// Ctrl and Alt with a letter, sent as ESC and the Ctrl code
+    CtrlAlt(char),
+    /// Ctrl and Shift with a lowercase letter, only sent by terminals
+    /// reporting modified chars
+    CtrlShift(char),
+    F(u8),
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    Backspace,
+    Enter,
+    Tab,
+    BackTab,
+    Escape,
+    /// One of the keys from `Up` to `Delete` held with modifiers, e.g.
+    /// `Modified(Modifiers::CTRL, &Key::Right)`
+    Modified(Modifiers, &'static Key),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub alt: bool,
+    pub ctrl: bool,
+}
+
+impl Modifiers {
+    pub const SHIFT: Modifiers = Modifiers {
+        shift: true,
+        alt: false,
+        ctrl: false,
+    };
+    pub const ALT: Modifiers = Modifiers {
+        shift: false,
+        alt: true,
+        ctrl: false,
+    };
+    pub const CTRL: Modifiers = Modifiers {
+        shift: false,
+        alt: false,
+        ctrl: true,
+    };
+
+    /// From an xterm modifier parameter: 1 plus a bit mask of shift, alt, ctrl
+    fn from_parameter(parameter: u32) -> Modifiers {
+        let mask = parameter.saturating_sub(1);
+        Modifiers {
+            shift: mask & 1 != 0,
+            alt: mask & 2 != 0,
+            ctrl: mask & 4 != 0,
+        }
+    }
+}
+
+impl Key {
+    /// Key for a code from the C API: a byte, one of the `KILO_KEY_*`
+    /// constants or a Unicode code point
+    pub fn from_code(code: u32) -> Option<Key> {
+        Some(match code {
+            0..=127 => byte_key(code as u8),
+            1000 => Key::Left,
+            1001 => Key::Right,
+            1002 => Key::Up,
+            1003 => Key::Down,
+            1004 => Key::PageUp,
+            1005 => Key::PageDown,
+            1006 => Key::Home,
+            1007 => Key::End,
+            1008 => Key::Delete,
+            1009 => Key::Modified(Modifiers::ALT, &Key::Up),
+            1010 => Key::Modified(Modifiers::ALT, &Key::Down),
+            1011 => Key::Alt('n'),
+            1012 => Key::Alt('N'),
+            _ => Key::Char(char::from_u32(code).filter(|c| !c.is_control())?),
+        })
+    }
+
+    /// Key named like `ctrl-g`, `alt-x`, `f5`, `pageup` or a single char, as
+    /// in key bindings
+    pub fn from_name(name: &str) -> Option<Key> {
+        let single = |name: &str| {
+            let mut chars = name.chars();
+            chars.next().filter(|_| chars.next().is_none())
+        };
+        let lower = name.to_lowercase();
+        if let Some(rest) = lower.strip_prefix("ctrl-alt-") {
+            return single(rest).map(Key::CtrlAlt);
+        } else if let Some(rest) = lower.strip_prefix("ctrl-shift-") {
+            return single(rest).map(Key::CtrlShift);
+        } else if let Some(rest) = lower.strip_prefix("ctrl-") {
+            return single(rest).map(Key::Ctrl);
+        } else if lower.starts_with("alt-") {
+            return single(&name[4..]).map(Key::Alt);
+        } else if let Some(number) = lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+            return Some(Key::F(number));
+        }
+        Some(match lower.as_str() {
+            "up" => Key::Up,
+            "down" => Key::Down,
+            "left" => Key::Left,
+            "right" => Key::Right,
+            "home" => Key::Home,
+            "end" => Key::End,
+            "pageup" => Key::PageUp,
+            "pagedown" => Key::PageDown,
+            "insert" => Key::Insert,
+            "delete" => Key::Delete,
+            "backspace" => Key::Backspace,
+            "enter" => Key::Enter,
+            "tab" => Key::Tab,
+            "backtab" => Key::BackTab,
+            "esc" | "escape" => Key::Escape,
+            _ => Key::Char(single(name)?),
+        })
+    }
+
+    /// The key with its modifiers dropped
+    pub fn unmodified(self) -> Key {
+        match self {
+            Key::Modified(_, key) => *key,
+            key => key,
+        }
+    }
+}
+
+/// Key sent as a single byte
+fn byte_key(byte: u8) -> Key {
+    match byte {
+        b'\r' => Key::Enter,
+        b'\t' => Key::Tab,
+        127 => Key::Backspace,
+        0x1b => Key::Escape,
+        0 => Key::Ctrl(' '),
+        1..=26 => Key::Ctrl((b'a' + byte - 1) as char),
+        28..=31 => Key::Ctrl((byte + 64) as char),
+        // Not the start of a UTF-8 sequence
+        0x80..=0xff => Key::Char(char::REPLACEMENT_CHARACTER),
+        _ => Key::Char(byte as char),
+    }
+}
+
+/// Parses the key starting with byte `first`. `next` returns the following
+/// bytes, None once no more arrive in time. Also returns a byte that was read
+/// but starts the next key, as one cutting a UTF-8 sequence short does.
+pub fn parse_key(first: u8, mut next: impl FnMut() -> Option<u8>) -> (Key, Option<u8>) {
+    let mut leftover = None;
+    let key = parse_key_from(first, &mut next, &mut leftover);
+    (key, leftover)
+}
+
+fn parse_key_from(
+    first: u8,
+    next: &mut dyn FnMut() -> Option<u8>,
+    leftover: &mut Option<u8>,
+) -> Key {
+    match first {
+        0x1b => parse_escape(next, leftover),
+        0xc0..=0xf7 => parse_utf8(first, next, leftover),
+        _ => byte_key(first),
+    }
+}
+
+/// The char a UTF-8 lead byte `first` starts. An invalid or cut off sequence,
+/// such as Latin-1 text pasted into a UTF-8 terminal, reads as U+FFFD without
+/// swallowing the key after it.
+fn parse_utf8(first: u8, next: &mut dyn FnMut() -> Option<u8>, leftover: &mut Option<u8>) -> Key {
+    let len = match first {
+        0xf0.. => 4,
+        0xe0.. => 3,
+        _ => 2,
+    };
+    let mut bytes = vec![first];
+    while bytes.len() < len {
+        match next() {
+            Some(byte) if byte & 0xc0 == 0x80 => bytes.push(byte),
+            Some(byte) => {
+                *leftover = Some(byte);
+                return Key::Char(char::REPLACEMENT_CHARACTER);
+            }
+            None => break,
+        }
+    }
+    match std::str::from_utf8(&bytes).map(|s| s.chars().next()) {
+        Ok(Some(c)) => Key::Char(c),
+        _ => Key::Char(char::REPLACEMENT_CHARACTER),
+    }
+}
+
+fn parse_escape(next: &mut dyn FnMut() -> Option<u8>, leftover: &mut Option<u8>) -> Key {
+    match next() {
+        None => Key::Escape,
+        // Escape pressed twice, or before an escape sequence
+        Some(0x1b) => {
+            *leftover = Some(0x1b);
+            Key::Escape
+        }
+        Some(b'[') => parse_csi(next),
+        Some(b'O') => parse_ss3(next),
+        Some(byte) => match parse_key_from(byte, next, leftover) {
+            Key::Char(c) => Key::Alt(c),
+            Key::Ctrl(c) => Key::CtrlAlt(c),
+            _ => Key::Escape,
+        },
+    }
+}
+
+/// `ESC O final`, with a modifier digit before the final byte on some
+/// terminals
+fn parse_ss3(next: &mut dyn FnMut() -> Option<u8>) -> Key {
+    let mut byte = next();
+    let mut modifiers = Modifiers::default();
+    if let Some(digit @ b'2'..=b'8') = byte {
+        modifiers = Modifiers::from_parameter((digit - b'0') as u32);
+        byte = next();
+    }
+    let key: &'static Key = match byte {
+        Some(b'A') => &Key::Up,
+        Some(b'B') => &Key::Down,
+        Some(b'C') => &Key::Right,
+        Some(b'D') => &Key::Left,
+        Some(b'H') => &Key::Home,
+        Some(b'F') => &Key::End,
+        Some(byte @ b'P'..=b'S') => return Key::F(byte - b'P' + 1),
+        // rxvt's Ctrl+arrows
+        Some(b'a') => return Key::Modified(Modifiers::CTRL, &Key::Up),
+        Some(b'b') => return Key::Modified(Modifiers::CTRL, &Key::Down),
+        Some(b'c') => return Key::Modified(Modifiers::CTRL, &Key::Right),
+        Some(b'd') => return Key::Modified(Modifiers::CTRL, &Key::Left),
+        _ => return Key::Escape,
+    };
+    with_modifiers(modifiers, key)
+}
+
+fn with_modifiers(modifiers: Modifiers, key: &'static Key) -> Key {
+    if modifiers == Modifiers::default() {
+        *key
+    } else {
+        Key::Modified(modifiers, key)
+    }
+}
+
+/// `ESC [ parameters final`, e.g. `\x1b[3~` or `\x1b[1;3A`
+fn parse_csi(next: &mut dyn FnMut() -> Option<u8>) -> Key {
+    let mut parameters = String::new();
+    let final_byte = loop {
+        match next() {
+            // The linux console's F1-F5
+            Some(b'[') if parameters.is_empty() => {
+                return match next() {
+                    Some(byte @ b'A'..=b'E') => Key::F(byte - b'A' + 1),
+                    _ => Key::Escape,
+                };
+            }
+            // rxvt ends Shift and Ctrl modified keys with `$` and `^`
+            Some(byte @ (0x40..=0x7e | b'$')) => break byte,
+            Some(byte) if parameters.len() < 16 => parameters.push(byte as char),
+            _ => return Key::Escape,
+        }
+    };
+    let numbers: Vec<u32> = parameters
+        .split(';')
+        .map(|number| number.parse().unwrap_or(1))
+        .collect();
+    let mut modifiers = Modifiers::from_parameter(numbers.get(1).copied().unwrap_or(1));
+    match (final_byte, numbers.as_slice()) {
+        (b'~', &[27, _, code]) => return modified_char(modifiers, code),
+        (b'u', &[code, ..]) => return modified_char(modifiers, code),
+        _ => {}
+    }
+    match final_byte {
+        b'$' => modifiers.shift = true,
+        b'^' => modifiers.ctrl = true,
+        _ => {}
+    }
+
+    let key: &'static Key = match final_byte {
+        b'A' => &Key::Up,
+        b'B' => &Key::Down,
+        b'C' => &Key::Right,
+        b'D' => &Key::Left,
+        b'H' => &Key::Home,
+        b'F' => &Key::End,
+        b'P'..=b'S' => return Key::F(final_byte - b'P' + 1),
+        b'Z' => return Key::BackTab,
+        // rxvt's Shift+arrows
+        b'a' => return Key::Modified(Modifiers::SHIFT, &Key::Up),
+        b'b' => return Key::Modified(Modifiers::SHIFT, &Key::Down),
+        b'c' => return Key::Modified(Modifiers::SHIFT, &Key::Right),
+        b'd' => return Key::Modified(Modifiers::SHIFT, &Key::Left),
+        b'~' | b'$' | b'^' => match numbers[0] {
+            1 | 7 => &Key::Home,
+            2 => &Key::Insert,
+            3 => &Key::Delete,
+            4 | 8 => &Key::End,
+            5 => &Key::PageUp,
+            6 => &Key::PageDown,
+            number @ 11..=15 => return Key::F(number as u8 - 10),
+            number @ 17..=21 => return Key::F(number as u8 - 11),
+            number @ 23..=24 => return Key::F(number as u8 - 12),
+            _ => return Key::Escape,
+        },
+        _ => return Key::Escape,
+    };
+    with_modifiers(modifiers, key)
+}
+
+/// A char reported with its modifiers, as the `Key` the same keys send
+/// without reporting where there is one
+fn modified_char(modifiers: Modifiers, code: u32) -> Key {
+    let Some(c) = char::from_u32(code) else {
+        return Key::Escape;
+    };
+    let letter = c.to_ascii_lowercase();
+    match modifiers {
+        Modifiers {
+            ctrl: true,
+            shift: true,
+            alt: false,
+        } if letter.is_ascii_lowercase() => Key::CtrlShift(letter),
+        Modifiers {
+            ctrl: true, alt, ..
+        } if letter.is_ascii_lowercase() || letter == ' ' => {
+            if alt {
+                Key::CtrlAlt(letter)
+            } else {
+                Key::Ctrl(letter)
+            }
+        }
+        Modifiers {
+            ctrl: false,
+            alt: true,
+            ..
+        } => Key::Alt(c),
+        _ if c.is_ascii() => byte_key(c as u8),
+        _ => Key::Char(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The key `bytes` start and the byte left over for the next one
+    fn parse(bytes: &[u8]) -> (Key, Option<u8>) {
+        let mut rest = bytes[1..].iter().copied();
+        parse_key(bytes[0], || rest.next())
+    }
+
+    const REPLACEMENT: Key = Key::Char(char::REPLACEMENT_CHARACTER);
+
+    #[test]
+    fn parses_utf8_chars() {
+        assert_eq!(parse("é".as_bytes()), (Key::Char('é'), None));
+        assert_eq!(parse("€".as_bytes()), (Key::Char('€'), None));
+        assert_eq!(parse("🦀".as_bytes()), (Key::Char('🦀'), None));
+    }
+
+    #[test]
+    fn replaces_invalid_utf8() {
+        // Latin-1 é followed by a key
+        assert_eq!(parse(&[0xe9, b'a']), (REPLACEMENT, Some(b'a')));
+        // Cut off before the last byte
+        assert_eq!(parse(&[0xe2, 0x82]), (REPLACEMENT, None));
+        // A continuation byte on its own, and an overlong encoding
+        assert_eq!(parse(&[0x80]), (REPLACEMENT, None));
+        assert_eq!(parse(&[0xc0, 0xaf]), (REPLACEMENT, None));
+        // A surrogate is not a char
+        assert_eq!(parse(&[0xed, 0xa0, 0x80]), (REPLACEMENT, None));
+    }
+
+    #[test]
+    fn keeps_escape_for_escape() {
+        assert_eq!(parse(&[0x1b]), (Key::Escape, None));
+        assert_eq!(parse(&[0x1b, 0x1b]), (Key::Escape, Some(0x1b)));
+        assert_eq!(parse(&[0x1b, b'x']), (Key::Alt('x'), None));
+    }
+
+    #[test]
+    fn parses_modified_chars() {
+        assert_eq!(parse(b"\x1b[27;6;70~").0, Key::CtrlShift('f'));
+        assert_eq!(parse(b"\x1b[102;6u").0, Key::CtrlShift('f'));
+        assert_eq!(parse(b"\x1b[27;5;102~").0, Key::Ctrl('f'));
+        assert_eq!(parse(b"\x1b[27;2;70~").0, Key::Char('F'));
+        assert_eq!(parse(b"\x1b[13;2u").0, Key::Enter);
+        assert_eq!(
+            parse(b"\x1b[3;5~").0,
+            Key::Modified(Modifiers::CTRL, &Key::Delete)
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! Minimal JSON value, parser and serializer (enough for the LSP client)
+
+use std::fmt::Write as _;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn string(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(field, _)| field == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64().filter(|n| *n >= 0.0).map(|n| n as usize)
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Json> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos == parser.chars.len() {
+            Some(value)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) => {
+                if n.fract() == 0.0 && n.abs() < 1e15 {
+                    write!(f, "{}", *n as i64)
+                } else {
+                    write!(f, "{}", n)
+                }
+            }
+            Json::String(s) => write_escaped(f, s),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_escaped(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_escaped(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    f.write_str(&escaped)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect_word(&mut self, word: &str, value: Json) -> Option<Json> {
+        for expected in word.chars() {
+            if self.next()? != expected {
+                return None;
+            }
+        }
+        Some(value)
+    }
+
+    fn value(&mut self) -> Option<Json> {
+        self.skip_whitespace();
+        match self.peek()? {
+            'n' => self.expect_word("null", Json::Null),
+            't' => self.expect_word("true", Json::Bool(true)),
+            'f' => self.expect_word("false", Json::Bool(false)),
+            '"' => self.string().map(Json::String),
+            '[' => self.array(),
+            '{' => self.object(),
+            _ => self.number(),
+        }
+    }
+
+    fn number(&mut self) -> Option<Json> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse().ok().map(Json::Number)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.next(); // Opening quote
+        let mut s = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Some(s),
+                '\\' => match self.next()? {
+                    'n' => s.push('\n'),
+                    'r' => s.push('\r'),
+                    't' => s.push('\t'),
+                    'b' => s.push('\x08'),
+                    'f' => s.push('\x0c'),
+                    'u' => {
+                        let mut code = self.hex4()?;
+                        // Surrogate pair, a high surrogate without its low
+                        // half is replaced and whatever follows kept
+                        if (0xd800..0xdc00).contains(&code) && self.peek_escape_u() {
+                            self.pos += 2;
+                            let low = self.hex4()?;
+                            if (0xdc00..0xe000).contains(&low) {
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                            } else {
+                                s.push('\u{fffd}');
+                                code = low;
+                            }
+                        }
+                        s.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                    }
+                    c => s.push(c),
+                },
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// Whether a `\u` escape comes next
+    fn peek_escape_u(&self) -> bool {
+        self.chars.get(self.pos..self.pos + 2) == Some(&['\\', 'u'])
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            code = code * 16 + self.next()?.to_digit(16)?;
+        }
+        Some(code)
+    }
+
+    fn array(&mut self) -> Option<Json> {
+        self.next(); // [
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.next();
+            return Some(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                ']' => return Some(Json::Array(items)),
+                _ => return None,
+            }
+        }
+    }
+
+    fn object(&mut self) -> Option<Json> {
+        self.next(); // {
+        let mut fields = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.next();
+            return Some(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return None;
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if self.next()? != ':' {
+                return None;
+            }
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                '}' => return Some(Json::Object(fields)),
+                _ => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_values() {
+        let json = Json::parse(r#" {"a": [1, -2.5e1, true, false, null], "b": {}} "#).unwrap();
+        assert_eq!(
+            json.get("a"),
+            Some(&Json::Array(vec![
+                Json::Number(1.0),
+                Json::Number(-25.0),
+                Json::Bool(true),
+                Json::Bool(false),
+                Json::Null,
+            ]))
+        );
+        assert_eq!(json.get("b"), Some(&Json::Object(vec![])));
+        assert_eq!(Json::parse("[]"), Some(Json::Array(vec![])));
+    }
+
+    #[test]
+    fn rejects_malformed_text() {
+        for text in [
+            "",
+            "{",
+            "[1,",
+            r#"{"a" 1}"#,
+            "[1] 2",
+            "nul",
+            r#""open"#,
+            r#""\u12""#,
+        ] {
+            assert_eq!(Json::parse(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn parses_escapes() {
+        let json = Json::parse(r#""a\"\\\n\t\u00e9\/""#).unwrap();
+        assert_eq!(json.as_str(), Some("a\"\\\n\t\u{e9}/"));
+    }
+
+    #[test]
+    fn parses_surrogate_pairs() {
+        let json = Json::parse(r#""\ud83d\ude00""#).unwrap();
+        assert_eq!(json.as_str(), Some("\u{1f600}"));
+    }
+
+    #[test]
+    fn replaces_broken_surrogates() {
+        // High surrogate followed by an escape that isn't a low one
+        let json = Json::parse(r#""\ud83d\u0041""#).unwrap();
+        assert_eq!(json.as_str(), Some("\u{fffd}A"));
+        let json = Json::parse(r#""\ud83d\ud83d""#).unwrap();
+        assert_eq!(json.as_str(), Some("\u{fffd}\u{fffd}"));
+        // Or by another escape, or by nothing
+        let json = Json::parse(r#""\ud83d\n""#).unwrap();
+        assert_eq!(json.as_str(), Some("\u{fffd}\n"));
+        let json = Json::parse(r#""\udc00x""#).unwrap();
+        assert_eq!(json.as_str(), Some("\u{fffd}x"));
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        let json = Json::object(vec![
+            ("text", Json::string("line\n\"quoted\"\u{1}")),
+            ("n", Json::Number(3.0)),
+            ("f", Json::Number(0.5)),
+        ]);
+        let text = json.to_string();
+        assert_eq!(text, r#"{"text":"line\n\"quoted\"\u0001","n":3,"f":0.5}"#);
+        assert_eq!(Json::parse(&text), Some(json));
+    }
+}
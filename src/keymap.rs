@@ -0,0 +1,248 @@
+//! Key bindings of the buffer: `editor_process_keypress` runs them from this
+//! table and F1 lists them from it, so the two can't drift apart. Typing,
+//! plain cursor movement and deleting are handled by the editor directly.
+
+use crate::*;
+
+pub struct Binding {
+    /// Keys that run it
+    pub keys: &'static [Key],
+    /// How help names the keys
+    pub label: &'static str,
+    pub description: &'static str,
+    /// Called with the key that was pressed
+    pub run: fn(&mut Editor, Key),
+}
+
+/// Bindings in the order help lists them
+pub const KEYMAP: &[Binding] = &[
+    Binding {
+        keys: &[Key::F(1)],
+        label: "F1",
+        description: "Show this help",
+        run: |editor, _| editor.editor_help(),
+    },
+    Binding {
+        keys: &[Key::Ctrl('s')],
+        label: "Ctrl-S",
+        description: "Save",
+        run: |editor, _| editor.editor_save(),
+    },
+    Binding {
+        keys: &[Key::CtrlAlt('s')],
+        label: "Ctrl-Alt-S",
+        description: "Save all open files",
+        run: |editor, _| {
+            editor.editor_save_all();
+        },
+    },
+    Binding {
+        keys: &[Key::Ctrl('q')],
+        label: "Ctrl-Q",
+        description: "Quit",
+        run: |editor, _| editor.editor_quit(),
+    },
+    Binding {
+        keys: &[Key::Ctrl('p')],
+        label: "Ctrl-P",
+        description: "Run a command",
+        run: |editor, _| editor.editor_command_prompt(),
+    },
+    Binding {
+        keys: &[Key::Ctrl('f')],
+        label: "Ctrl-F",
+        description: "Search",
+        run: |editor, _| editor.editor_find(),
+    },
+    Binding {
+        keys: &[Key::Alt('n'), Key::Alt('N')],
+        label: "Alt-n / Alt-N",
+        description: "Next / previous search match",
+        run: |editor, key| editor.editor_search_next(key == Key::Alt('n')),
+    },
+    Binding {
+        keys: &[Key::Insert],
+        label: "Insert",
+        description: "Switch between inserting and overwriting",
+        run: |editor, _| editor.editor_toggle_overwrite(),
+    },
+    Binding {
+        keys: &[Key::Ctrl('_')],
+        label: "Ctrl-_",
+        description: "Undo",
+        run: |editor, _| editor.editor_undo(),
+    },
+    Binding {
+        keys: &[Key::Ctrl('r')],
+        label: "Ctrl-R",
+        description: "Redo",
+        run: |editor, _| editor.editor_redo(),
+    },
+    Binding {
+        keys: &[Key::Ctrl(' ')],
+        label: "Ctrl-Space",
+        description: "Start or clear the selection",
+        run: |editor, _| editor.editor_toggle_mark(),
+    },
+    Binding {
+        keys: &[
+            Key::Modified(Modifiers::SHIFT, &Key::Left),
+            Key::Modified(Modifiers::SHIFT, &Key::Right),
+            Key::Modified(Modifiers::SHIFT, &Key::Up),
+            Key::Modified(Modifiers::SHIFT, &Key::Down),
+            Key::Modified(Modifiers::SHIFT, &Key::Home),
+            Key::Modified(Modifiers::SHIFT, &Key::End),
+        ],
+        label: "Shift-arrows",
+        description: "Extend the selection",
+        run: |editor, key| editor.editor_extend_selection(key.unmodified()),
+    },
+    Binding {
+        keys: &[Key::Ctrl('c'), Key::Ctrl('v')],
+        label: "Ctrl-C / Ctrl-V",
+        description: "Copy / paste",
+        run: |editor, key| match key {
+            Key::Ctrl('c') => editor.editor_copy(),
+            _ => editor.editor_paste(),
+        },
+    },
+    Binding {
+        keys: &[Key::Modified(Modifiers::SHIFT, &Key::Delete)],
+        label: "Shift-Delete",
+        description: "Cut",
+        run: |editor, _| editor.editor_cut(),
+    },
+    Binding {
+        keys: &[Key::Escape],
+        label: "Escape",
+        description: "Clear the selection and search highlights",
+        run: |editor, _| {
+            editor.buffer.mark = None;
+            editor.editor_search_clear();
+        },
+    },
+    Binding {
+        keys: &[
+            Key::Modified(Modifiers::CTRL, &Key::Home),
+            Key::Modified(Modifiers::CTRL, &Key::End),
+        ],
+        label: "Ctrl-Home / Ctrl-End",
+        description: "Go to the start / end of the buffer",
+        run: |editor, key| match key.unmodified() {
+            Key::Home => editor.editor_goto(0, 0),
+            _ => editor.editor_goto(editor.get_num_rows(), usize::MAX),
+        },
+    },
+    Binding {
+        keys: &[
+            Key::Modified(Modifiers::ALT, &Key::Up),
+            Key::Modified(Modifiers::ALT, &Key::Down),
+        ],
+        label: "Alt-Up / Alt-Down",
+        description: "Move by file line when wrapping (by display line with nodisplaymove)",
+        run: |editor, key| editor.editor_move_cursor(key),
+    },
+    Binding {
+        keys: &[Key::Ctrl('e'), Key::Ctrl('y')],
+        label: "Ctrl-E / Ctrl-Y",
+        description: "Scroll a line down / up",
+        run: |editor, key| editor.editor_scroll_line(key == Key::Ctrl('e')),
+    },
+    Binding {
+        keys: &[Key::Ctrl('d'), Key::Ctrl('u')],
+        label: "Ctrl-D / Ctrl-U",
+        description: "Scroll half a page down / up",
+        run: |editor, key| editor.editor_scroll_half_page(key == Key::Ctrl('d')),
+    },
+    Binding {
+        keys: &[Key::Ctrl('g')],
+        label: "Ctrl-G",
+        description: "Jump to the last change",
+        run: |editor, _| editor.editor_jump_to_change(true),
+    },
+    Binding {
+        keys: &[Key::Ctrl('o')],
+        label: "Ctrl-O",
+        description: "Complete the word before the cursor",
+        run: |editor, _| editor.editor_complete(),
+    },
+    Binding {
+        keys: &[Key::Ctrl('k')],
+        label: "Ctrl-K",
+        description: "Insert a char by code point or digraph",
+        run: |editor, _| editor.editor_insert_unicode(""),
+    },
+    Binding {
+        keys: &[Key::Ctrl('t')],
+        label: "Ctrl-T",
+        description: "Swap the chars around the cursor, or replace a misspelled word with the next suggestion",
+        run: |editor, _| {
+            if !editor.editor_spell_next_suggestion() {
+                editor.editor_transpose_chars();
+            }
+        },
+    },
+    Binding {
+        keys: &[Key::Alt('t')],
+        label: "Alt-T",
+        description: "Swap the word at the cursor with the next one",
+        run: |editor, _| editor.editor_transpose_words(),
+    },
+    Binding {
+        keys: &[Key::CtrlAlt('t')],
+        label: "Ctrl-Alt-T",
+        description: "Swap the line with the one above",
+        run: |editor, _| editor.editor_transpose_lines(),
+    },
+    Binding {
+        keys: &[Key::CtrlShift('f')],
+        label: "Ctrl-Shift-F",
+        description: "Format the buffer or selection (terminals reporting modified keys)",
+        run: |editor, _| editor.editor_format(),
+    },
+    Binding {
+        keys: &[Key::Ctrl('w')],
+        label: "Ctrl-W",
+        description: "Add a misspelled word to the dictionary (was Ctrl-Y)",
+        run: |editor, _| editor.editor_spell_add_word("", false),
+    },
+    Binding {
+        keys: &[Key::Ctrl('n'), Key::Ctrl('b')],
+        label: "Ctrl-N / Ctrl-B",
+        description: "Next / previous quickfix entry",
+        run: |editor, key| editor.editor_quickfix_next(key == Key::Ctrl('n')),
+    },
+    Binding {
+        keys: &[Key::Ctrl(']')],
+        label: "Ctrl-]",
+        description: "Go to the definition (language server)",
+        run: |editor, _| editor.editor_lsp_goto_definition(),
+    },
+    Binding {
+        keys: &[Key::Ctrl('z')],
+        label: "Ctrl-Z",
+        description: "Suspend",
+        run: |editor, _| editor.editor_suspend(),
+    },
+];
+
+/// The binding `key` runs, if any
+pub fn binding_for(key: Key) -> Option<&'static Binding> {
+    KEYMAP.iter().find(|binding| binding.keys.contains(&key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binds_each_key_once() {
+        let keys: Vec<Key> = KEYMAP
+            .iter()
+            .flat_map(|binding| binding.keys.iter().copied())
+            .collect();
+        for (index, key) in keys.iter().enumerate() {
+            assert!(!keys[index + 1..].contains(key), "{:?} is bound twice", key);
+        }
+    }
+}
@@ -0,0 +1,2059 @@
+use abbrev::Abbreviations;
+use bgsave::Saving;
+use broadcast::Broadcast;
+use case::Case;
+use cast::CastRecorder;
+use changes::ChangeList;
+use cli::Args;
+use comments::BlockComments;
+use completion::Completion;
+use filter::LineFilter;
+use follow::Follow;
+use format::{FormatOnSave, Formatters};
+use frame::{char_width, frame_to_ansi, to_cells, Color, Frame, FrameLine, WIDE_FILLER};
+use frontmatter::FrontMatterKind;
+use git::{GitGutter, GitRefresh, GutterSign};
+use grep::{Grep, GrepResultKeys};
+use hex::{is_binary, HexKeys, HexView};
+use history::{add_history_entry, HistoryBrowser, HistoryKind};
+use hooks::{EditorHook, InputHook, Mode};
+use index::ProjectIndex;
+use input::{parse_key, Key, Modifiers};
+use lsp::{LspClient, LspServers, LspSync};
+use memory::{MEGABYTE, MEMORY_CHECK_INTERVAL};
+use modified::SavedChanges;
+use netfs::describe_io_error;
+use normalize::SaveText;
+use options::Options;
+use overlay::floor_char_boundary;
+use pipe::{read_piped_stdin, take_piped_stdin, use_terminal_as_stdin};
+use profile::FrameTimes;
+use quickfix::Quickfix;
+use recent::{RecentFiles, StartScreen};
+use recovery::Recovery;
+use reopen::{Encoding, FileFormat};
+use script::Script;
+#[cfg(feature = "scripting")]
+use scripting::Scripting;
+use search::{Search, SearchMatches};
+use slowterm::SlowTerminal;
+use spell::{ProjectDictionary, SpellChecker};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fs::OpenOptions,
+    io::{self, ErrorKind, Read, Write},
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+    path::PathBuf,
+    process,
+    time::{Duration, Instant},
+};
+use style::CellStyle;
+use syntax::SyntaxWork;
+use termios::*;
+use theme::Theme;
+use typography::SmartPunctuation;
+use undo::{EditKind, UndoFile, UndoHistory};
+use usage::Usage;
+use watch::FileWatch;
+use workdir::{absolute_path, display_path};
+
+mod abbrev;
+#[cfg(feature = "bench")]
+mod bench;
+mod bgsave;
+mod broadcast;
+mod buffers;
+mod case;
+mod cast;
+mod changes;
+mod checksum;
+mod cli;
+mod clipboard;
+mod command;
+mod comments;
+pub mod completion;
+mod config;
+mod diff;
+mod difftool;
+mod digraph;
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod filter;
+mod follow;
+mod format;
+#[cfg(feature = "frontend")]
+pub mod frame;
+#[cfg(not(feature = "frontend"))]
+mod frame;
+mod frontmatter;
+pub mod fuzzy;
+mod git;
+mod grep;
+mod help;
+mod hex;
+mod history;
+mod hooks;
+mod indent;
+mod index;
+pub mod input;
+mod json;
+mod keymap;
+mod lines;
+mod lsp;
+mod memory;
+mod modified;
+mod netfs;
+mod normalize;
+mod options;
+mod overlay;
+mod overwrite;
+mod picker;
+mod pipe;
+pub mod prelude;
+mod preview;
+mod profile;
+mod quickfix;
+mod range;
+mod recent;
+mod recovery;
+mod reopen;
+mod script;
+#[cfg(feature = "scripting")]
+mod scripting;
+mod scroll;
+mod search;
+mod selection;
+mod session;
+mod shell;
+mod slowterm;
+mod spell;
+mod stats;
+mod storage;
+mod style;
+mod sudo;
+mod syntax;
+mod theme;
+mod title;
+mod transpose;
+mod typeahead;
+mod typography;
+mod undo;
+mod usage;
+mod watch;
+mod workdir;
+mod wrap;
+
+/*** Constants ***/
+const KILO_VERSION: &str = "0.0.1";
+const KILO_TAB_STOP: usize = 8;
+const KILO_MESSAGE_BAR_HEIGHT: usize = 2;
+/// How long to wait for a key before doing idle work
+const IDLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How a char that would upset the terminal is drawn: caret notation for
+/// ASCII control chars, the code point for other ones. None for the rest and
+/// for tabs, which are expanded.
+fn control_placeholder(c: char) -> Option<String> {
+    match c {
+        '\t' => None,
+        '\0'..='\x1f' => Some(format!("^{}", (c as u8 + b'@') as char)),
+        '\x7f' => Some("^?".to_string()),
+        c if c.is_control() => Some(format!("<U+{:04X}>", c as u32)),
+        _ => None,
+    }
+}
+
+/// Columns and rows from the COLUMNS and LINES variables
+fn env_dimensions() -> Option<(usize, usize)> {
+    let dimension = |name| match std::env::var(name).ok()?.trim().parse() {
+        Ok(0) | Err(_) => None,
+        Ok(size) => Some(size),
+    };
+    Some((dimension("COLUMNS")?, dimension("LINES")?))
+}
+
+/// Render columns `c` takes other than as a tab
+fn render_width(c: char) -> usize {
+    control_placeholder(c).map_or(char_width(c), |placeholder| placeholder.len())
+}
+
+/// A line of text. What it looks like on screen is worked out from `chars`
+/// when it is drawn, only the rows shown pay for it.
+struct EditorRow {
+    chars: String,
+    /// Block comment or string still open at the end of the row
+    hl_open: syntax::Open,
+    /// What the row started in when `hl_open` was worked out, None if it
+    /// changed since
+    hl_from: Option<syntax::Open>,
+}
+
+impl EditorRow {
+    pub fn new(chars: String) -> Self {
+        EditorRow {
+            chars,
+            hl_open: syntax::Open::Nothing,
+            hl_from: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    /// Forgets the syntax state worked out for the row, after `chars` changed
+    pub fn chars_changed(&mut self) {
+        self.hl_from = None;
+    }
+
+    /// Render columns `start..end` of the row, one char a column: tabs become
+    /// spaces, control chars their placeholders and wide chars take a filler
+    /// cell too. A tab or wide char cut by either end shows as spaces.
+    pub fn render(&self, tab_stop: usize, start: usize, end: usize) -> String {
+        let mut render = String::with_capacity(self.chars.len().min(end.saturating_sub(start)));
+        for (_, c, column, width) in self.columns(tab_stop) {
+            if column >= end {
+                break;
+            }
+            // Marks drawn over the char before them have no cell of their own
+            if width == 0 {
+                continue;
+            }
+            if column + width <= start {
+                continue;
+            }
+            // The part of the char's columns in the range
+            let from = start.saturating_sub(column);
+            let to = width.min(end - column);
+            match control_placeholder(c) {
+                Some(placeholder) => render.push_str(&placeholder[from..to]),
+                None if c == '\t' || to - from < width => {
+                    render.extend(std::iter::repeat_n(' ', to - from))
+                }
+                None => {
+                    render.push(c);
+                    if width == 2 {
+                        render.push(WIDE_FILLER);
+                    }
+                }
+            }
+        }
+        render
+    }
+
+    /// Each char of the row with its byte index in `chars`, the render
+    /// column it starts at and the columns it takes. Cursor positions (bytes
+    /// of `chars`) and screen positions (render columns) are mapped with it.
+    fn columns(&self, tab_stop: usize) -> impl Iterator<Item = (usize, char, usize, usize)> + '_ {
+        let mut column = 0;
+        self.chars.char_indices().map(move |(index, c)| {
+            let width = match c {
+                '\t' => tab_stop - (column % tab_stop),
+                c => render_width(c),
+            };
+            let start = column;
+            column += width;
+            (index, c, start, width)
+        })
+    }
+
+    /// Render columns the whole row takes
+    pub fn render_columns(&self, tab_stop: usize) -> usize {
+        self.columns(tab_stop)
+            .last()
+            .map_or(0, |(_, _, column, width)| column + width)
+    }
+
+    /// Render column of byte `cursor_x` in `chars`
+    pub fn cursor_x_to_render_cursor_x(&self, tab_stop: usize, cursor_x: usize) -> usize {
+        let cursor_x = floor_char_boundary(&self.chars, cursor_x);
+        self.columns(tab_stop)
+            .find(|&(index, ..)| index >= cursor_x)
+            .map_or_else(|| self.render_columns(tab_stop), |(_, _, column, _)| column)
+    }
+
+    /// Byte in `chars` of the char shown at render column `render_cursor_x`,
+    /// the end of the row if it is past the last one
+    pub fn render_cursor_x_to_cursor_x(&self, tab_stop: usize, render_cursor_x: usize) -> usize {
+        self.columns(tab_stop)
+            .find(|&(_, _, column, width)| column + width > render_cursor_x)
+            .map_or(self.chars.len(), |(index, ..)| index)
+    }
+
+    /// Render column ranges of the placeholders of control chars
+    pub fn control_char_columns(&self, tab_stop: usize) -> Vec<(usize, usize)> {
+        self.columns(tab_stop)
+            .filter(|&(_, c, ..)| control_placeholder(c).is_some())
+            .map(|(_, _, column, width)| (column, column + width))
+            .collect()
+    }
+
+    pub fn insert_char(&mut self, at: usize, c: char) {
+        self.chars.insert(at, c);
+        self.chars_changed();
+    }
+
+    pub fn delete_char(&mut self, at: usize) {
+        self.chars.remove(at);
+        self.chars_changed();
+    }
+
+    pub fn append_string(&mut self, s: &str) {
+        self.chars.push_str(s);
+        self.chars_changed();
+    }
+
+    pub fn split_off(&mut self, at: usize) -> String {
+        let split = self.chars.split_off(at);
+        self.chars_changed();
+        split
+    }
+}
+
+/// Why the buffer wasn't saved
+#[derive(Debug)]
+enum SaveError {
+    /// Not written, with the message saying why
+    Refused(&'static str),
+    /// Writing failed, and why
+    Failed(String),
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Refused(message) => f.write_str(message),
+            SaveError::Failed(reason) => write!(f, "Error saving file: {}", reason),
+        }
+    }
+}
+
+/// The shown buffer and its revision
+pub type RowsKey = (usize, usize);
+
+/// A value worked out from the shown rows, kept until `key` changes. The
+/// key is `editor_rows_key` and whatever else the value depends on.
+pub struct RowsCache<K, T> {
+    cached: RefCell<Option<(K, T)>>,
+}
+
+impl<K, T> Default for RowsCache<K, T> {
+    fn default() -> Self {
+        RowsCache {
+            cached: RefCell::new(None),
+        }
+    }
+}
+
+impl<K: PartialEq, T: Clone> RowsCache<K, T> {
+    /// The value for `key`, from `compute` if it isn't the cached one
+    pub fn get(&self, key: K, compute: impl FnOnce() -> T) -> T {
+        if let Some((cached_key, value)) = &*self.cached.borrow() {
+            if *cached_key == key {
+                return value.clone();
+            }
+        }
+        let value = compute();
+        *self.cached.borrow_mut() = Some((key, value.clone()));
+        value
+    }
+}
+
+/*** AppendBuffer ***/
+struct AppendBuffer {
+    buf: String,
+}
+
+impl AppendBuffer {
+    pub fn new() -> Self {
+        AppendBuffer { buf: String::new() }
+    }
+
+    pub fn push(&mut self, s: &str) {
+        self.buf.push_str(s)
+    }
+
+    pub fn write(self, editor: &Editor) {
+        editor.write_to_stdout(&self.buf);
+        editor.flush_stdout();
+    }
+}
+
+/// The state of one open file. The shown one is `Editor::buffer`, switching
+/// files swaps it with one of `Editor::buffers`.
+#[derive(Default)]
+struct Buffer {
+    rows: Vec<EditorRow>,
+    filename: Option<String>,
+    /// Directory of the open file
+    directory: Option<PathBuf>,
+    dirty: usize,
+    cursor_x: usize,
+    cursor_y: usize,
+    row_offset: usize,
+    column_offset: usize,
+    mark: Option<(usize, usize)>,
+    undo: UndoHistory,
+    git_gutter: GitGutter,
+    /// Rows of the marks set with `mark x`
+    named_marks: HashMap<char, usize>,
+    /// Encoding, line endings and read-only state of the open file
+    file_format: FileFormat,
+    filter: Option<LineFilter>,
+    front_matter_folded: bool,
+    /// The bytes of a file in hex mode, shown instead of the rows
+    hex: Option<HexView>,
+    /// Lines appended to the file are read into the buffer
+    follow: Option<Follow>,
+    changes: ChangeList,
+    saved_changes: SavedChanges,
+    recovery: Recovery,
+    watch: FileWatch,
+    search: Search,
+    /// Language the rows' open comments and strings were worked out for
+    highlighted_syntax: Option<&'static str>,
+    syntax_work: SyntaxWork,
+    lsp: Option<LspClient>,
+    spell_project: ProjectDictionary,
+}
+
+/*** Editor ***/
+pub struct Editor {
+    render_cursor_x: usize,
+    screen_num_rows: usize,
+    screen_num_columns: usize,
+    /// Columns and rows the terminal answered a size query with, so it is
+    /// only asked while the size is unknown
+    reported_dimensions: Option<(usize, usize)>,
+    /// Goes up with every change to the rows and, unlike `dirty`, never back
+    revision: usize,
+    status_message: Option<String>,
+    status_message_time: Instant,
+    /// Set with an error message, batch mode fails a command that set it
+    status_is_error: bool,
+    original_terminal: Option<Termios>,
+    overlay_active: bool,
+    /// Save running on a worker thread
+    saving: Option<Saving>,
+    options: Options,
+    clipboard: String,
+    /// Registers a to z, filled by ranged `y` and `d`
+    registers: HashMap<char, String>,
+    /// Window title last set, None while the terminal shows its own
+    window_title: Option<String>,
+    /// Recording of the screen, from `--record-cast`
+    cast: Option<CastRecorder>,
+    /// Recent files shown while started without a file
+    start_screen: Option<StartScreen>,
+    /// The shown file
+    buffer: Buffer,
+    /// The other open files, see buffers.rs
+    buffers: Vec<Buffer>,
+    current_buffer: usize,
+    /// Colors of the UI elements, set with `color`
+    theme: Theme,
+    wrap_before_zen: Option<bool>,
+    /// Markdown files are shown next to their preview
+    markdown_preview: bool,
+    /// Column Up and Down keep to, whether it counts within display lines
+    /// and where the cursor was left with it
+    goal_column: Option<(bool, usize, (usize, usize))>,
+    /// Typed chars replace the one under the cursor
+    overwrite: bool,
+    quickfix: Quickfix,
+    /// Bytes in the rows, for the position in the status bar
+    byte_total: RowsCache<RowsKey, usize>,
+    /// Matches of the search in all rows, by rows, query and whole word
+    search_matches: SearchMatches,
+    /// Front matter kind and closing row found in the rows
+    front_matter: RowsCache<RowsKey, Option<(FrontMatterKind, usize)>>,
+    formatters: Formatters,
+    lsp_servers: LspServers,
+    spell: SpellChecker,
+    usage: Usage,
+    completion: Completion,
+    index: ProjectIndex,
+    slow_terminal: SlowTerminal,
+    frame_times: FrameTimes,
+    broadcast: Broadcast,
+    /// Trigger to expansion, set with `abbrev`
+    abbreviations: HashMap<String, String>,
+    input_hooks: Vec<Box<dyn InputHook>>,
+    editor_hooks: Vec<Box<dyn EditorHook>>,
+    mode: Mode,
+    /// Rhai engine with the commands and key bindings scripts added
+    #[cfg(feature = "scripting")]
+    scripting: Scripting,
+    grep: Grep,
+    /// Embedded through the C API: no terminal, keys come from `pending_keys`
+    headless: bool,
+    pending_keys: VecDeque<Key>,
+    /// Bytes read from the terminal that no key was parsed from yet
+    input: VecDeque<u8>,
+    /// When the last frame was drawn, for `maxfps`
+    last_frame: Option<Instant>,
+    quit_requested: bool,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        let mut editor = Self::with_dimensions(0, 0, false);
+        editor.get_dimensions();
+
+        editor
+    }
+
+    /// Editor without a terminal, for embedding
+    pub fn new_headless(num_rows: usize, num_columns: usize) -> Self {
+        Self::with_dimensions(num_rows, num_columns, true)
+    }
+
+    fn with_dimensions(num_rows: usize, num_columns: usize, headless: bool) -> Self {
+        Self {
+            render_cursor_x: 0,
+            screen_num_rows: num_rows.saturating_sub(KILO_MESSAGE_BAR_HEIGHT),
+            screen_num_columns: num_columns,
+            reported_dimensions: None,
+            revision: 0,
+            status_message: None,
+            status_message_time: Instant::now(),
+            status_is_error: false,
+            original_terminal: None,
+            overlay_active: false,
+            saving: None,
+            options: Options::default(),
+            clipboard: String::new(),
+            registers: HashMap::new(),
+            window_title: None,
+            cast: None,
+            start_screen: None,
+            buffer: Buffer::default(),
+            buffers: vec![Buffer::default()],
+            current_buffer: 0,
+            theme: Theme::default(),
+            wrap_before_zen: None,
+            markdown_preview: false,
+            goal_column: None,
+            overwrite: false,
+            quickfix: Quickfix::default(),
+            byte_total: RowsCache::default(),
+            search_matches: RowsCache::default(),
+            front_matter: RowsCache::default(),
+            formatters: Formatters::new(),
+            lsp_servers: LspServers::new(),
+            spell: SpellChecker::default(),
+            usage: Usage::default(),
+            completion: Completion::default(),
+            index: ProjectIndex::default(),
+            slow_terminal: SlowTerminal::default(),
+            frame_times: FrameTimes::default(),
+            broadcast: Broadcast::default(),
+            abbreviations: HashMap::new(),
+            input_hooks: vec![
+                Box::new(HexKeys),
+                Box::new(Abbreviations::default()),
+                Box::new(SmartPunctuation::default()),
+                Box::new(BlockComments::default()),
+                Box::new(GrepResultKeys),
+            ],
+            editor_hooks: vec![
+                Box::new(RecentFiles),
+                Box::new(UndoFile),
+                Box::new(GitRefresh),
+                Box::new(LspSync),
+                Box::new(FormatOnSave),
+            ],
+            mode: Mode::Edit,
+            #[cfg(feature = "scripting")]
+            scripting: Scripting::default(),
+            grep: Grep::default(),
+            headless,
+            pending_keys: VecDeque::new(),
+            input: VecDeque::new(),
+            last_frame: None,
+            quit_requested: false,
+        }
+    }
+
+    pub fn editor_insert_row(&mut self, at: usize, row: String) {
+        if at > self.get_num_rows() {
+            return;
+        }
+
+        let editor_row = EditorRow::new(row);
+        self.buffer.rows.insert(at, editor_row);
+        self.editor_shift_named_marks(at, 0, 1);
+        self.editor_count_edit();
+    }
+
+    /// Counts a change to the rows, unsaved until the next save
+    pub fn editor_count_edit(&mut self) {
+        self.buffer.dirty += 1;
+        self.revision += 1;
+    }
+
+    /// What caches of values worked out from the shown rows are keyed on
+    pub fn editor_rows_key(&self) -> RowsKey {
+        (self.current_buffer, self.revision)
+    }
+
+    pub fn set_original_terminal(&mut self, original_terminal: Termios) {
+        self.original_terminal = Some(original_terminal);
+    }
+
+    pub fn get_dimensions(&mut self) {
+        // Serial consoles and some PTYs don't know their size, the terminal
+        // at the other end is asked then, until it has answered once. COLUMNS
+        // and LINES stand in when it doesn't answer either, as in some
+        // containers, and 80x24 after that.
+        let (num_columns, num_rows) = term_size::dimensions()
+            .filter(|&(num_columns, num_rows)| num_columns > 0 && num_rows > 0)
+            .or(self.reported_dimensions)
+            .or_else(|| {
+                self.reported_dimensions = self.query_dimensions();
+                self.reported_dimensions
+            })
+            .or_else(env_dimensions)
+            .unwrap_or((80, 24));
+        self.screen_num_rows = num_rows.saturating_sub(KILO_MESSAGE_BAR_HEIGHT);
+        self.screen_num_columns = num_columns;
+        // Called after anything that may have changed the screen under us
+        self.editor_invalidate_screen();
+    }
+
+    pub fn get_num_rows(&self) -> usize {
+        self.buffer.rows.len()
+    }
+
+    pub fn gutter_width(&self) -> usize {
+        if self.options.zen {
+            return 0;
+        }
+
+        let signs = self.saved_changes_visible() as usize
+            + self.buffer.git_gutter.is_active() as usize
+            + self.buffer.lsp.is_some() as usize;
+        if signs == 0 {
+            0
+        } else {
+            signs + 1
+        }
+    }
+
+    /// Number of screen columns available for text, excluding the gutter
+    pub fn text_num_columns(&self) -> usize {
+        let columns = self
+            .screen_num_columns
+            .saturating_sub(self.gutter_width() + self.editor_preview_width());
+        if self.options.zen {
+            columns.min(self.options.zen_width.max(1))
+        } else {
+            columns
+        }
+    }
+
+    /// Blank columns left of the gutter, used to center the text in zen mode
+    pub fn left_margin(&self) -> usize {
+        if self.options.zen {
+            (self.screen_num_columns - self.editor_preview_width())
+                .saturating_sub(self.text_num_columns())
+                / 2
+        } else {
+            0
+        }
+    }
+
+    /// Whether the status and message bars are drawn. In zen mode they only
+    /// appear while there is a fresh status message.
+    fn bars_visible(&self) -> bool {
+        !self.options.zen || self.status_message_is_fresh()
+    }
+
+    fn status_message_is_fresh(&self) -> bool {
+        self.status_message
+            .as_deref()
+            .is_some_and(|msg| !msg.is_empty())
+            && (Instant::now() - self.status_message_time).as_secs() < 5
+    }
+
+    /*** Terminal ***/
+    fn enable_raw_mode(&mut self) {
+        if self.headless {
+            return;
+        }
+        let original_termios = Termios::from_fd(io::stdin().as_raw_fd()).unwrap();
+
+        let mut termios = original_termios;
+
+        termios.c_iflag &= !(BRKINT | ICRNL | INPCK | ISTRIP | IXON);
+        termios.c_oflag &= !(OPOST);
+        termios.c_cflag |= CS8;
+        termios.c_lflag &= !(ECHO | ICANON | IEXTEN | ISIG);
+        termios.c_cc[VMIN] = 0;
+        termios.c_cc[VTIME] = 1;
+
+        // Apply the updated termios settings
+        tcsetattr(io::stdin().as_raw_fd(), TCSANOW, &termios).unwrap();
+
+        self.set_original_terminal(original_termios);
+        // xterm's modifyOtherKeys, so keys like Ctrl-Shift-F that would send
+        // the same byte as without Shift arrive as `\x1b[27;6;70~`
+        self.write_to_stdout("\x1b[>4;1m");
+        self.flush_stdout();
+    }
+
+    /// Columns and rows of the terminal from where the cursor ends up when
+    /// sent to the far bottom right, as reported back by it, then put back.
+    /// Only in raw mode, where the report isn't echoed and can be read byte by
+    /// byte.
+    fn query_dimensions(&mut self) -> Option<(usize, usize)> {
+        if self.headless || self.original_terminal.is_none() {
+            return None;
+        }
+        let mut stdout = io::stdout().lock();
+        stdout
+            .write_all(b"\x1b7\x1b[999C\x1b[999B\x1b[6n\x1b8")
+            .ok()?;
+        stdout.flush().ok()?;
+
+        // Keys typed meanwhile stay queued around the report
+        loop {
+            if let Some(dimensions) = take_cursor_report(&mut self.input) {
+                return Some(dimensions).filter(|&(columns, rows)| columns > 0 && rows > 0);
+            }
+            if !self.editor_fill_input(Duration::from_millis(200)) {
+                return None;
+            }
+        }
+    }
+
+    fn disable_terminal(&self) {
+        if let Some(original_termios) = &self.original_terminal {
+            self.write_to_stdout("\x1b[>4m");
+            self.flush_stdout();
+            tcsetattr(io::stdin().as_raw_fd(), TCSANOW, original_termios).unwrap();
+        }
+    }
+
+    fn cleanup(&self) {
+        self.editor_restore_window_title();
+        self.write_to_stdout("\x1b[2J");
+        self.write_to_stdout("\x1b[H");
+
+        self.flush_stdout();
+
+        self.disable_terminal();
+    }
+
+    /// Ctrl-Z: hand the terminal back to the shell until resumed with SIGCONT
+    fn editor_suspend(&mut self) {
+        if self.headless {
+            return;
+        }
+        self.cleanup();
+        self.window_title = None;
+
+        // Stops the process here, execution continues after `fg`
+        unsafe {
+            libc::raise(libc::SIGTSTP);
+        }
+
+        self.enable_raw_mode();
+        self.get_dimensions();
+        self.editor_refresh_screen();
+    }
+
+    fn die(&self, s: &str) {
+        self.cleanup();
+        eprintln!("Error: {}", s);
+        process::exit(1);
+    }
+
+    /*** Output ***/
+    fn write_to_stdout(&self, s: &str) {
+        if self.headless {
+            return;
+        }
+        self.editor_record_cast(s);
+        let mut stdout = io::stdout().lock();
+        let write_ok = stdout.write(s.as_bytes());
+        if let Err(error) = write_ok {
+            self.die(&format!("Write error: {}", error));
+        }
+    }
+
+    fn flush_stdout(&self) {
+        if self.headless {
+            return;
+        }
+        let mut stdout = io::stdout().lock();
+        let flush_ok = stdout.flush();
+        if let Err(error) = flush_ok {
+            self.die(&format!("Flush error: {}", error));
+        }
+    }
+
+    fn editor_draw_status_bar(&self, line: &mut FrameLine) {
+        if !self.bars_visible() {
+            return;
+        }
+
+        let filename = self.buffer.filename.as_deref().map(display_path);
+        let mut truncated_filename = filename.as_deref().unwrap_or("[No Name]");
+        if truncated_filename.len() > 20 {
+            truncated_filename = &truncated_filename[..floor_char_boundary(truncated_filename, 20)];
+        }
+
+        let mut status = format!(
+            "{} - {} lines {}",
+            truncated_filename,
+            self.get_num_rows(),
+            if self.buffer.dirty != 0 {
+                "(modified)"
+            } else {
+                ""
+            }
+        );
+        if let Some(buffers_status) = self.editor_buffers_status() {
+            status.push(' ');
+            status.push_str(&buffers_status);
+        }
+        if let Some(format_status) = self.editor_file_format_status() {
+            status.push(' ');
+            status.push_str(&format_status);
+        }
+        if let Some(overwrite_status) = self.editor_overwrite_status() {
+            status.push(' ');
+            status.push_str(&overwrite_status);
+        }
+        if let Some(hex_status) = self.editor_hex_status() {
+            status.push(' ');
+            status.push_str(&hex_status);
+        }
+        if let Some(follow_status) = self.editor_follow_status() {
+            status.push(' ');
+            status.push_str(&follow_status);
+        }
+        if let Some(filter_status) = self.editor_filter_status() {
+            status.push(' ');
+            status.push_str(&filter_status);
+        }
+        if let Some(lsp_status) = self.editor_lsp_status() {
+            status.push(' ');
+            status.push_str(&lsp_status);
+        }
+        if let Some(search_status) = self.editor_search_status() {
+            status.push(' ');
+            status.push_str(&search_status);
+        }
+        if let Some(slow_status) = self.editor_slow_terminal_status() {
+            status.push(' ');
+            status.push_str(&slow_status);
+        }
+        if let Some(broadcast_status) = self.editor_broadcast_status() {
+            status.push(' ');
+            status.push_str(&broadcast_status);
+        }
+        if let Some(grep_status) = self.editor_grep_status() {
+            status.push(' ');
+            status.push_str(&grep_status);
+        }
+        if let Some(save_status) = self.editor_save_status() {
+            status.push(' ');
+            status.push_str(&save_status);
+        }
+        if let Some(frame_time_status) = self.editor_frame_time_status() {
+            status.push(' ');
+            status.push_str(&frame_time_status);
+        }
+
+        let r_status = self.editor_position_status();
+
+        let mut status = to_cells(&status, self.screen_num_columns);
+        let mut status_width = status.chars().count();
+        while status_width < self.screen_num_columns {
+            if self.screen_num_columns - status_width == r_status.chars().count() {
+                status.push_str(&r_status);
+                break;
+            }
+            status.push(' ');
+            status_width += 1;
+        }
+
+        line.push_styled(
+            &status,
+            CellStyle {
+                inverse: true,
+                ..CellStyle::default()
+            },
+        );
+    }
+
+    fn editor_draw_message_bar(&self, line: &mut FrameLine) {
+        let msg = self.status_message.as_deref().unwrap_or("");
+        if (Instant::now() - self.status_message_time).as_secs() < 5 {
+            line.push(&to_cells(msg, self.screen_num_columns));
+        }
+    }
+
+    /*** Editor ***/
+    fn editor_scroll(&mut self) {
+        self.render_cursor_x = 0;
+        if self.buffer.cursor_y < self.get_num_rows() {
+            self.render_cursor_x = self.buffer.rows[self.buffer.cursor_y]
+                .cursor_x_to_render_cursor_x(self.options.tab_stop, self.buffer.cursor_x);
+        }
+
+        if self.options.typewriter {
+            self.editor_place_cursor_at_screen_line(self.screen_num_rows / 2);
+        } else if self.editor_uses_layout() {
+            self.editor_scroll_layout();
+        } else {
+            // Row offset, keeping `scrolloff` rows of context around the cursor
+            let margin = self.editor_scroll_margin();
+            let margin_below =
+                margin.min(self.get_num_rows().saturating_sub(self.buffer.cursor_y + 1));
+            if self.buffer.cursor_y < self.buffer.row_offset + margin {
+                self.buffer.row_offset = self.buffer.cursor_y.saturating_sub(margin);
+            }
+
+            if self.buffer.cursor_y + margin_below >= self.buffer.row_offset + self.screen_num_rows
+            {
+                self.buffer.row_offset =
+                    self.buffer.cursor_y + margin_below + 1 - self.screen_num_rows;
+            }
+        }
+
+        if self.options.wrap {
+            return;
+        }
+
+        // Column offset
+        if self.render_cursor_x < self.buffer.column_offset {
+            self.buffer.column_offset = self.render_cursor_x;
+        }
+
+        let text_num_columns = self.text_num_columns();
+        if self.render_cursor_x >= self.buffer.column_offset + text_num_columns {
+            self.buffer.column_offset = self.render_cursor_x - text_num_columns + 1;
+        }
+    }
+
+    /// Rows of context kept above and below the cursor, at most half the screen
+    fn editor_scroll_margin(&self) -> usize {
+        self.options
+            .scroll_off
+            .min(self.screen_num_rows.saturating_sub(1) / 2)
+    }
+
+    /// `zz`, `zt` and `zb`: scrolls so the cursor line is in the middle, at the
+    /// top or at the bottom of the screen
+    pub fn editor_position_cursor_line(&mut self, position: &str) {
+        let margin = self.editor_scroll_margin();
+        let screen_line = match position {
+            "zz" => self.screen_num_rows / 2,
+            "zt" => margin,
+            _ => self.screen_num_rows.saturating_sub(margin + 1),
+        };
+        self.editor_place_cursor_at_screen_line(screen_line);
+    }
+
+    /// The (file row, first render column, is first segment) shown on each screen line
+    fn editor_screen_lines(&self) -> Vec<(usize, usize, bool)> {
+        if self.editor_uses_layout() {
+            return self.editor_layout_screen_lines();
+        }
+
+        let last_row = (self.buffer.row_offset + self.screen_num_rows).min(self.get_num_rows());
+        (self.buffer.row_offset..last_row)
+            .map(|file_row| (file_row, self.buffer.column_offset, true))
+            .collect()
+    }
+
+    /// Cursor position on screen (row, column), relative to the text area
+    fn editor_cursor_screen_position(&self) -> (usize, usize) {
+        if let Some(position) = self.editor_hex_cursor_position() {
+            return position;
+        }
+        if self.editor_uses_layout() {
+            return self.editor_layout_cursor_position();
+        }
+
+        (
+            self.buffer.cursor_y - self.buffer.row_offset,
+            self.render_cursor_x - self.buffer.column_offset,
+        )
+    }
+
+    fn editor_draw_rows(&self, lines: &mut Vec<FrameLine>) {
+        if self.editor_draw_start_screen(lines) || self.editor_draw_hex(lines) {
+            return;
+        }
+        let editor_num_rows = self.screen_num_rows;
+        let editor_num_columns = self.text_num_columns();
+
+        let screen_lines = self.editor_screen_lines();
+        let cursor_column = (self.options.cursor_column && !self.editor_slow_terminal())
+            .then(|| self.editor_cursor_screen_position().1);
+
+        for y in 0..editor_num_rows {
+            let mut line = FrameLine::default();
+            if let Some(&(file_row, start_column, first_segment)) = screen_lines.get(y) {
+                line.push(&" ".repeat(self.left_margin()));
+                if first_segment {
+                    self.editor_draw_gutter(&mut line, file_row);
+                } else {
+                    line.push(&" ".repeat(self.gutter_width()));
+                }
+
+                self.editor_draw_row_slice(
+                    &mut line,
+                    file_row,
+                    start_column,
+                    editor_num_columns,
+                    cursor_column,
+                );
+                self.editor_draw_scroll_markers(&mut line, file_row);
+            } else {
+                line.push("~");
+            }
+
+            lines.push(line);
+        }
+        self.editor_draw_preview(lines);
+    }
+
+    /// Draws `width` render columns of `file_row` starting at `start_column`
+    fn editor_draw_row_slice(
+        &self,
+        line: &mut FrameLine,
+        file_row: usize,
+        start_column: usize,
+        width: usize,
+        cursor_column: Option<usize>,
+    ) {
+        let mut row = self.buffer.rows[file_row].render(
+            self.options.tab_stop,
+            start_column,
+            start_column + width,
+        );
+        let mut styles = self.editor_row_styles(file_row, start_column, row.chars().count());
+
+        // Cursor line and column are drawn over the other attributes, padding
+        // short rows so the column stays continuous
+        let cursor_line = self.options.cursor_line
+            && !self.editor_slow_terminal()
+            && file_row == self.buffer.cursor_y;
+        let highlight = |style: &mut CellStyle| match self.options.cursor_style.as_str() {
+            "dim" => style.dim = true,
+            _ => style.underline = true,
+        };
+        let color_columns = self.editor_color_columns(start_column, width);
+        let padded_width = match cursor_column {
+            Some(column) if column < width => (column + 1).max(styles.len()),
+            _ => styles.len(),
+        };
+        let padded_width = color_columns
+            .iter()
+            .map(|column| column + 1)
+            .fold(padded_width, usize::max);
+        row.push_str(&" ".repeat(padded_width - styles.len()));
+        styles.resize(padded_width, CellStyle::default());
+        for &column in &color_columns {
+            styles[column].bg.get_or_insert(self.theme.color_column);
+        }
+        if cursor_line {
+            styles.iter_mut().for_each(highlight);
+        }
+        if let Some(style) = cursor_column.and_then(|column| styles.get_mut(column)) {
+            if !cursor_line {
+                highlight(style);
+            }
+        }
+        line.push_cells(&row, &styles);
+
+        if let Some(summary) = self.editor_front_matter_fold_summary(file_row) {
+            let room = width.saturating_sub(styles.len());
+            let summary: String = summary.chars().take(room).collect();
+            line.push_styled(
+                &summary,
+                CellStyle {
+                    dim: true,
+                    ..CellStyle::default()
+                },
+            );
+        }
+    }
+
+    /// Screen columns of the `colorcolumn` guides on a row drawn from render
+    /// column `start_column`, `width` columns wide
+    fn editor_color_columns(&self, start_column: usize, width: usize) -> Vec<usize> {
+        self.options
+            .color_column
+            .split(',')
+            .filter_map(|column| column.trim().parse::<usize>().ok())
+            // Columns count from 1
+            .filter_map(|column| column.checked_sub(1 + start_column))
+            .filter(|&column| column < width)
+            .collect()
+    }
+
+    /// Style of each of the `count` render columns starting at `start`
+    fn editor_row_styles(&self, file_row: usize, start: usize, count: usize) -> Vec<CellStyle> {
+        let mut styles = vec![CellStyle::default(); count];
+        let row = &self.buffer.rows[file_row];
+        // Render column range to indices in `styles`
+        let span = |from: usize, to: usize| {
+            let from = from.max(start) - start;
+            let to = to.max(start) - start;
+            from.min(count)..to.max(from).min(count)
+        };
+
+        let highlights = self.editor_syntax_highlights(file_row).into_iter();
+        for (from, to, color) in highlights.chain(self.editor_front_matter_highlights(file_row)) {
+            let from = row.cursor_x_to_render_cursor_x(self.options.tab_stop, from);
+            let to = row.cursor_x_to_render_cursor_x(self.options.tab_stop, to);
+            for style in &mut styles[span(from, to)] {
+                style.fg = Some(color);
+            }
+        }
+
+        for diagnostic in self.editor_row_diagnostics(file_row) {
+            let from = row.cursor_x_to_render_cursor_x(
+                self.options.tab_stop,
+                diagnostic.start_column.min(row.len()),
+            );
+            let to = row.cursor_x_to_render_cursor_x(
+                self.options.tab_stop,
+                diagnostic.end_column.min(row.len()),
+            );
+            for style in &mut styles[span(from, to)] {
+                style.underline = true;
+                style.fg = Some(diagnostic.severity.color());
+            }
+        }
+
+        for (word_start, word_end) in self.editor_misspelled_words(file_row) {
+            let from = row.cursor_x_to_render_cursor_x(self.options.tab_stop, word_start);
+            let to = row.cursor_x_to_render_cursor_x(self.options.tab_stop, word_end);
+            for style in &mut styles[span(from, to)] {
+                style.underline = true;
+                style.fg.get_or_insert(self.theme.spell);
+            }
+        }
+
+        for (match_start, match_end) in self.editor_search_row_matches(file_row) {
+            let from = row.cursor_x_to_render_cursor_x(self.options.tab_stop, match_start);
+            let to = row.cursor_x_to_render_cursor_x(self.options.tab_stop, match_end);
+            let current = file_row == self.buffer.cursor_y && match_start == self.buffer.cursor_x;
+            for style in &mut styles[span(from, to)] {
+                style.fg = Some(self.theme.search_text);
+                style.bg = Some(if current {
+                    self.theme.current_search
+                } else {
+                    self.theme.search
+                });
+            }
+        }
+
+        // Control chars stand out from text that looks like their placeholders
+        for (from, to) in row.control_char_columns(self.options.tab_stop) {
+            for style in &mut styles[span(from, to)] {
+                style.inverse = true;
+            }
+        }
+
+        if let Some((sel_start, sel_end)) = self.editor_selected_render_columns(file_row) {
+            for style in &mut styles[span(sel_start, sel_end)] {
+                style.inverse = true;
+            }
+        }
+
+        styles
+    }
+
+    fn editor_draw_gutter(&self, line: &mut FrameLine, file_row: usize) {
+        if self.gutter_width() == 0 {
+            return;
+        }
+
+        let colored = |color: Color| CellStyle {
+            fg: Some(color),
+            ..CellStyle::default()
+        };
+        if self.saved_changes_visible() {
+            match self.buffer.saved_changes.sign(file_row) {
+                Some(GutterSign::Deleted) => {
+                    line.push_styled("_", colored(self.theme.change_marks))
+                }
+                Some(_) => line.push_styled("*", colored(self.theme.change_marks)),
+                None => line.push(" "),
+            }
+        }
+        if self.buffer.git_gutter.is_active() {
+            match self.buffer.git_gutter.sign(file_row) {
+                Some(sign) => line.push_styled(&sign.symbol().to_string(), colored(sign.color())),
+                None => line.push(" "),
+            }
+        }
+        if self.buffer.lsp.is_some() {
+            match self.editor_row_diagnostic(file_row) {
+                Some(diagnostic) => line.push_styled(
+                    &diagnostic.severity.symbol().to_string(),
+                    colored(diagnostic.severity.color()),
+                ),
+                None => line.push(" "),
+            }
+        }
+        line.push(" ");
+    }
+
+    /// Builds the whole screen: text rows, status bar and message bar
+    pub fn editor_frame(&mut self) -> Frame {
+        self.editor_scroll();
+        self.editor_hex_scroll();
+        // Hidden rows let more than a screenful be shown
+        let last_shown = if self.buffer.filter.is_some() || self.buffer.front_matter_folded {
+            usize::MAX
+        } else {
+            self.buffer.row_offset + self.screen_num_rows
+        };
+        self.editor_update_syntax_state(last_shown);
+
+        let mut lines = Vec::with_capacity(self.screen_num_rows + KILO_MESSAGE_BAR_HEIGHT);
+        self.editor_draw_rows(&mut lines);
+        let mut status_bar = FrameLine::default();
+        self.editor_draw_status_bar(&mut status_bar);
+        lines.push(status_bar);
+        let mut message_bar = FrameLine::default();
+        self.editor_draw_message_bar(&mut message_bar);
+        lines.push(message_bar);
+
+        let (screen_y, screen_x) = self.editor_cursor_screen_position();
+        let mut frame = Frame {
+            lines,
+            cursor: Some((
+                screen_y,
+                screen_x + self.left_margin() + self.gutter_width(),
+            )),
+        };
+        self.editor_draw_completion_popup(&mut frame);
+        frame
+    }
+
+    fn editor_refresh_screen(&mut self) {
+        if self.editor_skip_redraw() {
+            return;
+        }
+        self.editor_update_window_title();
+        let start = Instant::now();
+        let frame = self.editor_frame();
+        self.editor_record_build_time(start.elapsed());
+        self.editor_present(&frame);
+    }
+
+    /// Handles keys from a front end without a terminal. Keys following
+    /// one that opens a prompt are typed into that prompt; a prompt still open
+    /// after the last key is cancelled.
+    pub fn editor_handle_keys(&mut self, keys: impl IntoIterator<Item = Key>) {
+        self.pending_keys.extend(keys);
+        while !self.pending_keys.is_empty() && !self.quit_requested {
+            self.editor_process_keypress();
+        }
+        self.pending_keys.clear();
+    }
+
+    /// Sets the screen size of a front end without a terminal
+    pub fn editor_resize(&mut self, num_rows: usize, num_columns: usize) {
+        self.screen_num_rows = num_rows.saturating_sub(KILO_MESSAGE_BAR_HEIGHT);
+        self.screen_num_columns = num_columns;
+    }
+
+    /// Draws `frame` on the terminal
+    fn editor_present(&mut self, frame: &Frame) {
+        let mut buffer = AppendBuffer::new();
+        buffer.push(&self.editor_frame_output(frame));
+        let start = Instant::now();
+        self.last_frame = Some(start);
+        buffer.write(self);
+        self.editor_record_frame_time(start.elapsed());
+        self.editor_broadcast_frame(frame);
+    }
+
+    fn editor_set_status_message(&mut self, message: &str) {
+        self.status_message = Some(message.to_string());
+        self.status_message_time = Instant::now();
+        self.status_is_error = false;
+    }
+
+    /// Shows why a command or operation failed
+    fn editor_set_error_message(&mut self, message: &str) {
+        self.editor_set_status_message(message);
+        self.status_is_error = true;
+    }
+
+    /*** File I/O ***/
+    fn editor_open(&mut self, filename: &str) {
+        let decoded = std::fs::read(filename).map(|bytes| {
+            // UTF-16 text has NUL bytes too
+            if self.buffer.file_format.encoding == Encoding::Utf8 && is_binary(&bytes) {
+                self.buffer.hex = Some(HexView::new(bytes));
+                return Ok(String::new());
+            }
+            self.buffer.file_format.decode(&bytes)
+        });
+        let error = match decoded {
+            Ok(Ok(_)) if self.buffer.hex.is_some() => None,
+            Ok(Ok(file_contents)) => {
+                for line in self.buffer.file_format.split_lines(&file_contents) {
+                    self.editor_insert_row(self.get_num_rows(), line);
+                }
+                None
+            }
+            Ok(Err(error)) => Some(error),
+            Err(error) if error.kind() == ErrorKind::NotFound => None,
+            Err(error) => Some(describe_io_error(&error)),
+        };
+        if let Some(error) = error {
+            // The name is kept for `reopen`, saving over the file is refused
+            self.buffer.filename = Some(filename.to_string());
+            self.buffer.file_format.read_failed = true;
+            self.editor_set_error_message(&format!("Could not read {}: {}", filename, error));
+            return;
+        }
+
+        self.buffer.filename = Some(filename.to_string());
+        self.editor_update_directory();
+        self.editor_check_writable();
+        self.editor_record_disk_stamp();
+        self.start_screen = None;
+        self.buffer.dirty = 0;
+        self.buffer.front_matter_folded = true;
+        self.buffer.undo.clear();
+        self.editor_mark_saved();
+        self.editor_spell_read_modeline();
+        self.editor_run_hooks(|hook, editor| hook.on_open(editor));
+    }
+
+    /// Clears the buffer so another file can be opened into it
+    fn editor_reset_buffer(&mut self) {
+        self.editor_finish_save();
+        self.buffer.rows.clear();
+        self.revision += 1;
+        self.buffer.cursor_x = 0;
+        self.buffer.cursor_y = 0;
+        self.buffer.row_offset = 0;
+        self.buffer.column_offset = 0;
+        self.buffer.mark = None;
+        self.buffer.named_marks.clear();
+        self.buffer.filter = None;
+        self.buffer.hex = None;
+        self.buffer.follow = None;
+        self.buffer.filename = None;
+        self.buffer.directory = None;
+        self.buffer.dirty = 0;
+        self.buffer.undo.clear();
+        self.buffer.changes.clear();
+        self.buffer.file_format = FileFormat::default();
+        self.buffer.watch = FileWatch::default();
+        self.editor_remove_recovery();
+    }
+
+    /// Replaces the buffer with `filename`, asking first if there are unsaved
+    /// changes. Returns false if the user declined.
+    pub fn editor_switch_file(&mut self, filename: &str) -> bool {
+        if let Some(index) = self.editor_find_buffer(filename) {
+            self.editor_select_buffer(index);
+            return true;
+        }
+        // The results of `grep` stay around to open the next match from
+        if self.editor_showing_grep_results() {
+            self.editor_add_buffer(filename);
+            return true;
+        }
+        if self.buffer.dirty != 0
+            && !self.editor_confirm("Buffer has unsaved changes. Discard them? (y/n)")
+        {
+            self.editor_set_status_message("Open cancelled");
+            return false;
+        }
+
+        self.editor_reset_buffer();
+        self.editor_open(filename);
+        true
+    }
+
+    /// Moves the cursor to `row`, `column` (clamped to the buffer)
+    pub fn editor_goto(&mut self, row: usize, column: usize) {
+        self.buffer.cursor_y = row.min(self.get_num_rows().saturating_sub(1));
+        self.buffer.cursor_x = match self.buffer.rows.get(self.buffer.cursor_y) {
+            Some(row) => floor_char_boundary(&row.chars, column),
+            None => 0,
+        };
+    }
+
+    fn editor_save(&mut self) {
+        if self.saving.is_some() {
+            self.editor_set_status_message("Still saving, wait for it to finish");
+            return;
+        }
+        if self.buffer.filename.is_none() {
+            self.buffer.filename =
+                self.editor_prompt("Save as: %s (ESC to cancel)", HistoryKind::File);
+            if self.buffer.filename.is_none() {
+                self.editor_set_status_message("Save aborted");
+                return;
+            }
+        }
+        if let Err(error) = self.editor_write_buffer(true) {
+            self.editor_set_error_message(&error.to_string());
+        }
+    }
+
+    /// Writes the buffer to its file, on a worker thread if `background`
+    /// allows it and the buffer is big. The status bar says how it went once
+    /// it is written.
+    fn editor_write_buffer(&mut self, background: bool) -> Result<(), SaveError> {
+        if self.buffer.file_format.read_only {
+            return Err(SaveError::Refused(
+                "File was opened read-only (reopen rw to save it)",
+            ));
+        } else if self.buffer.file_format.read_failed {
+            return Err(SaveError::Refused(
+                "File could not be read, saving would overwrite it",
+            ));
+        }
+        // Hooks work on the rows, which hex mode doesn't save
+        if self.buffer.hex.is_none() {
+            self.editor_run_hooks(|hook, editor| hook.on_save_pre(editor));
+        }
+
+        let filename = self.buffer.filename.clone().unwrap_or_default();
+        // Big files are made into bytes and written on a worker thread,
+        // editing goes on meanwhile
+        if background && self.editor_saves_in_background() {
+            let source = self.editor_save_source(self.editor_save_check());
+            return self.editor_start_save(&filename, source);
+        }
+        let bytes = self.editor_bytes_to_save()?;
+        let elevated = match self.editor_write_file(&filename, &bytes) {
+            Ok(()) => false,
+            Err(error) if error.kind() == ErrorKind::PermissionDenied => {
+                self.editor_save_elevated(&filename, &bytes, &error)?;
+                true
+            }
+            Err(error) => return Err(SaveError::Failed(describe_io_error(&error))),
+        };
+
+        // The buffer stays modified, saving again may succeed
+        if let Some(error) = self.editor_verify_save(&bytes) {
+            return Err(SaveError::Failed(error));
+        }
+        self.editor_saved(&bytes, elevated, 0);
+        Ok(())
+    }
+
+    /// What saving writes: the normalized rows in the file's encoding, or the
+    /// bytes in hex mode
+    fn editor_bytes_to_save(&mut self) -> Result<Vec<u8>, SaveError> {
+        match self.editor_hex_bytes() {
+            Some(bytes) => Ok(bytes),
+            None => {
+                let buf = self
+                    .editor_save_contents()
+                    .ok_or(SaveError::Refused("Save aborted"))?;
+                self.buffer
+                    .file_format
+                    .encode(&buf)
+                    .map_err(SaveError::Failed)
+            }
+        }
+    }
+
+    /// Brings the buffer up to date after `bytes` were written to its file,
+    /// `edits_since` edits after they were taken from the rows
+    fn editor_saved(&mut self, bytes: &[u8], elevated: bool, edits_since: usize) {
+        let num_new_lines = bytes.iter().filter(|&&byte| byte == b'\n').count();
+        let mut message = format!("{} bytes written to disk {num_new_lines}", bytes.len());
+        if edits_since > 0 {
+            message.push_str(", edits made while saving aren't saved yet");
+        }
+        self.editor_set_status_message(&message);
+        self.buffer.dirty = edits_since;
+        // Still not ours to write after a save as root
+        self.buffer.file_format.not_writable = elevated;
+        self.editor_record_saved_stamp(bytes);
+        self.editor_follow_saved();
+        if edits_since == 0 {
+            self.editor_mark_saved();
+            self.editor_remove_recovery();
+        }
+        self.editor_record_save_usage();
+        self.editor_update_directory();
+        // The hooks would see rows that weren't saved
+        if edits_since == 0 {
+            self.editor_run_hooks(|hook, editor| hook.on_save_post(editor));
+        }
+    }
+
+    fn editor_rows_to_string(&self) -> String {
+        let mut buf = String::new();
+        let rows_len = self.buffer.rows.len();
+        for (idx, row) in self.buffer.rows.iter().enumerate() {
+            buf.push_str(&row.chars);
+
+            if idx < rows_len - 1 {
+                buf.push('\n');
+            }
+        }
+
+        buf
+    }
+
+    /*** Editor operations ***/
+    fn editor_insert_char(&mut self, c: char) {
+        self.editor_record_edit(self.buffer.cursor_y, 1, EditKind::InsertChar);
+        if self.buffer.cursor_y == self.get_num_rows() {
+            self.editor_insert_row(self.get_num_rows(), String::new());
+        }
+
+        let row = &mut self.buffer.rows[self.buffer.cursor_y];
+        row.insert_char(self.buffer.cursor_x, c);
+        self.buffer.cursor_x += c.len_utf8();
+        self.editor_count_edit();
+        self.editor_run_hooks(|hook, editor| hook.on_char_insert(editor, c));
+    }
+
+    fn editor_insert_newline(&mut self) {
+        self.editor_record_edit(self.buffer.cursor_y, 1, EditKind::Other);
+        if self.buffer.cursor_x == 0 {
+            self.editor_insert_row(self.buffer.cursor_y, String::new());
+        } else {
+            let row = &mut self.buffer.rows[self.buffer.cursor_y];
+            let new_row = row.split_off(self.buffer.cursor_x);
+            self.editor_insert_row(self.buffer.cursor_y + 1, new_row);
+            self.editor_count_edit();
+        }
+
+        self.buffer.cursor_y += 1;
+        self.buffer.cursor_x = 0;
+    }
+
+    /// Removes row `at`, whose text is joined onto the row above. Marks on it
+    /// move up with the text.
+    fn editor_delete_row(&mut self, at: usize) -> Option<EditorRow> {
+        if at >= self.get_num_rows() {
+            return None;
+        }
+
+        self.editor_count_edit();
+        match at.checked_sub(1) {
+            Some(joined) => self.editor_shift_named_marks(joined, 2, 1),
+            None => self.editor_shift_named_marks(at, 1, 0),
+        }
+        Some(self.buffer.rows.remove(at))
+    }
+
+    fn editor_delete_char(&mut self) {
+        if self.buffer.cursor_y == self.screen_num_rows {
+            return;
+        };
+        if self.buffer.cursor_x == 0 && self.buffer.cursor_y == 0 {
+            return;
+        }
+
+        if self.buffer.cursor_x > 0 {
+            self.editor_record_edit(self.buffer.cursor_y, 1, EditKind::DeleteChar);
+            let row = &mut self.buffer.rows[self.buffer.cursor_y];
+            let at = previous_char_boundary(&row.chars, self.buffer.cursor_x);
+            row.delete_char(at);
+            self.buffer.cursor_x = at;
+            self.editor_count_edit();
+        } else {
+            self.editor_record_edit(self.buffer.cursor_y - 1, 2, EditKind::Other);
+            self.buffer.cursor_x = self.buffer.rows[self.buffer.cursor_y - 1].len();
+            let deleted_row = self.editor_delete_row(self.buffer.cursor_y);
+            if let Some(row) = deleted_row {
+                self.buffer.rows[self.buffer.cursor_y - 1].append_string(&row.chars);
+            }
+
+            self.buffer.cursor_y -= 1;
+            // Dirty is incremented in editor_delete_row
+        }
+    }
+
+    /*** Input ***/
+    /// Reads what the terminal has sent into `input`, waiting up to `timeout`
+    /// for something to arrive. False if nothing did.
+    fn editor_fill_input(&mut self, timeout: Duration) -> bool {
+        if self.headless || !stdin_ready(timeout) {
+            return false;
+        }
+        // Straight from the descriptor: bytes kept in std's buffer would be
+        // hidden from `stdin_ready`
+        let mut buf = [0u8; 256];
+        let read = unsafe {
+            libc::read(
+                io::stdin().as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+            )
+        };
+        match read {
+            0 => self.die("Read error: the terminal was closed"),
+            -1 => {
+                let error = io::Error::last_os_error();
+                if !matches!(error.kind(), ErrorKind::Interrupted | ErrorKind::WouldBlock) {
+                    self.die(&format!("Read error: {}", error));
+                }
+            }
+            read => self.input.extend(&buf[..read as usize]),
+        }
+        read > 0
+    }
+
+    /// Called while waiting for input
+    fn editor_idle(&mut self) {
+        let mut redraw = false;
+        redraw |= self.editor_update_git_signs();
+        if self.options.change_marks {
+            redraw |= self.editor_update_saved_changes();
+        }
+        redraw |= self.editor_lsp_poll();
+        redraw |= self.editor_grep_poll();
+        redraw |= self.editor_follow_poll();
+        redraw |= self.editor_syntax_poll();
+        redraw |= self.editor_save_poll();
+        redraw |= self.editor_check_disk_changes();
+        self.editor_write_recovery();
+        self.editor_flush_usage(false);
+
+        // Idle redraws are cosmetic, a slow terminal waits for the next key
+        if redraw && !self.overlay_active && !self.editor_slow_terminal() {
+            self.editor_refresh_screen();
+        }
+    }
+
+    fn editor_read_key(&mut self) -> Key {
+        if let Some(key) = self.pending_keys.pop_front() {
+            return key;
+        }
+        if self.headless && self.input.is_empty() {
+            // Prompts opened by an injected key are cancelled unless the keys
+            // answering them were injected along with it
+            return Key::Escape;
+        }
+
+        self.editor_read_typeahead();
+        while self.input.is_empty() {
+            if !self.editor_fill_input(IDLE_INTERVAL) {
+                self.editor_idle();
+            }
+        }
+        let first = self.input.pop_front().unwrap();
+        self.editor_parse_key(first)
+    }
+
+    /// Parses the key starting with byte `first` from `input`. The rest of an
+    /// escape sequence or UTF-8 char follows within `esctimeout`
+    /// milliseconds, so an ESC with nothing after it by then is the Escape
+    /// key. Bytes read past the key stay queued for the next one.
+    fn editor_parse_key(&mut self, first: u8) -> Key {
+        let timeout = Duration::from_millis(self.options.esc_timeout as u64);
+        let (key, leftover) = parse_key(first, || {
+            if self.input.is_empty() {
+                self.editor_fill_input(timeout);
+            }
+            self.input.pop_front()
+        });
+        if let Some(byte) = leftover {
+            self.input.push_front(byte);
+        }
+        key
+    }
+
+    /// Reads a line in the message bar, Up/Down browse the `history` of this
+    /// kind of prompt
+    fn editor_prompt(&mut self, prompt: &str, history: HistoryKind) -> Option<String> {
+        self.editor_prompt_with_text(prompt, "", history)
+    }
+
+    /// Like `editor_prompt`, starting with `text` already typed
+    fn editor_prompt_with_text(
+        &mut self,
+        prompt: &str,
+        text: &str,
+        history: HistoryKind,
+    ) -> Option<String> {
+        let previous = self.editor_set_mode(Mode::Prompt);
+        let result = self.editor_read_prompt(prompt, text, history);
+        self.editor_set_mode(previous);
+        result
+    }
+
+    fn editor_read_prompt(
+        &mut self,
+        prompt: &str,
+        text: &str,
+        history: HistoryKind,
+    ) -> Option<String> {
+        let mut buffer = AppendBuffer::new();
+        buffer.push(text);
+        let mut browser = HistoryBrowser::new(history);
+
+        loop {
+            self.editor_set_status_message(&prompt.replace("%s", &buffer.buf));
+            self.editor_refresh_screen();
+
+            let key = self.editor_read_key();
+
+            if key == Key::Escape {
+                self.editor_set_status_message("");
+                return None;
+            } else if key == Key::Enter {
+                self.editor_set_status_message("");
+
+                if buffer.buf.is_empty() {
+                    return None;
+                } else {
+                    add_history_entry(history, &buffer.buf);
+                    return Some(buffer.buf);
+                }
+            } else if key == Key::Up || key == Key::Down {
+                if let Some(entry) = browser.step(key == Key::Up, &buffer.buf) {
+                    buffer.buf = entry;
+                }
+            } else if matches!(key, Key::Backspace | Key::Ctrl('h') | Key::Delete) {
+                buffer.buf.pop();
+            } else if let Key::Char(c) = key {
+                buffer.buf.push(c);
+            } else if key == Key::Tab {
+                buffer.buf.push('\t');
+            }
+        }
+    }
+
+    /// Asks a yes/no question in the message bar
+    fn editor_confirm(&mut self, question: &str) -> bool {
+        self.editor_set_status_message(question);
+        self.editor_refresh_screen();
+
+        let key = self.editor_read_key();
+        self.editor_set_status_message("");
+
+        matches!(key, Key::Char('y' | 'Y'))
+    }
+
+    fn editor_move_cursor(&mut self, key: Key) {
+        if matches!(key.unmodified(), Key::Up | Key::Down) && self.editor_move_by_display_line(key)
+        {
+            return;
+        }
+
+        let on_row = self.buffer.cursor_y < self.get_num_rows();
+        match key.unmodified() {
+            Key::Left => {
+                if self.buffer.cursor_x != 0 {
+                    self.buffer.cursor_x = previous_char_boundary(
+                        &self.buffer.rows[self.buffer.cursor_y].chars,
+                        self.buffer.cursor_x,
+                    );
+                } else if let Some(previous_row) =
+                    self.editor_next_visible_row(self.buffer.cursor_y, true)
+                {
+                    self.buffer.cursor_y = previous_row;
+                    self.buffer.cursor_x = self.buffer.rows[self.buffer.cursor_y].len();
+                }
+            }
+            Key::Right => {
+                if on_row && self.buffer.cursor_x < self.buffer.rows[self.buffer.cursor_y].len() {
+                    let row = &self.buffer.rows[self.buffer.cursor_y].chars;
+                    self.buffer.cursor_x += row[self.buffer.cursor_x..]
+                        .chars()
+                        .next()
+                        .map_or(1, char::len_utf8);
+                } else if on_row
+                    && self.buffer.cursor_x == self.buffer.rows[self.buffer.cursor_y].len()
+                {
+                    if let Some(next_row) =
+                        self.editor_next_visible_row(self.buffer.cursor_y, false)
+                    {
+                        self.buffer.cursor_y = next_row;
+                        self.buffer.cursor_x = 0;
+                    }
+                }
+            }
+            Key::Up | Key::Down if key.unmodified() == Key::Up || on_row => {
+                let render_x = match self.buffer.rows.get(self.buffer.cursor_y) {
+                    Some(row) => {
+                        row.cursor_x_to_render_cursor_x(self.options.tab_stop, self.buffer.cursor_x)
+                    }
+                    None => 0,
+                };
+                let column = self.editor_goal_column(false, render_x);
+                self.buffer.cursor_y = self
+                    .editor_next_visible_row(self.buffer.cursor_y, key.unmodified() == Key::Up)
+                    .unwrap_or(self.buffer.cursor_y);
+                if let Some(row) = self.buffer.rows.get(self.buffer.cursor_y) {
+                    self.buffer.cursor_x =
+                        row.render_cursor_x_to_cursor_x(self.options.tab_stop, column);
+                }
+                self.editor_keep_goal_column(false, column);
+            }
+            // Home/End move within the display line when wrapping
+            Key::Home if !self.editor_move_to_display_line_edge(false) => {
+                self.buffer.cursor_x = 0;
+            }
+            // Without wrapping, first to the right edge of the screen
+            Key::End if !self.editor_move_to_display_line_edge(true) && on_row => {
+                self.buffer.cursor_x = self
+                    .editor_end_of_visible_line()
+                    .unwrap_or(self.buffer.rows[self.buffer.cursor_y].len());
+                self.editor_stick_to_line_end();
+            }
+            Key::End => self.editor_stick_to_line_end(),
+            _ => {}
+        }
+
+        // Snap to end of line, and to the start of a char moving between rows
+        self.buffer.cursor_x = match self.buffer.rows.get(self.buffer.cursor_y) {
+            Some(row) => floor_char_boundary(&row.chars, self.buffer.cursor_x),
+            None => 0,
+        };
+    }
+
+    /// Saves what is kept across runs and exits, after asking about unsaved
+    /// buffers
+    pub fn editor_quit(&mut self) {
+        self.editor_finish_save();
+        if self.editor_any_buffer_dirty() && !self.editor_confirm_quit() {
+            return;
+        }
+        self.editor_remove_all_recovery();
+        self.editor_broadcast_stop();
+        self.editor_flush_usage(true);
+        self.editor_write_undo_file();
+        self.editor_write_session();
+        if self.headless {
+            self.quit_requested = true;
+            return;
+        }
+        self.cleanup();
+        process::exit(0);
+    }
+
+    /** Returns true if should continue */
+    fn editor_process_keypress(&mut self) {
+        let revision = self.revision;
+        self.editor_process_key();
+        // An edit can make the filter hide the row the cursor is on
+        if self.revision != revision {
+            self.editor_snap_to_visible_row();
+        }
+    }
+
+    fn editor_process_key(&mut self) {
+        let key = self.editor_read_key();
+        if self.editor_disk_conflict_key(key) {
+            return;
+        }
+        if self.start_screen.is_some() && self.editor_start_screen_key(key) {
+            return;
+        }
+        #[cfg(feature = "scripting")]
+        if self.editor_script_key(key) {
+            return;
+        }
+        if self.editor_run_input_hooks(key) {
+            return;
+        }
+
+        if let Some(binding) = keymap::binding_for(key) {
+            (binding.run)(self, key);
+            return;
+        }
+
+        match key {
+            Key::Enter => {
+                self.editor_insert_newline();
+            }
+            Key::Left | Key::Right | Key::Up | Key::Down => self.editor_move_cursor(key),
+            Key::PageDown | Key::PageUp => {
+                let (by_display_line, column) = self.editor_vertical_goal();
+                if key == Key::PageUp {
+                    self.buffer.cursor_y = self.buffer.row_offset;
+                } else {
+                    self.buffer.cursor_y = self.buffer.row_offset + self.screen_num_rows - 1;
+                    if self.buffer.cursor_y > self.get_num_rows() {
+                        self.buffer.cursor_y = self.get_num_rows();
+                    }
+                }
+                self.editor_keep_goal_column(by_display_line, column);
+
+                let mut times = self.screen_num_rows;
+                while times > 0 {
+                    self.editor_move_cursor(if key == Key::PageUp {
+                        Key::Up
+                    } else {
+                        Key::Down
+                    });
+                    times -= 1;
+                }
+            }
+            Key::Home | Key::End => self.editor_move_cursor(key),
+            Key::Backspace | Key::Delete | Key::Ctrl('h') => {
+                if key == Key::Delete {
+                    self.editor_move_cursor(Key::Right);
+                }
+                self.editor_delete_char();
+            }
+            Key::Ctrl('l') => {
+                // Same as ESCAPE
+                // Do nothing
+            }
+            Key::Char(c) if self.overwrite => self.editor_overwrite_char(c),
+            Key::Char(c) => self.editor_insert_char(c),
+            Key::Tab => self.editor_insert_char('\t'),
+            _ => {}
+        };
+    }
+}
+
+/// Columns and rows in a cursor position report, `ESC [ rows ; columns R`,
+/// taken out of `input` with the bytes around it left in place
+fn take_cursor_report(input: &mut VecDeque<u8>) -> Option<(usize, usize)> {
+    let bytes = input.make_contiguous();
+    let (start, len, rows, columns) = (0..bytes.len()).find_map(|start| {
+        let rest = bytes[start..].strip_prefix(b"\x1b[")?;
+        let end = rest
+            .iter()
+            .position(|byte| !matches!(byte, b'0'..=b'9' | b';'))?;
+        let (rows, columns) = std::str::from_utf8(&rest[..end]).ok()?.split_once(';')?;
+        (rest[end] == b'R').then_some((start, end + 3, rows.parse().ok()?, columns.parse().ok()?))
+    })?;
+    input.drain(start..start + len);
+    Some((columns, rows))
+}
+
+/// Whether a key can be read from stdin within `timeout`
+fn stdin_ready(timeout: Duration) -> bool {
+    let mut fds = libc::pollfd {
+        fd: io::stdin().as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    unsafe { libc::poll(&mut fds, 1, timeout.as_millis() as i32) > 0 }
+}
+
+/// Start of the char before byte `index` in `s`
+fn previous_char_boundary(s: &str, index: usize) -> usize {
+    s[..floor_char_boundary(s, index)]
+        .char_indices()
+        .next_back()
+        .map_or(0, |(at, _)| at)
+}
+
+/// Splits file contents into lines, stripping any trailing carriage returns
+fn split_lines(contents: &str) -> Vec<String> {
+    contents
+        .split('\n')
+        .map(|line| line.trim_end_matches(['\n', '\r']).to_string())
+        .collect()
+}
+
+/// Runs the terminal editor, opening the files named by the arguments or the
+/// text piped to stdin
+pub fn run() {
+    let mut args = Args::parse(std::env::args().skip(1));
+    // Piped text is used when asked for with `-` or when no file is named
+    let use_stdin = args.read_stdin || (args.filenames.is_empty() && !args.session);
+    let stdin_error = |error: io::Error| -> Option<String> {
+        eprintln!("Error: could not read stdin: {}", error);
+        process::exit(1);
+    };
+
+    if let Some(script) = args.script.take() {
+        let piped = match use_stdin {
+            true => read_piped_stdin().unwrap_or_else(stdin_error),
+            false => None,
+        };
+        script.run(&args, piped);
+    }
+
+    // The terminal has to be stdin before raw mode is set up
+    let piped = match use_stdin {
+        true => take_piped_stdin().unwrap_or_else(stdin_error),
+        false => {
+            if let Err(error) = use_terminal_as_stdin() {
+                eprintln!("Error: could not open the terminal: {}", error);
+                process::exit(1);
+            }
+            None
+        }
+    };
+    let mut editor = Editor::new();
+
+    editor.enable_raw_mode();
+    // A terminal that doesn't know its size can only be asked in raw mode
+    editor.get_dimensions();
+    editor.editor_configure(&args);
+    #[cfg(feature = "scripting")]
+    editor.editor_load_init_script();
+    if let Some(path) = &args.cast {
+        editor.editor_start_cast(path);
+    }
+
+    let mut rest = args.filenames.as_slice();
+    match piped {
+        Some(contents) => editor.editor_open_piped(&contents),
+        None if args.session => {
+            if !editor.editor_restore_session() {
+                editor.editor_set_status_message("No session to restore");
+                editor.start_screen = Some(StartScreen::new());
+            }
+        }
+        None => match args.filenames.split_first() {
+            Some((first, others)) => {
+                editor.editor_open(first);
+                if let Some(line) = args.line {
+                    editor.editor_goto(line - 1, 0);
+                }
+                if args.follow {
+                    editor.editor_toggle_follow();
+                }
+                rest = others;
+            }
+            None => editor.start_screen = Some(StartScreen::new()),
+        },
+    }
+    if !rest.is_empty() {
+        for filename in rest {
+            editor.editor_add_buffer(filename);
+        }
+        editor.editor_select_buffer(0);
+    }
+    if args.readonly {
+        editor.editor_set_all_read_only();
+    }
+    editor.editor_recover(true);
+
+    // Warnings from opening the files come first
+    if editor.status_message.is_none() {
+        editor.editor_set_status_message(
+            "HELP: F1 = help | Ctrl-S = save | Ctrl-Q = quit | Ctrl-P = command",
+        );
+    }
+
+    loop {
+        editor.editor_refresh_screen();
+        editor.editor_process_keypress();
+    }
+}
@@ -0,0 +1,331 @@
+//! Commands transforming whole lines: sort, reverse, uniq and align
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use crate::*;
+
+/// The `field`th (1 based) field of `line`, split on `delimiter` or whitespace
+fn line_field<'a>(line: &'a str, field: usize, delimiter: Option<&str>) -> &'a str {
+    let field = field.saturating_sub(1);
+    match delimiter {
+        Some(delimiter) => line.split(delimiter).nth(field),
+        None => line.split_whitespace().nth(field),
+    }
+    .unwrap_or("")
+    .trim()
+}
+
+pub struct SortOptions<'a> {
+    /// 1 based field to sort by, 0 for the whole line
+    pub field: usize,
+    pub delimiter: Option<&'a str>,
+    /// Compare by the number the key starts with
+    pub numeric: bool,
+    pub reverse: bool,
+    /// Keep only the first of lines with equal keys
+    pub unique: bool,
+}
+
+/// The number `key` starts with, like `sort -n`: keys without one count as 0
+fn leading_number(key: &str) -> f64 {
+    let key = key.trim_start();
+    let mut end = 0;
+    let mut seen_point = false;
+    for (index, c) in key.char_indices() {
+        match c {
+            '-' | '+' if index == 0 => {}
+            '.' if !seen_point => seen_point = true,
+            '0'..='9' => {}
+            _ => break,
+        }
+        end = index + c.len_utf8();
+    }
+    key[..end].parse().unwrap_or(0.0)
+}
+
+/// The part of `line` sorted by
+fn sort_key<'a>(line: &'a str, options: &SortOptions) -> &'a str {
+    if options.field == 0 {
+        line
+    } else {
+        line_field(line, options.field, options.delimiter)
+    }
+}
+
+pub fn sort_lines(lines: &mut Vec<String>, options: &SortOptions) {
+    let compare = |a: &String, b: &String| -> Ordering {
+        let (a, b) = (sort_key(a, options), sort_key(b, options));
+        if options.numeric {
+            leading_number(a).total_cmp(&leading_number(b))
+        } else {
+            a.cmp(b)
+        }
+    };
+
+    lines.sort_by(|a, b| {
+        if options.reverse {
+            compare(b, a)
+        } else {
+            compare(a, b)
+        }
+    });
+    if options.unique {
+        lines.dedup_by(|a, b| compare(a, b) == Ordering::Equal);
+    }
+}
+
+/// Removes repeated lines, keeping the first of each wherever it is
+pub fn unique_lines(lines: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    lines
+        .into_iter()
+        .filter(|line| seen.insert(line.clone()))
+        .collect()
+}
+
+/// Columns `text` takes on screen
+fn text_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// `text` padded with spaces to `width` screen columns
+fn pad_to(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(text_width(text));
+    format!("{}{}", text, " ".repeat(padding))
+}
+
+/// Pads lines so the first occurrence of `delimiter` lines up in every line
+/// that contains it, or with `every` each occurrence, as in a Markdown table
+pub fn align_lines(lines: &[String], delimiter: &str, every: bool) -> Vec<String> {
+    let limit = if every { usize::MAX } else { 2 };
+    let cells: Vec<Vec<&str>> = lines
+        .iter()
+        .map(|line| line.splitn(limit, delimiter).collect())
+        .collect();
+    // The first cell keeps its indentation, the others are trimmed; the last
+    // one is left as it is
+    let cell_text = |index: usize, cell: &'_ str| -> String {
+        if index == 0 {
+            cell.trim_end().to_string()
+        } else {
+            cell.trim().to_string()
+        }
+    };
+
+    let mut widths: Vec<usize> = vec![];
+    for row in &cells {
+        for (index, cell) in row.iter().enumerate().take(row.len() - 1) {
+            let width = text_width(&cell_text(index, cell));
+            match widths.get_mut(index) {
+                Some(column) => *column = (*column).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+
+    cells
+        .iter()
+        .zip(lines)
+        .map(|(row, line)| {
+            if row.len() == 1 {
+                return line.clone();
+            }
+            let mut aligned = pad_to(&cell_text(0, row[0]), widths[0]);
+            for (index, cell) in row.iter().enumerate().skip(1) {
+                if !aligned.is_empty() {
+                    aligned.push(' ');
+                }
+                aligned.push_str(delimiter);
+                if index + 1 == row.len() {
+                    aligned.push_str(cell);
+                } else {
+                    let text = cell_text(index, cell);
+                    aligned.push(' ');
+                    aligned.push_str(&pad_to(&text, widths[index]));
+                }
+            }
+            aligned
+        })
+        .collect()
+}
+
+impl Editor {
+    /// `sort [-n] [-r] [-u] [-k FIELD] [-t DELIMITER]` over the selected rows
+    /// or whole buffer
+    pub fn editor_sort(&mut self, args: &str) {
+        let mut options = SortOptions {
+            field: 0,
+            delimiter: None,
+            numeric: false,
+            reverse: false,
+            unique: false,
+        };
+
+        let mut tokens = args.split_whitespace();
+        while let Some(token) = tokens.next() {
+            if let Some(value) = token.strip_prefix("-k") {
+                let value = if value.is_empty() {
+                    tokens.next().unwrap_or("")
+                } else {
+                    value
+                };
+                match value.parse() {
+                    Ok(field) => options.field = field,
+                    Err(_) => {
+                        self.editor_set_error_message(&format!("Invalid field: {}", value));
+                        return;
+                    }
+                }
+            } else if let Some(value) = token.strip_prefix("-t") {
+                options.delimiter = Some(if value.is_empty() {
+                    tokens.next().unwrap_or(",")
+                } else {
+                    value
+                });
+            } else if token.len() > 1
+                && token.starts_with('-')
+                && token[1..].chars().all(|flag| "nru".contains(flag))
+            {
+                options.numeric |= token.contains('n');
+                options.reverse |= token.contains('r');
+                options.unique |= token.contains('u');
+            } else {
+                self.editor_set_error_message(&format!("Unknown sort flag: {}", token));
+                return;
+            }
+        }
+
+        let (start, count) = self.editor_selected_rows();
+        let mut lines: Vec<String> = self.buffer.rows[start..start + count]
+            .iter()
+            .map(|row| row.chars.clone())
+            .collect();
+        sort_lines(&mut lines, &options);
+        let removed = count - lines.len();
+
+        self.editor_replace_rows(start, count, lines);
+        self.buffer.mark = None;
+        if removed > 0 {
+            self.editor_set_status_message(&format!(
+                "Sorted {} lines, removed {} duplicates",
+                count, removed
+            ));
+        } else {
+            self.editor_set_status_message(&format!("Sorted {} lines", count));
+        }
+    }
+
+    /// `reverse`: reverses the order of the selected rows or whole buffer
+    pub fn editor_reverse_lines(&mut self) {
+        let (start, count) = self.editor_selected_rows();
+        let lines: Vec<String> = self.buffer.rows[start..start + count]
+            .iter()
+            .rev()
+            .map(|row| row.chars.clone())
+            .collect();
+
+        self.editor_replace_rows(start, count, lines);
+        self.buffer.mark = None;
+        self.editor_set_status_message(&format!("Reversed {} lines", count));
+    }
+
+    /// `uniq`: removes repeated rows from the selection or whole buffer,
+    /// keeping the first of each
+    pub fn editor_unique_lines(&mut self) {
+        let (start, count) = self.editor_selected_rows();
+        let lines: Vec<String> = self.buffer.rows[start..start + count]
+            .iter()
+            .map(|row| row.chars.clone())
+            .collect();
+        let lines = unique_lines(lines);
+        let removed = count - lines.len();
+        if removed == 0 {
+            self.editor_set_status_message("No duplicate lines");
+            return;
+        }
+
+        self.editor_replace_rows(start, count, lines);
+        self.buffer.mark = None;
+        self.editor_set_status_message(&format!("Removed {} duplicate lines", removed));
+    }
+
+    /// `align [-a] DELIMITER` over the selected rows or whole buffer
+    pub fn editor_align(&mut self, args: &str) {
+        let (every, delimiter) = match args.strip_prefix("-a ") {
+            Some(delimiter) => (true, delimiter.trim()),
+            None => (false, args),
+        };
+        if delimiter.is_empty() {
+            self.editor_set_error_message("Usage: align [-a] DELIMITER");
+            return;
+        }
+
+        let (start, count) = self.editor_selected_rows();
+        let lines: Vec<String> = self.buffer.rows[start..start + count]
+            .iter()
+            .map(|row| row.chars.clone())
+            .collect();
+        let aligned = align_lines(&lines, delimiter, every);
+
+        self.editor_replace_rows(start, count, aligned);
+        self.buffer.mark = None;
+        self.editor_set_status_message(&format!("Aligned {} lines on '{}'", count, delimiter));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn sorts_by_field_and_number() {
+        let mut rows = lines(&["b,10", "a,9", "c,10", "d,x"]);
+        let options = SortOptions {
+            field: 2,
+            delimiter: Some(","),
+            numeric: true,
+            reverse: false,
+            unique: false,
+        };
+        sort_lines(&mut rows, &options);
+        assert_eq!(rows, ["d,x", "a,9", "b,10", "c,10"]);
+
+        let options = SortOptions {
+            reverse: true,
+            unique: true,
+            ..options
+        };
+        sort_lines(&mut rows, &options);
+        assert_eq!(rows, ["b,10", "a,9", "d,x"]);
+    }
+
+    #[test]
+    fn keeps_the_first_of_repeated_lines() {
+        let rows = lines(&["b", "a", "b", "", "a", ""]);
+        assert_eq!(unique_lines(rows), ["b", "a", ""]);
+    }
+
+    #[test]
+    fn aligns_on_screen_columns() {
+        let rows = lines(&["  x = 1", "名前 = 2", "no delimiter", "long_name=3"]);
+        assert_eq!(
+            align_lines(&rows, "=", false),
+            [
+                "  x       = 1",
+                "名前      = 2",
+                "no delimiter",
+                "long_name =3"
+            ]
+        );
+        let table = lines(&["| a | bb |", "| 表 | c |"]);
+        assert_eq!(
+            align_lines(&table, "|", true),
+            ["| a  | bb |", "| 表 | c  |"]
+        );
+    }
+}
@@ -0,0 +1,818 @@
+//! Minimal language server client: diagnostics and goto definition over stdio
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use crate::hooks::EditorHook;
+use crate::json::Json;
+use crate::quickfix::{Quickfix, QuickfixEntry};
+use crate::*;
+
+/// Language servers used when the config doesn't set one for the filetype
+fn default_server(extension: &str) -> Option<&'static str> {
+    match extension {
+        "rs" => Some("rust-analyzer"),
+        "c" | "h" | "cc" | "cpp" | "hpp" => Some("clangd"),
+        "go" => Some("gopls"),
+        "py" => Some("pylsp"),
+        "ts" | "tsx" | "js" | "jsx" => Some("typescript-language-server --stdio"),
+        _ => None,
+    }
+}
+
+fn language_id(extension: &str) -> &str {
+    match extension {
+        "rs" => "rust",
+        "h" => "c",
+        "cc" | "hpp" => "cpp",
+        "py" => "python",
+        "ts" => "typescript",
+        "tsx" => "typescriptreact",
+        "js" => "javascript",
+        "jsx" => "javascriptreact",
+        other => other,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    fn from_lsp(value: Option<usize>) -> Severity {
+        match value {
+            Some(1) | None => Severity::Error,
+            Some(2) => Severity::Warning,
+            Some(3) => Severity::Information,
+            _ => Severity::Hint,
+        }
+    }
+
+    pub fn symbol(&self) -> char {
+        match self {
+            Severity::Error => 'E',
+            Severity::Warning => 'W',
+            Severity::Information => 'I',
+            Severity::Hint => 'H',
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Severity::Error => Color::RED,
+            Severity::Warning => Color::YELLOW,
+            Severity::Information => Color::BLUE,
+            Severity::Hint => Color::CYAN,
+        }
+    }
+}
+
+pub struct Diagnostic {
+    pub line: usize,
+    pub start_column: usize,
+    /// Exclusive, on `line` (multi-line ranges are cut at the end of the first line)
+    pub end_column: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+pub struct LspClient {
+    child: Child,
+    stdin: ChildStdin,
+    receiver: Receiver<Json>,
+    next_id: i64,
+    initialized: bool,
+    pending_definition: Option<i64>,
+    pending_completion: Option<i64>,
+    /// Response to the last completion request: (label, text, detail)
+    completion_items: Option<Vec<(String, String, String)>>,
+    uri: String,
+    language_id: String,
+    version: i64,
+    /// `Editor::revision` when the document was last sent
+    synced_revision: Option<usize>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Language server command per file extension, set with `lspserver`
+pub type LspServers = HashMap<String, String>;
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+    let mut bytes = vec![];
+    let mut iter = encoded.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let high = (iter.next()? as char).to_digit(16)?;
+            let low = (iter.next()? as char).to_digit(16)?;
+            bytes.push((high * 16 + low) as u8);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+/// UTF-16 code units before byte `index` of `line`: LSP positions count
+/// characters that way
+fn utf16_column(line: &str, index: usize) -> usize {
+    line[..floor_char_boundary(line, index)]
+        .encode_utf16()
+        .count()
+}
+
+/// Byte of `line` at LSP character `character`, the end of the line if it is
+/// past it
+fn byte_column(line: &str, character: usize) -> usize {
+    let mut units = 0;
+    for (index, c) in line.char_indices() {
+        if units >= character {
+            return index;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+/// Reads `Content-Length` framed messages until the server closes stdout
+fn read_messages(stdout: impl io::Read, sender: mpsc::Sender<Json>) {
+    let mut reader = BufReader::new(stdout);
+    loop {
+        let mut content_length = None;
+        loop {
+            let mut header = String::new();
+            match reader.read_line(&mut header) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {}
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(length) = header.strip_prefix("Content-Length:") {
+                content_length = length.trim().parse().ok();
+            }
+        }
+
+        let mut body = vec![0; content_length.unwrap_or(0)];
+        if reader.read_exact(&mut body).is_err() {
+            return;
+        }
+        if let Some(message) = Json::parse(&String::from_utf8_lossy(&body)) {
+            if sender.send(message).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl LspClient {
+    fn start(command: &str, path: &Path, language_id: &str) -> io::Result<LspClient> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdin = child.stdin.take().ok_or(ErrorKind::BrokenPipe)?;
+        let stdout = child.stdout.take().ok_or(ErrorKind::BrokenPipe)?;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || read_messages(stdout, sender));
+
+        let mut client = LspClient {
+            child,
+            stdin,
+            receiver,
+            next_id: 1,
+            initialized: false,
+            pending_definition: None,
+            pending_completion: None,
+            completion_items: None,
+            uri: path_to_uri(path),
+            language_id: language_id.to_string(),
+            version: 0,
+            synced_revision: None,
+            diagnostics: vec![],
+        };
+
+        let root = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+        client.request(
+            "initialize",
+            Json::object(vec![
+                ("processId", Json::Number(process::id() as f64)),
+                ("rootUri", Json::String(path_to_uri(&root))),
+                (
+                    "capabilities",
+                    Json::object(vec![(
+                        "textDocument",
+                        Json::object(vec![
+                            ("publishDiagnostics", Json::object(vec![])),
+                            ("definition", Json::object(vec![])),
+                            ("completion", Json::object(vec![])),
+                        ]),
+                    )]),
+                ),
+            ]),
+        )?;
+
+        Ok(client)
+    }
+
+    fn send(&mut self, message: Json) -> io::Result<()> {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        self.stdin.flush()
+    }
+
+    fn request(&mut self, method: &str, params: Json) -> io::Result<i64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(Json::object(vec![
+            ("jsonrpc", Json::string("2.0")),
+            ("id", Json::Number(id as f64)),
+            ("method", Json::string(method)),
+            ("params", params),
+        ]))?;
+        Ok(id)
+    }
+
+    fn notify(&mut self, method: &str, params: Json) -> io::Result<()> {
+        self.send(Json::object(vec![
+            ("jsonrpc", Json::string("2.0")),
+            ("method", Json::string(method)),
+            ("params", params),
+        ]))
+    }
+
+    fn text_document(&self) -> Json {
+        Json::object(vec![("uri", Json::String(self.uri.clone()))])
+    }
+
+    /// Whether the server has yet to see the buffer as of `revision`
+    fn needs_sync(&self, revision: usize) -> bool {
+        self.initialized && self.synced_revision != Some(revision)
+    }
+
+    /// Sends the full document if it changed since it was last sent
+    fn sync(&mut self, text: &str, revision: usize) -> io::Result<()> {
+        if !self.needs_sync(revision) {
+            return Ok(());
+        }
+
+        self.version += 1;
+        if self.synced_revision.is_none() {
+            self.notify(
+                "textDocument/didOpen",
+                Json::object(vec![(
+                    "textDocument",
+                    Json::object(vec![
+                        ("uri", Json::String(self.uri.clone())),
+                        ("languageId", Json::String(self.language_id.clone())),
+                        ("version", Json::Number(self.version as f64)),
+                        ("text", Json::string(text)),
+                    ]),
+                )]),
+            )?;
+        } else {
+            self.notify(
+                "textDocument/didChange",
+                Json::object(vec![
+                    (
+                        "textDocument",
+                        Json::object(vec![
+                            ("uri", Json::String(self.uri.clone())),
+                            ("version", Json::Number(self.version as f64)),
+                        ]),
+                    ),
+                    (
+                        "contentChanges",
+                        Json::Array(vec![Json::object(vec![("text", Json::string(text))])]),
+                    ),
+                ]),
+            )?;
+        }
+        self.synced_revision = Some(revision);
+
+        Ok(())
+    }
+}
+
+/// Shuts the server down with the client, however it is let go of
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        let _ = self.request("shutdown", Json::Null);
+        let _ = self.notify("exit", Json::Null);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Parses the `diagnostics` array of a publishDiagnostics notification
+fn parse_diagnostics(params: &Json) -> Vec<Diagnostic> {
+    let items = params
+        .get("diagnostics")
+        .and_then(Json::as_array)
+        .unwrap_or(&[]);
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let range = item.get("range")?;
+            let start = range.get("start")?;
+            let end = range.get("end")?;
+            let line = start.get("line")?.as_usize()?;
+            let start_column = start.get("character")?.as_usize()?;
+            let end_column = if end.get("line")?.as_usize()? == line {
+                end.get("character")?.as_usize()?.max(start_column + 1)
+            } else {
+                usize::MAX
+            };
+
+            Some(Diagnostic {
+                line,
+                start_column,
+                end_column,
+                severity: Severity::from_lsp(item.get("severity").and_then(Json::as_usize)),
+                message: item
+                    .get("message")
+                    .and_then(Json::as_str)
+                    .unwrap_or("")
+                    .lines()
+                    .next()
+                    .unwrap_or("")
+                    .to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Items of a completion response (CompletionItem[] or CompletionList)
+fn parse_completions(result: &Json) -> Vec<(String, String, String)> {
+    let items = result
+        .as_array()
+        .or_else(|| result.get("items").and_then(Json::as_array))
+        .unwrap_or(&[]);
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let label = item.get("label")?.as_str()?;
+            let text = item
+                .get("textEdit")
+                .and_then(|edit| edit.get("newText"))
+                .or_else(|| item.get("insertText"))
+                .and_then(Json::as_str)
+                .unwrap_or(label);
+            let detail = item.get("detail").and_then(Json::as_str).unwrap_or("");
+            Some((
+                label.trim().to_string(),
+                text.to_string(),
+                detail.lines().next().unwrap_or("").to_string(),
+            ))
+        })
+        .collect()
+}
+
+/// First location of a definition response (Location, Location[] or LocationLink[])
+fn parse_location(result: &Json) -> Option<(PathBuf, usize, usize)> {
+    let location = match result {
+        Json::Array(items) => items.first()?,
+        Json::Null => return None,
+        location => location,
+    };
+
+    let uri = location
+        .get("uri")
+        .or_else(|| location.get("targetUri"))?
+        .as_str()?;
+    let range = location
+        .get("range")
+        .or_else(|| location.get("targetSelectionRange"))?;
+    let start = range.get("start")?;
+
+    Some((
+        uri_to_path(uri)?,
+        start.get("line")?.as_usize()?,
+        start.get("character")?.as_usize()?,
+    ))
+}
+
+/// Moves a running language server to each file opened and tells it about
+/// saves
+pub struct LspSync;
+
+impl EditorHook for LspSync {
+    fn on_open(&mut self, editor: &mut Editor) {
+        if editor.buffer.lsp.is_some() {
+            editor.editor_lsp_start();
+        }
+    }
+
+    fn on_save_post(&mut self, editor: &mut Editor) {
+        editor.editor_lsp_did_save();
+    }
+}
+
+impl Editor {
+    /// `lsp [stop|diagnostics]`: starts a language server for the current file
+    pub fn editor_lsp_command(&mut self, args: &str) {
+        match args {
+            "" | "start" => self.editor_lsp_start(),
+            "stop" => {
+                for buffer in self.editor_all_buffers_mut() {
+                    buffer.lsp = None;
+                }
+                self.editor_set_status_message("Language servers stopped");
+            }
+            "diagnostics" => self.editor_lsp_diagnostics_to_quickfix(),
+            _ => self.editor_set_error_message("Usage: lsp [start|stop|diagnostics]"),
+        }
+    }
+
+    /// `lspserver EXTENSION COMMAND` sets the language server for a filetype
+    pub fn editor_set_lsp_server(&mut self, args: &str) {
+        match args.split_once(char::is_whitespace) {
+            Some((extension, command)) => {
+                self.lsp_servers.insert(
+                    extension.trim_start_matches('.').to_string(),
+                    command.trim().to_string(),
+                );
+            }
+            None => self.editor_set_error_message("Usage: lspserver EXTENSION COMMAND"),
+        }
+    }
+
+    pub fn editor_lsp_start(&mut self) {
+        let path = match self.buffer.filename.as_deref().map(Path::new) {
+            Some(path) => path.canonicalize().unwrap_or_else(|_| path.to_path_buf()),
+            None => {
+                self.editor_set_status_message("Save the file before starting a language server");
+                return;
+            }
+        };
+        let extension = self.editor_file_extension().unwrap_or_default();
+        let command = match self
+            .lsp_servers
+            .get(&extension)
+            .map(String::as_str)
+            .or_else(|| default_server(&extension))
+        {
+            Some(command) => command.to_string(),
+            None => {
+                self.editor_set_status_message(&format!(
+                    "No language server for '{}' files (lspserver EXTENSION COMMAND)",
+                    extension
+                ));
+                return;
+            }
+        };
+
+        self.buffer.lsp = None;
+        match LspClient::start(&command, &path, language_id(&extension)) {
+            Ok(client) => {
+                self.buffer.lsp = Some(client);
+                self.editor_set_status_message(&format!("Started {}", command));
+            }
+            Err(error) => {
+                self.editor_set_error_message(&format!("Could not start {}: {}", command, error))
+            }
+        }
+    }
+
+    /// Handles messages from the server and keeps it in sync with the buffer.
+    /// Returns true if the screen needs redrawing.
+    pub fn editor_lsp_poll(&mut self) -> bool {
+        let mut redraw = false;
+        let mut definition = None;
+
+        if let Some(client) = self.buffer.lsp.as_mut() {
+            loop {
+                let message = match client.receiver.try_recv() {
+                    Ok(message) => message,
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.buffer.lsp = None;
+                        self.editor_set_status_message("Language server exited");
+                        return true;
+                    }
+                };
+
+                let id = message.get("id").and_then(Json::as_f64).map(|id| id as i64);
+                match (message.get("method").and_then(Json::as_str), id) {
+                    // Server to client request, answer so the server doesn't wait on us
+                    (Some(_), Some(id)) => {
+                        let _ = client.send(Json::object(vec![
+                            ("jsonrpc", Json::string("2.0")),
+                            ("id", Json::Number(id as f64)),
+                            ("result", Json::Null),
+                        ]));
+                    }
+                    (Some("textDocument/publishDiagnostics"), None) => {
+                        if let Some(params) = message.get("params") {
+                            if params.get("uri").and_then(Json::as_str) == Some(&client.uri) {
+                                client.diagnostics = parse_diagnostics(params);
+                                for diagnostic in &mut client.diagnostics {
+                                    let line = match self.buffer.rows.get(diagnostic.line) {
+                                        Some(row) => &row.chars,
+                                        None => continue,
+                                    };
+                                    diagnostic.start_column =
+                                        byte_column(line, diagnostic.start_column);
+                                    diagnostic.end_column =
+                                        byte_column(line, diagnostic.end_column);
+                                }
+                                redraw = true;
+                            }
+                        }
+                    }
+                    (None, Some(1)) if !client.initialized => {
+                        client.initialized = true;
+                        let _ = client.notify("initialized", Json::object(vec![]));
+                    }
+                    (None, Some(id)) if client.pending_definition == Some(id) => {
+                        client.pending_definition = None;
+                        definition = Some(
+                            message
+                                .get("result")
+                                .and_then(parse_location)
+                                .ok_or("No definition found"),
+                        );
+                    }
+                    (None, Some(id)) if client.pending_completion == Some(id) => {
+                        client.pending_completion = None;
+                        client.completion_items = Some(
+                            message
+                                .get("result")
+                                .map(parse_completions)
+                                .unwrap_or_default(),
+                        );
+                        redraw = true;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // The buffer is only serialized when the server is behind on it
+        let revision = self.revision;
+        if self
+            .buffer
+            .lsp
+            .as_ref()
+            .is_some_and(|client| client.needs_sync(revision))
+        {
+            let text = self.editor_rows_to_string();
+            if let Some(client) = self.buffer.lsp.as_mut() {
+                if client.sync(&text, revision).is_err() {
+                    self.buffer.lsp = None;
+                    self.editor_set_status_message("Lost connection to the language server");
+                }
+            }
+        }
+
+        match definition {
+            Some(Ok((path, line, character))) => {
+                if self.editor_jump_to_location(&path.to_string_lossy(), line, 0) {
+                    let column = self
+                        .buffer
+                        .rows
+                        .get(self.buffer.cursor_y)
+                        .map_or(0, |row| byte_column(&row.chars, character));
+                    self.editor_goto(self.buffer.cursor_y, column);
+                }
+                redraw = true;
+            }
+            Some(Err(message)) => {
+                self.editor_set_status_message(message);
+                redraw = true;
+            }
+            None => {}
+        }
+
+        redraw
+    }
+
+    /// Sends the saved text if the server is behind on it, then didSave
+    pub fn editor_lsp_did_save(&mut self) {
+        let text = self.editor_rows_to_string();
+        let revision = self.revision;
+        if let Some(client) = self.buffer.lsp.as_mut() {
+            let _ = client.sync(&text, revision);
+            let params = Json::object(vec![("textDocument", client.text_document())]);
+            let _ = client.notify("textDocument/didSave", params);
+        }
+    }
+
+    /// The cursor as an LSP position
+    fn editor_lsp_position(&self) -> Json {
+        let character = self
+            .buffer
+            .rows
+            .get(self.buffer.cursor_y)
+            .map_or(0, |row| utf16_column(&row.chars, self.buffer.cursor_x));
+        Json::object(vec![
+            ("line", Json::Number(self.buffer.cursor_y as f64)),
+            ("character", Json::Number(character as f64)),
+        ])
+    }
+
+    /// Ctrl-]: asks the server for the definition of the symbol under the cursor
+    pub fn editor_lsp_goto_definition(&mut self) {
+        let position = self.editor_lsp_position();
+        let client = match self.buffer.lsp.as_mut() {
+            Some(client) if client.initialized => client,
+            Some(_) => {
+                self.editor_set_status_message("Language server is still starting");
+                return;
+            }
+            None => {
+                self.editor_set_status_message("No language server running (lsp to start one)");
+                return;
+            }
+        };
+
+        let params = Json::object(vec![
+            ("textDocument", client.text_document()),
+            ("position", position),
+        ]);
+        match client.request("textDocument/definition", params) {
+            Ok(id) => client.pending_definition = Some(id),
+            Err(error) => self.editor_set_error_message(&format!("LSP error: {}", error)),
+        }
+    }
+
+    /// Asks the server for completions at the cursor. The answer comes in
+    /// through `editor_lsp_poll` and is taken with
+    /// `editor_lsp_take_completions`. False if there is no server to ask.
+    pub fn editor_lsp_request_completions(&mut self) -> bool {
+        // Make sure the server sees what was just typed
+        self.editor_lsp_poll();
+        let position = self.editor_lsp_position();
+        let client = match self.buffer.lsp.as_mut() {
+            Some(client) if client.initialized => client,
+            _ => return false,
+        };
+
+        let params = Json::object(vec![
+            ("textDocument", client.text_document()),
+            ("position", position),
+        ]);
+        client.completion_items = None;
+        client.pending_completion = client.request("textDocument/completion", params).ok();
+        client.pending_completion.is_some()
+    }
+
+    /// Whether completions were asked for and haven't come in yet
+    pub fn editor_lsp_completion_pending(&self) -> bool {
+        self.buffer
+            .lsp
+            .as_ref()
+            .is_some_and(|client| client.pending_completion.is_some())
+    }
+
+    /// The completions the server answered with, once they are in
+    pub fn editor_lsp_take_completions(&mut self) -> Option<Vec<(String, String, String)>> {
+        self.buffer.lsp.as_mut()?.completion_items.take()
+    }
+
+    /// Stops waiting for completions, an answer still on its way is dropped
+    pub fn editor_lsp_cancel_completions(&mut self) {
+        if let Some(client) = self.buffer.lsp.as_mut() {
+            client.pending_completion = None;
+            client.completion_items = None;
+        }
+    }
+
+    /// Opens `file` if it isn't the current one and moves to `line`,
+    /// `column`. False if switching files was declined.
+    pub fn editor_jump_to_location(&mut self, file: &str, line: usize, column: usize) -> bool {
+        let is_current = self
+            .buffer
+            .filename
+            .as_deref()
+            .is_some_and(|filename| quickfix::same_file(filename, file));
+        if !is_current && !self.editor_switch_file(file) {
+            return false;
+        }
+        self.editor_goto(line, column);
+        true
+    }
+
+    fn editor_lsp_diagnostics_to_quickfix(&mut self) {
+        let filename = self.buffer.filename.clone().unwrap_or_default();
+        let entries: Vec<QuickfixEntry> = match &self.buffer.lsp {
+            Some(client) => client
+                .diagnostics
+                .iter()
+                .map(|diagnostic| QuickfixEntry {
+                    file: filename.clone(),
+                    line: diagnostic.line + 1,
+                    column: diagnostic.start_column + 1,
+                    message: format!(
+                        "{}: {}",
+                        match diagnostic.severity {
+                            Severity::Error => "error",
+                            Severity::Warning => "warning",
+                            Severity::Information => "info",
+                            Severity::Hint => "hint",
+                        },
+                        diagnostic.message
+                    ),
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        self.quickfix = Quickfix::new(entries);
+        self.editor_quickfix_list();
+    }
+
+    /// Most severe diagnostic on `file_row`
+    pub fn editor_row_diagnostic(&self, file_row: usize) -> Option<&Diagnostic> {
+        self.buffer
+            .lsp
+            .as_ref()?
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.line == file_row)
+            .min_by_key(|diagnostic| diagnostic.severity as usize)
+    }
+
+    pub fn editor_row_diagnostics(&self, file_row: usize) -> impl Iterator<Item = &Diagnostic> {
+        self.buffer
+            .lsp
+            .iter()
+            .flat_map(|client| client.diagnostics.iter())
+            .filter(move |diagnostic| diagnostic.line == file_row)
+    }
+
+    /// Status bar summary, e.g. "E:2 W:1"
+    pub fn editor_lsp_status(&self) -> Option<String> {
+        let client = self.buffer.lsp.as_ref()?;
+        let count = |severity| {
+            client
+                .diagnostics
+                .iter()
+                .filter(|diagnostic| diagnostic.severity == severity)
+                .count()
+        };
+        Some(format!(
+            "[lsp E:{} W:{}]",
+            count(Severity::Error),
+            count(Severity::Warning)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_columns_to_and_from_utf16() {
+        // é is 2 bytes and 1 code unit, 😀 4 bytes and 2 code units
+        let line = "é😀x";
+        assert_eq!(utf16_column(line, 0), 0);
+        assert_eq!(utf16_column(line, 2), 1);
+        assert_eq!(utf16_column(line, 6), 3);
+        assert_eq!(utf16_column(line, line.len()), 4);
+        assert_eq!(byte_column(line, 1), 2);
+        assert_eq!(byte_column(line, 3), 6);
+        assert_eq!(byte_column(line, 4), 7);
+        assert_eq!(byte_column(line, 99), 7);
+        // Halfway through a surrogate pair rounds up to the next char
+        assert_eq!(byte_column(line, 2), 6);
+    }
+
+    #[test]
+    fn stops_the_server_when_dropped() {
+        let client = LspClient::start("exec sleep 30", Path::new("a.rs"), "rust").unwrap();
+        let process = PathBuf::from(format!("/proc/{}", client.child.id()));
+        assert!(process.exists());
+        drop(client);
+        assert!(!process.exists());
+    }
+}
@@ -0,0 +1,148 @@
+//! `memory`: approximate memory taken by the buffer (text, undo and redo
+//! history) and by other caches. When the buffer grows over
+//! `set memorylimit=MB` the buffer is compacted: the oldest undo steps and the
+//! redo steps are dropped and the rows give back unused capacity.
+//! `memory compact` does the same right away.
+
+use std::mem::size_of;
+
+use crate::overlay::OverlayLine;
+use crate::style::Color;
+use crate::*;
+
+pub const MEGABYTE: usize = 1024 * 1024;
+/// Edits recorded between two checks of the memory limit and `undomemory`
+pub const MEMORY_CHECK_INTERVAL: usize = 256;
+
+/// `bytes` in B, KB or MB
+fn format_bytes(bytes: usize) -> String {
+    if bytes >= MEGABYTE {
+        format!("{:.1} MB", bytes as f64 / MEGABYTE as f64)
+    } else if bytes >= 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+struct BufferMemory {
+    text: usize,
+    undo: usize,
+    redo: usize,
+}
+
+impl BufferMemory {
+    fn total(&self) -> usize {
+        self.text + self.undo + self.redo
+    }
+}
+
+impl Editor {
+    fn editor_buffer_memory(&self) -> BufferMemory {
+        let (undo, redo) = self.buffer.undo.memory();
+        BufferMemory {
+            text: self
+                .buffer
+                .rows
+                .iter()
+                .map(|row| size_of::<EditorRow>() + row.chars.capacity())
+                .sum(),
+            undo,
+            redo,
+        }
+    }
+
+    /// Drops history and spare capacity until the buffer fits in
+    /// `max_bytes`, as far as it can. Returns the bytes freed.
+    fn editor_compact(&mut self, max_bytes: usize) -> usize {
+        let before = self.editor_buffer_memory().total();
+        for row in &mut self.buffer.rows {
+            row.chars.shrink_to_fit();
+        }
+        self.buffer.rows.shrink_to_fit();
+        self.buffer.undo.clear_redo();
+        let memory = self.editor_buffer_memory();
+        let room = max_bytes.saturating_sub(memory.text);
+        self.buffer.undo.trim(room);
+        self.editor_invalidate_screen();
+        before.saturating_sub(self.editor_buffer_memory().total())
+    }
+
+    /// Compacts the buffer if it has grown over `memorylimit`
+    pub fn editor_check_memory(&mut self) {
+        let limit = self.options.memory_limit * MEGABYTE;
+        if limit == 0 || self.editor_buffer_memory().total() <= limit {
+            return;
+        }
+        let freed = self.editor_compact(limit);
+        self.editor_set_status_message(&format!(
+            "Buffer over memorylimit, freed {} of undo history and caches",
+            format_bytes(freed)
+        ));
+    }
+
+    /// `memory [compact]`: shows the memory use, or compacts the buffer
+    pub fn editor_memory_command(&mut self, args: &str) {
+        match args {
+            "" => {}
+            "compact" => {
+                let limit = match self.options.memory_limit {
+                    0 => usize::MAX,
+                    limit => limit * MEGABYTE,
+                };
+                let freed = self.editor_compact(limit);
+                self.editor_set_status_message(&format!("Freed {}", format_bytes(freed)));
+                return;
+            }
+            _ => {
+                self.editor_set_error_message("Usage: memory [compact]");
+                return;
+            }
+        }
+
+        let memory = self.editor_buffer_memory();
+        let (undo_steps, redo_steps) = self.buffer.undo.steps();
+        let (spell_bytes, spell_words) = self.spell.memory(&self.buffer.spell_project);
+        let registers: usize = self.clipboard.capacity()
+            + self
+                .registers
+                .values()
+                .map(|text| text.capacity())
+                .sum::<usize>();
+        let line = |label: &str, bytes: usize, detail: String| {
+            OverlayLine::new(format!(
+                "  {:<16} {:>10}  {}",
+                label,
+                format_bytes(bytes),
+                detail
+            ))
+        };
+
+        let name = self.buffer.filename.as_deref().unwrap_or("[No Name]");
+        let lines = vec![
+            OverlayLine::colored(format!("Buffer {}", name), Color::CYAN),
+            line(
+                "Text",
+                memory.text,
+                format!("{} lines", self.get_num_rows()),
+            ),
+            line("Undo history", memory.undo, format!("{} steps", undo_steps)),
+            line("Redo history", memory.redo, format!("{} steps", redo_steps)),
+            line("Total", memory.total(), String::new()),
+            OverlayLine::new(String::new()),
+            OverlayLine::colored("Editor".to_string(), Color::CYAN),
+            line(
+                "Registers",
+                registers,
+                format!("{} used", self.registers.len()),
+            ),
+            line("Dictionary", spell_bytes, format!("{} words", spell_words)),
+            OverlayLine::new(String::new()),
+            OverlayLine::new(format!(
+                "memorylimit={} MB undomemory={} MB undolevels={} (memory compact frees now)",
+                self.options.memory_limit, self.options.undo_memory, self.options.undo_levels
+            )),
+        ];
+        self.editor_show_overlay("Memory", &lines);
+    }
+}
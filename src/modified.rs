@@ -0,0 +1,130 @@
+//! Gutter marks for lines changed since the file was last opened or saved
+
+use crate::git::{compute_signs, GutterSign};
+use crate::*;
+
+#[derive(Default)]
+pub struct SavedChanges {
+    /// Rows as they were on disk after the last open or save
+    saved_lines: Vec<String>,
+    signs: Vec<Option<GutterSign>>,
+    /// Rows the signs were last computed for
+    computed_at: Option<RowsKey>,
+}
+
+impl SavedChanges {
+    pub fn sign(&self, row: usize) -> Option<GutterSign> {
+        self.signs.get(row).copied().flatten()
+    }
+}
+
+impl Editor {
+    /// Takes the current rows as the saved state, called on open and save
+    pub fn editor_mark_saved(&mut self) {
+        self.buffer.saved_changes = SavedChanges {
+            saved_lines: self
+                .buffer
+                .rows
+                .iter()
+                .map(|row| row.chars.clone())
+                .collect(),
+            signs: vec![],
+            computed_at: Some(self.editor_rows_key()),
+        };
+    }
+
+    /// Recomputes the marks if the buffer changed. Returns true if they were updated.
+    pub fn editor_update_saved_changes(&mut self) -> bool {
+        if self.buffer.saved_changes.computed_at == Some(self.editor_rows_key()) {
+            return false;
+        }
+
+        let current: Vec<&str> = self
+            .buffer
+            .rows
+            .iter()
+            .map(|row| row.chars.as_str())
+            .collect();
+        self.buffer.saved_changes.signs =
+            compute_signs(&self.buffer.saved_changes.saved_lines, &current);
+        self.buffer.saved_changes.computed_at = Some(self.editor_rows_key());
+
+        true
+    }
+
+    /// Whether the gutter has a column for unsaved change marks. Kept while
+    /// they are on, so the text doesn't shift as marks come and go.
+    pub fn saved_changes_visible(&self) -> bool {
+        self.options.change_marks
+    }
+
+    /// Moves to the start of the next (or previous) block of changed lines
+    pub fn editor_goto_changed_line(&mut self, forward: bool) {
+        self.editor_update_saved_changes();
+        let num_rows = self.get_num_rows();
+        let changed = |row: usize| self.buffer.saved_changes.sign(row).is_some();
+        // First row of each block of consecutive changed rows
+        let starts: Vec<usize> = (0..num_rows)
+            .filter(|&row| changed(row) && (row == 0 || !changed(row - 1)))
+            .collect();
+
+        if starts.is_empty() {
+            self.editor_set_status_message("No changes since last save");
+            return;
+        }
+
+        let target = if forward {
+            starts.iter().find(|&&row| row > self.buffer.cursor_y)
+        } else {
+            starts.iter().rev().find(|&&row| row < self.buffer.cursor_y)
+        };
+        let (row, wrapped) = match target {
+            Some(&row) => (row, false),
+            None if forward => (starts[0], true),
+            None => (starts[starts.len() - 1], true),
+        };
+
+        let index = starts.iter().position(|&start| start == row).unwrap_or(0);
+        self.editor_goto(row, 0);
+        self.editor_set_status_message(&format!(
+            "Changed block {} of {}{}",
+            index + 1,
+            starts.len(),
+            if wrapped { " (wrapped)" } else { "" }
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_rows_changed_since_the_save() {
+        let mut editor = Editor::new_headless(24, 80);
+        let lines = ["a", "b", "c"].map(String::from).to_vec();
+        editor.editor_replace_rows(0, 0, lines);
+        editor.editor_mark_saved();
+
+        editor.editor_replace_rows(1, 1, vec!["B".to_string(), "new".to_string()]);
+        editor.editor_idle();
+        assert_eq!(
+            editor.buffer.saved_changes.sign(1),
+            Some(GutterSign::Modified)
+        );
+        assert_eq!(editor.buffer.saved_changes.sign(2), Some(GutterSign::Added));
+        assert_eq!(editor.buffer.saved_changes.sign(0), None);
+
+        // A reload counts edits from 0 again, the marks still follow the rows
+        editor.buffer.dirty = 0;
+        editor.editor_replace_rows(1, 2, vec!["b".to_string()]);
+        editor.editor_idle();
+        assert_eq!(editor.buffer.saved_changes.sign(1), None);
+
+        // Not worked out while change marks are off
+        editor.options.change_marks = false;
+        editor.editor_replace_rows(0, 1, vec!["A".to_string()]);
+        editor.editor_idle();
+        assert_eq!(editor.buffer.saved_changes.sign(0), None);
+    }
+}
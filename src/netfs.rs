@@ -0,0 +1,273 @@
+//! Saving on network filesystems (NFS, SMB): writes are flushed with fsync
+//! (`set fsync=off|file|full`), transient failures are retried with backoff
+//! (`set writeretries=N`) and errors such as stale file handles are explained
+//! in the status bar. A save goes to a temporary file next to the file, which
+//! is renamed over it once written, so a failed save leaves the file as it
+//! was. A symlink is saved through to its target unless `set
+//! nofollowsymlinks`, and the file keeps its permissions and owner. Files we
+//! can't write are tagged `[readonly]` when opened.
+
+use std::ffi::CString;
+use std::fs::File;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::*;
+
+/// Wait before the first retry, doubled for each one after it
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Errors a network filesystem may recover from by itself
+fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut
+    ) || matches!(
+        error.raw_os_error(),
+        Some(libc::ESTALE | libc::EIO | libc::EAGAIN | libc::ETIMEDOUT)
+    )
+}
+
+/// `error` with a hint for the errors network filesystems are known for
+pub fn describe_io_error(error: &io::Error) -> String {
+    match error.raw_os_error() {
+        Some(libc::ESTALE) => format!(
+            "{} (the network share may have been remounted or the file replaced)",
+            error
+        ),
+        Some(libc::EIO) => format!("{} (the file server may be unreachable)", error),
+        _ => error.to_string(),
+    }
+}
+
+/// Mode of files the editor creates, before the umask
+const NEW_FILE_MODE: u32 = 0o644;
+
+/// Whether we may write `path`, or create it when it doesn't exist
+fn is_writable(path: &Path) -> bool {
+    let path = match path.parent() {
+        _ if path.exists() => path,
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match CString::new(path.as_os_str().as_bytes()) {
+        Ok(path) => unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 },
+        Err(_) => true,
+    }
+}
+
+/// Bytes written at a time, `progress` is told after each
+const WRITE_CHUNK: usize = 1 << 20;
+
+/// The file a save of `path` is written to before it is renamed over it
+fn temporary_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.kilo-{}.tmp", name, process::id()))
+}
+
+/// Writes `contents` to the new file `temporary` with `mode`, and `owner`
+/// when it replaces a file that has one
+fn write_temporary(
+    temporary: &Path,
+    contents: &[u8],
+    fsync: &str,
+    mode: u32,
+    owner: Option<(u32, u32)>,
+    progress: &dyn Fn(usize),
+) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode)
+        .open(temporary)?;
+    if let Some((uid, gid)) = owner {
+        // The umask only applies to files that are new to the user
+        file.set_permissions(std::fs::Permissions::from_mode(mode))?;
+        // Only root can give a file away, the group may still be kept
+        if std::os::unix::fs::fchown(&file, Some(uid), Some(gid)).is_err() {
+            let _ = std::os::unix::fs::fchown(&file, None, Some(gid));
+        }
+    }
+    let mut written = 0;
+    for chunk in contents.chunks(WRITE_CHUNK) {
+        file.write_all(chunk)?;
+        written += chunk.len();
+        progress(written);
+    }
+    if fsync == "off" {
+        return Ok(());
+    }
+    // Network filesystems often only report write errors here
+    file.sync_all()
+}
+
+/// Replaces `path` with `contents` through a temporary file, syncing them to
+/// the server as `fsync` asks
+fn write_once(
+    path: &Path,
+    contents: &[u8],
+    fsync: &str,
+    mode: u32,
+    owner: Option<(u32, u32)>,
+    progress: &dyn Fn(usize),
+) -> io::Result<()> {
+    let temporary = temporary_path(path);
+    // Left behind by a save of ours that was cut short
+    let _ = std::fs::remove_file(&temporary);
+    let written = write_temporary(&temporary, contents, fsync, mode, owner, progress)
+        .and_then(|_| std::fs::rename(&temporary, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+        return written;
+    }
+    if fsync == "full" {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Where and how a save writes its file, worked out before writing so the
+/// writing itself can happen on another thread
+pub struct FileWrite {
+    path: PathBuf,
+    mode: u32,
+    /// User and group of the file being replaced
+    owner: Option<(u32, u32)>,
+    fsync: String,
+    retries: usize,
+}
+
+impl FileWrite {
+    /// Writes `contents`, retrying transient failures. `progress` is told the
+    /// bytes written so far and `retrying` about each failure retried. The
+    /// last error is returned once the retries are used up.
+    pub fn write(
+        &self,
+        contents: &[u8],
+        progress: &dyn Fn(usize),
+        retrying: &mut dyn FnMut(&io::Error, usize),
+    ) -> io::Result<()> {
+        let mut backoff = RETRY_BACKOFF;
+        let mut retries = 0;
+        loop {
+            let error = match write_once(
+                &self.path,
+                contents,
+                &self.fsync,
+                self.mode,
+                self.owner,
+                progress,
+            ) {
+                Ok(()) => return Ok(()),
+                Err(error) => error,
+            };
+            if retries >= self.retries || !is_transient(&error) {
+                return Err(error);
+            }
+            retries += 1;
+            retrying(&error, retries);
+            thread::sleep(backoff);
+            backoff *= 2;
+        }
+    }
+}
+
+impl Editor {
+    /// How a save of `path` is written
+    pub fn editor_file_write(&self, path: &str) -> io::Result<FileWrite> {
+        // Permissions and owner of the file as it is, kept for the file that
+        // replaces it
+        let metadata = std::fs::metadata(path).ok();
+        let mode = metadata.as_ref().map_or(NEW_FILE_MODE, |metadata| {
+            metadata.permissions().mode() & 0o7777
+        });
+        Ok(FileWrite {
+            path: self.editor_write_target(Path::new(path)),
+            mode,
+            owner: metadata.map(|metadata| (metadata.uid(), metadata.gid())),
+            fsync: self.options.fsync.clone(),
+            retries: self.options.write_retries,
+        })
+    }
+
+    /// Writes a file, retrying transient failures. The last error is returned
+    /// once the retries are used up.
+    pub fn editor_write_file(&mut self, path: &str, contents: &[u8]) -> io::Result<()> {
+        let write = self.editor_file_write(path)?;
+        write.write(contents, &|_| {}, &mut |error, retries| {
+            self.editor_set_status_message(&format!(
+                "Write failed: {}, retrying ({}/{})",
+                describe_io_error(error),
+                retries,
+                write.retries
+            ));
+            self.editor_refresh_screen();
+        })
+    }
+
+    /// The file a save of `path` replaces. A symlink is resolved to its
+    /// target, with `nofollowsymlinks` the file takes the link's place.
+    fn editor_write_target(&self, path: &Path) -> PathBuf {
+        let is_symlink =
+            std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink());
+        if !is_symlink || !self.options.follow_symlinks {
+            return path.to_path_buf();
+        }
+        std::fs::canonicalize(path).unwrap_or_else(|_| {
+            // A dangling link is written through, creating its target
+            match std::fs::read_link(path) {
+                Ok(target) => path.parent().unwrap_or(Path::new("")).join(target),
+                Err(_) => path.to_path_buf(),
+            }
+        })
+    }
+
+    /// Tags the open file `[readonly]` and warns if we can't save it
+    pub fn editor_check_writable(&mut self) {
+        let filename = match &self.buffer.filename {
+            Some(filename) => filename.clone(),
+            None => return,
+        };
+        self.buffer.file_format.not_writable = !is_writable(Path::new(&filename));
+        if self.buffer.file_format.not_writable && !self.buffer.file_format.read_only {
+            self.editor_set_status_message(&format!(
+                "Warning: {} is not writable, changes can't be saved to it",
+                filename
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_the_file_keeping_its_mode() {
+        let dir = std::env::temp_dir().join(format!("kilo-netfs-test-{}", process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        std::fs::write(&path, "old contents that are longer").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+        write_once(&path, b"new", "file", 0o640, None, &|_| {}).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode, 0o640);
+        // Nothing is left next to it
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        // A write that can't happen leaves the file alone
+        let missing = dir.join("missing").join("file.txt");
+        assert!(write_once(&missing, b"x", "file", 0o644, None, &|_| {}).is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
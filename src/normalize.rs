@@ -0,0 +1,196 @@
+//! Normalization applied when saving (`eol`, `trimwhitespace`, `finalnewline`)
+//! and the `savecheck` warning when it would change lines that weren't edited,
+//! e.g. converting every line of a CRLF file for a one line fix
+
+use crate::diff::{diff_lines, DiffOp};
+use crate::*;
+
+/// The normalization options, taken from `Options` so a save on a worker
+/// thread can apply them
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Normalization {
+    crlf: bool,
+    trim_whitespace: bool,
+    final_newline: bool,
+    /// Whether `savecheck` asks before changing unedited lines
+    check: bool,
+}
+
+impl Normalization {
+    pub fn new(options: &Options) -> Self {
+        Normalization {
+            crlf: options.line_ending == "crlf",
+            trim_whitespace: options.trim_whitespace,
+            final_newline: options.final_newline,
+            check: options.save_check,
+        }
+    }
+}
+
+/// How the text a save writes is made from the buffer
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SaveText {
+    /// Normalized, unless that changes unedited lines and `savecheck` is on
+    Checked,
+    Normalized,
+    /// As it is in the buffer, with CRLF line endings if the file had them
+    AsIs,
+}
+
+/// Unedited lines normalizing would change, for the user to decide about
+pub struct UneditedChanges {
+    count: usize,
+    reasons: Vec<&'static str>,
+}
+
+/// Buffer `contents` with the normalization options applied
+fn normalize(contents: &str, normalization: &Normalization) -> String {
+    let eol = if normalization.crlf { "\r\n" } else { "\n" };
+    let mut normalized = contents
+        .split('\n')
+        .map(|line| {
+            if normalization.trim_whitespace {
+                line.trim_end_matches([' ', '\t'])
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(eol);
+    if normalization.final_newline && !normalized.is_empty() && !normalized.ends_with('\n') {
+        normalized.push_str(eol);
+    }
+    normalized
+}
+
+/// Whether file contents `text` are as normalizing leaves them. Not if
+/// empty, a final newline is still added to what is typed into it.
+pub fn is_normalized(text: &str, normalization: &Normalization) -> bool {
+    !text.is_empty() && normalize(&text.replace("\r\n", "\n"), normalization) == text
+}
+
+/// The text to write to `filename` for `buffer`, the rows joined with LF.
+/// Reads the file to see which lines weren't edited, so it can run on a
+/// worker thread with the rest of the save.
+pub fn save_text(
+    buffer: String,
+    filename: &str,
+    normalization: &Normalization,
+    how: SaveText,
+) -> Result<String, UneditedChanges> {
+    let check = how == SaveText::Checked && normalization.check;
+    if how == SaveText::Normalized || (how == SaveText::Checked && !check) {
+        return Ok(normalize(&buffer, normalization));
+    }
+    let disk = match std::fs::read_to_string(filename) {
+        Ok(disk) => disk,
+        Err(_) if check => return Ok(normalize(&buffer, normalization)),
+        Err(_) => return Ok(buffer),
+    };
+    if how == SaveText::AsIs {
+        // Written as it was read: with the file's line endings
+        return Ok(match disk.contains("\r\n") {
+            true => buffer.replace('\n', "\r\n"),
+            false => buffer,
+        });
+    }
+
+    let normalized = normalize(&buffer, normalization);
+    let (count, reasons) = unedited_changes(&disk, &buffer, &normalized);
+    if count == 0 {
+        Ok(normalized)
+    } else {
+        Err(UneditedChanges { count, reasons })
+    }
+}
+
+/// Lines of `disk` the user didn't edit in `buffer` but `normalized` changes,
+/// and what changes about them
+fn unedited_changes(disk: &str, buffer: &str, normalized: &str) -> (usize, Vec<&'static str>) {
+    let disk_lines: Vec<&str> = disk.split('\n').collect();
+    let logical: Vec<&str> = disk_lines
+        .iter()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect();
+    let buffer_lines: Vec<&str> = buffer.split('\n').collect();
+    let normalized_lines: Vec<&str> = normalized.split('\n').collect();
+
+    let mut count = 0;
+    let mut reasons = vec![];
+    let mut reason = |reason: &'static str| {
+        if !reasons.contains(&reason) {
+            reasons.push(reason);
+        }
+    };
+    for op in diff_lines(&logical, &buffer_lines) {
+        if let DiffOp::Equal(old, new) = op {
+            let (before, after) = (disk_lines[old], normalized_lines[new]);
+            if before == after {
+                continue;
+            }
+            count += 1;
+            if before.ends_with('\r') != after.ends_with('\r') {
+                reason("line endings");
+            }
+            if before.trim_end_matches('\r') != after.trim_end_matches('\r') {
+                reason("trailing whitespace");
+            }
+        }
+    }
+    if !disk.ends_with('\n') && !buffer.ends_with('\n') && normalized.ends_with('\n') {
+        count += 1;
+        reason("final newline");
+    }
+    (count, reasons)
+}
+
+impl Editor {
+    /// Asks whether to normalize the unedited lines `changes` describes. None
+    /// if the save was cancelled.
+    pub fn editor_ask_normalize(&mut self, changes: &UneditedChanges) -> Option<SaveText> {
+        self.editor_set_status_message(&format!(
+            "Saving would change {} unedited line{} ({}). Normalize? (y/n, ESC to cancel)",
+            changes.count,
+            if changes.count == 1 { "" } else { "s" },
+            changes.reasons.join(", ")
+        ));
+        self.editor_refresh_screen();
+        loop {
+            match self.editor_read_key() {
+                Key::Char('y' | 'Y') => return Some(SaveText::Normalized),
+                Key::Char('n' | 'N') => return Some(SaveText::AsIs),
+                Key::Escape => return None,
+                _ => {}
+            }
+        }
+    }
+
+    /// How a save makes its text: checked against the file, unless the file
+    /// is still as a save left it normalized and has no lines to change
+    pub fn editor_save_check(&self) -> SaveText {
+        match self.editor_disk_normalized(&Normalization::new(&self.options)) {
+            true => SaveText::Normalized,
+            false => SaveText::Checked,
+        }
+    }
+
+    /// Text to write when saving, normalized unless that would change unedited
+    /// lines and the user declines. None if the save was cancelled.
+    pub fn editor_save_contents(&mut self) -> Option<String> {
+        let filename = self.buffer.filename.clone().unwrap_or_default();
+        let normalization = Normalization::new(&self.options);
+        let buffer = self.editor_rows_to_string();
+        match save_text(
+            buffer.clone(),
+            &filename,
+            &normalization,
+            self.editor_save_check(),
+        ) {
+            Ok(text) => Some(text),
+            Err(changes) => {
+                let how = self.editor_ask_normalize(&changes)?;
+                save_text(buffer, &filename, &normalization, how).ok()
+            }
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! Runtime options, changed with the `set` command
+
+use crate::undo::UNDO_LIMIT;
+use crate::*;
+
+pub struct Options {
+    /// Soft wrap rows longer than the screen
+    pub wrap: bool,
+    /// Up/Down move by display line when wrapping (Alt-Up/Down do the other)
+    pub display_line_movement: bool,
+    /// Distraction free mode: centered text column, no gutter or status bar
+    pub zen: bool,
+    /// Width of the text column in zen mode
+    pub zen_width: usize,
+    /// Rows of context kept visible above and below the cursor
+    pub scroll_off: usize,
+    /// Keep the cursor line vertically centered
+    pub typewriter: bool,
+    /// Mark lines changed since the last save in the gutter
+    pub change_marks: bool,
+    /// Underline misspelled words in prose and comments
+    pub spell: bool,
+    /// Word list or hunspell .dic file, empty to use the system word list
+    pub spell_file: String,
+    /// Comma separated languages to spell check against (en_US,de_DE), empty
+    /// for the system word list
+    pub spell_languages: String,
+    /// Collect local usage statistics, shown by `usage`
+    pub usage_stats: bool,
+    /// Highlight the line the cursor is on
+    pub cursor_line: bool,
+    /// Highlight the screen column the cursor is in
+    pub cursor_column: bool,
+    /// Comma separated columns to draw a guide at, e.g. "80,100"
+    pub color_column: String,
+    /// The status bar column counts screen columns, tabs expanded, instead of
+    /// chars
+    pub tab_column: bool,
+    /// Columns between tab stops
+    pub tab_stop: usize,
+    /// Milliseconds to wait for the rest of an escape sequence before an ESC
+    /// is taken as the Escape key
+    pub esc_timeout: usize,
+    /// Frames drawn per second at most while keys keep coming, 1 to 1000
+    pub max_fps: usize,
+    /// How `cursorline` and `cursorcolumn` are drawn: "underline" or "dim"
+    pub cursor_style: String,
+    /// Keep the undo history of saved files across sessions
+    pub undo_file: bool,
+    /// Index the project in the background for `files`, `symbols` and completion
+    pub project_index: bool,
+    /// Curly quotes and em dashes while typing prose
+    pub smart_quotes: bool,
+    /// Slow terminal mode: "auto" detects it from write times, or "on"/"off"
+    pub slow_terminal: String,
+    /// Build command run by `make`
+    pub make_command: String,
+    /// External tool run by `difftool`, `{old}`/`{new}` stand for the two files
+    pub diff_tool: String,
+    /// Strip trailing whitespace from lines when saving
+    pub trim_whitespace: bool,
+    /// End the file with a newline when saving
+    pub final_newline: bool,
+    /// Line endings written when saving: "lf" or "crlf"
+    pub line_ending: String,
+    /// Ask before a save whose normalization would change lines that weren't
+    /// edited
+    pub save_check: bool,
+    /// Run the file type's formatter before saving
+    pub format_on_save: bool,
+    /// Syncing of saved files: "off", "file" or "full" (also the directory)
+    pub fsync: String,
+    /// Times a save is retried after a transient write failure
+    pub write_retries: usize,
+    /// Megabytes above which a buffer is saved on a worker thread, 0 never
+    pub async_save: usize,
+    /// Save a symlinked file through to its target instead of replacing the
+    /// link with a file
+    pub follow_symlinks: bool,
+    /// Read the file again when it changes on disk, asking first if the
+    /// buffer has unsaved changes
+    pub auto_reload: bool,
+    /// Command a save refused for lack of permission is retried with, as
+    /// `<sudo> tee FILE`. Empty to not offer it.
+    pub sudo: String,
+    /// Show the file name in the terminal window title
+    pub title: bool,
+    /// Show how long frames take to build in the status bar
+    pub frame_time: bool,
+    /// Undo steps kept
+    pub undo_levels: usize,
+    /// Megabytes of undo history kept, the oldest steps are dropped beyond it
+    pub undo_memory: usize,
+    /// Megabytes the buffer may take with its caches and history before it
+    /// is compacted, 0 for no limit
+    pub memory_limit: usize,
+    /// Close block comments as they are opened and continue them on Enter
+    pub auto_comment: bool,
+    /// Color keywords, strings and comments
+    pub syntax: bool,
+    /// Colors the terminal shows: "auto" detects them from COLORTERM and
+    /// TERM, or "truecolor", "256" or "16"
+    pub colors: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            wrap: false,
+            display_line_movement: true,
+            zen: false,
+            zen_width: 80,
+            scroll_off: 0,
+            typewriter: false,
+            change_marks: true,
+            spell: false,
+            spell_file: String::new(),
+            spell_languages: String::new(),
+            usage_stats: false,
+            cursor_line: false,
+            cursor_column: false,
+            color_column: String::new(),
+            tab_column: false,
+            tab_stop: KILO_TAB_STOP,
+            esc_timeout: 100,
+            max_fps: 60,
+            cursor_style: "underline".to_string(),
+            undo_file: true,
+            project_index: false,
+            smart_quotes: false,
+            slow_terminal: "auto".to_string(),
+            make_command: "make".to_string(),
+            diff_tool: "vimdiff".to_string(),
+            trim_whitespace: false,
+            final_newline: false,
+            line_ending: "lf".to_string(),
+            save_check: true,
+            format_on_save: false,
+            fsync: "file".to_string(),
+            write_retries: 3,
+            async_save: 16,
+            follow_symlinks: true,
+            auto_reload: true,
+            sudo: "sudo".to_string(),
+            title: true,
+            frame_time: false,
+            colors: "auto".to_string(),
+            auto_comment: true,
+            syntax: true,
+            undo_levels: UNDO_LIMIT,
+            undo_memory: 64,
+            memory_limit: 512,
+        }
+    }
+}
+
+impl Options {
+    fn bool_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "wrap" => Some(&mut self.wrap),
+            "displaymove" => Some(&mut self.display_line_movement),
+            "zen" => Some(&mut self.zen),
+            "typewriter" => Some(&mut self.typewriter),
+            "changemarks" => Some(&mut self.change_marks),
+            "spell" => Some(&mut self.spell),
+            "usagestats" => Some(&mut self.usage_stats),
+            "cursorline" => Some(&mut self.cursor_line),
+            "cursorcolumn" => Some(&mut self.cursor_column),
+            "undofile" => Some(&mut self.undo_file),
+            "index" => Some(&mut self.project_index),
+            "smartquotes" => Some(&mut self.smart_quotes),
+            "title" => Some(&mut self.title),
+            "trimwhitespace" => Some(&mut self.trim_whitespace),
+            "finalnewline" => Some(&mut self.final_newline),
+            "savecheck" => Some(&mut self.save_check),
+            "formatonsave" => Some(&mut self.format_on_save),
+            "followsymlinks" => Some(&mut self.follow_symlinks),
+            "autoreload" => Some(&mut self.auto_reload),
+            "autocomment" => Some(&mut self.auto_comment),
+            "syntax" => Some(&mut self.syntax),
+            "tabcolumn" => Some(&mut self.tab_column),
+            "frametime" => Some(&mut self.frame_time),
+            _ => None,
+        }
+    }
+
+    fn number_mut(&mut self, name: &str) -> Option<&mut usize> {
+        match name {
+            "zenwidth" => Some(&mut self.zen_width),
+            "scrolloff" => Some(&mut self.scroll_off),
+            "tabstop" => Some(&mut self.tab_stop),
+            "esctimeout" => Some(&mut self.esc_timeout),
+            "maxfps" => Some(&mut self.max_fps),
+            "writeretries" => Some(&mut self.write_retries),
+            "asyncsave" => Some(&mut self.async_save),
+            "undolevels" => Some(&mut self.undo_levels),
+            "undomemory" => Some(&mut self.undo_memory),
+            "memorylimit" => Some(&mut self.memory_limit),
+            _ => None,
+        }
+    }
+
+    fn string_mut(&mut self, name: &str) -> Option<&mut String> {
+        match name {
+            "makeprg" => Some(&mut self.make_command),
+            "difftool" => Some(&mut self.diff_tool),
+            "spellfile" => Some(&mut self.spell_file),
+            "spelllang" => Some(&mut self.spell_languages),
+            "cursorstyle" => Some(&mut self.cursor_style),
+            "colorcolumn" => Some(&mut self.color_column),
+            "slowterm" => Some(&mut self.slow_terminal),
+            "eol" => Some(&mut self.line_ending),
+            "fsync" => Some(&mut self.fsync),
+            "sudo" => Some(&mut self.sudo),
+            "colors" => Some(&mut self.colors),
+            _ => None,
+        }
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "wrap={} displaymove={} zen={} zenwidth={} scrolloff={} typewriter={} changemarks={} spell={} cursorline={} cursorcolumn={}",
+            self.wrap,
+            self.display_line_movement,
+            self.zen,
+            self.zen_width,
+            self.scroll_off,
+            self.typewriter,
+            self.change_marks,
+            self.spell,
+            self.cursor_line,
+            self.cursor_column
+        )
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "on" | "true" | "yes" | "1" => Some(true),
+        "off" | "false" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+impl Editor {
+    /// Handles `set name`, `set noname` and `set name=value`
+    pub fn editor_set_option(&mut self, args: &str) {
+        if args.is_empty() {
+            let summary = self.options.summary();
+            self.editor_set_status_message(&summary);
+            return;
+        }
+
+        let (name, value) = match args.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (args, None),
+        };
+
+        if let Some(option) = self.options.string_mut(name) {
+            match value {
+                Some(value) => *option = value.to_string(),
+                None => {
+                    let message = format!("{}={}", name, option);
+                    self.editor_set_status_message(&message);
+                    return;
+                }
+            }
+        } else if let Some(option) = self.options.number_mut(name) {
+            match value.map(str::parse) {
+                Some(Ok(number)) => *option = number,
+                Some(Err(_)) => {
+                    self.editor_set_status_message(&format!("Invalid number for {}", name));
+                    return;
+                }
+                None => {
+                    let message = format!("{}={}", name, option);
+                    self.editor_set_status_message(&message);
+                    return;
+                }
+            }
+        } else {
+            // Boolean options accept `name`, `noname` and `name=on|off`
+            let (name, bool_value) = match (name.strip_prefix("no"), value) {
+                (Some(stripped), None) => (stripped, Some(false)),
+                (None, None) => (name, Some(true)),
+                (_, Some(value)) => (name, parse_bool(value)),
+            };
+
+            let option = match self.options.bool_mut(name) {
+                Some(option) => option,
+                None => {
+                    self.editor_set_error_message(&format!("Unknown option: {}", name));
+                    return;
+                }
+            };
+
+            match bool_value {
+                Some(value) => *option = value,
+                None => {
+                    self.editor_set_status_message(&format!("Invalid value for {}", name));
+                    return;
+                }
+            }
+        }
+
+        self.editor_options_changed();
+        let summary = self.options.summary();
+        self.editor_set_status_message(&summary);
+    }
+
+    /// Applies side effects of options that were just changed
+    pub fn editor_options_changed(&mut self) {
+        // Zen mode turns on soft wrap, restoring the previous setting afterwards
+        if self.options.zen && self.wrap_before_zen.is_none() {
+            self.wrap_before_zen = Some(self.options.wrap);
+            self.options.wrap = true;
+        } else if !self.options.zen {
+            if let Some(wrap) = self.wrap_before_zen.take() {
+                self.options.wrap = wrap;
+            }
+        }
+
+        self.options.max_fps = self.options.max_fps.clamp(1, 1000);
+        self.options.tab_stop = self.options.tab_stop.max(1);
+        self.editor_invalidate_screen();
+
+        self.editor_spell_option_changed();
+        self.editor_usage_option_changed();
+    }
+
+    pub fn editor_toggle_zen(&mut self) {
+        self.options.zen = !self.options.zen;
+        self.editor_options_changed();
+        self.editor_set_status_message(if self.options.zen {
+            "Zen mode on (zen again to leave)"
+        } else {
+            "Zen mode off"
+        });
+    }
+}
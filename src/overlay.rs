@@ -0,0 +1,158 @@
+//! Read-only full screen overlay used for diffs, lists and reports
+
+use crate::frame::{Frame, FrameLine};
+use crate::fuzzy::highlight_matches;
+use crate::*;
+
+pub struct OverlayLine {
+    pub text: String,
+    pub color: Option<Color>,
+    /// Char indices picked out as fuzzy matches
+    pub matched: Vec<usize>,
+}
+
+impl OverlayLine {
+    pub fn new(text: String) -> Self {
+        OverlayLine {
+            text,
+            color: None,
+            matched: vec![],
+        }
+    }
+
+    pub fn colored(text: String, color: Color) -> Self {
+        OverlayLine {
+            text,
+            color: Some(color),
+            matched: vec![],
+        }
+    }
+}
+
+impl Editor {
+    /// Draws `lines` from `offset` with a status bar and `message` below it
+    pub fn editor_draw_overlay(
+        &mut self,
+        title: &str,
+        lines: &[OverlayLine],
+        offset: usize,
+        selected: usize,
+        message: &str,
+    ) {
+        let mut frame = Frame::default();
+
+        for y in 0..self.screen_num_rows {
+            let mut frame_line = FrameLine::default();
+            if let Some(line) = lines.get(offset + y) {
+                let text = line.text.replace('\t', "    ");
+                let cells = to_cells(&text, self.screen_num_columns);
+
+                let style = CellStyle {
+                    fg: line.color,
+                    inverse: offset + y == selected,
+                    ..CellStyle::default()
+                };
+                // Fillers of wide chars take the style of their char
+                let mut char_styles =
+                    highlight_matches(text.chars().count(), &line.matched, style).into_iter();
+                let mut styles: Vec<CellStyle> = vec![];
+                for c in cells.chars() {
+                    let style = match (c, styles.last()) {
+                        (WIDE_FILLER, Some(&last)) => last,
+                        _ => char_styles.next().unwrap_or(style),
+                    };
+                    styles.push(style);
+                }
+                frame_line.push_cells(&cells, &styles);
+            } else {
+                frame_line.push("~");
+            }
+            frame.lines.push(frame_line);
+        }
+
+        let r_status = format!("{}/{} (ESC to close)", selected + 1, lines.len());
+        let mut status = to_cells(&format!(" {} ", title), self.screen_num_columns);
+        let mut status_width = status.chars().count();
+        while status_width < self.screen_num_columns {
+            if self.screen_num_columns - status_width == r_status.len() {
+                status.push_str(&r_status);
+                break;
+            }
+            status.push(' ');
+            status_width += 1;
+        }
+        let mut status_bar = FrameLine::default();
+        status_bar.push_styled(
+            &status,
+            CellStyle {
+                inverse: true,
+                ..CellStyle::default()
+            },
+        );
+        frame.lines.push(status_bar);
+        let mut message_line = FrameLine::default();
+        message_line.push(message);
+        frame.lines.push(message_line);
+        if !message.is_empty() {
+            frame.cursor = Some((self.screen_num_rows + 1, message.chars().count()));
+        }
+
+        self.editor_present(&frame);
+    }
+
+    /// Shows `lines` full screen until dismissed. Returns the index of the line
+    /// selected with Enter, or None if the overlay was closed with Escape / q.
+    pub fn editor_show_overlay(&mut self, title: &str, lines: &[OverlayLine]) -> Option<usize> {
+        let mut offset = 0;
+        let mut selected = 0;
+        self.overlay_active = true;
+        let previous = self.editor_set_mode(Mode::Overlay);
+
+        let result = loop {
+            if selected < offset {
+                offset = selected;
+            }
+            if selected >= offset + self.screen_num_rows {
+                offset = selected + 1 - self.screen_num_rows;
+            }
+
+            self.editor_draw_overlay(title, lines, offset, selected, "");
+
+            let key = self.editor_read_key();
+            let last = lines.len().saturating_sub(1);
+            match key {
+                Key::Escape => break None,
+                Key::Char('q') => break None,
+                Key::Enter => {
+                    if lines.is_empty() {
+                        break None;
+                    }
+                    break Some(selected);
+                }
+                Key::Up => selected = selected.saturating_sub(1),
+                Key::Down => selected = (selected + 1).min(last),
+                Key::PageUp => selected = selected.saturating_sub(self.screen_num_rows),
+                Key::PageDown => selected = (selected + self.screen_num_rows).min(last),
+                Key::Home => selected = 0,
+                Key::End => selected = last,
+                _ => {}
+            }
+        };
+
+        self.overlay_active = false;
+        self.editor_set_mode(previous);
+        result
+    }
+}
+
+/// Largest index <= `index` that lies on a char boundary of `s`
+pub fn floor_char_boundary(s: &str, index: usize) -> usize {
+    if index >= s.len() {
+        return s.len();
+    }
+    let mut index = index;
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
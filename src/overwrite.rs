@@ -0,0 +1,44 @@
+//! Overwrite mode, switched with the Insert key: typed chars replace the char
+//! under the cursor instead of going in before it, and at the end of a line
+//! they are added as usual. A run of overtyping is undone as one step.
+
+use crate::*;
+
+impl Editor {
+    /// Insert: switches between inserting and overwriting
+    pub fn editor_toggle_overwrite(&mut self) {
+        self.overwrite = !self.overwrite;
+        self.editor_set_status_message(if self.overwrite {
+            "Overwrite mode, Insert to go back to inserting"
+        } else {
+            "Insert mode"
+        });
+    }
+
+    /// Types `c` over the char under the cursor
+    pub fn editor_overwrite_char(&mut self, c: char) {
+        let replaced = match self.buffer.rows.get(self.buffer.cursor_y) {
+            Some(row) => row.chars[self.buffer.cursor_x..].chars().next(),
+            None => None,
+        };
+        let replaced = match replaced {
+            Some(replaced) => replaced,
+            None => return self.editor_insert_char(c),
+        };
+
+        self.editor_record_edit(self.buffer.cursor_y, 1, EditKind::OverwriteChar);
+        let row = &mut self.buffer.rows[self.buffer.cursor_y];
+        let end = self.buffer.cursor_x + replaced.len_utf8();
+        row.chars
+            .replace_range(self.buffer.cursor_x..end, c.encode_utf8(&mut [0; 4]));
+        row.chars_changed();
+        self.buffer.cursor_x += c.len_utf8();
+        self.editor_count_edit();
+        self.editor_run_hooks(|hook, editor| hook.on_char_insert(editor, c));
+    }
+
+    /// Status bar tag in overwrite mode
+    pub fn editor_overwrite_status(&self) -> Option<String> {
+        self.overwrite.then(|| "[overwrite]".to_string())
+    }
+}
@@ -0,0 +1,112 @@
+//! Fuzzy pickers: the file finder (`files`) and the command palette
+//! (`commands`), both built on `editor_fuzzy_pick`
+
+use crate::command::COMMANDS;
+use crate::fuzzy::fuzzy_rank;
+use crate::index::project_files;
+use crate::overlay::OverlayLine;
+use crate::*;
+
+impl Editor {
+    /// Full screen list of `items` narrowed by typing, best fuzzy matches
+    /// first. Returns the index of the item chosen with Enter.
+    pub fn editor_fuzzy_pick(&mut self, title: &str, items: &[String]) -> Option<usize> {
+        let mut query = String::new();
+        let mut offset = 0;
+        let mut selected = 0;
+        self.overlay_active = true;
+        let previous = self.editor_set_mode(Mode::Overlay);
+
+        let result = loop {
+            let ranked = fuzzy_rank(&query, items);
+            let lines: Vec<OverlayLine> = ranked
+                .iter()
+                .map(|(i, matched)| OverlayLine {
+                    matched: matched.positions.clone(),
+                    ..OverlayLine::new(items[*i].clone())
+                })
+                .collect();
+
+            selected = selected.min(lines.len().saturating_sub(1));
+            if selected < offset {
+                offset = selected;
+            }
+            if selected >= offset + self.screen_num_rows {
+                offset = selected + 1 - self.screen_num_rows;
+            }
+            self.editor_draw_overlay(title, &lines, offset, selected, &format!("> {}", query));
+
+            let key = self.editor_read_key();
+            let last = lines.len().saturating_sub(1);
+            match key {
+                Key::Escape => break None,
+                Key::Enter => match ranked.get(selected) {
+                    Some(&(i, _)) => break Some(i),
+                    None => break None,
+                },
+                Key::Up => selected = selected.saturating_sub(1),
+                Key::Down => selected = (selected + 1).min(last),
+                Key::PageUp => selected = selected.saturating_sub(self.screen_num_rows),
+                Key::PageDown => selected = (selected + self.screen_num_rows).min(last),
+                Key::Backspace | Key::Delete | Key::Ctrl('h') => {
+                    query.pop();
+                    selected = 0;
+                }
+                Key::Char(c) => {
+                    query.push(c);
+                    selected = 0;
+                }
+                _ => {}
+            }
+        };
+
+        self.overlay_active = false;
+        self.editor_set_mode(previous);
+        result
+    }
+
+    /// `files`: opens a file under the working directory picked by fuzzy name
+    pub fn editor_find_file(&mut self) {
+        // The index may still be building, then list the files directly
+        let files = match self.options.project_index {
+            true => self.index.files(),
+            false => None,
+        };
+        let files = files.unwrap_or_else(project_files);
+        if files.is_empty() {
+            self.editor_set_status_message("No files found");
+            return;
+        }
+
+        let title = format!("Open file ({} files)", files.len());
+        if let Some(selected) = self.editor_fuzzy_pick(&title, &files) {
+            self.editor_switch_file(&files[selected]);
+        }
+    }
+
+    /// `commands`: command palette, the chosen command is put in the command
+    /// prompt to add arguments
+    pub fn editor_command_palette(&mut self) {
+        let items: Vec<String> = COMMANDS
+            .iter()
+            .map(|(name, description)| format!("{:<12} {}", name, description))
+            .collect();
+
+        if let Some(selected) = self.editor_fuzzy_pick("Commands", &items) {
+            self.editor_prompt_command(COMMANDS[selected].0);
+        }
+    }
+
+    /// Opens the command prompt with `name` typed, then runs the command
+    pub fn editor_prompt_command(&mut self, name: &str) {
+        let initial = format!("{} ", name);
+        if let Some(command) = self.editor_prompt_with_text(
+            "Command: %s (ESC to cancel)",
+            &initial,
+            HistoryKind::Command,
+        ) {
+            self.editor_record_command_usage(command.trim());
+            self.editor_execute_command(command.trim());
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Reading a pipe, as in `git diff | kilo -`: the piped text is read into an
+//! unnamed buffer and the terminal is opened as stdin in its place, so keys
+//! are read from it like when started from a shell
+
+use std::io::IsTerminal;
+
+use crate::*;
+
+/// Reads stdin to the end, None if it is a terminal
+pub fn read_piped_stdin() -> io::Result<Option<String>> {
+    if io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    let mut bytes = vec![];
+    io::stdin().lock().read_to_end(&mut bytes)?;
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// Reads stdin like `read_piped_stdin` and then puts `/dev/tty` in its place
+pub fn take_piped_stdin() -> io::Result<Option<String>> {
+    let piped = read_piped_stdin()?;
+    if piped.is_some() {
+        use_terminal_as_stdin()?;
+    }
+    Ok(piped)
+}
+
+/// Puts `/dev/tty` in place of stdin if it isn't the terminal, leaving a
+/// pipe nobody asked to read unread
+pub fn use_terminal_as_stdin() -> io::Result<()> {
+    if io::stdin().is_terminal() {
+        return Ok(());
+    }
+    let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    if unsafe { libc::dup2(tty.as_raw_fd(), libc::STDIN_FILENO) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl Editor {
+    /// Fills the empty buffer with `contents` read from a pipe, unnamed and
+    /// unmodified
+    pub fn editor_open_piped(&mut self, contents: &str) {
+        for line in split_lines(contents) {
+            self.editor_insert_row(self.get_num_rows(), line);
+        }
+        self.start_screen = None;
+        self.buffer.dirty = 0;
+        self.buffer.undo.clear();
+        self.editor_mark_saved();
+        self.editor_spell_read_modeline();
+    }
+}
@@ -0,0 +1,287 @@
+//! Markdown preview: `preview` splits the screen of a Markdown file, the text
+//! on the left and on the right how it reads, with headings, emphasis, lists,
+//! quotes and code blocks styled. The preview is read-only and made from the
+//! buffer on every redraw, starting at the first row in view.
+
+use crate::*;
+
+const HEADING_COLOR: Color = Color::CYAN;
+const CODE_COLOR: Color = Color::GREEN;
+const LINK_COLOR: Color = Color::BLUE;
+const MARKER_COLOR: Color = Color::YELLOW;
+const QUOTE_COLOR: Color = Color::BRIGHT_BLACK;
+
+/// A line of the preview, one style per char
+struct PreviewLine {
+    text: String,
+    styles: Vec<CellStyle>,
+}
+
+fn is_markdown(extension: &str) -> bool {
+    matches!(extension, "md" | "markdown")
+}
+
+/// Whether `line` opens or closes a code block
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// Whether `line` is a thematic break like `---` or `* * *`
+fn is_rule(line: &str) -> bool {
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&mark| marks.chars().all(|c| c == mark))
+}
+
+/// `text` without its inline markup: `**bold**`, `*emphasis*`, `` `code` ``
+/// and `[links](url)`, with the styles those leave
+fn render_inline(text: &str, base: CellStyle) -> (String, Vec<CellStyle>) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut rendered = String::with_capacity(text.len());
+    let mut styles = vec![];
+    let (mut bold, mut emphasis, mut code, mut link) = (false, false, false, false);
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let previous = i.checked_sub(1).map(|at| chars[at]);
+        match c {
+            '`' => {
+                code = !code;
+                i += 1;
+                continue;
+            }
+            '*' | '_' if !code && next == Some(c) => {
+                bold = !bold;
+                i += 2;
+                continue;
+            }
+            // Opening needs text after it, and `_` within a word is a letter
+            '*' | '_'
+                if !code
+                    && (emphasis || next.is_some_and(|next| !next.is_whitespace()))
+                    && !(c == '_' && previous.is_some_and(char::is_alphanumeric)) =>
+            {
+                emphasis = !emphasis;
+                i += 1;
+                continue;
+            }
+            '[' if !code && text_has_link(&chars[i..]) => {
+                link = true;
+                i += 1;
+                continue;
+            }
+            ']' if link => {
+                // The url in parentheses isn't shown
+                link = false;
+                i += chars[i..].iter().position(|&c| c == ')').unwrap_or(0) + 1;
+                continue;
+            }
+            _ => {}
+        }
+        let mut style = base;
+        style.bold |= bold;
+        style.underline |= emphasis || link;
+        if code {
+            style.fg = Some(CODE_COLOR);
+        } else if link {
+            style.fg = Some(LINK_COLOR);
+        }
+        rendered.push(c);
+        styles.push(style);
+        i += 1;
+    }
+    (rendered, styles)
+}
+
+/// Whether `chars`, starting at a `[`, is a `[text](url)` link
+fn text_has_link(chars: &[char]) -> bool {
+    let close = match chars.iter().position(|&c| c == ']') {
+        Some(close) => close,
+        None => return false,
+    };
+    chars.get(close + 1) == Some(&'(') && chars[close..].contains(&')')
+}
+
+/// Breaks `text` into lines of at most `width` chars at spaces. Lines after
+/// the first start with `indent` spaces.
+fn wrap(text: &str, styles: &[CellStyle], width: usize, indent: usize) -> Vec<PreviewLine> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut lines = vec![];
+    let mut start = 0;
+    loop {
+        let prefix = if lines.is_empty() { 0 } else { indent };
+        let room = width.saturating_sub(prefix).max(1);
+        let mut end = (start + room).min(chars.len());
+        if end < chars.len() {
+            // Break after the last space that fits, unless the word is too long
+            if let Some(space) = chars[start..end].iter().rposition(|&c| c == ' ') {
+                if space > 0 {
+                    end = start + space + 1;
+                }
+            }
+        }
+        let mut line = PreviewLine {
+            text: " ".repeat(prefix),
+            styles: vec![CellStyle::default(); prefix],
+        };
+        line.text.extend(&chars[start..end]);
+        line.styles.extend_from_slice(&styles[start..end]);
+        lines.push(line);
+        start = end;
+        if start >= chars.len() {
+            return lines;
+        }
+    }
+}
+
+/// The preview of source line `line`, `width` columns wide
+fn render_line(line: &str, in_code: bool, width: usize) -> Vec<PreviewLine> {
+    let plain = CellStyle::default();
+    if in_code {
+        let text = format!("  {}", line.replace('\t', "    "));
+        let styles = vec![
+            CellStyle {
+                fg: Some(CODE_COLOR),
+                ..plain
+            };
+            text.chars().count()
+        ];
+        return wrap(&text, &styles, width, 2);
+    }
+
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+    let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+        let style = CellStyle {
+            fg: Some(HEADING_COLOR),
+            bold: true,
+            underline: hashes == 1,
+            ..plain
+        };
+        let (text, styles) = render_inline(trimmed[hashes..].trim(), style);
+        return wrap(&text, &styles, width, 0);
+    }
+    if is_rule(line) {
+        let rule = "─".repeat(width);
+        let styles = vec![CellStyle { dim: true, ..plain }; width];
+        return vec![PreviewLine { text: rule, styles }];
+    }
+
+    // Quotes and list items get a marker in front of their text
+    let (marker, text) = if let Some(quoted) = trimmed.strip_prefix('>') {
+        ("│ ".to_string(), quoted.trim_start())
+    } else if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| trimmed.strip_prefix(bullet))
+    {
+        ("• ".to_string(), item)
+    } else {
+        let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+        match trimmed[digits..].strip_prefix(". ") {
+            Some(item) if digits > 0 => (format!("{}. ", &trimmed[..digits]), item),
+            _ => (String::new(), trimmed),
+        }
+    };
+    let base = if marker.starts_with('│') {
+        CellStyle {
+            fg: Some(QUOTE_COLOR),
+            ..plain
+        }
+    } else {
+        plain
+    };
+    let (text, text_styles) = render_inline(text, base);
+    let lead = format!("{}{}", " ".repeat(indent), marker);
+    let mut styles = vec![plain; indent];
+    styles.extend(marker.chars().map(|_| CellStyle {
+        fg: Some(MARKER_COLOR),
+        ..plain
+    }));
+    styles.extend(text_styles);
+    wrap(
+        &format!("{}{}", lead, text),
+        &styles,
+        width,
+        lead.chars().count(),
+    )
+}
+
+impl Editor {
+    /// Columns the preview takes from the right of the screen, with the line
+    /// dividing it from the text. 0 when it's off.
+    pub fn editor_preview_width(&self) -> usize {
+        let markdown = self
+            .editor_file_extension()
+            .is_some_and(|extension| is_markdown(&extension));
+        if self.markdown_preview && markdown {
+            self.screen_num_columns / 2
+        } else {
+            0
+        }
+    }
+
+    /// `preview`: shows or hides the Markdown preview
+    pub fn editor_toggle_preview(&mut self) {
+        let markdown = self
+            .editor_file_extension()
+            .is_some_and(|extension| is_markdown(&extension));
+        if !markdown && !self.markdown_preview {
+            self.editor_set_status_message("Preview is only for Markdown files");
+            return;
+        }
+        self.markdown_preview = !self.markdown_preview;
+        self.editor_invalidate_screen();
+    }
+
+    /// A screenful of preview, `width` columns wide, from the first row in
+    /// view on
+    fn editor_preview_lines(&self, width: usize) -> Vec<PreviewLine> {
+        let start = self.buffer.row_offset.min(self.get_num_rows());
+        let mut in_code = self.buffer.rows[..start]
+            .iter()
+            .filter(|row| is_fence(&row.chars))
+            .count()
+            % 2
+            == 1;
+        let mut lines = vec![];
+        for row in &self.buffer.rows[start..] {
+            if lines.len() >= self.screen_num_rows {
+                break;
+            }
+            if is_fence(&row.chars) {
+                in_code = !in_code;
+                continue;
+            }
+            lines.extend(render_line(&row.chars, in_code, width));
+        }
+        lines
+    }
+
+    /// Draws the preview over the right of the text lines
+    pub fn editor_draw_preview(&self, lines: &mut [FrameLine]) {
+        let width = self.editor_preview_width();
+        if width == 0 {
+            return;
+        }
+        let column = self.screen_num_columns - width;
+        let preview = self.editor_preview_lines(width.saturating_sub(2));
+        let divider = CellStyle {
+            dim: true,
+            ..CellStyle::default()
+        };
+        for (y, line) in lines.iter_mut().enumerate() {
+            let mut text = "│ ".to_string();
+            let mut styles = vec![divider, CellStyle::default()];
+            if let Some(preview) = preview.get(y) {
+                text.push_str(&preview.text);
+                styles.extend_from_slice(&preview.styles);
+            }
+            line.overlay(column, &text, &styles);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Frame timing for the render path: `set frametime` shows how long the last
+//! frames took to build, on average and at worst, in the status bar. `cargo
+//! bench --bench render --features bench` times the renderer over big
+//! synthetic buffers.
+
+use std::collections::VecDeque;
+
+use crate::*;
+
+/// Frames the status bar figures are taken over
+const FRAMES_KEPT: usize = 60;
+
+#[derive(Default)]
+pub struct FrameTimes {
+    /// Build times of the last frames, the newest last
+    recent: VecDeque<Duration>,
+}
+
+/// `duration` in milliseconds, as `1.25ms`
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+impl Editor {
+    /// Counts a frame that took `elapsed` to build
+    pub fn editor_record_build_time(&mut self, elapsed: Duration) {
+        let recent = &mut self.frame_times.recent;
+        if recent.len() == FRAMES_KEPT {
+            recent.pop_front();
+        }
+        recent.push_back(elapsed);
+    }
+
+    /// Status bar tag with `frametime` set
+    pub fn editor_frame_time_status(&self) -> Option<String> {
+        let recent = &self.frame_times.recent;
+        if !self.options.frame_time || recent.is_empty() {
+            return None;
+        }
+        let total: Duration = recent.iter().sum();
+        let slowest = recent.iter().max().copied().unwrap_or_default();
+        Some(format!(
+            "[frame {} max {}]",
+            milliseconds(total / recent.len() as u32),
+            milliseconds(slowest)
+        ))
+    }
+}
@@ -0,0 +1,197 @@
+//! Quickfix list: run a build command and jump between the reported errors
+
+use std::path::Path;
+
+use crate::overlay::OverlayLine;
+use crate::workdir::{absolute_path, display_path};
+use crate::*;
+
+pub struct QuickfixEntry {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub struct Quickfix {
+    entries: Vec<QuickfixEntry>,
+    current: usize,
+}
+
+impl Quickfix {
+    pub fn new(entries: Vec<QuickfixEntry>) -> Quickfix {
+        Quickfix {
+            entries,
+            current: 0,
+        }
+    }
+
+    pub fn push(&mut self, entry: QuickfixEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Turns relative file names into absolute ones, before a `cd`
+    pub fn make_paths_absolute(&mut self) {
+        for entry in &mut self.entries {
+            entry.file = absolute_path(Path::new(&entry.file))
+                .to_string_lossy()
+                .into_owned();
+        }
+    }
+}
+
+/// Parses `file:line:col: message` or `file:line: message`
+fn parse_diagnostic(line: &str) -> Option<QuickfixEntry> {
+    let mut parts = line.splitn(4, ':');
+    let file = parts.next()?.trim();
+    let line_number: usize = parts.next()?.trim().parse().ok()?;
+    let rest: Vec<&str> = parts.collect();
+
+    let (column, message) = match rest.as_slice() {
+        [column, message] => match column.trim().parse() {
+            Ok(column) => (column, message.trim().to_string()),
+            Err(_) => (1, format!("{}:{}", column, message).trim().to_string()),
+        },
+        [message] => (1, message.trim().to_string()),
+        _ => (1, String::new()),
+    };
+
+    if file.is_empty() || file.contains(char::is_whitespace) || line_number == 0 {
+        return None;
+    }
+
+    Some(QuickfixEntry {
+        file: file.to_string(),
+        line: line_number,
+        column,
+        message,
+    })
+}
+
+pub fn parse_diagnostics(output: &str) -> Vec<QuickfixEntry> {
+    output.lines().filter_map(parse_diagnostic).collect()
+}
+
+pub fn same_file(a: &str, b: &str) -> bool {
+    match (Path::new(a).canonicalize(), Path::new(b).canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+impl Editor {
+    /// `make [args]`: runs the configured build command and collects diagnostics
+    pub fn editor_make(&mut self, args: &str) {
+        let mut command = self.options.make_command.clone();
+        if !args.is_empty() {
+            command.push(' ');
+            command.push_str(args);
+        }
+
+        self.editor_set_status_message(&format!("Running {}...", command));
+        self.editor_refresh_screen();
+
+        let output = match self.editor_run_shell(&command, None) {
+            Some(output) => output,
+            None => return,
+        };
+
+        let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+
+        self.quickfix = Quickfix::new(parse_diagnostics(&text));
+
+        if self.quickfix.entries.is_empty() {
+            self.editor_set_status_message(&format!("{}: no errors ({})", command, output.status));
+        } else {
+            self.editor_quickfix_list();
+        }
+    }
+
+    /// Shows the quickfix list, Enter jumps to the selected entry
+    pub fn editor_quickfix_list(&mut self) {
+        if self.quickfix.entries.is_empty() {
+            self.editor_set_status_message("Quickfix list is empty");
+            return;
+        }
+
+        let lines: Vec<OverlayLine> = self
+            .quickfix
+            .entries
+            .iter()
+            .map(|entry| {
+                let text = format!(
+                    "{}:{}:{}: {}",
+                    display_path(&entry.file),
+                    entry.line,
+                    entry.column,
+                    entry.message
+                );
+                if entry.message.starts_with("error") {
+                    OverlayLine::colored(text, Color::RED)
+                } else if entry.message.starts_with("warning") {
+                    OverlayLine::colored(text, Color::YELLOW)
+                } else {
+                    OverlayLine::new(text)
+                }
+            })
+            .collect();
+
+        let title = format!("Quickfix ({} entries)", lines.len());
+        if let Some(selected) = self.editor_show_overlay(&title, &lines) {
+            self.quickfix.current = selected;
+            self.editor_quickfix_jump();
+        }
+    }
+
+    pub fn editor_quickfix_next(&mut self, forward: bool) {
+        let count = self.quickfix.entries.len();
+        if count == 0 {
+            self.editor_set_status_message("Quickfix list is empty (run make first)");
+            return;
+        }
+
+        self.quickfix.current = if forward {
+            (self.quickfix.current + 1) % count
+        } else {
+            (self.quickfix.current + count - 1) % count
+        };
+        self.editor_quickfix_jump();
+    }
+
+    /// Jumps to entry `index` of the quickfix list
+    pub fn editor_quickfix_select(&mut self, index: usize) {
+        if index < self.quickfix.entries.len() {
+            self.quickfix.current = index;
+            self.editor_quickfix_jump();
+        }
+    }
+
+    fn editor_quickfix_jump(&mut self) {
+        let entry = &self.quickfix.entries[self.quickfix.current];
+        let (file, line, column) = (entry.file.clone(), entry.line, entry.column);
+        let message = format!(
+            "({}/{}) {}",
+            self.quickfix.current + 1,
+            self.quickfix.entries.len(),
+            entry.message
+        );
+
+        let is_current = self
+            .buffer
+            .filename
+            .as_deref()
+            .is_some_and(|filename| same_file(filename, &file));
+        if !is_current && !self.editor_switch_file(&file) {
+            return;
+        }
+
+        self.editor_goto(line.saturating_sub(1), column.saturating_sub(1));
+        self.editor_set_status_message(&message);
+    }
+}
@@ -0,0 +1,284 @@
+//! Ex-style line ranges: `.,+5y`, `'a,'bd`, `%y a`, `d 3`. An address is `.`
+//! (the cursor line), `$` (the last line), a line number or `'x` (mark x, set
+//! with `mark x`), followed by any `+N` / `-N` offsets. `y` and `d` copy the
+//! lines into a register, the clipboard unless a register letter is given,
+//! and take a count of lines starting at the end of the range.
+
+use crate::*;
+
+/// What was parsed from the start of a command and the rest of it, None if
+/// the command doesn't start with it
+type Parsed<'a, T> = Option<Result<(T, &'a str), String>>;
+
+/// A named mark or register: a lowercase letter
+fn is_register_name(c: char) -> bool {
+    c.is_ascii_lowercase()
+}
+
+/// Splits the leading number off `text`
+fn split_number(text: &str) -> (Option<usize>, &str) {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    (text[..end].parse().ok(), &text[end..])
+}
+
+impl Editor {
+    /// Last line of the buffer, leaving out the empty row after a final newline
+    fn editor_last_line(&self) -> usize {
+        let num_rows = self.get_num_rows();
+        if num_rows > 1 && self.buffer.rows[num_rows - 1].chars.is_empty() {
+            num_rows - 2
+        } else {
+            num_rows.saturating_sub(1)
+        }
+    }
+
+    /// Parses the address at the start of `text` into a row
+    fn editor_parse_address<'a>(&self, text: &'a str) -> Parsed<'a, usize> {
+        let (mut row, mut rest) = if let Some(rest) = text.strip_prefix('.') {
+            (self.buffer.cursor_y as isize, rest)
+        } else if let Some(rest) = text.strip_prefix('$') {
+            (self.editor_last_line() as isize, rest)
+        } else if let Some(rest) = text.strip_prefix('\'') {
+            let name = rest.chars().next().filter(|c| is_register_name(*c));
+            let row = name.and_then(|name| self.buffer.named_marks.get(&name));
+            match (name, row) {
+                (Some(_), Some(row)) => (*row as isize, &rest[1..]),
+                (Some(name), None) => return Some(Err(format!("Mark '{} is not set", name))),
+                (None, _) => return Some(Err("Marks are named a to z".to_string())),
+            }
+        } else if text.starts_with(|c: char| c.is_ascii_digit()) {
+            let (number, rest) = split_number(text);
+            (number.unwrap_or(0) as isize - 1, rest)
+        } else if text.starts_with(['+', '-']) {
+            (self.buffer.cursor_y as isize, text)
+        } else {
+            return None;
+        };
+
+        // Offsets, `+` alone means +1
+        while let Some(sign) = rest.chars().next().filter(|c| *c == '+' || *c == '-') {
+            let (number, after) = split_number(&rest[1..]);
+            let offset = number.unwrap_or(1) as isize;
+            row += if sign == '+' { offset } else { -offset };
+            rest = after;
+        }
+
+        if row < 0 || row as usize > self.editor_last_line() {
+            return Some(Err(format!("Line {} is outside the buffer", row + 1)));
+        }
+        Some(Ok((row as usize, rest)))
+    }
+
+    /// Parses the range at the start of `command` into first and last rows
+    fn editor_parse_range<'a>(&self, command: &'a str) -> Parsed<'a, (usize, usize)> {
+        if let Some(rest) = command.strip_prefix('%') {
+            return Some(Ok(((0, self.editor_last_line()), rest)));
+        }
+        let (start, rest) = match self.editor_parse_address(command)? {
+            Ok(address) => address,
+            Err(error) => return Some(Err(error)),
+        };
+        let (end, rest) = match rest.strip_prefix(',') {
+            Some(rest) => match self.editor_parse_address(rest) {
+                Some(Ok(address)) => address,
+                Some(Err(error)) => return Some(Err(error)),
+                None => return Some(Err("Missing address after ','".to_string())),
+            },
+            None => (start, rest),
+        };
+        Some(Ok(((start.min(end), start.max(end)), rest)))
+    }
+
+    /// Runs `command` if it is a line command (`y` or `d`) with or without a
+    /// range. Returns false for other commands.
+    pub fn editor_line_command(&mut self, command: &str) -> bool {
+        let (range, rest) = match self.editor_parse_range(command) {
+            Some(Ok((range, rest))) => (Some(range), rest.trim_start()),
+            Some(Err(error)) => {
+                self.editor_set_status_message(&error);
+                return true;
+            }
+            None => (None, command),
+        };
+        let (name, args) = match rest.split_once(char::is_whitespace) {
+            Some((name, args)) => (name, args.trim()),
+            None => (rest, ""),
+        };
+        let delete = match name {
+            "d" | "delete" => true,
+            "y" | "yank" => false,
+            _ if range.is_none() => return false,
+            "" => {
+                // A bare address jumps to that line
+                let (_, end) = range.unwrap_or_default();
+                self.editor_goto(end, 0);
+                return true;
+            }
+            _ => {
+                self.editor_set_status_message(&format!("{} doesn't take a range", name));
+                return true;
+            }
+        };
+        if self.get_num_rows() == 0 {
+            self.editor_set_status_message("Buffer is empty");
+            return true;
+        }
+
+        // Optional register letter, then optional count
+        let mut args = args;
+        let mut register = None;
+        if let Some(name) = args.chars().next().filter(|c| is_register_name(*c)) {
+            register = Some(name);
+            args = args[1..].trim_start();
+        }
+        let (mut start, mut end) = range.unwrap_or((self.buffer.cursor_y, self.buffer.cursor_y));
+        match split_number(args) {
+            (Some(count), "") if count > 0 => {
+                start = end;
+                end = (end + count - 1).min(self.editor_last_line());
+            }
+            (None, "") => {}
+            _ => {
+                self.editor_set_error_message(&format!("Usage: [range]{} [a-z] [count]", name));
+                return true;
+            }
+        }
+
+        let count = end - start + 1;
+        let mut text = String::new();
+        for row in &self.buffer.rows[start..=end] {
+            text.push_str(&row.chars);
+            text.push('\n');
+        }
+        if delete {
+            self.editor_replace_rows(start, count, vec![]);
+            self.editor_goto(start, 0);
+        }
+
+        let lines = if count == 1 { "line" } else { "lines" };
+        let verb = if delete { "Deleted" } else { "Copied" };
+        match register {
+            Some(name) => {
+                self.registers.insert(name, text);
+                self.editor_set_status_message(&format!(
+                    "{} {} {} into register {}",
+                    verb, count, lines, name
+                ));
+            }
+            None => {
+                self.clipboard = text;
+                self.editor_export_clipboard();
+            }
+        }
+        true
+    }
+
+    /// Moves the named marks along with their rows after rows `at..at +
+    /// removed` were replaced by `inserted` rows. Marks on replaced rows stay
+    /// on what took their place, and go if nothing did.
+    pub fn editor_shift_named_marks(&mut self, at: usize, removed: usize, inserted: usize) {
+        self.buffer.named_marks.retain(|_, row| {
+            if *row >= at + removed {
+                *row = *row + inserted - removed;
+            } else if *row >= at {
+                if inserted == 0 {
+                    return false;
+                }
+                *row = (*row).min(at + inserted - 1);
+            }
+            true
+        });
+    }
+
+    /// `mark x`: sets mark x on the cursor line, for use in ranges as `'x`
+    pub fn editor_set_named_mark(&mut self, args: &str) {
+        let mut chars = args.chars();
+        match (chars.next(), chars.next()) {
+            (Some(name), None) if is_register_name(name) => {
+                self.buffer.named_marks.insert(name, self.buffer.cursor_y);
+                self.editor_set_status_message(&format!(
+                    "Mark '{} set on line {}",
+                    name,
+                    self.buffer.cursor_y + 1
+                ));
+            }
+            _ => self.editor_set_error_message("Usage: mark [a-z]"),
+        }
+    }
+
+    /// `put [x]`: inserts the lines of register x, or of the clipboard, below
+    /// the cursor line
+    pub fn editor_put(&mut self, args: &str) {
+        let text = match args.chars().next() {
+            None => self.clipboard.clone(),
+            Some(name) if is_register_name(name) && args.len() == 1 => {
+                match self.registers.get(&name) {
+                    Some(text) => text.clone(),
+                    None => {
+                        self.editor_set_status_message(&format!("Register {} is empty", name));
+                        return;
+                    }
+                }
+            }
+            Some(_) => {
+                self.editor_set_error_message("Usage: put [a-z]");
+                return;
+            }
+        };
+        if text.is_empty() {
+            self.editor_set_status_message("Clipboard is empty");
+            return;
+        }
+
+        let lines: Vec<String> = text
+            .strip_suffix('\n')
+            .unwrap_or(&text)
+            .split('\n')
+            .map(str::to_string)
+            .collect();
+        let at = (self.buffer.cursor_y + 1).min(self.get_num_rows());
+        self.editor_replace_rows(at, 0, lines);
+        self.editor_goto(at, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_move_with_their_rows() {
+        let mut editor = Editor::new_headless(24, 80);
+        let lines = ["a", "b", "c", "d"].map(String::from).to_vec();
+        editor.editor_replace_rows(0, 0, lines);
+        for (name, row) in [('a', 0), ('b', 1), ('c', 2), ('d', 3)] {
+            editor.buffer.named_marks.insert(name, row);
+        }
+
+        editor.editor_insert_row(1, "new".to_string());
+        editor.editor_delete_row(3);
+        editor.editor_replace_rows(0, 2, vec!["joined".to_string()]);
+        let mut marks: Vec<(char, usize)> = editor.buffer.named_marks.clone().into_iter().collect();
+        marks.sort();
+        assert_eq!(marks, [('a', 0), ('b', 1), ('c', 1), ('d', 2)]);
+
+        editor.editor_undo();
+        assert_eq!(editor.buffer.named_marks[&'d'], 3);
+    }
+
+    #[test]
+    fn marks_follow_joined_rows_and_not_other_files() {
+        let mut editor = Editor::new_headless(24, 80);
+        editor.editor_replace_rows(0, 0, ["one", "two"].map(String::from).to_vec());
+        editor.editor_handle_keys([Key::Down]);
+        editor.editor_execute_command("mark a");
+        editor.editor_handle_keys([Key::Home, Key::Backspace]);
+        assert_eq!(editor.buffer.rows[0].chars, "onetwo");
+        assert_eq!(editor.buffer.named_marks.get(&'a'), Some(&0));
+
+        editor.editor_reset_buffer();
+        assert!(editor.buffer.named_marks.is_empty());
+    }
+}
@@ -0,0 +1,237 @@
+//! Recently opened and saved files, newest first, kept in the state
+//! directory. They are listed by `recent` and by the start screen shown when
+//! the editor is started without a file.
+
+use std::path::{Path, PathBuf};
+
+use crate::hooks::EditorHook;
+use crate::storage::{read_atomic, state_dir, write_atomic};
+use crate::*;
+
+const RECENT_HEADER: &str = "kilo-recent 1";
+/// Files remembered
+const RECENT_SIZE: usize = 50;
+/// Files listed on the start screen, if the screen is tall enough
+const START_SCREEN_FILES: usize = 9;
+
+fn recent_path() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("recent"))
+}
+
+fn read_recent_file(path: &Path) -> Vec<String> {
+    match read_atomic(path, RECENT_HEADER) {
+        Some(contents) => contents.lines().map(str::to_string).collect(),
+        None => vec![],
+    }
+}
+
+/// Recent files that still exist, newest first
+pub fn recent_files() -> Vec<String> {
+    recent_path()
+        .map(|path| read_recent_file(&path))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|file| Path::new(file).is_file())
+        .collect()
+}
+
+/// Moves `filename` to the front of the recent files
+pub fn add_recent_file(filename: &str) {
+    let path = match recent_path() {
+        Some(path) => path,
+        None => return,
+    };
+    let absolute = match std::fs::canonicalize(filename) {
+        Ok(absolute) => absolute.to_string_lossy().into_owned(),
+        Err(_) => return,
+    };
+    if absolute.contains('\n') {
+        return;
+    }
+
+    let mut files = read_recent_file(&path);
+    files.retain(|file| *file != absolute);
+    files.insert(0, absolute);
+    files.truncate(RECENT_SIZE);
+    let contents = format!("{}\n{}\n", RECENT_HEADER, files.join("\n"));
+    // Like the prompt history, a failed write shouldn't interrupt editing
+    let _ = write_atomic(&path, contents);
+}
+
+/// `path` with the home directory shortened to ~
+fn home_relative(path: &str) -> String {
+    match std::env::var("HOME") {
+        Ok(home) if !home.is_empty() && path.starts_with(&format!("{}/", home)) => {
+            format!("~{}", &path[home.len()..])
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Puts each file opened or saved at the top of the recent files
+pub struct RecentFiles;
+
+impl EditorHook for RecentFiles {
+    fn on_open(&mut self, editor: &mut Editor) {
+        if let Some(filename) = &editor.buffer.filename {
+            add_recent_file(filename);
+        }
+    }
+
+    fn on_save_post(&mut self, editor: &mut Editor) {
+        self.on_open(editor);
+    }
+}
+
+pub struct StartScreen {
+    files: Vec<String>,
+    /// Selected entry: a file, or the "New file" entry after them
+    selected: usize,
+}
+
+impl StartScreen {
+    pub fn new() -> Self {
+        StartScreen {
+            files: recent_files(),
+            selected: 0,
+        }
+    }
+}
+
+impl Editor {
+    /// `recent`: opens one of the recently used files, picked by fuzzy name
+    pub fn editor_open_recent(&mut self) {
+        let current = self
+            .buffer
+            .filename
+            .as_ref()
+            .and_then(|filename| std::fs::canonicalize(filename).ok())
+            .map(|path| path.to_string_lossy().into_owned());
+        let files: Vec<String> = recent_files()
+            .into_iter()
+            .filter(|file| Some(file) != current.as_ref())
+            .collect();
+        if files.is_empty() {
+            self.editor_set_status_message("No recent files");
+            return;
+        }
+
+        let names: Vec<String> = files.iter().map(|file| home_relative(file)).collect();
+        if let Some(selected) = self.editor_fuzzy_pick("Recent files", &names) {
+            self.editor_switch_file(&files[selected]);
+        }
+    }
+
+    /// Whether the start screen is drawn instead of the empty buffer
+    fn editor_start_screen_visible(&self) -> bool {
+        self.start_screen.is_some() && self.buffer.filename.is_none() && self.get_num_rows() == 0
+    }
+
+    /// Recent files that fit on the screen
+    fn editor_start_screen_files(&self) -> &[String] {
+        let files = match &self.start_screen {
+            Some(start) => &start.files[..],
+            None => return &[],
+        };
+        // Room for the title, headings, "New file" and the hints
+        let room = self.screen_num_rows.saturating_sub(7);
+        &files[..files.len().min(START_SCREEN_FILES).min(room)]
+    }
+
+    /// Draws the start screen over the text rows, false if it isn't shown
+    pub fn editor_draw_start_screen(&self, lines: &mut Vec<FrameLine>) -> bool {
+        let start = match &self.start_screen {
+            Some(start) if self.editor_start_screen_visible() => start,
+            _ => return false,
+        };
+        let files = self.editor_start_screen_files();
+        let plain = CellStyle::default();
+        let dim = CellStyle {
+            dim: true,
+            ..CellStyle::default()
+        };
+        let entry = |index: usize| CellStyle {
+            inverse: index == start.selected,
+            ..CellStyle::default()
+        };
+
+        let mut items = vec![
+            (
+                format!("Kilo editor -- version {}", KILO_VERSION),
+                CellStyle {
+                    bold: true,
+                    ..CellStyle::default()
+                },
+            ),
+            (String::new(), plain),
+        ];
+        if files.is_empty() {
+            items.push(("No recent files".to_string(), dim));
+        } else {
+            items.push(("Recent files".to_string(), dim));
+            for (index, file) in files.iter().enumerate() {
+                items.push((format!(" {} ", home_relative(file)), entry(index)));
+            }
+        }
+        items.push((String::new(), plain));
+        items.push((" New file ".to_string(), entry(files.len())));
+        items.push((String::new(), plain));
+        items.push((
+            "Up/Down select, Enter open, F1 help, Ctrl-Q quit".to_string(),
+            dim,
+        ));
+
+        let columns = self.screen_num_columns.saturating_sub(1);
+        let width = items
+            .iter()
+            .map(|(text, _)| text.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(columns);
+        let left = (columns - width) / 2;
+        let top = self.screen_num_rows.saturating_sub(items.len()) / 3;
+        for y in 0..self.screen_num_rows {
+            let mut line = FrameLine::default();
+            line.push("~");
+            if let Some((text, style)) = y.checked_sub(top).and_then(|i| items.get(i)) {
+                let text: String = text.chars().take(columns).collect();
+                line.push(&" ".repeat(left));
+                line.push_styled(&text, *style);
+            }
+            lines.push(line);
+        }
+        true
+    }
+
+    /// Keys on the start screen: Up/Down select and Enter opens. Typing
+    /// starts a new file. Returns true if the key was used up.
+    pub fn editor_start_screen_key(&mut self, key: Key) -> bool {
+        if !self.editor_start_screen_visible() {
+            self.start_screen = None;
+            return false;
+        }
+        let files = self.editor_start_screen_files().to_vec();
+        let start = match self.start_screen.as_mut() {
+            Some(start) => start,
+            None => return false,
+        };
+        match key {
+            Key::Up => start.selected = start.selected.saturating_sub(1),
+            Key::Down => start.selected = (start.selected + 1).min(files.len()),
+            Key::Enter => {
+                let selected = start.selected;
+                self.start_screen = None;
+                if let Some(file) = files.get(selected) {
+                    self.editor_open(file);
+                }
+            }
+            Key::Escape => self.start_screen = None,
+            Key::Char(_) | Key::Tab => {
+                self.start_screen = None;
+                return false;
+            }
+            _ => return false,
+        }
+        true
+    }
+}
@@ -0,0 +1,230 @@
+//! Recovery files: unsaved changes are written to the state directory while
+//! editing, and files left behind by crashed sessions are offered on startup
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::git::format_date;
+use crate::overlay::OverlayLine;
+use crate::storage::{is_temporary, read_atomic, state_dir, write_atomic};
+use crate::*;
+
+const RECOVERY_HEADER: &str = "kilo-recovery 1";
+/// Minimum time between two writes of the recovery file while typing
+const RECOVERY_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+pub struct Recovery {
+    /// Recovery file of the current buffer, if one was written
+    path: Option<PathBuf>,
+    /// Value of `Editor::dirty` when the recovery file was last written
+    written_at: Option<usize>,
+    last_write: Option<Instant>,
+}
+
+/// A recovery file left by a session that is no longer running
+struct OrphanedFile {
+    path: PathBuf,
+    /// File the contents belong to, empty for an unnamed buffer
+    filename: String,
+    modified: SystemTime,
+    contents: String,
+}
+
+fn recovery_dir() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("recovery"))
+}
+
+/// Recovery file name for `filename` in this process
+fn recovery_file_name(filename: Option<&str>) -> String {
+    let absolute = filename.map(|filename| {
+        Path::new(filename)
+            .canonicalize()
+            .unwrap_or_else(|_| PathBuf::from(filename))
+    });
+    let name: String = match &absolute {
+        Some(path) => path
+            .to_string_lossy()
+            .chars()
+            .map(|c| if c == '/' { '%' } else { c })
+            .collect(),
+        None => "[No Name]".to_string(),
+    };
+    format!("{}.{}.swp", name, process::id())
+}
+
+fn process_is_running(pid: i32) -> bool {
+    // Signal 0 only checks whether the process exists (EPERM means it does)
+    let alive = unsafe { libc::kill(pid, 0) } == 0;
+    alive || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Parses a recovery file: header, pid, file name, then the buffer contents
+fn read_recovery_file(path: &Path) -> Option<(i32, String, String)> {
+    let contents = read_atomic(path, RECOVERY_HEADER)?;
+    let mut parts = contents.splitn(3, '\n');
+    let pid = parts.next()?.parse().ok()?;
+    let filename = parts.next()?.to_string();
+    Some((pid, filename, parts.next().unwrap_or("").to_string()))
+}
+
+fn find_orphaned_files() -> Vec<OrphanedFile> {
+    let entries = match recovery_dir().map(std::fs::read_dir) {
+        Some(Ok(entries)) => entries,
+        _ => return vec![],
+    };
+
+    let mut orphans: Vec<OrphanedFile> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if is_temporary(&path) {
+                return None;
+            }
+            let (pid, filename, contents) = read_recovery_file(&path)?;
+            if pid as u32 == process::id() || process_is_running(pid) {
+                return None;
+            }
+            let modified = path.metadata().and_then(|meta| meta.modified()).ok()?;
+            Some(OrphanedFile {
+                path,
+                filename,
+                modified,
+                contents,
+            })
+        })
+        .collect();
+    orphans.sort_by_key(|orphan| std::cmp::Reverse(orphan.modified));
+    orphans
+}
+
+fn format_time(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0);
+    format!(
+        "{} {:02}:{:02} UTC",
+        format_date(seconds),
+        seconds.rem_euclid(86400) / 3600,
+        seconds.rem_euclid(3600) / 60
+    )
+}
+
+impl Editor {
+    /// Writes unsaved changes to the recovery file, at most every few seconds
+    pub fn editor_write_recovery(&mut self) {
+        // Bytes in hex mode aren't kept, the rows are empty then
+        if self.buffer.dirty == 0
+            || self.buffer.recovery.written_at == Some(self.buffer.dirty)
+            || self.buffer.hex.is_some()
+        {
+            return;
+        }
+        if self
+            .buffer
+            .recovery
+            .last_write
+            .is_some_and(|last| last.elapsed() < RECOVERY_INTERVAL)
+        {
+            return;
+        }
+
+        let dir = match recovery_dir() {
+            Some(dir) => dir,
+            None => return,
+        };
+        let path = dir.join(recovery_file_name(self.buffer.filename.as_deref()));
+        let contents = format!(
+            "{}\n{}\n{}\n{}",
+            RECOVERY_HEADER,
+            process::id(),
+            self.buffer.filename.as_deref().unwrap_or(""),
+            self.editor_rows_to_string()
+        );
+
+        // Failing to write a recovery file shouldn't interrupt editing
+        if write_atomic(&path, contents).is_ok() {
+            if self
+                .buffer
+                .recovery
+                .path
+                .as_ref()
+                .is_some_and(|old| *old != path)
+            {
+                self.editor_remove_recovery();
+            }
+            self.buffer.recovery.path = Some(path);
+        }
+        self.buffer.recovery.written_at = Some(self.buffer.dirty);
+        self.buffer.recovery.last_write = Some(Instant::now());
+    }
+
+    /// Deletes the recovery file, called once the changes are saved or dropped
+    pub fn editor_remove_recovery(&mut self) {
+        if let Some(path) = self.buffer.recovery.path.take() {
+            let _ = std::fs::remove_file(path);
+        }
+        self.buffer.recovery.written_at = None;
+    }
+
+    /// `recover`: lists recovery files left by crashed sessions, Enter restores one
+    pub fn editor_recover(&mut self, on_startup: bool) {
+        let orphans = find_orphaned_files();
+        if orphans.is_empty() {
+            if !on_startup {
+                self.editor_set_status_message("No recovery files found");
+            }
+            return;
+        }
+
+        let lines: Vec<OverlayLine> = orphans
+            .iter()
+            .map(|orphan| {
+                let name = if orphan.filename.is_empty() {
+                    "[No Name]"
+                } else {
+                    &orphan.filename
+                };
+                OverlayLine::new(format!(
+                    "{}  {}  ({} lines)",
+                    format_time(orphan.modified),
+                    name,
+                    split_lines(&orphan.contents).len()
+                ))
+            })
+            .collect();
+
+        let title = "Unsaved changes from crashed sessions (Enter to recover)";
+        let selected = match self.editor_show_overlay(title, &lines) {
+            Some(selected) => &orphans[selected],
+            None => {
+                self.editor_set_status_message("Recovery skipped (recover to list again)");
+                return;
+            }
+        };
+
+        if selected.filename.is_empty() {
+            if self.buffer.dirty != 0 && !self.editor_confirm("Discard unsaved changes? (y/n)") {
+                return;
+            }
+            self.editor_reset_buffer();
+        } else {
+            let is_current = self
+                .buffer
+                .filename
+                .as_deref()
+                .is_some_and(|filename| quickfix::same_file(filename, &selected.filename));
+            if !is_current && !self.editor_switch_file(&selected.filename) {
+                return;
+            }
+        }
+
+        let count = self.get_num_rows();
+        self.editor_replace_rows(0, count, split_lines(&selected.contents));
+        let _ = std::fs::remove_file(&selected.path);
+        self.editor_set_status_message(&format!(
+            "Recovered changes from {} (Ctrl-S to save, undo to revert)",
+            format_time(selected.modified)
+        ));
+    }
+}
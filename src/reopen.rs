@@ -0,0 +1,232 @@
+//! How the open file is read and written: its encoding, how line endings are
+//! split and whether it may be saved. `reopen` reads the file again with
+//! other choices, keeping the cursor where it was.
+
+use crate::overlay::OverlayLine;
+use crate::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Latin1,
+    Utf16Le,
+    Utf16Be,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEndings {
+    /// Lines end with LF, a CR before it is dropped
+    #[default]
+    Lf,
+    /// Lines end with CR (classic Mac OS)
+    Cr,
+    /// Lines end with LF, CRs are kept in the text
+    KeepCr,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct FileFormat {
+    pub encoding: Encoding,
+    pub line_endings: LineEndings,
+    pub read_only: bool,
+    /// The file couldn't be read, it isn't saved over until reopened
+    pub read_failed: bool,
+    /// The file, or the directory of a new one, can't be written by us
+    pub not_writable: bool,
+}
+
+/// `reopen` arguments with what they choose, in the order offered
+const CHOICES: [(&str, &str); 9] = [
+    ("utf8", "UTF-8"),
+    ("latin1", "Latin-1 (ISO-8859-1)"),
+    ("utf16le", "UTF-16 little endian"),
+    ("utf16be", "UTF-16 big endian"),
+    ("lf", "Lines end with LF or CRLF"),
+    ("cr", "Lines end with CR (classic Mac OS)"),
+    ("keepcr", "Lines end with LF, keep CRs in the text"),
+    ("ro", "Read-only"),
+    ("rw", "Writable"),
+];
+
+impl FileFormat {
+    /// Applies one `reopen` argument, false if it isn't one
+    fn apply(&mut self, choice: &str) -> bool {
+        match choice {
+            "utf8" | "utf-8" => self.encoding = Encoding::Utf8,
+            "latin1" | "iso-8859-1" => self.encoding = Encoding::Latin1,
+            "utf16le" | "utf-16le" => self.encoding = Encoding::Utf16Le,
+            "utf16be" | "utf-16be" => self.encoding = Encoding::Utf16Be,
+            "lf" | "crlf" => self.line_endings = LineEndings::Lf,
+            "cr" => self.line_endings = LineEndings::Cr,
+            "keepcr" => self.line_endings = LineEndings::KeepCr,
+            "ro" | "readonly" => self.read_only = true,
+            "rw" => self.read_only = false,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Text of a file's `bytes`
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, String> {
+        match self.encoding {
+            Encoding::Utf8 => String::from_utf8(bytes.to_vec())
+                .map_err(|_| "not valid UTF-8 (reopen latin1 reads it as Latin-1)".to_string()),
+            Encoding::Latin1 => Ok(bytes.iter().map(|&byte| byte as char).collect()),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                if !bytes.len().is_multiple_of(2) {
+                    return Err("odd number of bytes for UTF-16".to_string());
+                }
+                let little_endian = self.encoding == Encoding::Utf16Le;
+                let mut units: Vec<u16> = bytes
+                    .chunks(2)
+                    .map(|pair| {
+                        if little_endian {
+                            u16::from_le_bytes([pair[0], pair[1]])
+                        } else {
+                            u16::from_be_bytes([pair[0], pair[1]])
+                        }
+                    })
+                    .collect();
+                if units.first() == Some(&0xfeff) {
+                    units.remove(0);
+                }
+                String::from_utf16(&units).map_err(|_| "not valid UTF-16".to_string())
+            }
+        }
+    }
+
+    /// Lines of decoded `contents`
+    pub fn split_lines(&self, contents: &str) -> Vec<String> {
+        match self.line_endings {
+            LineEndings::Lf => split_lines(contents),
+            LineEndings::Cr => contents.split('\r').map(str::to_string).collect(),
+            LineEndings::KeepCr => contents.split('\n').map(str::to_string).collect(),
+        }
+    }
+
+    /// Bytes to write for `text`, whose lines are joined with LF
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, String> {
+        let text = match self.line_endings {
+            LineEndings::Cr => text.replace('\n', "\r"),
+            _ => text.to_string(),
+        };
+        match self.encoding {
+            Encoding::Utf8 => Ok(text.into_bytes()),
+            Encoding::Latin1 => text
+                .chars()
+                .map(|c| u8::try_from(c).map_err(|_| format!("'{}' can't be saved as Latin-1", c)))
+                .collect(),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let little_endian = self.encoding == Encoding::Utf16Le;
+                Ok(std::iter::once(0xfeff)
+                    .chain(text.encode_utf16())
+                    .flat_map(|unit| {
+                        if little_endian {
+                            unit.to_le_bytes()
+                        } else {
+                            unit.to_be_bytes()
+                        }
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+impl Editor {
+    /// Status bar tag when the file isn't read and written the default way
+    pub fn editor_file_format_status(&self) -> Option<String> {
+        let format = &self.buffer.file_format;
+        let mut tags = vec![];
+        match format.encoding {
+            Encoding::Utf8 => {}
+            Encoding::Latin1 => tags.push("latin1"),
+            Encoding::Utf16Le => tags.push("utf16le"),
+            Encoding::Utf16Be => tags.push("utf16be"),
+        }
+        match format.line_endings {
+            LineEndings::Lf => {}
+            LineEndings::Cr => tags.push("cr"),
+            LineEndings::KeepCr => tags.push("keepcr"),
+        }
+        if format.read_only || format.read_failed {
+            tags.push("RO");
+        } else if format.not_writable {
+            tags.push("readonly");
+        }
+        (!tags.is_empty()).then(|| format!("[{}]", tags.join(" ")))
+    }
+
+    /// Replaces the buffer with `filename` read as `format`, keeping the
+    /// cursor and scroll position
+    pub fn editor_read_again(&mut self, filename: &str, format: FileFormat) {
+        let (row, column) = (self.buffer.cursor_y, self.buffer.cursor_x);
+        let (row_offset, column_offset) = (self.buffer.row_offset, self.buffer.column_offset);
+        self.editor_reset_buffer();
+        self.buffer.file_format = format;
+        self.editor_open(filename);
+        self.editor_goto(row, column);
+        self.buffer.cursor_x = floor_char_boundary(
+            self.buffer
+                .rows
+                .get(self.buffer.cursor_y)
+                .map_or("", |row| &row.chars),
+            self.buffer.cursor_x,
+        );
+        (self.buffer.row_offset, self.buffer.column_offset) = (row_offset, column_offset);
+    }
+
+    /// `reopen [utf8|latin1|utf16le|utf16be] [lf|cr|keepcr] [ro|rw]`: reads
+    /// the file again with these choices, offering them when none are given
+    pub fn editor_reopen(&mut self, args: &str) {
+        let filename = match &self.buffer.filename {
+            Some(filename) => filename.clone(),
+            None => {
+                self.editor_set_status_message("No file to reopen");
+                return;
+            }
+        };
+
+        let mut format = FileFormat {
+            read_failed: false,
+            ..self.buffer.file_format
+        };
+        if args.is_empty() {
+            let lines: Vec<OverlayLine> = CHOICES
+                .iter()
+                .map(|(name, description)| OverlayLine::new(format!("{:8} {}", name, description)))
+                .collect();
+            match self.editor_show_overlay("Reopen with", &lines) {
+                Some(selected) => format.apply(CHOICES[selected].0),
+                None => return,
+            };
+        } else {
+            for choice in args.split_whitespace() {
+                if !format.apply(&choice.to_lowercase()) {
+                    self.editor_set_error_message(&format!(
+                        "Unknown choice '{}' (utf8 latin1 utf16le utf16be lf cr keepcr ro rw)",
+                        choice
+                    ));
+                    return;
+                }
+            }
+        }
+
+        if self.buffer.dirty != 0
+            && !self.editor_confirm("Buffer has unsaved changes. Discard them? (y/n)")
+        {
+            self.editor_set_status_message("Reopen cancelled");
+            return;
+        }
+        self.editor_read_again(&filename, format);
+        if self.buffer.filename.is_some() {
+            let status = self.editor_file_format_status();
+            self.editor_set_status_message(&format!(
+                "Reopened {} {}",
+                filename,
+                status.unwrap_or_default()
+            ));
+        }
+    }
+}
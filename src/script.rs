@@ -0,0 +1,211 @@
+//! Batch mode: `kilo --script 'strip; retab 4' FILE...` runs commands on each
+//! file without a terminal and saves the result, `--cat` prints it to stdout
+//! instead. Also the whole-buffer commands scripts are mostly made of:
+//! `replace`, `strip` and `retab`.
+
+use std::path::Path;
+
+use crate::*;
+
+/// What to do in batch mode, from the command line
+#[derive(Default)]
+pub struct Script {
+    pub commands: Vec<String>,
+    /// Print the result instead of saving it
+    pub cat: bool,
+}
+
+impl Script {
+    /// Adds the `;` separated commands of a `--script` argument, `\;` is a
+    /// literal semicolon
+    pub fn push(&mut self, commands: &str) {
+        let mut command = String::new();
+        let mut chars = commands.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if chars.peek() == Some(&';') => command.push(chars.next().unwrap()),
+                ';' => self.commands.push(std::mem::take(&mut command)),
+                c => command.push(c),
+            }
+        }
+        self.commands.push(command);
+        self.commands.retain(|command| !command.trim().is_empty());
+    }
+
+    /// Runs the script on each of the files named in `args`, or on `piped`
+    /// text, and exits
+    pub fn run(&self, args: &Args, piped: Option<String>) -> ! {
+        if args.filenames.is_empty() && piped.is_none() {
+            eprintln!("Error: no file to run the script on");
+            process::exit(2);
+        }
+        if piped.is_some() && !self.cat {
+            eprintln!("Error: piped text can only be printed (--cat)");
+            process::exit(2);
+        }
+
+        let mut failed = false;
+        match piped {
+            Some(contents) => {
+                let mut editor = self.editor(args);
+                editor.editor_open_piped(&contents);
+                failed |= !self.run_on(&mut editor, "-");
+            }
+            None => {
+                for filename in &args.filenames {
+                    if !Path::new(filename).exists() {
+                        eprintln!("{}: no such file", filename);
+                        failed = true;
+                        continue;
+                    }
+                    let mut editor = self.editor(args);
+                    editor.editor_open(filename);
+                    failed |= !self.run_on(&mut editor, filename);
+                }
+            }
+        }
+        process::exit(failed as i32);
+    }
+
+    fn editor(&self, args: &Args) -> Editor {
+        let mut editor = Editor::new_headless(24, 80);
+        editor.editor_configure(args);
+        // Nobody is there to answer the question
+        editor.options.save_check = false;
+        editor
+    }
+
+    /// Runs the commands on the open file and saves or prints it. False if
+    /// it couldn't be read or written, or a command failed.
+    fn run_on(&self, editor: &mut Editor, name: &str) -> bool {
+        if let Some(message) = editor.editor_take_status_message() {
+            eprintln!("{}: {}", name, message);
+            if editor.buffer.file_format.read_failed {
+                return false;
+            }
+        }
+        let mut ok = true;
+        for command in &self.commands {
+            editor.status_is_error = false;
+            editor.editor_execute_command(command.trim());
+            ok &= !editor.status_is_error;
+            if let Some(message) = editor.editor_take_status_message() {
+                eprintln!("{}: {}: {}", name, command.trim(), message);
+            }
+        }
+
+        if self.cat {
+            let bytes = match editor.editor_bytes_to_save() {
+                Ok(bytes) => bytes,
+                Err(error) => {
+                    eprintln!("{}: {}", name, error);
+                    return false;
+                }
+            };
+            let mut stdout = io::stdout().lock();
+            return stdout
+                .write_all(&bytes)
+                .and_then(|_| stdout.flush())
+                .is_ok()
+                && ok;
+        }
+        if editor.buffer.dirty == 0 {
+            return ok;
+        }
+        editor.editor_save();
+        if let Some(message) = editor.editor_take_status_message() {
+            eprintln!("{}: {}", name, message);
+        }
+        editor.buffer.dirty == 0 && ok
+    }
+}
+
+/// `line` with its tabs expanded to spaces, `width` columns apart
+fn expand_tabs(line: &str, width: usize) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = width - column % width;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+    expanded
+}
+
+impl Editor {
+    /// The status message set since the last call, if any
+    fn editor_take_status_message(&mut self) -> Option<String> {
+        self.status_message
+            .take()
+            .filter(|message| !message.is_empty())
+    }
+
+    /// Replaces every row with `transform` of it as one undo step. Returns
+    /// the number of rows changed.
+    fn editor_transform_rows(&mut self, mut transform: impl FnMut(&str) -> String) -> usize {
+        let rows: Vec<String> = self
+            .buffer
+            .rows
+            .iter()
+            .map(|row| transform(&row.chars))
+            .collect();
+        let changed = rows
+            .iter()
+            .zip(&self.buffer.rows)
+            .filter(|(new, old)| **new != old.chars)
+            .count();
+        if changed > 0 {
+            self.editor_replace_rows(0, self.get_num_rows(), rows);
+        }
+        changed
+    }
+
+    /// `replace OLD NEW`: replaces every OLD in the buffer with NEW
+    pub fn editor_replace_all(&mut self, args: &str) {
+        let (old, new) = match args.split_once(char::is_whitespace) {
+            Some((old, new)) => (old, new.trim()),
+            None if !args.is_empty() => (args, ""),
+            None => {
+                self.editor_set_error_message("Usage: replace OLD NEW");
+                return;
+            }
+        };
+        let mut count = 0;
+        self.editor_transform_rows(|line| {
+            count += line.matches(old).count();
+            line.replace(old, new)
+        });
+        self.editor_set_status_message(&format!(
+            "Replaced {} occurrence{}",
+            count,
+            if count == 1 { "" } else { "s" }
+        ));
+    }
+
+    /// `strip`: removes trailing whitespace from every line
+    pub fn editor_strip_whitespace(&mut self) {
+        let count = self.editor_transform_rows(|line| line.trim_end().to_string());
+        self.editor_set_status_message(&format!("Stripped {} lines", count));
+    }
+
+    /// `retab [WIDTH]`: expands tabs to spaces, WIDTH columns apart
+    pub fn editor_retab(&mut self, args: &str) {
+        let width = match args {
+            "" => self.options.tab_stop,
+            args => match args.parse() {
+                Ok(width) if width > 0 => width,
+                _ => {
+                    self.editor_set_error_message("Usage: retab [WIDTH]");
+                    return;
+                }
+            },
+        };
+        let count = self.editor_transform_rows(|line| expand_tabs(line, width));
+        self.editor_set_status_message(&format!("Retabbed {} lines", count));
+    }
+}
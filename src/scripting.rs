@@ -0,0 +1,304 @@
+//! Rhai scripts, built with `--features scripting`: ~/.kilo.rhai runs at
+//! startup and `source FILE` runs another script. Scripts read and change the
+//! buffer through the functions registered below, and add commands and key
+//! bindings that call functions they define:
+//!
+//! ```text
+//! fn shout(args) { let row = cursor_row(); set_line(row, line(row).to_upper()); }
+//! fn stamp() { insert_line(cursor_row(), "// reviewed"); }
+//! register_command("shout", "shout");  // gets the command's arguments
+//! bind_key("ctrl-t", "stamp");
+//! ```
+//!
+//! Rows and columns count from 0, columns in chars. A script works on a copy
+//! of the buffer that is put back once it returns; commands it asks for with
+//! `command` run after that.
+
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+
+use crate::*;
+
+/// Operations a script may take before it's stopped, so a runaway loop can't
+/// hang the editor
+const MAX_OPERATIONS: u64 = 50_000_000;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// The part of the editor a running script sees, and what it asks for
+#[derive(Default)]
+struct ScriptState {
+    lines: Vec<String>,
+    lines_changed: bool,
+    /// Row and column (in chars) of the cursor
+    cursor: (usize, usize),
+    cursor_changed: bool,
+    filename: String,
+    status: Option<String>,
+    commands: Vec<String>,
+    registered: Vec<(String, String)>,
+    bindings: Vec<(Key, String)>,
+}
+
+impl ScriptState {
+    /// Index of line `row` for a script, at most `extra` past the last one
+    fn row(&self, row: i64, extra: usize) -> ScriptResult<usize> {
+        usize::try_from(row)
+            .ok()
+            .filter(|&row| row < self.lines.len() + extra)
+            .ok_or_else(|| format!("line {} out of range", row).into())
+    }
+}
+
+pub struct Scripting {
+    engine: Engine,
+    /// Functions of all scripts run so far
+    ast: AST,
+    state: Rc<RefCell<ScriptState>>,
+    /// Commands added with `register_command` and the functions they call
+    commands: HashMap<String, String>,
+    bindings: HashMap<Key, String>,
+}
+
+/// ~/.kilo.rhai
+fn init_script_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".kilo.rhai"))
+}
+
+impl Default for Scripting {
+    fn default() -> Self {
+        let state = Rc::new(RefCell::new(ScriptState::default()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let shared = state.clone();
+        engine.on_print(move |text| shared.borrow_mut().status = Some(text.to_string()));
+        let shared = state.clone();
+        engine.register_fn("line_count", move || shared.borrow().lines.len() as i64);
+        let shared = state.clone();
+        engine.register_fn("line", move |row: i64| -> ScriptResult<String> {
+            let state = shared.borrow();
+            Ok(state.lines[state.row(row, 0)?].clone())
+        });
+        let shared = state.clone();
+        engine.register_fn(
+            "set_line",
+            move |row: i64, text: &str| -> ScriptResult<()> {
+                let mut state = shared.borrow_mut();
+                let row = state.row(row, 0)?;
+                state.lines[row] = text.to_string();
+                state.lines_changed = true;
+                Ok(())
+            },
+        );
+        let shared = state.clone();
+        engine.register_fn(
+            "insert_line",
+            move |row: i64, text: &str| -> ScriptResult<()> {
+                let mut state = shared.borrow_mut();
+                let row = state.row(row, 1)?;
+                state.lines.insert(row, text.to_string());
+                state.lines_changed = true;
+                Ok(())
+            },
+        );
+        let shared = state.clone();
+        engine.register_fn("delete_line", move |row: i64| -> ScriptResult<()> {
+            let mut state = shared.borrow_mut();
+            let row = state.row(row, 0)?;
+            state.lines.remove(row);
+            state.lines_changed = true;
+            Ok(())
+        });
+        let shared = state.clone();
+        engine.register_fn("cursor_row", move || shared.borrow().cursor.0 as i64);
+        let shared = state.clone();
+        engine.register_fn("cursor_col", move || shared.borrow().cursor.1 as i64);
+        let shared = state.clone();
+        engine.register_fn("set_cursor", move |row: i64, column: i64| {
+            let mut state = shared.borrow_mut();
+            state.cursor = (row.max(0) as usize, column.max(0) as usize);
+            state.cursor_changed = true;
+        });
+        let shared = state.clone();
+        engine.register_fn("filename", move || shared.borrow().filename.clone());
+        let shared = state.clone();
+        engine.register_fn("status", move |text: &str| {
+            shared.borrow_mut().status = Some(text.to_string());
+        });
+        let shared = state.clone();
+        engine.register_fn("command", move |command: &str| {
+            shared.borrow_mut().commands.push(command.to_string());
+        });
+        let shared = state.clone();
+        engine.register_fn("register_command", move |name: &str, function: &str| {
+            let entry = (name.to_string(), function.to_string());
+            shared.borrow_mut().registered.push(entry);
+        });
+        let shared = state.clone();
+        engine.register_fn(
+            "bind_key",
+            move |key: &str, function: &str| -> ScriptResult<()> {
+                let key = Key::from_name(key).ok_or_else(|| format!("unknown key '{}'", key))?;
+                shared
+                    .borrow_mut()
+                    .bindings
+                    .push((key, function.to_string()));
+                Ok(())
+            },
+        );
+
+        Self {
+            engine,
+            ast: AST::empty(),
+            state,
+            commands: HashMap::new(),
+            bindings: HashMap::new(),
+        }
+    }
+}
+
+impl Editor {
+    /// Gives a script a copy of the buffer, runs `script` and puts back what
+    /// it changed. Errors end up in the status bar.
+    fn editor_with_script(&mut self, script: impl FnOnce(&Scripting) -> ScriptResult<()>) {
+        let cursor_column = self.buffer.rows.get(self.buffer.cursor_y).map_or(0, |row| {
+            row.chars[..floor_char_boundary(&row.chars, self.buffer.cursor_x)]
+                .chars()
+                .count()
+        });
+        *self.scripting.state.borrow_mut() = ScriptState {
+            lines: self
+                .buffer
+                .rows
+                .iter()
+                .map(|row| row.chars.clone())
+                .collect(),
+            cursor: (self.buffer.cursor_y, cursor_column),
+            filename: self.buffer.filename.clone().unwrap_or_default(),
+            ..ScriptState::default()
+        };
+
+        let result = script(&self.scripting);
+        let state = std::mem::take(&mut *self.scripting.state.borrow_mut());
+        if state.lines_changed {
+            // Only the rows between the first and last difference are replaced
+            let old: Vec<&str> = self
+                .buffer
+                .rows
+                .iter()
+                .map(|row| row.chars.as_str())
+                .collect();
+            let start = old
+                .iter()
+                .zip(&state.lines)
+                .take_while(|(old, new)| **old == new.as_str())
+                .count();
+            let common_end = old[start..]
+                .iter()
+                .rev()
+                .zip(state.lines[start..].iter().rev())
+                .take_while(|(old, new)| **old == new.as_str())
+                .count();
+            if start < old.len().max(state.lines.len()) {
+                let new_rows = state.lines[start..state.lines.len() - common_end].to_vec();
+                self.editor_replace_rows(start, old.len() - common_end - start, new_rows);
+            }
+        }
+        if state.cursor_changed {
+            let (row, column) = state.cursor;
+            self.editor_goto(row, 0);
+            if let Some(row) = self.buffer.rows.get(self.buffer.cursor_y) {
+                self.buffer.cursor_x = row
+                    .chars
+                    .char_indices()
+                    .nth(column)
+                    .map_or(row.len(), |(index, _)| index);
+            }
+        }
+        for (name, function) in state.registered {
+            self.scripting.commands.insert(name, function);
+        }
+        self.scripting.bindings.extend(state.bindings);
+
+        if let Some(status) = state.status {
+            self.editor_set_status_message(&status);
+        }
+        for command in state.commands {
+            self.editor_execute_command(&command);
+        }
+        if let Err(error) = result {
+            self.editor_set_error_message(&format!("Script error: {}", error));
+        }
+    }
+
+    /// Runs the script in `path` and keeps the functions it defines
+    fn editor_run_script_file(&mut self, path: PathBuf) {
+        let mut ast = match self.scripting.engine.compile_file(path) {
+            Ok(ast) => ast,
+            Err(error) => {
+                self.editor_set_error_message(&format!("Script error: {}", error));
+                return;
+            }
+        };
+        self.editor_with_script(|scripting| {
+            let mut scope = Scope::new();
+            scripting.engine.run_ast_with_scope(&mut scope, &ast)
+        });
+        // The statements ran once, later calls only need the functions
+        ast.clear_statements();
+        self.scripting.ast.combine(ast);
+    }
+
+    /// Runs ~/.kilo.rhai if there is one
+    pub fn editor_load_init_script(&mut self) {
+        if let Some(path) = init_script_path().filter(|path| path.exists()) {
+            self.editor_run_script_file(path);
+        }
+    }
+
+    /// `source FILE`: runs a Rhai script
+    pub fn editor_source_script(&mut self, args: &str) {
+        if args.is_empty() {
+            self.editor_set_error_message("Usage: source FILE");
+            return;
+        }
+        self.editor_run_script_file(PathBuf::from(args));
+    }
+
+    /// Calls script function `function` with `args`
+    fn editor_call_script(&mut self, function: &str, args: impl rhai::FuncArgs) {
+        self.editor_with_script(|scripting| {
+            let mut scope = Scope::new();
+            scripting
+                .engine
+                .call_fn::<Dynamic>(&mut scope, &scripting.ast, function, args)
+                .map(|_| ())
+        });
+    }
+
+    /// Runs a command added by a script. False if `name` isn't one.
+    pub fn editor_script_command(&mut self, name: &str, args: &str) -> bool {
+        match self.scripting.commands.get(name).cloned() {
+            Some(function) => {
+                self.editor_call_script(&function, (args.to_string(),));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Runs the script function bound to `key`. False if there is none.
+    pub fn editor_script_key(&mut self, key: Key) -> bool {
+        match self.scripting.bindings.get(&key).cloned() {
+            Some(function) => {
+                self.editor_call_script(&function, ());
+                true
+            }
+            None => false,
+        }
+    }
+}
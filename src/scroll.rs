@@ -0,0 +1,122 @@
+//! Viewport scrolling that moves `row_offset` independently of the cursor:
+//! Ctrl-E / Ctrl-Y by one line, Ctrl-D / Ctrl-U by half a screen. Without
+//! wrapping, lines going on past the edges of the screen are marked with `<`
+//! and `>`, and End stops at the right edge before going to the line's end.
+
+use crate::*;
+
+impl Editor {
+    /// Moves the row offset by one visible row. Returns false at either end.
+    fn editor_step_row_offset(&mut self, down: bool) -> bool {
+        let last_row = self.get_num_rows().saturating_sub(1);
+        let next = self.editor_next_visible_row(self.buffer.row_offset, !down);
+        match next {
+            Some(row) if row <= last_row => {
+                self.buffer.row_offset = row;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the cursor onto the screen after the viewport was scrolled,
+    /// respecting `scrolloff`
+    fn editor_keep_cursor_in_view(&mut self) {
+        let screen_lines = self.editor_screen_lines();
+        // A wrapped row cut off at the bottom of the screen doesn't count as visible
+        let file_rows: Vec<usize> = screen_lines
+            .iter()
+            .filter(|&&(_, _, first_segment)| first_segment)
+            .map(|&(file_row, _, _)| file_row)
+            .filter(|&file_row| {
+                screen_lines
+                    .iter()
+                    .filter(|line| line.0 == file_row)
+                    .count()
+                    == self.editor_row_screen_lines(file_row)
+            })
+            .collect();
+        let (first, last) = match (file_rows.first(), file_rows.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return,
+        };
+
+        let margin = self.editor_scroll_margin();
+        let at_end = last + 1 >= self.get_num_rows();
+        let top = if self.buffer.row_offset == 0 {
+            first
+        } else {
+            file_rows[margin.min(file_rows.len() - 1)]
+        };
+        let bottom = if at_end {
+            last
+        } else {
+            file_rows[file_rows.len().saturating_sub(margin + 1)]
+        };
+
+        if self.buffer.cursor_y < top {
+            self.buffer.cursor_y = top;
+        } else if self.buffer.cursor_y > bottom.max(top) {
+            self.buffer.cursor_y = bottom.max(top);
+        }
+        self.buffer.cursor_x = match self.buffer.rows.get(self.buffer.cursor_y) {
+            Some(row) => floor_char_boundary(&row.chars, self.buffer.cursor_x.min(row.len())),
+            None => 0,
+        };
+    }
+
+    /// Ctrl-E (down) / Ctrl-Y (up): scrolls the view one line, the cursor only
+    /// moves if it would leave the screen
+    pub fn editor_scroll_line(&mut self, down: bool) {
+        if self.editor_step_row_offset(down) {
+            self.editor_keep_cursor_in_view();
+        }
+    }
+
+    /// Ctrl-D (down) / Ctrl-U (up): scrolls the view and the cursor by half a
+    /// screen, so the cursor keeps its place on the screen
+    pub fn editor_scroll_half_page(&mut self, down: bool) {
+        let amount = (self.screen_num_rows / 2).max(1);
+        let key = if down { Key::Down } else { Key::Up };
+        for _ in 0..amount {
+            // The view stops when the end of the buffer is in sight, the cursor keeps going
+            let last_visible = self.buffer.row_offset + self.screen_num_rows >= self.get_num_rows();
+            if !(down && last_visible) {
+                self.editor_step_row_offset(down);
+            }
+            self.editor_move_cursor(key);
+        }
+        self.editor_keep_cursor_in_view();
+    }
+
+    /// Draws `<` over the first column of text on `line` if `file_row` has
+    /// text left of the screen, and `>` over the last if it goes on past it
+    pub fn editor_draw_scroll_markers(&self, line: &mut FrameLine, file_row: usize) {
+        if self.editor_uses_layout() {
+            return;
+        }
+        let width = self.text_num_columns();
+        let text_start = self.left_margin() + self.gutter_width();
+        let render_width = self.buffer.rows[file_row].render_columns(self.options.tab_stop);
+        let style = CellStyle {
+            dim: true,
+            ..CellStyle::default()
+        };
+        if self.buffer.column_offset > 0 && render_width > 0 {
+            line.overlay(text_start, "<", &[style]);
+        }
+        if width > 1 && render_width > self.buffer.column_offset + width {
+            line.overlay(text_start + width - 1, ">", &[style]);
+        }
+    }
+
+    /// Where End stops first on a line going on past the right edge of the
+    /// screen: the last column shown. None if the cursor is there already.
+    pub fn editor_end_of_visible_line(&self) -> Option<usize> {
+        let row = self.buffer.rows.get(self.buffer.cursor_y)?;
+        let last_shown = self.buffer.column_offset + self.text_num_columns().checked_sub(1)?;
+        let render_x = row.cursor_x_to_render_cursor_x(self.options.tab_stop, self.buffer.cursor_x);
+        (row.render_columns(self.options.tab_stop) > last_shown + 1 && render_x < last_shown)
+            .then(|| row.render_cursor_x_to_cursor_x(self.options.tab_stop, last_shown))
+    }
+}
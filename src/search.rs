@@ -0,0 +1,282 @@
+//! Search: Ctrl-F searches as you type and highlights every match, Alt-n and
+//! Alt-N keep moving between matches after the prompt is closed, Escape
+//! clears the highlights. Up/Down in the prompt recall earlier searches.
+//! The `*` and `#` commands search for the identifier under the cursor.
+
+use crate::completion::is_word_char;
+use crate::*;
+
+#[derive(Default, Clone)]
+pub struct Search {
+    /// Last searched text, used by next/previous match
+    query: String,
+    /// Matches of `query` are highlighted
+    highlight: bool,
+    /// Only match `query` as a whole word, with its case
+    whole_word: bool,
+}
+
+/// Matches in all rows by rows, query and whole word
+pub type SearchMatches = RowsCache<(RowsKey, String, bool), Vec<(usize, usize)>>;
+
+impl Search {
+    fn matches(&self, text: &str) -> Vec<(usize, usize)> {
+        if !self.whole_word {
+            return find_matches(text, &self.query);
+        }
+        text.match_indices(&self.query)
+            .map(|(at, query)| (at, at + query.len()))
+            .filter(|&(start, end)| {
+                !text[..start].chars().next_back().is_some_and(is_word_char)
+                    && !text[end..].chars().next().is_some_and(is_word_char)
+            })
+            .collect()
+    }
+}
+
+/// Byte range of the identifier at byte `at` of `line`, or else the next one
+/// on the line
+fn word_at(line: &str, at: usize) -> Option<(usize, usize)> {
+    let at = floor_char_boundary(line, at);
+    let start = match line[at..].chars().next() {
+        Some(c) if is_word_char(c) => line[..at]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_word_char(c))
+            .last()
+            .map_or(at, |(i, _)| i),
+        _ => at + line[at..].find(is_word_char)?,
+    };
+    let end = line[start..]
+        .find(|c: char| !is_word_char(c))
+        .map_or(line.len(), |len| start + len);
+    Some((start, end))
+}
+
+/// Byte ranges of the matches of `query` in `text`. Case is ignored unless
+/// the query contains an uppercase letter.
+pub fn find_matches(text: &str, query: &str) -> Vec<(usize, usize)> {
+    if query.is_empty() {
+        return vec![];
+    }
+    let matches: Vec<usize> = if query.chars().any(|c| c.is_uppercase()) {
+        text.match_indices(query).map(|(at, _)| at).collect()
+    } else {
+        // ASCII lowercasing keeps the byte offsets of the original text
+        text.to_ascii_lowercase()
+            .match_indices(&query.to_ascii_lowercase())
+            .map(|(at, _)| at)
+            .collect()
+    };
+    matches
+        .into_iter()
+        .map(|at| (at, at + query.len()))
+        .collect()
+}
+
+impl Editor {
+    /// Highlighted matches in `file_row`, as byte ranges of its chars
+    pub fn editor_search_row_matches(&self, file_row: usize) -> Vec<(usize, usize)> {
+        if !self.buffer.search.highlight {
+            return vec![];
+        }
+        self.buffer
+            .search
+            .matches(&self.buffer.rows[file_row].chars)
+    }
+
+    /// Start (row, column) of every match in the visible rows, in order. The
+    /// matches in all rows are kept until the rows or the query change.
+    fn editor_search_matches(&self) -> Vec<(usize, usize)> {
+        let key = (
+            self.editor_rows_key(),
+            self.buffer.search.query.clone(),
+            self.buffer.search.whole_word,
+        );
+        let matches = self.search_matches.get(key, || {
+            self.buffer
+                .rows
+                .iter()
+                .enumerate()
+                .flat_map(|(row, text)| {
+                    self.buffer
+                        .search
+                        .matches(&text.chars)
+                        .into_iter()
+                        .map(move |(start, _)| (row, start))
+                })
+                .collect()
+        });
+        matches
+            .into_iter()
+            .filter(|&(row, _)| self.editor_row_visible(row))
+            .collect()
+    }
+
+    /// Status bar tag while matches are highlighted, "match 3 of 17" when the
+    /// cursor is on one
+    pub fn editor_search_status(&self) -> Option<String> {
+        if !self.buffer.search.highlight || self.buffer.search.query.is_empty() {
+            return None;
+        }
+        let matches = self.editor_search_matches();
+        let cursor = (self.buffer.cursor_y, self.buffer.cursor_x);
+        Some(match matches.iter().position(|&at| at == cursor) {
+            Some(index) => format!("[match {} of {}]", index + 1, matches.len()),
+            None if matches.len() == 1 => "[1 match]".to_string(),
+            None => format!("[{} matches]", matches.len()),
+        })
+    }
+
+    /// Moves to the next match after (or at, if `inclusive`) `from`, or the
+    /// previous one before it, wrapping around the buffer. Returns whether it
+    /// wrapped, None without matches.
+    fn editor_search_jump(
+        &mut self,
+        from: (usize, usize),
+        forward: bool,
+        inclusive: bool,
+    ) -> Option<bool> {
+        let matches = self.editor_search_matches();
+        let found = if forward {
+            matches
+                .iter()
+                .find(|&&at| at > from || inclusive && at == from)
+                .map(|&at| (at, false))
+                .or_else(|| matches.first().map(|&at| (at, true)))
+        } else {
+            matches
+                .iter()
+                .rev()
+                .find(|&&at| at < from)
+                .map(|&at| (at, false))
+                .or_else(|| matches.last().map(|&at| (at, true)))
+        };
+
+        let ((row, column), wrapped) = found?;
+        self.editor_goto(row, column);
+        Some(wrapped)
+    }
+
+    /// Ctrl-F: incremental search, arrows move between matches while typing.
+    /// Enter keeps the cursor on the match, Escape goes back.
+    pub fn editor_find(&mut self) {
+        let saved_cursor = (self.buffer.cursor_y, self.buffer.cursor_x);
+        let saved_offsets = (self.buffer.row_offset, self.buffer.column_offset);
+        let saved_search = self.buffer.search.clone();
+        let mut query = String::new();
+        let mut browser = HistoryBrowser::new(HistoryKind::Search);
+        self.buffer.search.highlight = true;
+        self.buffer.search.whole_word = false;
+
+        loop {
+            self.buffer.search.query = query.clone();
+            self.editor_set_status_message(&format!(
+                "Search: {} (ESC to cancel, Left/Right for matches, Up/Down for history)",
+                query
+            ));
+            self.editor_refresh_screen();
+
+            let key = self.editor_read_key();
+            let cursor = (self.buffer.cursor_y, self.buffer.cursor_x);
+            match key {
+                Key::Escape => {
+                    (self.buffer.cursor_y, self.buffer.cursor_x) = saved_cursor;
+                    (self.buffer.row_offset, self.buffer.column_offset) = saved_offsets;
+                    self.buffer.search = saved_search;
+                    self.editor_set_status_message("");
+                    return;
+                }
+                Key::Enter => {
+                    if query.is_empty() {
+                        self.buffer.search = saved_search;
+                    } else {
+                        add_history_entry(HistoryKind::Search, &query);
+                    }
+                    self.editor_set_status_message("");
+                    return;
+                }
+                Key::Right | Key::Alt('n') => {
+                    self.editor_search_jump(cursor, true, false);
+                    continue;
+                }
+                Key::Left | Key::Alt('N') => {
+                    self.editor_search_jump(cursor, false, false);
+                    continue;
+                }
+                Key::Up | Key::Down => match browser.step(key == Key::Up, &query) {
+                    Some(entry) => query = entry,
+                    None => continue,
+                },
+                Key::Backspace | Key::Delete | Key::Ctrl('h') => {
+                    query.pop();
+                }
+                Key::Char(c) => {
+                    query.push(c);
+                }
+                _ => continue,
+            }
+
+            // The query changed, look again from where the search started
+            self.buffer.search.query = query.clone();
+            if self.editor_search_jump(saved_cursor, true, true).is_none() {
+                (self.buffer.cursor_y, self.buffer.cursor_x) = saved_cursor;
+            }
+        }
+    }
+
+    /// Alt-n / Alt-N: next or previous match of the last search, highlighting
+    /// the matches again if they were cleared
+    pub fn editor_search_next(&mut self, forward: bool) {
+        if self.buffer.search.query.is_empty() {
+            self.editor_set_status_message("No previous search (Ctrl-F to search)");
+            return;
+        }
+        self.buffer.search.highlight = true;
+
+        let cursor = (self.buffer.cursor_y, self.buffer.cursor_x);
+        match self.editor_search_jump(cursor, forward, false) {
+            None => {
+                let message = format!("Not found: {}", self.buffer.search.query);
+                self.editor_set_status_message(&message);
+            }
+            Some(true) => self.editor_set_status_message(if forward {
+                "Search wrapped to the top"
+            } else {
+                "Search wrapped to the bottom"
+            }),
+            Some(false) => {}
+        }
+    }
+
+    /// `*` / `#`: searches forward or backward for the identifier under the
+    /// cursor as a whole word
+    pub fn editor_search_word(&mut self, forward: bool) {
+        let word = self
+            .buffer
+            .rows
+            .get(self.buffer.cursor_y)
+            .and_then(|row| word_at(&row.chars, self.buffer.cursor_x));
+        let (start, end) = match word {
+            Some(word) => word,
+            None => {
+                self.editor_set_status_message("No identifier under the cursor");
+                return;
+            }
+        };
+
+        self.buffer.search = Search {
+            query: self.buffer.rows[self.buffer.cursor_y].chars[start..end].to_string(),
+            highlight: true,
+            whole_word: true,
+        };
+        // Searching from the word's start skips the word itself either way
+        self.buffer.cursor_x = start;
+        self.editor_search_next(forward);
+    }
+
+    /// Escape: stops highlighting matches, the query is kept for Alt-n
+    pub fn editor_search_clear(&mut self) {
+        self.buffer.search.highlight = false;
+    }
+}
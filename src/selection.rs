@@ -0,0 +1,87 @@
+//! Mark based selection (Ctrl-Space sets the mark, the region runs to the cursor)
+
+use crate::*;
+
+impl Editor {
+    pub fn editor_toggle_mark(&mut self) {
+        if self.buffer.mark.take().is_some() {
+            self.editor_set_status_message("Mark cleared");
+        } else {
+            self.buffer.mark = Some((self.buffer.cursor_x, self.buffer.cursor_y));
+            self.editor_set_status_message("Mark set");
+        }
+    }
+
+    /// Shift with a movement key: moves the cursor, selecting from where the
+    /// cursor was unless a selection is already under way
+    pub fn editor_extend_selection(&mut self, key: Key) {
+        if self.buffer.mark.is_none() {
+            self.buffer.mark = Some((self.buffer.cursor_x, self.buffer.cursor_y));
+        }
+        self.editor_move_cursor(key);
+    }
+
+    /// The selected region as ordered ((x, y), (x, y)) positions, end exclusive
+    pub fn editor_selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        let (mark_x, mark_y) = self.buffer.mark?;
+        let mark_y = mark_y.min(self.get_num_rows());
+        let mark = (mark_x, mark_y);
+        let cursor = (self.buffer.cursor_x, self.buffer.cursor_y);
+
+        // Compare by row first, then column
+        let (start, end) = if (mark.1, mark.0) <= (cursor.1, cursor.0) {
+            (mark, cursor)
+        } else {
+            (cursor, mark)
+        };
+
+        Some((start, end))
+    }
+
+    /// Rows covered by the selection (start, count), or the whole buffer if there
+    /// is no selection
+    pub fn editor_selected_rows(&self) -> (usize, usize) {
+        match self.editor_selection() {
+            Some(((_, start_y), (end_x, end_y))) => {
+                // A selection ending at column 0 does not include that row
+                let end_y = if end_x == 0 && end_y > start_y {
+                    end_y
+                } else {
+                    end_y + 1
+                };
+                let end_y = end_y.min(self.get_num_rows());
+                (start_y, end_y.saturating_sub(start_y))
+            }
+            None => {
+                // Leave out the empty row left by the file's final newline
+                let mut num_rows = self.get_num_rows();
+                if num_rows > 0 && self.buffer.rows[num_rows - 1].chars.is_empty() {
+                    num_rows -= 1;
+                }
+                (0, num_rows)
+            }
+        }
+    }
+
+    /// Render column range of `row` that is selected, if any
+    pub fn editor_selected_render_columns(&self, row: usize) -> Option<(usize, usize)> {
+        let ((start_x, start_y), (end_x, end_y)) = self.editor_selection()?;
+        if row < start_y || row > end_y || row >= self.get_num_rows() {
+            return None;
+        }
+
+        let editor_row = &self.buffer.rows[row];
+        let start = if row == start_y {
+            editor_row.cursor_x_to_render_cursor_x(self.options.tab_stop, start_x)
+        } else {
+            0
+        };
+        let end = if row == end_y {
+            editor_row.cursor_x_to_render_cursor_x(self.options.tab_stop, end_x)
+        } else {
+            editor_row.render_columns(self.options.tab_stop)
+        };
+
+        Some((start, end))
+    }
+}
@@ -0,0 +1,130 @@
+//! The files open when the editor last quit and where the cursor was in each,
+//! kept in the state directory. `kilo --session` opens them again.
+
+use std::path::{Path, PathBuf};
+
+use crate::storage::{read_atomic, state_dir, write_atomic};
+use crate::*;
+
+const SESSION_HEADER: &str = "kilo-session 1";
+
+fn session_path() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("session"))
+}
+
+/// The files of the session saved at `path` as file name, row and column,
+/// and the index of the one that was shown
+fn read_session(path: &Path) -> (Vec<(String, usize, usize)>, usize) {
+    read_atomic(path, SESSION_HEADER).map_or((vec![], 0), |contents| parse_session(&contents))
+}
+
+/// Session file contents after the header: the shown index, then a line
+/// `ROW COLUMN PATH` per file
+fn parse_session(contents: &str) -> (Vec<(String, usize, usize)>, usize) {
+    let mut lines = contents.lines();
+    let shown = lines.next().and_then(|line| line.parse().ok()).unwrap_or(0);
+    let files = lines
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ' ');
+            let row = fields.next()?.parse().ok()?;
+            let column = fields.next()?.parse().ok()?;
+            Some((fields.next()?.to_string(), row, column))
+        })
+        .collect();
+    (files, shown)
+}
+
+/// What `parse_session` reads back as `files` and `shown`. Paths with a line
+/// break are left out.
+fn session_contents(files: &[(String, usize, usize)], shown: usize) -> String {
+    let mut contents = format!("{}\n", shown);
+    for (path, row, column) in files {
+        if !path.contains('\n') {
+            contents.push_str(&format!("{} {} {}\n", row, column, path));
+        }
+    }
+    contents
+}
+
+impl Editor {
+    /// Remembers the open files for `--session`, on quitting. Quitting
+    /// without any keeps the last session.
+    pub fn editor_write_session(&self) {
+        let path = match session_path() {
+            Some(path) => path,
+            None => return,
+        };
+        let (positions, shown) = self.editor_buffer_positions();
+        if positions.is_empty() {
+            return;
+        }
+
+        let files: Vec<(String, usize, usize)> = positions
+            .into_iter()
+            .map(|(filename, row, column)| {
+                let absolute = absolute_path(Path::new(&filename));
+                (absolute.to_string_lossy().into_owned(), row, column)
+            })
+            .collect();
+        let contents = format!("{}\n{}", SESSION_HEADER, session_contents(&files, shown));
+        // Like the recent files, a failed write shouldn't stop the quit
+        let _ = write_atomic(&path, contents);
+    }
+
+    /// `--session`: opens the files of the last session, one buffer each.
+    /// False if there was none.
+    pub fn editor_restore_session(&mut self) -> bool {
+        let (files, shown) = match session_path() {
+            Some(path) => read_session(&path),
+            None => return false,
+        };
+        if files.is_empty() {
+            return false;
+        }
+
+        for (index, (filename, row, column)) in files.iter().enumerate() {
+            if index == 0 {
+                self.editor_open(filename);
+            } else {
+                self.editor_add_buffer(filename);
+            }
+            self.editor_goto(*row, *column);
+        }
+        self.editor_select_buffer(shown.min(files.len() - 1));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_the_written_session() {
+        let path = std::env::temp_dir().join(format!("kilo-session-test-{}", std::process::id()));
+        let files = vec![
+            ("/tmp/a file.txt".to_string(), 3, 7),
+            ("/tmp/b.rs".to_string(), 0, 0),
+        ];
+        let contents = format!("{}\n{}", SESSION_HEADER, session_contents(&files, 1));
+        write_atomic(&path, contents).unwrap();
+        assert_eq!(read_session(&path), (files, 1));
+
+        // Another format, or no file at all, is no session
+        std::fs::write(&path, "kilo-session 2\n0\n1 2 /a\n").unwrap();
+        assert_eq!(read_session(&path), (vec![], 0));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read_session(&path), (vec![], 0));
+    }
+
+    #[test]
+    fn skips_lines_it_cannot_read() {
+        let (files, shown) = parse_session("x\n1 2 /a\nbad line\n4 /b\n5 6 /c\n");
+        assert_eq!(
+            files,
+            vec![("/a".to_string(), 1, 2), ("/c".to_string(), 5, 6)]
+        );
+        assert_eq!(shown, 0);
+        assert_eq!(parse_session(""), (vec![], 0));
+    }
+}
@@ -0,0 +1,174 @@
+//! Running shell commands: `!cmd`, `r!cmd` and `|cmd` in the command prompt
+
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::thread;
+
+use crate::overlay::OverlayLine;
+use crate::*;
+
+/// `s` as one word for `sh`, whatever it contains
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Runs `command` with `sh -c`, feeding `input` to its stdin
+fn run_shell(command: &str, input: Option<String>) -> io::Result<Output> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Write from another thread so a large input can't deadlock against a full stdout pipe
+    let writer = match (child.stdin.take(), input) {
+        (Some(mut stdin), Some(input)) => {
+            Some(thread::spawn(move || stdin.write_all(input.as_bytes())))
+        }
+        _ => None,
+    };
+
+    let output = child.wait_with_output()?;
+    if let Some(writer) = writer {
+        // A filter is allowed to exit without reading all of its input
+        let _ = writer.join();
+    }
+
+    Ok(output)
+}
+
+/// Command output as text without the final newline
+fn output_text(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    text.strip_suffix('\n').unwrap_or(&text).to_string()
+}
+
+impl Editor {
+    /// Runs a shell command with the terminal restored to its normal mode
+    pub fn editor_run_shell(&mut self, command: &str, input: Option<String>) -> Option<Output> {
+        self.disable_terminal();
+        let result = run_shell(command, input);
+        self.enable_raw_mode();
+
+        match result {
+            Ok(output) => Some(output),
+            Err(error) => {
+                self.editor_set_error_message(&format!("Could not run '{}': {}", command, error));
+                None
+            }
+        }
+    }
+
+    /// Runs an interactive program that takes over the terminal until it exits
+    pub fn editor_run_interactive(&mut self, command: &str) -> Option<ExitStatus> {
+        self.write_to_stdout("\x1b[2J\x1b[H");
+        self.flush_stdout();
+        self.disable_terminal();
+        let result = Command::new("sh").arg("-c").arg(command).status();
+        self.enable_raw_mode();
+        self.get_dimensions();
+
+        match result {
+            Ok(status) => Some(status),
+            Err(error) => {
+                self.editor_set_error_message(&format!("Could not run '{}': {}", command, error));
+                None
+            }
+        }
+    }
+
+    /// `!cmd`: shows the output of `cmd` in an overlay
+    pub fn editor_shell_command(&mut self, command: &str) {
+        let output = match self.editor_run_shell(command, None) {
+            Some(output) => output,
+            None => return,
+        };
+
+        let mut lines: Vec<OverlayLine> = output_text(&output.stdout)
+            .lines()
+            .map(|line| OverlayLine::new(line.to_string()))
+            .collect();
+        let stderr = output_text(&output.stderr);
+        lines.extend(
+            stderr
+                .lines()
+                .map(|line| OverlayLine::colored(line.to_string(), Color::RED)),
+        );
+        if !output.status.success() {
+            lines.push(OverlayLine::colored(
+                format!("[{}]", output.status),
+                Color::YELLOW,
+            ));
+        }
+
+        self.editor_show_overlay(&format!("!{}", command), &lines);
+    }
+
+    /// `r!cmd`: inserts the output of `cmd` at the cursor
+    pub fn editor_shell_read(&mut self, command: &str) {
+        let output = match self.editor_run_shell(command, None) {
+            Some(output) => output,
+            None => return,
+        };
+
+        if !output.status.success() {
+            self.editor_set_error_message(&format!(
+                "'{}' failed: {}",
+                command,
+                output_text(&output.stderr)
+            ));
+            return;
+        }
+
+        self.editor_insert_text(&output_text(&output.stdout));
+    }
+
+    /// Pipes rows `start..start + count` through `command` and replaces them
+    /// with its output as one undo step. Returns the new number of rows, or
+    /// None (with a status message) if the command failed.
+    pub fn editor_pipe_rows(&mut self, start: usize, count: usize, command: &str) -> Option<usize> {
+        let mut input: String = self.buffer.rows[start..start + count]
+            .iter()
+            .map(|row| row.chars.as_str())
+            .collect::<Vec<&str>>()
+            .join("\n");
+        input.push('\n');
+
+        let output = self.editor_run_shell(command, Some(input))?;
+        if !output.status.success() {
+            self.editor_set_error_message(&format!(
+                "'{}' failed: {}",
+                command,
+                output_text(&output.stderr)
+            ));
+            return None;
+        }
+
+        let new_rows: Vec<String> = output_text(&output.stdout)
+            .split('\n')
+            .map(str::to_string)
+            .collect();
+        let new_count = new_rows.len();
+        self.editor_replace_rows(start, count, new_rows);
+        self.buffer.mark = None;
+
+        Some(new_count)
+    }
+
+    /// `|cmd`: replaces the selected rows (or the whole buffer) with their
+    /// output when piped through `cmd`
+    pub fn editor_shell_filter(&mut self, command: &str) {
+        let (start, count) = self.editor_selected_rows();
+        if let Some(new_count) = self.editor_pipe_rows(start, count, command) {
+            self.editor_set_status_message(&format!(
+                "Filtered {} lines through '{}' ({} lines now)",
+                count, command, new_count
+            ));
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Slow terminal detection. When writing frames backs up (a high latency SSH
+//! connection) only the changed lines are redrawn and cosmetic extras (cursor
+//! line/column, idle redraws) are turned off. `set slowterm on|off|auto`
+//! overrides detection.
+
+use std::time::Duration;
+
+use crate::frame::frame_to_ansi_changes;
+use crate::*;
+
+/// Smoothed frame write time above which the terminal counts as slow
+const SLOW_FRAME_TIME: Duration = Duration::from_millis(40);
+/// ... and below which it counts as fast again
+const FAST_FRAME_TIME: Duration = Duration::from_millis(10);
+/// Weight of the latest frame in the smoothed write time
+const SMOOTHING: f64 = 0.3;
+
+#[derive(Default)]
+pub struct SlowTerminal {
+    /// Smoothed time taken to write a frame, in seconds
+    frame_time: f64,
+    detected: bool,
+    /// Last frame written, the base for partial redraws. None when the screen
+    /// may no longer show it.
+    last_frame: Option<Frame>,
+}
+
+impl Editor {
+    pub fn editor_slow_terminal(&self) -> bool {
+        match self.options.slow_terminal.as_str() {
+            "on" => true,
+            "off" => false,
+            _ => self.slow_terminal.detected,
+        }
+    }
+
+    /// Status bar tag while in slow terminal mode
+    pub fn editor_slow_terminal_status(&self) -> Option<String> {
+        self.editor_slow_terminal()
+            .then(|| "[slow terminal]".to_string())
+    }
+
+    /// Escape sequences drawing `frame`, only its changed lines on a slow
+    /// terminal
+    pub fn editor_frame_output(&mut self, frame: &Frame) -> String {
+        let depth = self.editor_color_depth();
+        let output = match &self.slow_terminal.last_frame {
+            Some(last) if self.editor_slow_terminal() && last.lines.len() == frame.lines.len() => {
+                frame_to_ansi_changes(last, frame, depth)
+            }
+            _ => frame_to_ansi(frame, depth),
+        };
+        self.slow_terminal.last_frame = Some(frame.clone());
+        output
+    }
+
+    /// The next frame is drawn in full, the screen was cleared or resized
+    pub fn editor_invalidate_screen(&mut self) {
+        self.slow_terminal.last_frame = None;
+    }
+
+    /// Updates the detection with the time the last frame took to write
+    pub fn editor_record_frame_time(&mut self, elapsed: Duration) {
+        let monitor = &mut self.slow_terminal;
+        monitor.frame_time =
+            SMOOTHING * elapsed.as_secs_f64() + (1.0 - SMOOTHING) * monitor.frame_time;
+
+        if !monitor.detected && monitor.frame_time > SLOW_FRAME_TIME.as_secs_f64() {
+            monitor.detected = true;
+        } else if monitor.detected && monitor.frame_time < FAST_FRAME_TIME.as_secs_f64() {
+            monitor.detected = false;
+        }
+    }
+}
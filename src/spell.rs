@@ -0,0 +1,721 @@
+//! Spell checking of prose and comments against a word list, or against the
+//! dictionaries of several languages at once. The languages come from a
+//! modeline (`kilo: spelllang=en_US,de_DE`), then `spelllang EXTENSION LANGS`
+//! for the file type, then the `spelllang` option.
+//!
+//! In source files only comments and strings are checked, with identifiers
+//! split into their camelCase and snake_case parts. Words of the project are
+//! accepted from a `.kilo_dictionary` file in the file's directory or one
+//! above it, meant to be committed with the project (`spellgood!` adds to it,
+//! starting one at the root of the file's git repository if there is none).
+
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+
+use crate::syntax::syntax_for;
+use crate::*;
+
+/// Word lists tried when the `spellfile` option is empty
+const SYSTEM_WORD_LISTS: [&str; 4] = [
+    "/usr/share/dict/words",
+    "/usr/share/dict/american-english",
+    "/usr/share/hunspell/en_US.dic",
+    "/usr/share/myspell/en_US.dic",
+];
+
+/// Directories searched for the dictionary of a language
+const DICTIONARY_DIRS: [&str; 3] = [
+    "/usr/share/hunspell",
+    "/usr/share/myspell",
+    "/usr/share/myspell/dicts",
+];
+
+/// Lines at the start and end of a file searched for a modeline
+const MODELINE_LINES: usize = 5;
+
+const MAX_SUGGESTIONS: usize = 10;
+
+/// Project word list, looked for from the file's directory upwards
+const PROJECT_DICTIONARY: &str = ".kilo_dictionary";
+
+#[derive(Default)]
+pub struct SpellChecker {
+    words: HashSet<String>,
+    /// Sources of `words` joined with ", ", None until a word list was loaded
+    loaded_from: Option<String>,
+    /// Languages by file extension, set with `spelllang EXTENSION LANGS`
+    filetype_languages: HashMap<String, String>,
+    /// Languages of the open file, from its modeline or `spelllang LANGS`
+    buffer_languages: Option<String>,
+    /// Words added with `spellgood`, also saved to ~/.kilo_words
+    personal: HashSet<String>,
+    cycle: Option<SuggestionCycle>,
+}
+
+/// Words of a file's project dictionary and the file they were read from,
+/// kept with each buffer
+#[derive(Default)]
+pub struct ProjectDictionary {
+    words: HashSet<String>,
+    path: Option<PathBuf>,
+}
+
+/// State of repeated "next suggestion" presses on one word
+struct SuggestionCycle {
+    row: usize,
+    start: usize,
+    original: String,
+    suggestions: Vec<String>,
+    index: usize,
+}
+
+fn personal_dictionary_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".kilo_words"))
+}
+
+/// Directory of `filename`, the working directory without one
+fn file_directory(filename: Option<&str>) -> PathBuf {
+    let dir = match filename.and_then(|filename| Path::new(filename).parent()) {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    dir.canonicalize().unwrap_or(dir)
+}
+
+/// Project dictionary for `filename`: the nearest one in its directory or
+/// above, None if there is none
+fn find_project_dictionary(filename: Option<&str>) -> Option<PathBuf> {
+    file_directory(filename)
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_DICTIONARY))
+        .find(|path| path.is_file())
+}
+
+/// Where a new project dictionary for `filename` goes: the root of its git
+/// repository, else its directory
+fn new_project_dictionary(filename: Option<&str>) -> PathBuf {
+    let dir = file_directory(filename);
+    let root = dir
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(&dir);
+    root.join(PROJECT_DICTIONARY)
+}
+
+/// Reads a plain word list or a hunspell .dic file (first line is the word
+/// count, affix flags follow a '/')
+fn read_word_list(path: &str) -> io::Result<HashSet<String>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .map(|line| line.split('/').next().unwrap_or("").trim())
+        .filter(|word| !word.is_empty() && !word.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_lowercase)
+        .collect())
+}
+
+/// Dictionary of `language` (en_US, de): a hunspell or myspell .dic file,
+/// falling back to the system word list for English. A language containing
+/// a `/` is a path.
+fn language_word_list(language: &str) -> Option<String> {
+    if language.contains('/') {
+        return Some(language.to_string());
+    }
+    let language = language.replace('-', "_");
+    for dir in DICTIONARY_DIRS {
+        let exact = Path::new(dir).join(format!("{}.dic", language));
+        if exact.exists() {
+            return Some(exact.to_string_lossy().into_owned());
+        }
+        // A bare language picks any of its regions, `de` finds de_DE.dic
+        let prefix = format!("{}_", language);
+        let mut regional: Vec<PathBuf> = std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension().is_some_and(|extension| extension == "dic")
+                    && path
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with(&prefix))
+            })
+            .collect();
+        regional.sort();
+        if let Some(path) = regional.first() {
+            return Some(path.to_string_lossy().into_owned());
+        }
+    }
+    if language == "en" || language.starts_with("en_") {
+        return SYSTEM_WORD_LISTS
+            .iter()
+            .find(|path| Path::new(path).exists())
+            .map(|path| path.to_string());
+    }
+    None
+}
+
+/// `spelllang=` value of a `kilo:` or `vim:` modeline in `line`
+fn modeline_languages(line: &str) -> Option<String> {
+    let at = line.find("kilo:").or_else(|| line.find("vim:"))?;
+    line[at..]
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .find_map(|setting| {
+            setting
+                .strip_prefix("spelllang=")
+                .or_else(|| setting.strip_prefix("spl="))
+        })
+        .map(str::to_string)
+}
+
+/// Line comment marker for source files, None for prose files where the
+/// whole line is checked
+pub fn comment_marker(extension: &str) -> Option<&'static str> {
+    syntax_for(extension)?.line_comment.as_deref()
+}
+
+/// Byte ranges of the words in `text`, starting at `offset`
+fn words(text: &str, offset: usize) -> Vec<(usize, usize)> {
+    let mut ranges = vec![];
+    let mut start = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        // Apostrophes are part of a word when followed by a letter (don't, it's)
+        let in_word = c.is_alphabetic()
+            || (c == '\''
+                && start.is_some()
+                && chars.peek().is_some_and(|(_, n)| n.is_alphabetic()));
+        match (in_word, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                ranges.push((offset + s, offset + i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        ranges.push((offset + s, offset + text.len()));
+    }
+    ranges
+}
+
+/// Words that look like identifiers or abbreviations are not checked
+fn is_checkable(word: &str) -> bool {
+    word.chars().count() > 1
+        && !word.chars().skip(1).any(char::is_uppercase)
+        && !word.contains(|c: char| c.is_ascii_digit() || c == '_')
+}
+
+/// Chars starting a string literal in source files with this extension
+fn string_quotes(extension: &str) -> &'static [char] {
+    syntax_for(extension).map_or(&['"'], |syntax| &syntax.quotes)
+}
+
+/// Byte ranges of the comments and strings on a `line` of source code
+fn code_text_ranges(line: &str, marker: &str, quotes: &[char]) -> Vec<(usize, usize)> {
+    // Block comments are only followed within a line, but the ` * ` lines in
+    // the middle of one are recognized
+    let block_comments = marker == "//";
+    let trimmed = line.trim_start();
+    if block_comments && (trimmed.starts_with("* ") || trimmed == "*") {
+        return vec![(line.len() - trimmed.len(), line.len())];
+    }
+
+    let mut ranges = vec![];
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let rest = &line[i..];
+        if rest.starts_with(marker) {
+            ranges.push((i + marker.len(), line.len()));
+            break;
+        }
+        if block_comments && rest.starts_with("/*") {
+            let end = rest[2..].find("*/").map_or(line.len(), |at| i + 2 + at);
+            ranges.push((i + 2, end));
+            while chars.next_if(|&(j, _)| j < end + 2).is_some() {}
+        } else if quotes.contains(&c) {
+            let mut end = line.len();
+            while let Some((j, next)) = chars.next() {
+                if next == '\\' {
+                    chars.next();
+                } else if next == c {
+                    end = j;
+                    break;
+                }
+            }
+            ranges.push((i + c.len_utf8(), end));
+        }
+    }
+    ranges
+}
+
+/// Splits the word at `start..end` of `text` into its camelCase parts:
+/// `parseHTTPRequest` into parse, HTTP and Request
+fn identifier_parts(text: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+    let chars: Vec<(usize, char)> = text[start..end].char_indices().collect();
+    let mut parts = vec![];
+    let mut part_start = start;
+    for k in 1..chars.len() {
+        let (at, c) = chars[k];
+        let previous = chars[k - 1].1;
+        let next_lower = chars.get(k + 1).is_some_and(|(_, n)| n.is_lowercase());
+        let boundary = c.is_uppercase()
+            && (previous.is_lowercase() || (previous.is_uppercase() && next_lower));
+        if boundary {
+            parts.push((part_start, start + at));
+            part_start = start + at;
+        }
+    }
+    parts.push((part_start, end));
+    parts
+}
+
+impl SpellChecker {
+    /// Approximate memory of the loaded words, and how many there are
+    pub fn memory(&self, project: &ProjectDictionary) -> (usize, usize) {
+        let words = self.words.len() + self.personal.len() + project.words.len();
+        let bytes = (self.words.iter())
+            .chain(&self.personal)
+            .chain(&project.words)
+            .map(|word| std::mem::size_of::<String>() + word.capacity())
+            .sum();
+        (bytes, words)
+    }
+
+    fn is_known(&self, word: &str, project: &ProjectDictionary) -> bool {
+        let lower = word.to_lowercase();
+        self.words.contains(&lower)
+            || self.personal.contains(&lower)
+            || project.words.contains(&lower)
+            || lower
+                .strip_suffix("'s")
+                .is_some_and(|stem| self.words.contains(stem))
+    }
+
+    /// Known words one edit away from `word`, keeping its capitalization
+    fn suggestions(&self, word: &str, project: &ProjectDictionary) -> Vec<String> {
+        let lower: Vec<char> = word.to_lowercase().chars().collect();
+        let mut candidates: Vec<String> = vec![];
+        let mut push = |candidate: Vec<char>| {
+            let candidate: String = candidate.into_iter().collect();
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        };
+
+        for i in 0..lower.len() {
+            // Transposition
+            if i + 1 < lower.len() {
+                let mut swapped = lower.clone();
+                swapped.swap(i, i + 1);
+                push(swapped);
+            }
+            // Deletion
+            let mut deleted = lower.clone();
+            deleted.remove(i);
+            push(deleted);
+            // Replacement
+            for c in 'a'..='z' {
+                let mut replaced = lower.clone();
+                replaced[i] = c;
+                push(replaced);
+            }
+        }
+        // Insertion
+        for i in 0..=lower.len() {
+            for c in 'a'..='z' {
+                let mut inserted = lower.clone();
+                inserted.insert(i, c);
+                push(inserted);
+            }
+        }
+
+        let capitalized = word.chars().next().is_some_and(char::is_uppercase);
+        candidates
+            .into_iter()
+            .filter(|candidate| {
+                self.words.contains(candidate)
+                    || self.personal.contains(candidate)
+                    || project.words.contains(candidate)
+            })
+            .take(MAX_SUGGESTIONS)
+            .map(|candidate| {
+                if capitalized {
+                    let mut chars = candidate.chars();
+                    chars
+                        .next()
+                        .map(|first| first.to_uppercase().chain(chars).collect())
+                        .unwrap_or_default()
+                } else {
+                    candidate
+                }
+            })
+            .collect()
+    }
+}
+
+impl Editor {
+    /// Loads the word list if needed. Returns false (with a status message
+    /// unless `quiet`) if none could be read.
+    fn editor_spell_load(&mut self, quiet: bool) -> bool {
+        self.editor_spell_load_project();
+        let mut paths = vec![];
+        let languages = self.editor_spell_languages();
+        for language in languages
+            .split(',')
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+        {
+            match language_word_list(language) {
+                Some(path) => paths.push(path),
+                None => {
+                    if !quiet {
+                        self.editor_set_status_message(&format!(
+                            "No dictionary found for language '{}'",
+                            language
+                        ));
+                    }
+                    return false;
+                }
+            }
+        }
+        if !self.options.spell_file.is_empty() {
+            paths.push(self.options.spell_file.clone());
+        }
+        if paths.is_empty() {
+            match SYSTEM_WORD_LISTS
+                .iter()
+                .find(|path| Path::new(path).exists())
+            {
+                Some(path) => paths.push(path.to_string()),
+                None => {
+                    if !quiet {
+                        self.editor_set_status_message(
+                            "No word list found (set spellfile=PATH or spelllang=LANGS)",
+                        );
+                    }
+                    return false;
+                }
+            }
+        }
+        let sources = paths.join(", ");
+        if self.spell.loaded_from.as_ref() == Some(&sources) {
+            return true;
+        }
+
+        // A word is known if any of the dictionaries has it
+        let mut words = HashSet::new();
+        for path in &paths {
+            match read_word_list(path) {
+                Ok(list) => words.extend(list),
+                Err(error) => {
+                    if !quiet {
+                        let message = format!("Could not read {}: {}", path, error);
+                        self.editor_set_error_message(&message);
+                    }
+                    return false;
+                }
+            }
+        }
+        self.spell.words = words;
+        self.spell.loaded_from = Some(sources);
+
+        if let Some(Ok(personal)) =
+            personal_dictionary_path().map(|path| read_word_list(&path.to_string_lossy()))
+        {
+            self.spell.personal = personal;
+        }
+        true
+    }
+
+    /// Reads the project dictionary of the open file, if it isn't the one
+    /// already read. Looked for again for each file opened or switched to.
+    fn editor_spell_load_project(&mut self) {
+        let path = find_project_dictionary(self.buffer.filename.as_deref());
+        if path == self.buffer.spell_project.path {
+            return;
+        }
+        self.buffer.spell_project.words = path
+            .as_ref()
+            .and_then(|path| read_word_list(&path.to_string_lossy()).ok())
+            .unwrap_or_default();
+        self.buffer.spell_project.path = path;
+    }
+
+    /// Languages to check the open file against, empty for the default list
+    fn editor_spell_languages(&self) -> String {
+        let extension = self.editor_file_extension().unwrap_or_default();
+        self.spell
+            .buffer_languages
+            .as_ref()
+            .or_else(|| self.spell.filetype_languages.get(&extension))
+            .unwrap_or(&self.options.spell_languages)
+            .clone()
+    }
+
+    /// Picks up the spell languages of a modeline in the file just opened
+    pub fn editor_spell_read_modeline(&mut self) {
+        let num_rows = self.get_num_rows();
+        let head = 0..MODELINE_LINES.min(num_rows);
+        let tail = num_rows.saturating_sub(MODELINE_LINES).max(head.end)..num_rows;
+        self.spell.buffer_languages = head
+            .chain(tail)
+            .find_map(|row| modeline_languages(&self.buffer.rows[row].chars));
+        self.editor_spell_load_project();
+        self.editor_spell_option_changed();
+    }
+
+    /// `spelllang [EXTENSION] LANGS`: sets the spell languages of the open
+    /// file, or of a file type. Without arguments shows the languages in use.
+    pub fn editor_spell_language_command(&mut self, args: &str) {
+        match args.split_once(char::is_whitespace) {
+            Some((extension, languages)) => {
+                self.spell.filetype_languages.insert(
+                    extension.trim_start_matches('.').to_string(),
+                    languages.trim().to_string(),
+                );
+            }
+            None if !args.is_empty() => self.spell.buffer_languages = Some(args.to_string()),
+            None => {
+                let languages = self.editor_spell_languages();
+                let message = if languages.is_empty() {
+                    "Spell checking against the default word list".to_string()
+                } else {
+                    format!("Spell languages: {}", languages)
+                };
+                self.editor_set_status_message(&message);
+                return;
+            }
+        }
+        self.editor_spell_option_changed();
+    }
+
+    /// Words in the word list starting with `prefix`, in the prefix's case
+    pub fn editor_dictionary_words(&mut self, prefix: &str) -> Vec<String> {
+        if self.spell.loaded_from.is_none() && !self.editor_spell_load(true) {
+            return vec![];
+        }
+        let lower = prefix.to_lowercase();
+        let capitalized = prefix.starts_with(char::is_uppercase);
+        self.spell
+            .words
+            .iter()
+            .chain(self.spell.personal.iter())
+            .filter(|word| word.starts_with(&lower))
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) if capitalized => first.to_uppercase().chain(chars).collect(),
+                    _ => word.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Keeps the word list in sync with the `spell` option
+    pub fn editor_spell_option_changed(&mut self) {
+        if self.options.spell && !self.editor_spell_load(false) {
+            self.options.spell = false;
+        }
+    }
+
+    /// `spell`: toggles spell checking
+    pub fn editor_toggle_spell(&mut self) {
+        self.options.spell = !self.options.spell;
+        self.editor_spell_option_changed();
+        if self.options.spell {
+            let message = format!(
+                "Spell checking on ({} words from {})",
+                self.spell.words.len(),
+                self.spell.loaded_from.as_deref().unwrap_or("")
+            );
+            self.editor_set_status_message(&message);
+        } else if self.spell.loaded_from.is_some() {
+            self.editor_set_status_message("Spell checking off");
+        }
+    }
+
+    /// Byte ranges of misspelled words on `file_row`
+    pub fn editor_misspelled_words(&self, file_row: usize) -> Vec<(usize, usize)> {
+        if !self.options.spell || self.spell.loaded_from.is_none() {
+            return vec![];
+        }
+
+        let chars = &self.buffer.rows[file_row].chars;
+        let extension = self.editor_file_extension().unwrap_or_default();
+        let marker = comment_marker(&extension);
+        let ranges = match marker {
+            Some(marker) => code_text_ranges(chars, marker, string_quotes(&extension)),
+            None => vec![(0, chars.len())],
+        };
+
+        ranges
+            .into_iter()
+            .flat_map(|(from, to)| words(&chars[from..to], from))
+            .flat_map(|(start, end)| match marker {
+                Some(_) => identifier_parts(chars, start, end),
+                None => vec![(start, end)],
+            })
+            .filter(|&(start, end)| {
+                let word = &chars[start..end];
+                is_checkable(word) && !self.spell.is_known(word, &self.buffer.spell_project)
+            })
+            .collect()
+    }
+
+    /// Misspelled word under (or just before) the cursor
+    fn editor_misspelled_word_at_cursor(&self) -> Option<(usize, usize)> {
+        if self.buffer.cursor_y >= self.get_num_rows() {
+            return None;
+        }
+        self.editor_misspelled_words(self.buffer.cursor_y)
+            .into_iter()
+            .find(|&(start, end)| start <= self.buffer.cursor_x && self.buffer.cursor_x <= end)
+    }
+
+    /// Ctrl-T: replaces the word under the cursor with the next suggestion,
+    /// cycling back to the original spelling. False if spell checking is off
+    /// or the cursor isn't on a misspelled word.
+    pub fn editor_spell_next_suggestion(&mut self) -> bool {
+        if !self.options.spell {
+            return false;
+        }
+
+        // Continue cycling if the cursor is still on the word we replaced last
+        let row = self.buffer.cursor_y;
+        let continuing = self.spell.cycle.as_ref().is_some_and(|cycle| {
+            let current = if cycle.index >= cycle.suggestions.len() {
+                &cycle.original
+            } else {
+                &cycle.suggestions[cycle.index]
+            };
+            cycle.row == row
+                && self.buffer.rows.get(row).is_some_and(|editor_row| {
+                    editor_row
+                        .chars
+                        .get(cycle.start..cycle.start + current.len())
+                        == Some(current)
+                })
+                && (cycle.start..=cycle.start + current.len()).contains(&self.buffer.cursor_x)
+        });
+
+        if !continuing {
+            let (start, end) = match self.editor_misspelled_word_at_cursor() {
+                Some(range) => range,
+                None => {
+                    self.spell.cycle = None;
+                    return false;
+                }
+            };
+            let original = self.buffer.rows[row].chars[start..end].to_string();
+            let suggestions = self
+                .spell
+                .suggestions(&original, &self.buffer.spell_project);
+            if suggestions.is_empty() {
+                self.editor_set_status_message(&format!("No suggestions for '{}'", original));
+                return true;
+            }
+            // Each cycle is one undo step of its own
+            self.editor_break_undo_group();
+            self.spell.cycle = Some(SuggestionCycle {
+                row,
+                start,
+                original,
+                suggestions,
+                // One past the end means the original word is in the buffer
+                index: usize::MAX,
+            });
+        }
+
+        let (start, old_len, replacement, message) = match self.spell.cycle.as_mut() {
+            Some(cycle) => {
+                let old_len = if cycle.index < cycle.suggestions.len() {
+                    cycle.suggestions[cycle.index].len()
+                } else {
+                    cycle.original.len()
+                };
+                cycle.index = if cycle.index >= cycle.suggestions.len() {
+                    0
+                } else {
+                    cycle.index + 1
+                };
+                let (replacement, message) = if cycle.index == cycle.suggestions.len() {
+                    (cycle.original.clone(), "Original spelling".to_string())
+                } else {
+                    (
+                        cycle.suggestions[cycle.index].clone(),
+                        format!(
+                            "Suggestion {} of {}",
+                            cycle.index + 1,
+                            cycle.suggestions.len()
+                        ),
+                    )
+                };
+                (cycle.start, old_len, replacement, message)
+            }
+            None => return true,
+        };
+
+        let mut line = self.buffer.rows[row].chars.clone();
+        line.replace_range(start..start + old_len, &replacement);
+        self.editor_record_edit(row, 1, EditKind::Suggestion);
+        self.buffer.rows[row] = EditorRow::new(line);
+        self.editor_count_edit();
+        self.buffer.cursor_x = start + replacement.len();
+        self.editor_set_status_message(&message);
+        true
+    }
+
+    /// Ctrl-W / `spellgood [WORD]`: accepts a word and saves it to the
+    /// personal dictionary, or with `spellgood!` to the project dictionary
+    pub fn editor_spell_add_word(&mut self, word: &str, project: bool) {
+        let word = if word.is_empty() {
+            match self.editor_misspelled_word_at_cursor() {
+                Some((start, end)) => {
+                    self.buffer.rows[self.buffer.cursor_y].chars[start..end].to_string()
+                }
+                None => {
+                    self.editor_set_status_message("No misspelled word at the cursor");
+                    return;
+                }
+            }
+        } else {
+            word.to_string()
+        };
+
+        let path = if project {
+            self.editor_spell_load_project();
+            let buffer = &mut self.buffer;
+            buffer.spell_project.words.insert(word.to_lowercase());
+            let filename = buffer.filename.as_deref();
+            let path = buffer
+                .spell_project
+                .path
+                .get_or_insert_with(|| new_project_dictionary(filename));
+            Some(path.clone())
+        } else {
+            self.spell.personal.insert(word.to_lowercase());
+            personal_dictionary_path()
+        };
+        let dictionary = if project { "project" } else { "personal" };
+        let saved = path.map(|path| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .and_then(|mut file| writeln!(file, "{}", word))
+        });
+        match saved {
+            Some(Ok(_)) | None => self.editor_set_status_message(&format!(
+                "Added '{}' to the {} dictionary",
+                word, dictionary
+            )),
+            Some(Err(error)) => self.editor_set_status_message(&format!(
+                "Accepted '{}' for this session, saving failed: {}",
+                word, error
+            )),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Word count and document statistics (`wc`), and the cursor position
+//! shown on the right of the status bar
+
+use crate::*;
+
+struct TextStats {
+    words: usize,
+    chars: usize,
+    bytes: usize,
+}
+
+fn count_text(text: &str) -> TextStats {
+    TextStats {
+        words: text.split_whitespace().count(),
+        chars: text.chars().count(),
+        bytes: text.len(),
+    }
+}
+
+impl Editor {
+    /// Byte offset of the cursor in the buffer's text, lines joined with LF
+    fn editor_cursor_byte_offset(&self) -> usize {
+        self.buffer.rows[..self.buffer.cursor_y.min(self.get_num_rows())]
+            .iter()
+            .map(|row| row.len() + 1)
+            .sum::<usize>()
+            + self.buffer.cursor_x
+    }
+
+    /// Right of the status bar: `line:col (byte N, P%)`. The column counts
+    /// chars from 1, or screen columns with `set tabcolumn`.
+    pub fn editor_position_status(&self) -> String {
+        let column = match self.buffer.rows.get(self.buffer.cursor_y) {
+            Some(row) if self.options.tab_column => {
+                row.cursor_x_to_render_cursor_x(self.options.tab_stop, self.buffer.cursor_x)
+            }
+            Some(row) => row.chars[..floor_char_boundary(&row.chars, self.buffer.cursor_x)]
+                .chars()
+                .count(),
+            None => 0,
+        };
+        let size = self.byte_total.get(self.editor_rows_key(), || {
+            self.buffer
+                .rows
+                .iter()
+                .map(|row| row.len() + 1)
+                .sum::<usize>()
+                .saturating_sub(1)
+        });
+        let offset = self.editor_cursor_byte_offset().min(size);
+        let percent = (offset * 100).checked_div(size).unwrap_or(0);
+        format!(
+            "{}:{} (byte {}, {}%)",
+            self.buffer.cursor_y + 1,
+            column + 1,
+            offset,
+            percent
+        )
+    }
+
+    /// `wc`: counts lines, words, characters and bytes of the selection, or of
+    /// the buffer along with the cursor's position in it
+    pub fn editor_word_count(&mut self) {
+        let (_, num_lines) = self.editor_selected_rows();
+
+        if let Some(text) = self.editor_selected_text() {
+            let stats = count_text(&text);
+            self.editor_set_status_message(&format!(
+                "Selected {} lines; {} words; {} chars; {} bytes",
+                num_lines, stats.words, stats.chars, stats.bytes
+            ));
+            return;
+        }
+
+        let text = self.editor_rows_to_string();
+        let stats = count_text(&text);
+
+        // Byte offset of the cursor in `text`
+        let offset = self.editor_cursor_byte_offset();
+        let offset = floor_char_boundary(&text, offset.min(text.len()));
+        let before = count_text(&text[..offset]);
+        // The word under the cursor counts as the current one
+        let word = if text[offset..].starts_with(|c: char| !c.is_whitespace())
+            && text[..offset].ends_with(|c: char| !c.is_whitespace())
+        {
+            before.words
+        } else {
+            before.words + 1
+        };
+
+        self.editor_set_status_message(&format!(
+            "Line {} of {}; Word {} of {}; Char {} of {}; Byte {} of {}",
+            (self.buffer.cursor_y + 1).min(num_lines.max(1)),
+            num_lines,
+            word.min(stats.words),
+            stats.words,
+            before.chars + 1,
+            stats.chars,
+            offset + 1,
+            stats.bytes
+        ));
+    }
+}
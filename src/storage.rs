@@ -0,0 +1,147 @@
+//! Where the editor keeps its own files, following the XDG base directories:
+//! settings under $XDG_CONFIG_HOME/kilo (~/.config/kilo), rebuildable data
+//! such as the project index under $XDG_CACHE_HOME/kilo (~/.cache/kilo), and
+//! history, recent files, undo and recovery files under $XDG_STATE_HOME/kilo
+//! (~/.local/state/kilo). Files there are replaced atomically, so a crash or
+//! another editor writing at the same time never leaves one half written, and
+//! only we can read them: they hold text from the files edited.
+
+use std::fs::{DirBuilder, File, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+
+/// `$variable/kilo`, or `~/fallback/kilo` when it isn't set
+fn xdg_dir(variable: &str, fallback: &str) -> Option<PathBuf> {
+    let base = match std::env::var_os(variable) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(fallback),
+    };
+    Some(base.join("kilo"))
+}
+
+/// $XDG_CONFIG_HOME/kilo, or ~/.config/kilo
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// $XDG_CACHE_HOME/kilo, or ~/.cache/kilo
+pub fn cache_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+/// $XDG_STATE_HOME/kilo, or ~/.local/state/kilo
+pub fn state_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// Our own directory for temporary files, `$TMPDIR/kilo-UID`, readable by
+/// us only. One that is there already is only used if it still is ours and
+/// private, so nobody else can plant files or links in it.
+pub fn private_temp_dir() -> io::Result<PathBuf> {
+    let uid = unsafe { libc::getuid() };
+    let dir = std::env::temp_dir().join(format!("kilo-{}", uid));
+    match DirBuilder::new().mode(0o700).create(&dir) {
+        Ok(()) => return Ok(dir),
+        Err(error) if error.kind() != ErrorKind::AlreadyExists => return Err(error),
+        Err(_) => {}
+    }
+    let metadata = std::fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            ErrorKind::PermissionDenied,
+            format!("{} isn't a private directory of ours", dir.display()),
+        ));
+    }
+    Ok(dir)
+}
+
+/// Creates `name` in `private_temp_dir` readable by us only, replacing one
+/// left there before
+pub fn create_private_temp(name: &str) -> io::Result<(PathBuf, File)> {
+    let path = private_temp_dir()?.join(name);
+    let _ = std::fs::remove_file(&path);
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?;
+    Ok((path, file))
+}
+
+/// Whether `path` is a half written file `write_atomic` left behind
+pub fn is_temporary(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.') && name.ends_with(".tmp"))
+}
+
+/// Replaces `path` with `contents`, creating the directories it is in. The
+/// contents go to a temporary file next to it first, which is synced and then
+/// renamed over it. Both are private to us.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temporary = dir.join(format!(".{}.{}.tmp", name, std::process::id()));
+
+    // Left behind by a write of ours that was cut short
+    let _ = std::fs::remove_file(&temporary);
+    let written = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&temporary)
+        .and_then(|mut file| {
+            file.write_all(contents.as_ref())?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&temporary, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+    }
+    written
+}
+
+/// What follows the `header` line of a file `write_atomic` wrote. None if it
+/// can't be read or starts with another header, as one from another version
+/// does.
+pub fn read_atomic(path: &Path, header: &str) -> Option<String> {
+    let mut contents = std::fs::read_to_string(path).ok()?;
+    let rest = contents.strip_prefix(header)?;
+    let start = match rest.strip_prefix('\n') {
+        Some(_) => header.len() + 1,
+        None if rest.is_empty() => header.len(),
+        None => return None,
+    };
+    contents.drain(..start);
+    Some(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn writes_private_files_and_reads_them_back() {
+        let dir = std::env::temp_dir().join(format!("kilo-storage-test-{}", std::process::id()));
+        let path = dir.join("state").join("file");
+        write_atomic(&path, "kilo-test 1\nfirst\nsecond\n").unwrap();
+
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+        assert_eq!(
+            read_atomic(&path, "kilo-test 1").as_deref(),
+            Some("first\nsecond\n")
+        );
+        // Another version's file isn't read
+        assert_eq!(read_atomic(&path, "kilo-test 2"), None);
+        assert_eq!(read_atomic(&path, "kilo-test"), None);
+
+        write_atomic(&path, "kilo-test 1").unwrap();
+        assert_eq!(read_atomic(&path, "kilo-test 1").as_deref(), Some(""));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
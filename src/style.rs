@@ -0,0 +1,183 @@
+//! Per cell text attributes and their SGR escape sequences
+
+/// A color as a theme gives it, drawn as close as the terminal allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// One of the 16 colors of the terminal's own palette (0-7, 8-15 bright)
+    Ansi(u8),
+    /// An entry of the 256 color palette
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    pub const BLACK: Color = Color::Ansi(0);
+    pub const RED: Color = Color::Ansi(1);
+    pub const GREEN: Color = Color::Ansi(2);
+    pub const YELLOW: Color = Color::Ansi(3);
+    pub const BLUE: Color = Color::Ansi(4);
+    pub const MAGENTA: Color = Color::Ansi(5);
+    pub const CYAN: Color = Color::Ansi(6);
+    pub const WHITE: Color = Color::Ansi(7);
+    pub const BRIGHT_BLACK: Color = Color::Ansi(8);
+    pub const BRIGHT_RED: Color = Color::Ansi(9);
+    pub const BRIGHT_YELLOW: Color = Color::Ansi(11);
+}
+
+/// Colors a terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorDepth {
+    #[default]
+    Ansi16,
+    Indexed256,
+    TrueColor,
+}
+
+/// The xterm defaults for the 16 palette colors, to find the nearest one
+const ANSI_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Levels of each channel in the 6x6x6 cube of the 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let channel = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    channel(r1, r2) + channel(g1, g2) + channel(b1, b2)
+}
+
+fn nearest_cube_level(value: u8) -> usize {
+    (0..CUBE_LEVELS.len())
+        .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - value as i32).abs())
+        .unwrap_or(0)
+}
+
+/// RGB of an entry of the 256 color palette
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI_RGB[index as usize],
+        16..=231 => {
+            let index = index - 16;
+            (
+                CUBE_LEVELS[(index / 36) as usize],
+                CUBE_LEVELS[(index / 6 % 6) as usize],
+                CUBE_LEVELS[(index % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+/// Nearest entry of the 256 color palette, from the color cube or the gray
+/// ramp, leaving out the first 16 the user may have changed
+fn rgb_to_indexed(rgb: (u8, u8, u8)) -> u8 {
+    let (r, g, b) = rgb;
+    let (ri, gi, bi) = (
+        nearest_cube_level(r),
+        nearest_cube_level(g),
+        nearest_cube_level(b),
+    );
+    let cube = (16 + 36 * ri + 6 * gi + bi) as u8;
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    if distance(rgb, indexed_rgb(gray)) < distance(rgb, indexed_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn rgb_to_ansi(rgb: (u8, u8, u8)) -> u8 {
+    (0..ANSI_RGB.len())
+        .min_by_key(|&i| distance(rgb, ANSI_RGB[i]))
+        .unwrap_or(0) as u8
+}
+
+impl Color {
+    /// This color as the terminal can show it
+    pub fn downconvert(self, depth: ColorDepth) -> Color {
+        match (self, depth) {
+            (Color::Rgb(r, g, b), ColorDepth::Indexed256) => {
+                Color::Indexed(rgb_to_indexed((r, g, b)))
+            }
+            (Color::Rgb(r, g, b), ColorDepth::Ansi16) => Color::Ansi(rgb_to_ansi((r, g, b))),
+            (Color::Indexed(index), ColorDepth::Ansi16) if index < 16 => Color::Ansi(index),
+            (Color::Indexed(index), ColorDepth::Ansi16) => {
+                Color::Ansi(rgb_to_ansi(indexed_rgb(index)))
+            }
+            (color, _) => color,
+        }
+    }
+
+    /// SGR parameters selecting this color as the foreground, or the
+    /// background
+    fn sgr(self, background: bool) -> String {
+        match self {
+            Color::Ansi(index) => {
+                let base = if background { 40 } else { 30 };
+                let base = if index >= 8 { base + 60 } else { base };
+                (base + (index % 8) as usize).to_string()
+            }
+            Color::Indexed(index) => format!("{};5;{}", if background { 48 } else { 38 }, index),
+            Color::Rgb(r, g, b) => {
+                format!("{};2;{};{};{}", if background { 48 } else { 38 }, r, g, b)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CellStyle {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub underline: bool,
+    pub inverse: bool,
+}
+
+impl CellStyle {
+    /// Escape sequence switching the terminal to this style from the default,
+    /// with the colors brought down to `depth`
+    pub fn sgr(&self, depth: ColorDepth) -> String {
+        let mut codes: Vec<String> = vec![];
+        if self.bold {
+            codes.push("1".to_string());
+        }
+        if self.dim {
+            codes.push("2".to_string());
+        }
+        if self.underline {
+            codes.push("4".to_string());
+        }
+        if self.inverse {
+            codes.push("7".to_string());
+        }
+        if let Some(fg) = self.fg {
+            codes.push(fg.downconvert(depth).sgr(false));
+        }
+        if let Some(bg) = self.bg {
+            codes.push(bg.downconvert(depth).sgr(true));
+        }
+
+        format!("\x1b[0;{}m", codes.join(";"))
+    }
+}
@@ -0,0 +1,68 @@
+//! Saving with elevated privileges: when a save is refused for lack of
+//! permission, the editor offers to write the file through `sudo tee` instead,
+//! so a system config file can be saved without losing the edits. `set
+//! sudo=pkexec` uses pkexec (any command taking a program to run will do), and
+//! `set sudo=` never offers it.
+
+use std::process::{Command, Stdio};
+
+use crate::*;
+
+impl Editor {
+    /// Offers to write `contents` to `path` as root after a save failed with
+    /// `error`. Ok once the file is written.
+    pub(crate) fn editor_save_elevated(
+        &mut self,
+        path: &str,
+        contents: &[u8],
+        error: &io::Error,
+    ) -> Result<(), SaveError> {
+        let failed = SaveError::Failed(describe_io_error(error));
+        let escalate: Vec<String> = self
+            .options
+            .sudo
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        let program = match escalate.first() {
+            Some(program) if !self.headless => program.clone(),
+            _ => return Err(failed),
+        };
+        let question = format!("{}. Save with {}? (y/n)", failed, program);
+        if !self.editor_confirm(&question) {
+            return Err(failed);
+        }
+
+        // The password is asked for on the terminal
+        self.write_to_stdout("\x1b[2J\x1b[H");
+        self.flush_stdout();
+        self.disable_terminal();
+        let result = Command::new(&program)
+            .args(&escalate[1..])
+            .args(["tee", "--", path])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .and_then(|mut child| {
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(contents)?;
+                }
+                child.wait()
+            });
+        self.enable_raw_mode();
+        self.get_dimensions();
+        self.editor_invalidate_screen();
+
+        match result {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(SaveError::Failed(format!(
+                "'{} tee' failed: {}",
+                program, status
+            ))),
+            Err(error) => Err(SaveError::Failed(format!(
+                "could not run {}: {}",
+                program, error
+            ))),
+        }
+    }
+}
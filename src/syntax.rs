@@ -0,0 +1,579 @@
+//! Language definitions: file extensions, keywords, comment markers and
+//! string delimiters, written in TOML. The built-in ones are the files in
+//! syntax/ of the source tree; `.toml` files in ~/.config/kilo/syntax add
+//! languages or replace the built-in one of the same name. They are read once,
+//! when first needed. Spell checking and block comments use them, and
+//! keywords, strings and comments are colored with `set syntax`. `syntax`
+//! lists them along with files that couldn't be read.
+//!
+//! Block comments and backslash-continued strings carry over to the next
+//! rows: each row remembers what is still open at its end (`hl_open`) and the
+//! state it was colored from. A row changed since, or starting from a
+//! different state, is colored again before drawing, so opening or closing a
+//! `/*` recolors the rows after it as far as they change.
+//!
+//! Only a few hundred rows are worked out like that per frame. When more are
+//! out of date, as after jumping to the end of a long file or opening a
+//! comment at the top of one, the rest is worked out on a worker thread and
+//! those rows are drawn uncolored until it is done, so typing isn't held up.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::sync::OnceLock;
+use std::thread;
+
+use crate::overlay::OverlayLine;
+use crate::storage::config_dir;
+use crate::style::Color;
+use crate::*;
+
+const BUILTIN: &[(&str, &str)] = &[
+    ("c", include_str!("../syntax/c.toml")),
+    ("config", include_str!("../syntax/config.toml")),
+    ("csharp", include_str!("../syntax/csharp.toml")),
+    ("css", include_str!("../syntax/css.toml")),
+    ("go", include_str!("../syntax/go.toml")),
+    ("haskell", include_str!("../syntax/haskell.toml")),
+    ("html", include_str!("../syntax/html.toml")),
+    ("java", include_str!("../syntax/java.toml")),
+    ("javascript", include_str!("../syntax/javascript.toml")),
+    ("kotlin", include_str!("../syntax/kotlin.toml")),
+    ("lua", include_str!("../syntax/lua.toml")),
+    ("markdown", include_str!("../syntax/markdown.toml")),
+    ("perl", include_str!("../syntax/perl.toml")),
+    ("python", include_str!("../syntax/python.toml")),
+    ("ruby", include_str!("../syntax/ruby.toml")),
+    ("rust", include_str!("../syntax/rust.toml")),
+    ("shell", include_str!("../syntax/shell.toml")),
+    ("sql", include_str!("../syntax/sql.toml")),
+    ("swift", include_str!("../syntax/swift.toml")),
+    ("vim", include_str!("../syntax/vim.toml")),
+    ("zig", include_str!("../syntax/zig.toml")),
+];
+
+#[derive(Default)]
+pub struct Syntax {
+    pub name: String,
+    pub extensions: Vec<String>,
+    pub keywords: HashSet<String>,
+    pub line_comment: Option<String>,
+    pub block_comment: Option<(String, String)>,
+    /// Chars starting and ending a string literal
+    pub quotes: Vec<char>,
+    /// File it was read from, None for a built-in one
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct Syntaxes {
+    list: Vec<Syntax>,
+    /// Files that couldn't be read, with why
+    errors: Vec<String>,
+}
+
+/// A construct left open at the end of a row
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Open {
+    #[default]
+    Nothing,
+    Comment,
+    /// A string with this quote, continued with a backslash
+    String(char),
+}
+
+/// Rows worked out before drawing a frame, the worker gets any more
+const FRAME_ROWS: usize = 500;
+
+/// Rows whose state the worker worked out: what the first starts in, then
+/// each row's text with what is open at its end
+struct WorkedOut {
+    start: usize,
+    open: Open,
+    rows: Vec<(String, Open)>,
+}
+
+#[derive(Default)]
+pub struct SyntaxWork {
+    /// Rows before this one are up to date and can be colored
+    valid_to: usize,
+    /// Result of the worker, None when it isn't running
+    worker: Option<Receiver<WorkedOut>>,
+}
+
+/// Worker: what is left open at the end of each of `lines`, the first of
+/// which starts in `open`
+fn work_out(syntax: &Syntax, lines: Vec<String>, mut open: Open) -> Vec<(String, Open)> {
+    lines
+        .into_iter()
+        .map(|line| {
+            open = tokenize(syntax, &line, open).1;
+            (line, open)
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+enum Token {
+    Keyword,
+    String,
+    Comment,
+}
+
+/// A value in a syntax file: a string or a list of them
+enum Value {
+    String(String),
+    List(Vec<String>),
+}
+
+/// Reads a string at the start of `text`, `"basic"` with backslash escapes or
+/// `'literal'`. Returns it and the text after it.
+fn parse_string(text: &str) -> Result<(String, &str), String> {
+    let quote = text.chars().next().filter(|&c| c == '"' || c == '\'');
+    let quote = quote.ok_or_else(|| format!("expected a string at '{}'", first_line(text)))?;
+    let mut value = String::new();
+    let mut chars = text.char_indices().skip(1);
+    while let Some((at, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, escaped)) => value.push(escaped),
+                None => break,
+            },
+            '\n' => break,
+            c if c == quote => return Ok((value, &text[at + 1..])),
+            c => value.push(c),
+        }
+    }
+    Err(format!("unterminated string at '{}'", first_line(text)))
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or("")
+}
+
+/// `text` without the whitespace and `#` comments at its start
+fn skip_blank(mut text: &str) -> &str {
+    loop {
+        text = text.trim_start();
+        match text.strip_prefix('#') {
+            Some(comment) => text = comment.split_once('\n').map_or("", |(_, rest)| rest),
+            None => return text,
+        }
+    }
+}
+
+/// Reads the `key = value` lines of a syntax file. Only the part of TOML
+/// these files need is understood: strings and lists of strings.
+fn parse_entries(mut text: &str) -> Result<Vec<(String, Value)>, String> {
+    let mut entries = vec![];
+    loop {
+        text = skip_blank(text);
+        if text.is_empty() {
+            return Ok(entries);
+        }
+        let (key, rest) = text
+            .split_once('=')
+            .filter(|(key, _)| !key.contains('\n'))
+            .ok_or_else(|| format!("expected key = value at '{}'", first_line(text)))?;
+        text = rest.trim_start_matches([' ', '\t']);
+
+        let value = if let Some(rest) = text.strip_prefix('[') {
+            let mut items = vec![];
+            text = skip_blank(rest);
+            while !text.starts_with(']') {
+                let (item, rest) = parse_string(text)?;
+                items.push(item);
+                text = skip_blank(rest);
+                text = skip_blank(text.strip_prefix(',').unwrap_or(text));
+                if text.is_empty() {
+                    return Err(format!("list of {} isn't closed", key.trim()));
+                }
+            }
+            text = &text[1..];
+            Value::List(items)
+        } else {
+            let (value, rest) = parse_string(text)?;
+            text = rest;
+            Value::String(value)
+        };
+        entries.push((key.trim().to_string(), value));
+    }
+}
+
+/// Language `name` defined by the syntax file `text`
+fn parse_syntax(name: &str, text: &str) -> Result<Syntax, String> {
+    let mut syntax = Syntax {
+        name: name.to_string(),
+        quotes: vec!['"'],
+        ..Syntax::default()
+    };
+    for (key, value) in parse_entries(text)? {
+        match (key.as_str(), value) {
+            ("extensions", Value::List(extensions)) => syntax.extensions = extensions,
+            ("keywords", Value::List(keywords)) => syntax.keywords = keywords.into_iter().collect(),
+            ("line_comment", Value::String(marker)) => syntax.line_comment = Some(marker),
+            ("block_comment", Value::List(delimiters)) => match delimiters.as_slice() {
+                [opener, closer] => syntax.block_comment = Some((opener.clone(), closer.clone())),
+                _ => return Err("block_comment needs an opener and a closer".to_string()),
+            },
+            ("quotes", Value::List(quotes)) => {
+                syntax.quotes = quotes
+                    .iter()
+                    .filter_map(|quote| quote.chars().next())
+                    .collect()
+            }
+            (key, _) => return Err(format!("unknown key or wrong type: {}", key)),
+        }
+    }
+    Ok(syntax)
+}
+
+/// $XDG_CONFIG_HOME/kilo/syntax, or ~/.config/kilo/syntax
+fn user_syntax_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("syntax"))
+}
+
+fn load_syntaxes() -> Syntaxes {
+    let mut syntaxes = Syntaxes::default();
+    for (name, text) in BUILTIN {
+        match parse_syntax(name, text) {
+            Ok(syntax) => syntaxes.list.push(syntax),
+            Err(error) => syntaxes
+                .errors
+                .push(format!("built-in {}: {}", name, error)),
+        }
+    }
+
+    let entries = match user_syntax_dir().map(std::fs::read_dir) {
+        Some(Ok(entries)) => entries,
+        _ => return syntaxes,
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .collect();
+    paths.sort();
+    for path in paths {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| parse_syntax(&name, &text));
+        match parsed {
+            Ok(syntax) => {
+                syntaxes.list.retain(|builtin| builtin.name != name);
+                // Read first, so it wins over built-ins sharing an extension
+                syntaxes.list.insert(
+                    0,
+                    Syntax {
+                        path: Some(path),
+                        ..syntax
+                    },
+                );
+            }
+            Err(error) => syntaxes
+                .errors
+                .push(format!("{}: {}", path.display(), error)),
+        }
+    }
+    syntaxes
+}
+
+fn syntaxes() -> &'static Syntaxes {
+    static SYNTAXES: OnceLock<Syntaxes> = OnceLock::new();
+    SYNTAXES.get_or_init(load_syntaxes)
+}
+
+/// Language of files with this extension
+pub fn syntax_for(extension: &str) -> Option<&'static Syntax> {
+    syntaxes()
+        .list
+        .iter()
+        .find(|syntax| syntax.extensions.iter().any(|known| known == extension))
+}
+
+/// End of the string closed by `quote` in `line` after byte `from`, None if
+/// it isn't closed on this line
+fn string_end(line: &str, from: usize, quote: char) -> Option<usize> {
+    let mut chars = line[from..].char_indices();
+    while let Some((at, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c == quote {
+            return Some(from + at + quote.len_utf8());
+        }
+    }
+    None
+}
+
+/// What an unclosed string with `quote` leaves open at the end of `line`
+fn open_string(line: &str, quote: char) -> Open {
+    let backslashes = line.len() - line.trim_end_matches('\\').len();
+    if backslashes % 2 == 1 {
+        Open::String(quote)
+    } else {
+        Open::Nothing
+    }
+}
+
+/// Byte ranges of the keywords, strings and comments on `line`, which starts
+/// inside `open`, and what is left open at its end
+fn tokenize(syntax: &Syntax, line: &str, open: Open) -> (Vec<(usize, usize, Token)>, Open) {
+    let mut tokens = vec![];
+    let mut start = 0;
+    match (open, &syntax.block_comment) {
+        (Open::Comment, Some((_, closer))) => match line.find(closer.as_str()) {
+            Some(at) => start = at + closer.len(),
+            None => return (vec![(0, line.len(), Token::Comment)], Open::Comment),
+        },
+        (Open::String(quote), _) => match string_end(line, 0, quote) {
+            Some(end) => start = end,
+            None => {
+                return (
+                    vec![(0, line.len(), Token::String)],
+                    open_string(line, quote),
+                )
+            }
+        },
+        _ => {}
+    }
+    if start > 0 {
+        let token = if open == Open::Comment {
+            Token::Comment
+        } else {
+            Token::String
+        };
+        tokens.push((0, start, token));
+    }
+
+    let mut chars = line[start..]
+        .char_indices()
+        .map(|(at, c)| (start + at, c))
+        .peekable();
+    let mut previous_in_word = false;
+    while let Some((i, c)) = chars.next() {
+        let rest = &line[i..];
+        let in_word = c.is_alphanumeric() || c == '_';
+        // Block comments first, Lua's `--[[` starts like its line comments
+        if let Some((opener, closer)) = syntax
+            .block_comment
+            .as_ref()
+            .filter(|(opener, _)| rest.starts_with(opener.as_str()))
+        {
+            let end = rest[opener.len()..]
+                .find(closer.as_str())
+                .map(|at| i + opener.len() + at + closer.len());
+            tokens.push((i, end.unwrap_or(line.len()), Token::Comment));
+            match end {
+                Some(end) => while chars.next_if(|&(j, _)| j < end).is_some() {},
+                None => return (tokens, Open::Comment),
+            }
+        } else if syntax
+            .line_comment
+            .as_ref()
+            .is_some_and(|marker| rest.starts_with(marker.as_str()))
+        {
+            tokens.push((i, line.len(), Token::Comment));
+            break;
+        } else if syntax.quotes.contains(&c) {
+            let end = string_end(line, i + c.len_utf8(), c);
+            tokens.push((i, end.unwrap_or(line.len()), Token::String));
+            match end {
+                Some(end) => while chars.next_if(|&(j, _)| j < end).is_some() {},
+                None => return (tokens, open_string(line, c)),
+            }
+        } else if in_word && !previous_in_word {
+            let mut end = line.len();
+            while let Some(&(j, next)) = chars.peek() {
+                if !(next.is_alphanumeric() || next == '_') {
+                    end = j;
+                    break;
+                }
+                chars.next();
+            }
+            if syntax.keywords.contains(&line[i..end]) {
+                tokens.push((i, end, Token::Keyword));
+            }
+        }
+        previous_in_word = in_word;
+    }
+    (tokens, Open::Nothing)
+}
+
+impl Editor {
+    /// Language of the open file
+    pub fn editor_syntax(&self) -> Option<&'static Syntax> {
+        syntax_for(&self.editor_file_extension()?)
+    }
+
+    /// Brings what is open at the end of each row up to date, up to row
+    /// `last`. Rows that changed are colored again, and the ones after them
+    /// as long as what they start in changes.
+    pub fn editor_update_syntax_state(&mut self, last: usize) {
+        let syntax = match self.editor_syntax() {
+            Some(syntax) if self.options.syntax => syntax,
+            _ => return,
+        };
+        if self.buffer.highlighted_syntax != Some(syntax.name.as_str()) {
+            self.buffer.highlighted_syntax = Some(syntax.name.as_str());
+            for row in &mut self.buffer.rows {
+                row.hl_from = None;
+            }
+        }
+
+        let last = last.min(self.buffer.rows.len().saturating_sub(1));
+        let mut open = Open::Nothing;
+        let mut budget = FRAME_ROWS;
+        for index in 0..self.buffer.rows.len().min(last.saturating_add(1)) {
+            let row = &mut self.buffer.rows[index];
+            if row.hl_from != Some(open) {
+                if budget == 0 {
+                    self.buffer.syntax_work.valid_to = index;
+                    self.editor_start_syntax_worker(syntax, index, last, open);
+                    return;
+                }
+                budget -= 1;
+                row.hl_open = tokenize(syntax, &row.chars, open).1;
+                row.hl_from = Some(open);
+            }
+            open = row.hl_open;
+        }
+        self.buffer.syntax_work.valid_to = usize::MAX;
+    }
+
+    /// Works out rows `start` to `last`, the first starting in `open`, on a
+    /// worker thread unless one is already running
+    fn editor_start_syntax_worker(
+        &mut self,
+        syntax: &'static Syntax,
+        start: usize,
+        last: usize,
+        open: Open,
+    ) {
+        if self.buffer.syntax_work.worker.is_some() {
+            return;
+        }
+        let lines: Vec<String> = self.buffer.rows[start..=last]
+            .iter()
+            .map(|row| row.chars.clone())
+            .collect();
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let rows = work_out(syntax, lines, open);
+            let _ = sender.send(WorkedOut { start, open, rows });
+        });
+        self.buffer.syntax_work.worker = Some(receiver);
+    }
+
+    /// Whether rows are being worked out on the worker thread
+    pub fn editor_syntax_working(&self) -> bool {
+        self.buffer.syntax_work.worker.is_some()
+    }
+
+    /// Takes in what the worker worked out, true if the screen should be
+    /// redrawn. Rows edited in the meantime, and the ones after them, are
+    /// left to be worked out again.
+    pub fn editor_syntax_poll(&mut self) -> bool {
+        let worked_out = match self
+            .buffer
+            .syntax_work
+            .worker
+            .as_ref()
+            .map(Receiver::try_recv)
+        {
+            Some(Ok(worked_out)) => worked_out,
+            Some(Err(TryRecvError::Empty)) | None => return false,
+            Some(Err(TryRecvError::Disconnected)) => {
+                self.buffer.syntax_work.worker = None;
+                return false;
+            }
+        };
+        self.buffer.syntax_work.worker = None;
+
+        let WorkedOut {
+            start,
+            mut open,
+            rows,
+        } = worked_out;
+        let starts_in = match start.checked_sub(1) {
+            Some(previous) => self.buffer.rows.get(previous).map(|row| row.hl_open),
+            None => Some(Open::Nothing),
+        };
+        if starts_in != Some(open) || start > self.buffer.syntax_work.valid_to {
+            return true;
+        }
+        for (row, (line, hl_open)) in self.buffer.rows[start..].iter_mut().zip(rows) {
+            if row.chars != line {
+                break;
+            }
+            row.hl_from = Some(open);
+            row.hl_open = hl_open;
+            open = hl_open;
+        }
+        true
+    }
+
+    /// Syntax colors of `file_row` as byte ranges of its chars
+    pub fn editor_syntax_highlights(&self, file_row: usize) -> Vec<(usize, usize, Color)> {
+        let syntax = match self.editor_syntax() {
+            Some(syntax) if self.options.syntax => syntax,
+            _ => return vec![],
+        };
+        // Drawn uncolored until the worker has worked it out
+        if file_row >= self.buffer.syntax_work.valid_to {
+            return vec![];
+        }
+        let open = match file_row.checked_sub(1) {
+            Some(previous) => self.buffer.rows[previous].hl_open,
+            None => Open::Nothing,
+        };
+        tokenize(syntax, &self.buffer.rows[file_row].chars, open)
+            .0
+            .into_iter()
+            .map(|(from, to, token)| {
+                let color = match token {
+                    Token::Keyword => self.theme.keyword,
+                    Token::String => self.theme.string,
+                    Token::Comment => self.theme.comment,
+                };
+                (from, to, color)
+            })
+            .collect()
+    }
+
+    /// `syntax`: lists the language definitions and the files that couldn't
+    /// be read
+    pub fn editor_list_syntaxes(&mut self) {
+        let current = self.editor_syntax().map(|syntax| syntax.name.as_str());
+        let syntaxes = syntaxes();
+        let mut lines: Vec<OverlayLine> = syntaxes
+            .errors
+            .iter()
+            .map(|error| OverlayLine::colored(error.clone(), Color::RED))
+            .collect();
+        lines.extend(syntaxes.list.iter().map(|syntax| {
+            OverlayLine::new(format!(
+                "{} {:12} {:24} {}",
+                if Some(syntax.name.as_str()) == current {
+                    '%'
+                } else {
+                    ' '
+                },
+                syntax.name,
+                syntax.extensions.join(" "),
+                syntax
+                    .path
+                    .as_ref()
+                    .map_or("built-in".to_string(), |path| path.display().to_string())
+            ))
+        }));
+        self.editor_show_overlay("Syntax definitions", &lines);
+    }
+}
@@ -0,0 +1,252 @@
+//! Colors of the UI elements, changed with `color NAME VALUE` (a `#rrggbb`
+//! color, a 256 color palette index or a name like `brightred`), and how many
+//! colors the terminal shows: `set colors=auto|truecolor|256|16`. Colors are
+//! brought down to what the terminal supports when drawing.
+//!
+//! $KILO_THEME names a theme file of `NAME VALUE` lines loaded at startup,
+//! before the config file: either a path or a file in the themes directory
+//! of the config directory.
+
+use std::path::PathBuf;
+
+use crate::overlay::OverlayLine;
+use crate::storage::config_dir;
+use crate::style::{Color, ColorDepth};
+use crate::*;
+
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+pub struct Theme {
+    /// Background of search matches
+    pub search: Color,
+    /// Background of the match the cursor is on
+    pub current_search: Color,
+    /// Text of search matches
+    pub search_text: Color,
+    /// Misspelled words
+    pub spell: Color,
+    /// Background of the completion popup
+    pub completion: Color,
+    /// Gutter marks of lines changed since the last save
+    pub change_marks: Color,
+    /// Keywords, strings and comments of source files
+    pub keyword: Color,
+    pub string: Color,
+    pub comment: Color,
+    /// Background of the `colorcolumn` guides
+    pub color_column: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            search: Color::YELLOW,
+            current_search: Color::BRIGHT_YELLOW,
+            search_text: Color::BLACK,
+            spell: Color::BRIGHT_RED,
+            completion: Color::BRIGHT_BLACK,
+            change_marks: Color::MAGENTA,
+            keyword: Color::CYAN,
+            string: Color::GREEN,
+            comment: Color::BRIGHT_BLACK,
+            color_column: Color::BRIGHT_BLACK,
+        }
+    }
+}
+
+impl Theme {
+    fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+        match name {
+            "search" => Some(&mut self.search),
+            "currentsearch" => Some(&mut self.current_search),
+            "searchtext" => Some(&mut self.search_text),
+            "spell" => Some(&mut self.spell),
+            "completion" => Some(&mut self.completion),
+            "changemarks" => Some(&mut self.change_marks),
+            "keyword" => Some(&mut self.keyword),
+            "string" => Some(&mut self.string),
+            "comment" => Some(&mut self.comment),
+            "colorcolumn" => Some(&mut self.color_column),
+            _ => None,
+        }
+    }
+
+    fn colors(&self) -> [(&'static str, Color); 10] {
+        [
+            ("search", self.search),
+            ("currentsearch", self.current_search),
+            ("searchtext", self.search_text),
+            ("spell", self.spell),
+            ("completion", self.completion),
+            ("changemarks", self.change_marks),
+            ("keyword", self.keyword),
+            ("string", self.string),
+            ("comment", self.comment),
+            ("colorcolumn", self.color_column),
+        ]
+    }
+}
+
+/// Parses `#rrggbb`, a palette index (0-255) or a color name
+fn parse_color(value: &str) -> Option<Color> {
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |at: usize| u8::from_str_radix(&hex[at..at + 2], 16).ok();
+        return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    if let Ok(index) = value.parse() {
+        return Some(Color::Indexed(index));
+    }
+    let value = value.to_lowercase();
+    let (bright, name) = match value.strip_prefix("bright") {
+        Some(name) => (8, name),
+        None => (0, value.as_str()),
+    };
+    let index = COLOR_NAMES.iter().position(|color| *color == name)?;
+    Some(Color::Ansi(bright + index as u8))
+}
+
+fn color_to_string(color: Color) -> String {
+    match color {
+        Color::Ansi(index) if index >= 8 => format!("bright{}", COLOR_NAMES[index as usize % 8]),
+        Color::Ansi(index) => COLOR_NAMES[index as usize % 8].to_string(),
+        Color::Indexed(index) => index.to_string(),
+        Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+    }
+}
+
+/// The file of theme `name`: a path if it has a slash, else the file in the
+/// themes directory
+fn theme_path(name: &str) -> Option<PathBuf> {
+    if name.contains('/') {
+        return Some(PathBuf::from(name));
+    }
+    config_dir().map(|dir| dir.join("themes").join(name))
+}
+
+/// Colors the terminal supports according to COLORTERM and TERM
+fn detect_color_depth() -> ColorDepth {
+    let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+    let term = std::env::var("TERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" || term.ends_with("-direct") {
+        ColorDepth::TrueColor
+    } else if term.contains("256color") {
+        ColorDepth::Indexed256
+    } else {
+        ColorDepth::Ansi16
+    }
+}
+
+impl Editor {
+    /// Colors to draw with, from the `colors` option or detected
+    pub fn editor_color_depth(&self) -> ColorDepth {
+        match self.options.colors.as_str() {
+            "truecolor" | "24bit" => ColorDepth::TrueColor,
+            "256" => ColorDepth::Indexed256,
+            "16" => ColorDepth::Ansi16,
+            _ => detect_color_depth(),
+        }
+    }
+
+    /// Starts over from the default colors and sets the ones in theme file
+    /// `name`, lines starting with # are comments. Problems are reported.
+    pub fn editor_load_theme(&mut self, name: &str) {
+        let contents = match theme_path(name).map(std::fs::read_to_string) {
+            Some(Ok(contents)) => contents,
+            Some(Err(error)) => {
+                let message = format!(
+                    "Could not read theme {}: {}",
+                    name,
+                    describe_io_error(&error)
+                );
+                self.editor_set_error_message(&message);
+                return;
+            }
+            None => {
+                self.editor_set_status_message("No config directory to find themes in");
+                return;
+            }
+        };
+
+        let mut theme = Theme::default();
+        let mut invalid = vec![];
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // Lines copied from a config file keep their `color`
+            let line = line.strip_prefix("color ").unwrap_or(line).trim_start();
+            let parsed = line
+                .split_once(char::is_whitespace)
+                .and_then(|(color, value)| {
+                    Some((theme.color_mut(color)?, parse_color(value.trim())?))
+                });
+            match parsed {
+                Some((color, value)) => *color = value,
+                None => invalid.push((number + 1).to_string()),
+            }
+        }
+        self.theme = theme;
+
+        if !invalid.is_empty() {
+            self.editor_set_status_message(&format!(
+                "Theme {}: skipped invalid line {}",
+                name,
+                invalid.join(", ")
+            ));
+        }
+    }
+
+    /// `color [NAME [VALUE]]`: sets a theme color, shows one, or lists them all
+    pub fn editor_color_command(&mut self, args: &str) {
+        let (name, value) = match args.split_once(char::is_whitespace) {
+            Some((name, value)) => (name, value.trim()),
+            None => (args, ""),
+        };
+        if name.is_empty() {
+            let depth = match self.editor_color_depth() {
+                ColorDepth::TrueColor => "truecolor",
+                ColorDepth::Indexed256 => "256 colors",
+                ColorDepth::Ansi16 => "16 colors",
+            };
+            let lines: Vec<OverlayLine> = self
+                .theme
+                .colors()
+                .iter()
+                .map(|(name, color)| {
+                    OverlayLine::colored(format!("{:14} {}", name, color_to_string(*color)), *color)
+                })
+                .collect();
+            self.editor_show_overlay(&format!("Theme colors ({})", depth), &lines);
+            return;
+        }
+
+        let color = match self.theme.color_mut(name) {
+            Some(color) => color,
+            None => {
+                self.editor_set_error_message(&format!("Unknown theme color: {}", name));
+                return;
+            }
+        };
+        if value.is_empty() {
+            let message = format!("{} {}", name, color_to_string(*color));
+            self.editor_set_status_message(&message);
+            return;
+        }
+        match parse_color(value) {
+            Some(parsed) => {
+                *color = parsed;
+                self.editor_set_status_message(&format!("{} {}", name, value));
+            }
+            None => self.editor_set_status_message(&format!(
+                "Invalid color '{}' (#rrggbb, 0-255 or a name like brightred)",
+                value
+            )),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Terminal window title (OSC 2) showing the file name and whether it has
+//! unsaved changes. The terminal's own title is saved on its title stack
+//! first and restored on exit. `set notitle` leaves the title alone.
+
+use crate::*;
+
+/// Push and pop the window title on xterm's title stack
+const SAVE_TITLE: &str = "\x1b[22;0t";
+const RESTORE_TITLE: &str = "\x1b[23;0t";
+
+impl Editor {
+    fn editor_window_title(&self) -> String {
+        let name = match &self.buffer.filename {
+            Some(filename) => std::path::Path::new(filename)
+                .file_name()
+                .map_or(filename.clone(), |name| name.to_string_lossy().into_owned()),
+            None => "[No Name]".to_string(),
+        };
+        let modified = if self.buffer.dirty != 0 { " [+]" } else { "" };
+        format!("{}{} - kilo", name, modified)
+    }
+
+    /// Sets the window title if it changed since it was last set
+    pub fn editor_update_window_title(&mut self) {
+        if self.headless {
+            return;
+        }
+        if !self.options.title {
+            if self.window_title.take().is_some() {
+                self.write_to_stdout(RESTORE_TITLE);
+            }
+            return;
+        }
+
+        let title = self.editor_window_title();
+        if self.window_title.as_ref() == Some(&title) {
+            return;
+        }
+        if self.window_title.is_none() {
+            self.write_to_stdout(SAVE_TITLE);
+        }
+        // Control characters in a file name would end the sequence early
+        let printable: String = title.chars().filter(|c| !c.is_control()).collect();
+        self.write_to_stdout(&format!("\x1b]2;{}\x07", printable));
+        self.window_title = Some(title);
+    }
+
+    /// Gives the terminal its own title back
+    pub fn editor_restore_window_title(&self) {
+        if self.window_title.is_some() {
+            self.write_to_stdout(RESTORE_TITLE);
+        }
+    }
+}
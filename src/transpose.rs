@@ -0,0 +1,120 @@
+//! Emacs-style transposing: Ctrl-T swaps the chars on either side of the
+//! cursor (the last two at the end of a line), Alt-T the word at or before the
+//! cursor with the next one, and Ctrl-Alt-T the line with the one above. The
+//! cursor ends up after what moved forward, so repeating drags it along. Each
+//! is one undo step. Ctrl-T on a misspelled word cycles through suggestions
+//! instead while spell checking is on.
+
+use crate::completion::is_word_char;
+use crate::*;
+
+/// Byte ranges of the words in `line`
+fn word_ranges(line: &str) -> Vec<(usize, usize)> {
+    let mut words = vec![];
+    let mut start = None;
+    for (index, c) in line.char_indices() {
+        match (is_word_char(c), start) {
+            (true, None) => start = Some(index),
+            (false, Some(from)) => {
+                words.push((from, index));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(from) = start {
+        words.push((from, line.len()));
+    }
+    words
+}
+
+impl Editor {
+    /// Ctrl-T: swaps the char before the cursor with the one under it
+    pub fn editor_transpose_chars(&mut self) {
+        let row = match self.buffer.rows.get(self.buffer.cursor_y) {
+            Some(row) => &row.chars,
+            None => return,
+        };
+        let mut at = self.buffer.cursor_x;
+        // At the end of a line the last two chars are swapped
+        if at == row.len() {
+            at = row[..at]
+                .char_indices()
+                .next_back()
+                .map_or(0, |(index, _)| index);
+        }
+        let before = row[..at].chars().next_back();
+        let under = row[at..].chars().next();
+        let (before, under) = match (before, under) {
+            (Some(before), Some(under)) => (before, under),
+            _ => {
+                self.editor_set_status_message("Nothing to transpose");
+                return;
+            }
+        };
+        let start = at - before.len_utf8();
+        let end = at + under.len_utf8();
+        let mut line = row.clone();
+        line.replace_range(start..end, &format!("{}{}", under, before));
+
+        self.buffer.mark = None;
+        self.editor_replace_rows(self.buffer.cursor_y, 1, vec![line]);
+        self.buffer.cursor_x = end;
+    }
+
+    /// Alt-T: swaps the word at or before the cursor with the one after it,
+    /// or with the one before it when it's the last word of the line
+    pub fn editor_transpose_words(&mut self) {
+        let row = match self.buffer.rows.get(self.buffer.cursor_y) {
+            Some(row) => &row.chars,
+            None => return,
+        };
+        let words = word_ranges(row);
+        let current = words
+            .iter()
+            .rposition(|&(start, _)| start < self.buffer.cursor_x)
+            .unwrap_or(0);
+        let first = if current + 1 < words.len() {
+            current
+        } else {
+            current.saturating_sub(1)
+        };
+        let ((first_start, first_end), (second_start, second_end)) =
+            match (words.get(first), words.get(first + 1)) {
+                (Some(&first), Some(&second)) => (first, second),
+                _ => {
+                    self.editor_set_status_message("Need two words on the line to transpose");
+                    return;
+                }
+            };
+        let line = format!(
+            "{}{}{}{}{}",
+            &row[..first_start],
+            &row[second_start..second_end],
+            &row[first_end..second_start],
+            &row[first_start..first_end],
+            &row[second_end..]
+        );
+
+        self.buffer.mark = None;
+        self.editor_replace_rows(self.buffer.cursor_y, 1, vec![line]);
+        self.buffer.cursor_x = second_end;
+    }
+
+    /// Ctrl-Alt-T: swaps the line with the one above, moving down with it
+    pub fn editor_transpose_lines(&mut self) {
+        let row = self.buffer.cursor_y;
+        if row == 0 || row >= self.get_num_rows() {
+            self.editor_set_status_message("No line above to transpose with");
+            return;
+        }
+        let lines = vec![
+            self.buffer.rows[row].chars.clone(),
+            self.buffer.rows[row - 1].chars.clone(),
+        ];
+
+        self.buffer.mark = None;
+        self.editor_replace_rows(row - 1, 2, lines);
+        self.editor_goto(row + 1, 0);
+    }
+}
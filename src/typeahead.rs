@@ -0,0 +1,44 @@
+//! Typeahead: keys sent faster than they are handled, as when typing fast or
+//! pasting without bracketed paste, wait in `input` in the order they came.
+//! Whatever the terminal has sent is read in before each key is handled, so
+//! nothing backs up in the terminal, and the screen is redrawn once the keys
+//! waiting have all been handled rather than after each of them.
+//!
+//! Redraws are also limited to `maxfps` frames a second: a key arriving
+//! within a frame of the last one, as with a held down arrow key, is handled
+//! before drawing. A frame is still drawn every 1/maxfps seconds while keys
+//! keep coming, so the cursor doesn't freeze during a long paste.
+
+use crate::*;
+
+/// Bytes read ahead at most, a paste bigger than that is read in as the
+/// queue goes down
+const TYPEAHEAD_LIMIT: usize = 1 << 20;
+
+impl Editor {
+    /// Moves what the terminal has already sent into `input`
+    pub fn editor_read_typeahead(&mut self) {
+        while self.input.len() < TYPEAHEAD_LIMIT && self.editor_fill_input(Duration::ZERO) {}
+    }
+
+    /// Whether a key is waiting or arrives within `timeout`
+    pub fn editor_key_waiting(&self, timeout: Duration) -> bool {
+        !self.pending_keys.is_empty() || !self.input.is_empty() || stdin_ready(timeout)
+    }
+
+    /// A redraw is skipped while more keys are waiting or arrive before the
+    /// next frame is due, the one after the last of them shows what they all
+    /// did
+    pub fn editor_skip_redraw(&self) -> bool {
+        if self.headless {
+            return false;
+        }
+        let interval = Duration::from_secs(1) / self.options.max_fps as u32;
+        let since = self.last_frame.map_or(interval, |last| last.elapsed());
+        match interval.checked_sub(since) {
+            Some(left) if !left.is_zero() => self.editor_key_waiting(left),
+            // A frame is due, even with keys waiting
+            _ => false,
+        }
+    }
+}
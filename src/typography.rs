@@ -0,0 +1,92 @@
+//! Smart punctuation for prose (`set smartquotes`): straight quotes become
+//! curly quotes and `--` an em dash as they are typed. Backspace right after
+//! a conversion brings back the straight characters. A conversion is undone
+//! together with the typing around it.
+
+use crate::hooks::InputHook;
+use crate::*;
+
+/// Filetypes written as prose, plus files without an extension
+fn is_prose(extension: &str) -> bool {
+    matches!(
+        extension,
+        "" | "md" | "markdown" | "txt" | "text" | "rst" | "org" | "adoc" | "tex"
+    )
+}
+
+/// Whether a quote typed after `previous` opens rather than closes
+fn opens_quote(previous: Option<char>) -> bool {
+    match previous {
+        None => true,
+        Some(c) => c.is_whitespace() || "([{<\u{2014}\u{2013}-/\u{201c}\u{2018}".contains(c),
+    }
+}
+
+#[derive(Default)]
+pub struct SmartPunctuation {
+    /// Row and column right after the last conversion, the length of the
+    /// converted text and the straight text it replaced
+    last: Option<(usize, usize, usize, &'static str)>,
+}
+
+impl InputHook for SmartPunctuation {
+    fn on_key(&mut self, editor: &mut Editor, key: Key) -> bool {
+        let last = self.last.take();
+        let extension = editor.editor_file_extension().unwrap_or_default();
+        if !editor.options.smart_quotes
+            || !is_prose(&extension)
+            || editor.buffer.cursor_y >= editor.get_num_rows()
+        {
+            return false;
+        }
+
+        if key == Key::Backspace {
+            return match last {
+                Some((row, column, len, straight))
+                    if (row, column) == (editor.buffer.cursor_y, editor.buffer.cursor_x) =>
+                {
+                    editor.editor_retype_before_cursor(len, straight, EditKind::DeleteChar);
+                    true
+                }
+                _ => false,
+            };
+        }
+
+        let row = &editor.buffer.rows[editor.buffer.cursor_y].chars;
+        let previous = row[..floor_char_boundary(row, editor.buffer.cursor_x)]
+            .chars()
+            .next_back();
+        let (replaced, text, straight) = match key {
+            Key::Char('"') if opens_quote(previous) => (0, "\u{201c}", "\""),
+            Key::Char('"') => (0, "\u{201d}", "\""),
+            Key::Char('\'') if opens_quote(previous) => (0, "\u{2018}", "'"),
+            Key::Char('\'') => (0, "\u{2019}", "'"),
+            Key::Char('-') if previous == Some('-') => (1, "\u{2014}", "--"),
+            _ => return false,
+        };
+
+        editor.editor_retype_before_cursor(replaced, text, EditKind::InsertChar);
+        self.last = Some((
+            editor.buffer.cursor_y,
+            editor.buffer.cursor_x,
+            text.len(),
+            straight,
+        ));
+        true
+    }
+}
+
+impl Editor {
+    /// Replaces `len` bytes before the cursor with `text` on the cursor's
+    /// row, recorded as `kind` so it joins the undo step of the typing
+    fn editor_retype_before_cursor(&mut self, len: usize, text: &str, kind: EditKind) {
+        self.editor_record_edit(self.buffer.cursor_y, 1, kind);
+        let row = &mut self.buffer.rows[self.buffer.cursor_y];
+        let at = floor_char_boundary(&row.chars, self.buffer.cursor_x);
+        let start = floor_char_boundary(&row.chars, at.saturating_sub(len));
+        row.chars.replace_range(start..at, text);
+        row.chars_changed();
+        self.buffer.cursor_x = start + text.len();
+        self.editor_count_edit();
+    }
+}
@@ -0,0 +1,481 @@
+//! Undo / redo history
+//!
+//! Every edit is recorded as the replacement of a contiguous range of rows,
+//! which is enough to describe single character edits as well as whole buffer
+//! transformations.
+
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+
+use crate::hooks::EditorHook;
+use crate::storage::{read_atomic, state_dir, write_atomic};
+use crate::*;
+
+pub const UNDO_LIMIT: usize = 1000;
+const UNDO_FILE_HEADER: &str = "kilo-undo 1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditKind {
+    InsertChar,
+    OverwriteChar,
+    DeleteChar,
+    /// A spelling suggestion put in place of the last one
+    Suggestion,
+    Other,
+}
+
+pub struct UndoEntry {
+    start: usize,
+    removed: Vec<String>,
+    /// Number of rows that replaced `removed`, known once the edit is finished
+    inserted: Option<usize>,
+    rows_before: usize,
+    cursor: (usize, usize),
+    kind: EditKind,
+}
+
+#[derive(Default)]
+pub struct UndoHistory {
+    undo: Vec<UndoEntry>,
+    redo: Vec<UndoEntry>,
+    /// Entries recorded, for checking the memory use every so often
+    pub recorded: usize,
+}
+
+/// Approximate memory taken by `entries`
+fn entries_bytes(entries: &[UndoEntry]) -> usize {
+    entries
+        .iter()
+        .map(|entry| {
+            size_of::<UndoEntry>()
+                + entry
+                    .removed
+                    .iter()
+                    .map(|row| size_of::<String>() + row.capacity())
+                    .sum::<usize>()
+        })
+        .sum()
+}
+
+impl UndoHistory {
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Approximate memory of the undo and the redo entries
+    pub fn memory(&self) -> (usize, usize) {
+        (entries_bytes(&self.undo), entries_bytes(&self.redo))
+    }
+
+    /// Number of undo and redo steps
+    pub fn steps(&self) -> (usize, usize) {
+        (self.undo.len(), self.redo.len())
+    }
+
+    /// Drops the oldest undo steps until the undo entries take at most
+    /// `max_bytes`, always keeping the last one. Returns the steps dropped.
+    pub fn trim(&mut self, max_bytes: usize) -> usize {
+        let mut bytes = entries_bytes(&self.undo);
+        let mut dropped = 0;
+        while bytes > max_bytes && dropped + 1 < self.undo.len() {
+            bytes -= entries_bytes(&self.undo[dropped..dropped + 1]);
+            dropped += 1;
+        }
+        self.undo.drain(..dropped);
+        dropped
+    }
+
+    /// Forgets the undone steps, returns how many there were
+    pub fn clear_redo(&mut self) -> usize {
+        let count = self.redo.len();
+        self.redo = vec![];
+        count
+    }
+
+    fn finish(&mut self, num_rows: usize) {
+        if let Some(entry) = self.undo.last_mut() {
+            if entry.inserted.is_none() {
+                entry.inserted = Some(entry.removed.len() + num_rows - entry.rows_before);
+            }
+        }
+    }
+}
+
+impl Editor {
+    /// Records that rows `start..start + count` are about to be replaced. Must be
+    /// called before the rows are modified.
+    pub fn editor_record_edit(&mut self, start: usize, count: usize, kind: EditKind) {
+        let num_rows = self.get_num_rows();
+        self.buffer.undo.finish(num_rows);
+        self.buffer.undo.redo.clear();
+        self.editor_record_change();
+        let count = count.min(num_rows.saturating_sub(start));
+
+        // Consecutive typing or deleting on one row is undone as a single step
+        if kind != EditKind::Other && count == 1 {
+            if let Some(last) = self.buffer.undo.undo.last() {
+                if last.kind == kind
+                    && last.start == start
+                    && last.removed.len() == 1
+                    && last.inserted == Some(1)
+                {
+                    return;
+                }
+            }
+        }
+
+        self.buffer.undo.undo.push(UndoEntry {
+            start,
+            removed: self.buffer.rows[start..start + count]
+                .iter()
+                .map(|row| row.chars.clone())
+                .collect(),
+            inserted: None,
+            rows_before: num_rows,
+            cursor: (self.buffer.cursor_x, self.buffer.cursor_y),
+            kind,
+        });
+
+        if self.buffer.undo.undo.len() > self.options.undo_levels.max(1) {
+            self.buffer.undo.undo.remove(0);
+        }
+        // Measuring the history walks all of it, so not on every edit
+        self.buffer.undo.recorded += 1;
+        if self
+            .buffer
+            .undo
+            .recorded
+            .is_multiple_of(MEMORY_CHECK_INTERVAL)
+        {
+            self.buffer.undo.trim(self.options.undo_memory * MEGABYTE);
+            self.editor_check_memory();
+        }
+    }
+
+    /// Starts a new undo step even if the next edit could be merged into the last
+    pub fn editor_break_undo_group(&mut self) {
+        let num_rows = self.get_num_rows();
+        self.buffer.undo.finish(num_rows);
+        if let Some(last) = self.buffer.undo.undo.last_mut() {
+            last.kind = EditKind::Other;
+        }
+    }
+
+    /// Replaces rows `start..start + count` with `new_rows` as a single undo step
+    pub fn editor_replace_rows(&mut self, start: usize, count: usize, new_rows: Vec<String>) {
+        self.editor_record_edit(start, count, EditKind::Other);
+        let count = count.min(self.get_num_rows().saturating_sub(start));
+        let new_rows: Vec<EditorRow> = new_rows.into_iter().map(EditorRow::new).collect();
+        self.editor_shift_named_marks(start, count, new_rows.len());
+        self.buffer.rows.splice(start..start + count, new_rows);
+        self.editor_count_edit();
+        self.editor_break_undo_group();
+
+        self.buffer.cursor_y = self.buffer.cursor_y.min(self.get_num_rows());
+        if let Some(row) = self.buffer.rows.get(self.buffer.cursor_y) {
+            self.buffer.cursor_x = floor_char_boundary(&row.chars, self.buffer.cursor_x);
+        }
+    }
+
+    /// Puts back `history`, the undo history from before the file was read
+    /// again, with the reload from `old_rows` as one more step of it
+    pub fn editor_record_reload(
+        &mut self,
+        mut history: UndoHistory,
+        old_rows: Vec<String>,
+        cursor: (usize, usize),
+    ) {
+        let rows_before = old_rows.len();
+        history.finish(rows_before);
+        history.redo.clear();
+        history.undo.push(UndoEntry {
+            start: 0,
+            removed: old_rows,
+            inserted: Some(self.get_num_rows()),
+            rows_before,
+            cursor,
+            kind: EditKind::Other,
+        });
+        if history.undo.len() > self.options.undo_levels.max(1) {
+            history.undo.remove(0);
+        }
+        self.buffer.undo = history;
+    }
+
+    fn editor_apply_undo_entry(&mut self, entry: UndoEntry) -> UndoEntry {
+        let inserted = entry.inserted.unwrap_or(entry.removed.len());
+        let inserted = inserted.min(self.get_num_rows().saturating_sub(entry.start));
+        let num_rows = self.get_num_rows();
+
+        let restored: Vec<EditorRow> = entry.removed.into_iter().map(EditorRow::new).collect();
+        let restored_len = restored.len();
+        self.editor_shift_named_marks(entry.start, inserted, restored_len);
+        let removed: Vec<String> = self
+            .buffer
+            .rows
+            .splice(entry.start..entry.start + inserted, restored)
+            .map(|row| row.chars)
+            .collect();
+
+        self.buffer.cursor_y = entry.cursor.1.min(self.get_num_rows());
+        self.buffer.cursor_x = match self.buffer.rows.get(self.buffer.cursor_y) {
+            Some(row) => floor_char_boundary(&row.chars, entry.cursor.0),
+            None => 0,
+        };
+        self.editor_count_edit();
+
+        UndoEntry {
+            start: entry.start,
+            removed,
+            inserted: Some(restored_len),
+            rows_before: num_rows,
+            cursor: entry.cursor,
+            kind: EditKind::Other,
+        }
+    }
+
+    pub fn editor_undo(&mut self) {
+        let num_rows = self.get_num_rows();
+        self.buffer.undo.finish(num_rows);
+        match self.buffer.undo.undo.pop() {
+            Some(entry) => {
+                let redo = self.editor_apply_undo_entry(entry);
+                self.buffer.undo.redo.push(redo);
+            }
+            None => self.editor_set_status_message("Already at oldest change"),
+        }
+    }
+
+    pub fn editor_redo(&mut self) {
+        match self.buffer.undo.redo.pop() {
+            Some(entry) => {
+                let undo = self.editor_apply_undo_entry(entry);
+                self.buffer.undo.undo.push(undo);
+            }
+            None => self.editor_set_status_message("Already at newest change"),
+        }
+    }
+}
+
+/// FNV-1a hash of the buffer contents, ties an undo file to the text it was saved with
+fn content_hash(contents: &str) -> u64 {
+    contents.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// $XDG_STATE_HOME/kilo/undo/<absolute path with / replaced by %>
+fn undo_file_path(filename: &str) -> Option<PathBuf> {
+    let absolute = Path::new(filename)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(filename));
+    let name: String = absolute
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c == '/' { '%' } else { c })
+        .collect();
+    state_dir().map(|dir| dir.join("undo").join(name))
+}
+
+/// Appends `entries` as a count followed by one header line per entry and its
+/// removed rows
+fn write_entries(out: &mut String, label: &str, entries: &[UndoEntry]) {
+    out.push_str(&format!("{} {}\n", label, entries.len()));
+    for entry in entries {
+        let inserted = entry.inserted.unwrap_or(entry.removed.len());
+        out.push_str(&format!(
+            "{} {} {} {} {} {}\n",
+            entry.start,
+            entry.removed.len(),
+            inserted,
+            entry.rows_before,
+            entry.cursor.0,
+            entry.cursor.1
+        ));
+        for row in &entry.removed {
+            out.push_str(row);
+            out.push('\n');
+        }
+    }
+}
+
+fn read_entries<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+    label: &str,
+) -> Option<Vec<UndoEntry>> {
+    let count: usize = lines.next()?.strip_prefix(label)?.trim().parse().ok()?;
+    let mut entries = Vec::with_capacity(count.min(UNDO_LIMIT));
+    for _ in 0..count {
+        let numbers: Vec<usize> = lines
+            .next()?
+            .split(' ')
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .ok()?;
+        let &[start, removed, inserted, rows_before, cursor_x, cursor_y] = numbers.as_slice()
+        else {
+            return None;
+        };
+        let removed = (0..removed)
+            .map(|_| lines.next().map(str::to_string))
+            .collect::<Option<Vec<String>>>()?;
+        entries.push(UndoEntry {
+            start,
+            removed,
+            inserted: Some(inserted),
+            rows_before,
+            cursor: (cursor_x, cursor_y),
+            kind: EditKind::Other,
+        });
+    }
+    Some(entries)
+}
+
+/// Whether `entries`, undone or redone from the last one on, fit a buffer of
+/// `num_rows` rows: each replaces rows that are there by then
+fn entries_fit(entries: &[UndoEntry], mut num_rows: usize) -> bool {
+    entries.iter().rev().all(|entry| {
+        let inserted = entry.inserted.unwrap_or(entry.removed.len());
+        let fits = entry
+            .start
+            .checked_add(inserted)
+            .is_some_and(|end| end <= num_rows);
+        num_rows = num_rows - inserted.min(num_rows) + entry.removed.len();
+        fits
+    })
+}
+
+/// Keeps the undo history across sessions (`set undofile`)
+pub struct UndoFile;
+
+impl EditorHook for UndoFile {
+    fn on_open(&mut self, editor: &mut Editor) {
+        editor.editor_read_undo_file();
+    }
+
+    fn on_save_post(&mut self, editor: &mut Editor) {
+        editor.editor_write_undo_file();
+    }
+}
+
+impl Editor {
+    /// Writes the undo history next to the saved contents' hash, so it can be
+    /// restored when the file is opened again
+    pub fn editor_write_undo_file(&mut self) {
+        if !self.options.undo_file || self.buffer.dirty != 0 {
+            return;
+        }
+        let path = match self.buffer.filename.as_deref().and_then(undo_file_path) {
+            Some(path) => path,
+            None => return,
+        };
+        let num_rows = self.get_num_rows();
+        self.buffer.undo.finish(num_rows);
+
+        let mut contents = format!(
+            "{}\n{:016x}\n",
+            UNDO_FILE_HEADER,
+            content_hash(&self.editor_rows_to_string())
+        );
+        write_entries(&mut contents, "undo", &self.buffer.undo.undo);
+        write_entries(&mut contents, "redo", &self.buffer.undo.redo);
+
+        // Losing the history shouldn't get in the way of saving
+        let _ = write_atomic(&path, contents);
+    }
+
+    /// Restores the history saved for the file just opened, unless the file
+    /// was changed since
+    pub fn editor_read_undo_file(&mut self) {
+        if !self.options.undo_file {
+            return;
+        }
+        let path = match self.buffer.filename.as_deref().and_then(undo_file_path) {
+            Some(path) => path,
+            None => return,
+        };
+        let contents = match read_atomic(&path, UNDO_FILE_HEADER) {
+            Some(contents) => contents,
+            None => return,
+        };
+
+        let mut lines = contents.split('\n');
+        let hash = format!("{:016x}", content_hash(&self.editor_rows_to_string()));
+        if lines.next() != Some(hash.as_str()) {
+            return;
+        }
+        // A damaged or hand edited file could point past the rows
+        let num_rows = self.get_num_rows();
+        match (
+            read_entries(&mut lines, "undo"),
+            read_entries(&mut lines, "redo"),
+        ) {
+            (Some(undo), Some(redo))
+                if entries_fit(&undo, num_rows) && entries_fit(&redo, num_rows) =>
+            {
+                self.buffer.undo.undo = undo;
+                self.buffer.undo.redo = redo;
+            }
+            _ => {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(start: usize, removed: usize, inserted: usize) -> UndoEntry {
+        UndoEntry {
+            start,
+            removed: vec![String::new(); removed],
+            inserted: Some(inserted),
+            rows_before: 0,
+            cursor: (0, 0),
+            kind: EditKind::Other,
+        }
+    }
+
+    #[test]
+    fn checks_entries_against_the_rows() {
+        // Undone last first: the second entry joined two rows into one, the
+        // first added a row at the end of a two row buffer
+        let entries = [entry(2, 0, 1), entry(0, 2, 1)];
+        assert!(entries_fit(&entries, 2));
+        assert!(!entries_fit(&entries, 0));
+        assert!(!entries_fit(&[entry(5, 1, 1)], 3));
+        assert!(!entries_fit(&[entry(usize::MAX, 1, 1)], 3));
+        assert!(entries_fit(&[], 0));
+    }
+
+    #[test]
+    fn reads_back_written_entries() {
+        let mut out = String::new();
+        write_entries(&mut out, "undo", &[entry(1, 2, 1)]);
+        write_entries(&mut out, "redo", &[]);
+        let mut lines = out.split('\n');
+        let undo = read_entries(&mut lines, "undo").unwrap();
+        assert_eq!(
+            (undo[0].start, undo[0].removed.len(), undo[0].inserted),
+            (1, 2, Some(1))
+        );
+        assert!(read_entries(&mut lines, "redo").unwrap().is_empty());
+        // A count with fewer entries after it
+        let mut lines = "undo 2\n0 0 0 0 0 0".split('\n');
+        assert!(read_entries(&mut lines, "undo").is_none());
+    }
+
+    #[test]
+    fn keeps_the_cursor_on_a_char_when_rows_are_replaced() {
+        let mut editor = Editor::new_headless(24, 80);
+        editor.editor_handle_keys(" éé".chars().map(Key::Char).chain([Key::Left]));
+        assert_eq!(editor.buffer.cursor_x, 3);
+        editor.editor_replace_rows(0, 1, vec!["éé".to_string()]);
+        assert_eq!(editor.buffer.cursor_x, 2);
+        editor.editor_handle_keys([Key::Char('x')]);
+        assert_eq!(editor.buffer.rows[0].chars, "éxé");
+    }
+}
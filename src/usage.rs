@@ -0,0 +1,214 @@
+//! Opt-in usage statistics (`set usagestats`): commands run, files saved and
+//! time spent in the editor, kept in a local file and shown by `usage`.
+//! Nothing is ever sent anywhere.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::overlay::OverlayLine;
+use crate::storage::{read_atomic, state_dir, write_atomic};
+use crate::*;
+
+const USAGE_HEADER: &str = "kilo-usage 1";
+/// How often the counts collected so far are added to the usage file
+const USAGE_INTERVAL: Duration = Duration::from_secs(60);
+/// Entries listed per section of the dashboard
+const USAGE_TOP: usize = 10;
+
+/// Counts collected since the usage file was last written
+#[derive(Default)]
+pub struct Usage {
+    commands: HashMap<String, usize>,
+    files: HashMap<String, usize>,
+    /// Start of the time not yet added to the file, None while disabled
+    since: Option<Instant>,
+}
+
+#[derive(Default)]
+struct UsageTotals {
+    seconds: u64,
+    commands: HashMap<String, usize>,
+    files: HashMap<String, usize>,
+}
+
+fn usage_path() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("usage"))
+}
+
+/// Parses the usage file: `time SECONDS`, `command COUNT NAME` and
+/// `file COUNT PATH` lines after the header
+fn read_usage_file(path: &Path) -> UsageTotals {
+    let mut totals = UsageTotals::default();
+    let contents = match read_atomic(path, USAGE_HEADER) {
+        Some(contents) => contents,
+        None => return totals,
+    };
+
+    for line in contents.lines() {
+        let mut parts = line.splitn(3, ' ');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("time"), Some(seconds), None) => {
+                totals.seconds = seconds.parse().unwrap_or(0);
+            }
+            (Some("command"), Some(count), Some(name)) => {
+                *totals.commands.entry(name.to_string()).or_default() += count.parse().unwrap_or(0);
+            }
+            (Some("file"), Some(count), Some(file)) => {
+                *totals.files.entry(file.to_string()).or_default() += count.parse().unwrap_or(0);
+            }
+            _ => {}
+        }
+    }
+    totals
+}
+
+fn write_usage_file(path: &Path, totals: &UsageTotals) -> io::Result<()> {
+    let mut contents = format!("{}\ntime {}\n", USAGE_HEADER, totals.seconds);
+    for (name, count) in &totals.commands {
+        contents.push_str(&format!("command {} {}\n", count, name));
+    }
+    for (file, count) in &totals.files {
+        contents.push_str(&format!("file {} {}\n", count, file));
+    }
+    write_atomic(path, contents)
+}
+
+/// Entries sorted by count, most used first
+fn top_entries(counts: &HashMap<String, usize>) -> Vec<(&String, &usize)> {
+    let mut entries: Vec<_> = counts.iter().collect();
+    entries.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    entries.truncate(USAGE_TOP);
+    entries
+}
+
+fn format_duration(seconds: u64) -> String {
+    format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60)
+}
+
+impl Editor {
+    /// Starts or stops collecting when the `usagestats` option changes
+    pub fn editor_usage_option_changed(&mut self) {
+        if self.options.usage_stats && self.usage.since.is_none() {
+            self.usage.since = Some(Instant::now());
+        } else if !self.options.usage_stats && self.usage.since.is_some() {
+            self.editor_flush_usage(true);
+            self.usage.since = None;
+        }
+    }
+
+    /// Records a command run from the prompt
+    pub fn editor_record_command_usage(&mut self, command: &str) {
+        if self.usage.since.is_none() {
+            return;
+        }
+        // Shell commands count by their prefix, not the command line
+        let name = if command.starts_with("r!") {
+            "r!"
+        } else if command.starts_with('!') || command.starts_with('|') {
+            &command[..1]
+        } else {
+            command.split_whitespace().next().unwrap_or("")
+        };
+        if !name.is_empty() {
+            *self.usage.commands.entry(name.to_string()).or_default() += 1;
+        }
+    }
+
+    /// Records a save of the current file
+    pub fn editor_record_save_usage(&mut self) {
+        if self.usage.since.is_none() {
+            return;
+        }
+        if let Some(filename) = &self.buffer.filename {
+            let path = Path::new(filename)
+                .canonicalize()
+                .unwrap_or_else(|_| PathBuf::from(filename));
+            let path = path.to_string_lossy().replace('\n', "");
+            *self.usage.files.entry(path).or_default() += 1;
+        }
+    }
+
+    /// Adds the counts collected so far to the usage file, at most once a
+    /// minute unless `force` is set. Other sessions' counts are kept.
+    pub fn editor_flush_usage(&mut self, force: bool) {
+        let since = match self.usage.since {
+            Some(since) if force || since.elapsed() >= USAGE_INTERVAL => since,
+            _ => return,
+        };
+        let path = match usage_path() {
+            Some(path) => path,
+            None => return,
+        };
+
+        let mut totals = read_usage_file(&path);
+        totals.seconds += since.elapsed().as_secs();
+        for (name, count) in self.usage.commands.drain() {
+            *totals.commands.entry(name).or_default() += count;
+        }
+        for (file, count) in self.usage.files.drain() {
+            *totals.files.entry(file).or_default() += count;
+        }
+        // Stats are best effort, a failed write shouldn't interrupt editing
+        let _ = write_usage_file(&path, &totals);
+        self.usage.since = Some(Instant::now());
+    }
+
+    /// `usage`: dashboard of the collected statistics, `usage reset` clears them
+    pub fn editor_usage(&mut self, args: &str) {
+        let path = match usage_path() {
+            Some(path) => path,
+            None => {
+                self.editor_set_status_message("No state directory (HOME is not set)");
+                return;
+            }
+        };
+
+        if args == "reset" {
+            self.usage.commands.clear();
+            self.usage.files.clear();
+            if self.usage.since.is_some() {
+                self.usage.since = Some(Instant::now());
+            }
+            let _ = std::fs::remove_file(&path);
+            self.editor_set_status_message("Usage statistics cleared");
+            return;
+        } else if !args.is_empty() {
+            self.editor_set_error_message("Usage: usage [reset]");
+            return;
+        }
+
+        self.editor_flush_usage(true);
+        let totals = read_usage_file(&path);
+        let mut lines = vec![];
+        if !self.options.usage_stats {
+            lines.push(OverlayLine::colored(
+                "Collection is off (set usagestats to turn it on)".to_string(),
+                Color::YELLOW,
+            ));
+            lines.push(OverlayLine::new(String::new()));
+        }
+        lines.push(OverlayLine::new(format!(
+            "Time in editor: {}",
+            format_duration(totals.seconds)
+        )));
+
+        let sections = [
+            ("Commands", &totals.commands, "runs"),
+            ("Files", &totals.files, "saves"),
+        ];
+        for (title, counts, unit) in sections {
+            lines.push(OverlayLine::new(String::new()));
+            let total: usize = counts.values().sum();
+            lines.push(OverlayLine::colored(
+                format!("{} ({} {})", title, total, unit),
+                Color::CYAN,
+            ));
+            for (name, count) in top_entries(counts) {
+                lines.push(OverlayLine::new(format!("{:>7}  {}", count, name)));
+            }
+        }
+
+        self.editor_show_overlay(&format!("Usage statistics ({})", path.display()), &lines);
+    }
+}
@@ -0,0 +1,217 @@
+//! Watching the open file for changes made outside the editor. While waiting
+//! for keys its size and modification time are checked every second; a
+//! changed file is read again if the buffer has no unsaved changes, otherwise
+//! the next key answers whether to reload it or keep the edits. Undo goes back
+//! to the text from before a reload. A cursor on the last line stays there,
+//! so a log being written can be followed. `set noautoreload` turns it off.
+
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::normalize::{is_normalized, Normalization};
+use crate::reopen::LineEndings;
+use crate::*;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+pub struct FileWatch {
+    /// Modification time and size of the file when last read or written, None
+    /// if it didn't exist
+    stamp: Option<(SystemTime, u64)>,
+    checked_at: Option<Instant>,
+    /// The file changed under unsaved edits, the next key says what to do
+    conflict: bool,
+    /// While the file has `stamp`, it is as saving normalized with these
+    /// options left it
+    normalized: Option<Normalization>,
+}
+
+fn disk_stamp(path: &str) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+impl Editor {
+    /// Remembers the file as it is on disk now, after reading or writing it
+    pub fn editor_record_disk_stamp(&mut self) {
+        self.buffer.watch.stamp = self.buffer.filename.as_deref().and_then(disk_stamp);
+        self.buffer.watch.conflict = false;
+        self.buffer.watch.normalized = None;
+        if let Some(filename) = &self.buffer.filename {
+            self.index.file_changed(filename);
+        }
+    }
+
+    /// Remembers the file as `bytes` were just written to it, and whether
+    /// they were normalized
+    pub fn editor_record_saved_stamp(&mut self, bytes: &[u8]) {
+        self.editor_record_disk_stamp();
+        let format = self.buffer.file_format;
+        let normalization = Normalization::new(&self.options);
+        // The save check reads the file as UTF-8 lines
+        let text = match (format.encoding, format.line_endings) {
+            (Encoding::Utf8, LineEndings::Lf | LineEndings::KeepCr) => std::str::from_utf8(bytes),
+            _ => return,
+        };
+        if text.is_ok_and(|text| is_normalized(text, &normalization)) {
+            self.buffer.watch.normalized = Some(normalization);
+        }
+    }
+
+    /// Whether the file is unchanged since a save left it normalized with
+    /// `normalization`, going by its modification time and size
+    pub fn editor_disk_normalized(&self, normalization: &Normalization) -> bool {
+        let watch = &self.buffer.watch;
+        watch.normalized.as_ref() == Some(normalization)
+            && watch.stamp.is_some()
+            && self.buffer.filename.as_deref().and_then(disk_stamp) == watch.stamp
+    }
+
+    /// Size of the file when last read or written
+    pub fn editor_disk_size(&self) -> Option<u64> {
+        self.buffer.watch.stamp.map(|(_, size)| size)
+    }
+
+    /// Reloads the file or asks about it if it changed on disk. True if the
+    /// screen needs redrawing.
+    pub fn editor_check_disk_changes(&mut self) -> bool {
+        let filename = match &self.buffer.filename {
+            Some(filename) if self.options.auto_reload => filename.clone(),
+            _ => return false,
+        };
+        // Not while a prompt or overlay is waiting for its own keys, and
+        // a followed file only grows
+        if self.mode != Mode::Edit
+            || self.saving.is_some()
+            || self.buffer.follow.is_some()
+            || self.overlay_active
+            || self.buffer.watch.conflict
+            || self.buffer.hex.is_some()
+            || self.buffer.file_format.read_failed
+            || self
+                .buffer
+                .watch
+                .checked_at
+                .is_some_and(|checked| checked.elapsed() < CHECK_INTERVAL)
+        {
+            return false;
+        }
+        self.buffer.watch.checked_at = Some(Instant::now());
+        let stamp = disk_stamp(&filename);
+        if stamp == self.buffer.watch.stamp {
+            return false;
+        }
+        self.buffer.watch.stamp = stamp;
+
+        if self.buffer.dirty == 0 {
+            self.editor_reload();
+            self.editor_set_status_message(&format!(
+                "{} changed on disk, reloaded",
+                display_path(&filename)
+            ));
+        } else {
+            self.buffer.watch.conflict = true;
+            self.editor_set_status_message(&format!(
+                "{} changed on disk. Reload (r) or keep your changes?",
+                display_path(&filename)
+            ));
+        }
+        true
+    }
+
+    /// Answers the question about a file changed under unsaved edits with
+    /// `key`. False if there is no question.
+    pub fn editor_disk_conflict_key(&mut self, key: Key) -> bool {
+        if !std::mem::take(&mut self.buffer.watch.conflict) {
+            return false;
+        }
+        if matches!(key, Key::Char('r' | 'R')) {
+            self.editor_reload();
+            self.editor_set_status_message("Reloaded, undo brings your changes back");
+        } else {
+            self.editor_set_status_message("Kept your changes, saving will overwrite the file");
+        }
+        true
+    }
+
+    /// Reads the file again, keeping the cursor where it was or on the last
+    /// line if it was there. The reload is one more undo step, so the text
+    /// from before it can be brought back.
+    fn editor_reload(&mut self) {
+        let filename = match &self.buffer.filename {
+            Some(filename) => filename.clone(),
+            None => return,
+        };
+        let at_end = self.buffer.cursor_y + 1 >= self.get_num_rows();
+        let history = std::mem::take(&mut self.buffer.undo);
+        let old_rows: Vec<String> = self
+            .buffer
+            .rows
+            .iter()
+            .map(|row| row.chars.clone())
+            .collect();
+        let cursor = (self.buffer.cursor_x, self.buffer.cursor_y);
+        self.editor_read_again(&filename, self.buffer.file_format);
+        // A file that turned binary is shown in hex, without rows to undo to
+        if self.buffer.hex.is_none() && !self.buffer.file_format.read_failed {
+            self.editor_record_reload(history, old_rows, cursor);
+        }
+        if at_end {
+            self.editor_goto(self.get_num_rows().saturating_sub(1), 0);
+        }
+        self.editor_invalidate_screen();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undoes_a_reload() {
+        let path = std::env::temp_dir().join(format!("kilo-watch-test-{}", std::process::id()));
+        std::fs::write(&path, "one\ntwo").unwrap();
+        let mut editor = Editor::new_headless(24, 80);
+        editor.editor_open(&path.to_string_lossy());
+        editor.editor_replace_rows(0, 1, vec!["edited".to_string()]);
+
+        std::fs::write(&path, "three").unwrap();
+        editor.editor_reload();
+        std::fs::remove_file(&path).unwrap();
+        let text = |editor: &Editor| -> Vec<String> {
+            editor
+                .buffer
+                .rows
+                .iter()
+                .map(|row| row.chars.clone())
+                .collect()
+        };
+        assert_eq!(text(&editor), ["three"]);
+        editor.editor_undo();
+        assert_eq!(text(&editor), ["edited", "two"]);
+        editor.editor_undo();
+        assert_eq!(text(&editor), ["one", "two"]);
+    }
+
+    #[test]
+    fn trusts_a_normalized_save_until_the_file_changes() {
+        let path = std::env::temp_dir().join(format!("kilo-stamp-test-{}", std::process::id()));
+        std::fs::write(&path, "one  \ntwo").unwrap();
+        let mut editor = Editor::new_headless(24, 80);
+        editor.options.trim_whitespace = true;
+        editor.options.final_newline = true;
+        editor.editor_open(&path.to_string_lossy());
+        let normalization = Normalization::new(&editor.options);
+        assert!(!editor.editor_disk_normalized(&normalization));
+
+        editor.editor_record_saved_stamp(b"one\ntwo \n");
+        assert!(!editor.editor_disk_normalized(&normalization));
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+        editor.editor_record_saved_stamp(b"one\ntwo\n");
+        assert!(editor.editor_disk_normalized(&normalization));
+
+        std::fs::write(&path, "one\ntwo\nthree  \n").unwrap();
+        assert!(!editor.editor_disk_normalized(&normalization));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+//! Working directories: each buffer remembers the directory of its file and
+//! `cd DIR` changes the editor's, the one shell commands, `grep` and `files`
+//! work in. `cd` alone goes to the buffer's directory, `pwd` shows the
+//! current one. File names are shown relative to it where they can be.
+
+use std::path::{Path, PathBuf};
+
+use crate::index::ProjectIndex;
+use crate::*;
+
+/// `path` made absolute against the working directory, symlinks kept
+pub fn absolute_path(path: &Path) -> PathBuf {
+    match std::env::current_dir() {
+        Ok(cwd) if path.is_relative() => cwd.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// `filename` relative to the working directory if it's under it
+pub fn display_path(filename: &str) -> String {
+    let path = Path::new(filename);
+    match std::env::current_dir() {
+        Ok(cwd) if path.is_absolute() => match path.strip_prefix(&cwd) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative.display().to_string(),
+            _ => filename.to_string(),
+        },
+        _ => filename.to_string(),
+    }
+}
+
+impl Editor {
+    /// Remembers the directory of the open file as the buffer's
+    pub fn editor_update_directory(&mut self) {
+        self.buffer.directory = self.buffer.filename.as_deref().and_then(|filename| {
+            absolute_path(Path::new(filename))
+                .parent()
+                .map(Path::to_path_buf)
+        });
+    }
+
+    /// `cd [DIR]`: changes the working directory, to the buffer's directory
+    /// without an argument
+    pub fn editor_change_directory(&mut self, args: &str) {
+        let target = match args {
+            "" => match &self.buffer.directory {
+                Some(directory) => directory.clone(),
+                None => {
+                    self.editor_set_status_message("The buffer has no file, give a directory");
+                    return;
+                }
+            },
+            "~" => match std::env::var_os("HOME") {
+                Some(home) => PathBuf::from(home),
+                None => return,
+            },
+            args => match (args.strip_prefix("~/"), std::env::var_os("HOME")) {
+                (Some(rest), Some(home)) => Path::new(&home).join(rest),
+                _ => PathBuf::from(args),
+            },
+        };
+
+        // Relative names of open files would point elsewhere afterwards
+        self.editor_make_filenames_absolute();
+        self.quickfix.make_paths_absolute();
+        if let Err(error) = std::env::set_current_dir(&target) {
+            self.editor_set_status_message(&format!(
+                "Can't change to {}: {}",
+                target.display(),
+                describe_io_error(&error)
+            ));
+            return;
+        }
+        // The index belongs to the directory it was started in, dropping it
+        // stops its worker
+        self.index = ProjectIndex::default();
+        self.editor_print_directory();
+    }
+
+    /// `pwd`: shows the working directory
+    pub fn editor_print_directory(&mut self) {
+        let message = match std::env::current_dir() {
+            Ok(cwd) => cwd.display().to_string(),
+            Err(error) => format!("No working directory: {}", describe_io_error(&error)),
+        };
+        self.editor_set_status_message(&message);
+    }
+}
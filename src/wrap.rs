@@ -0,0 +1,401 @@
+//! Screen layout for soft wrapped or filtered rows, and movement by display lines.
+//! Up and Down keep to the column they started from across short lines, by
+//! display line within a wrapped row or by file line, and after End to the
+//! ends of lines.
+
+use crate::*;
+
+impl Editor {
+    /// Rows don't map one to one onto screen lines: soft wrap, a line filter
+    /// or a closed fold
+    pub fn editor_uses_layout(&self) -> bool {
+        self.options.wrap || self.buffer.filter.is_some() || self.editor_front_matter_fold_closed()
+    }
+
+    fn editor_wrap_width(&self) -> usize {
+        self.text_num_columns().max(1)
+    }
+
+    /// Render columns where the screen lines of `file_row` start when
+    /// wrapped. A wide char the edge would cut in half starts the next one.
+    fn editor_segment_starts(&self, file_row: usize) -> Vec<usize> {
+        let cells: Vec<char> = match self.buffer.rows.get(file_row) {
+            Some(row) => row
+                .render(self.options.tab_stop, 0, usize::MAX)
+                .chars()
+                .collect(),
+            None => return vec![0],
+        };
+        let width = self.editor_wrap_width();
+        let mut starts = vec![0];
+        loop {
+            let mut next = starts[starts.len() - 1] + width;
+            if width > 1 && cells.get(next) == Some(&WIDE_FILLER) {
+                next -= 1;
+            }
+            if next > cells.len() {
+                break starts;
+            }
+            starts.push(next);
+        }
+    }
+
+    /// Screen line of `file_row` render column `render_x` is on when wrapped,
+    /// with the columns it starts at and takes
+    fn editor_segment_at(&self, file_row: usize, render_x: usize) -> (usize, usize, usize) {
+        let starts = self.editor_segment_starts(file_row);
+        let segment = starts
+            .iter()
+            .rposition(|&start| start <= render_x)
+            .unwrap_or(0);
+        let width = match starts.get(segment + 1) {
+            Some(next) => next - starts[segment],
+            None => self.editor_wrap_width(),
+        };
+        (segment, starts[segment], width)
+    }
+
+    /// Number of screen lines `file_row` occupies when wrapped
+    fn editor_row_segments(&self, file_row: usize) -> usize {
+        self.editor_segment_starts(file_row).len()
+    }
+
+    /// Number of screen lines `file_row` occupies, zero if it is filtered out
+    pub fn editor_row_screen_lines(&self, file_row: usize) -> usize {
+        if !self.editor_row_visible(file_row) {
+            0
+        } else if self.options.wrap {
+            self.editor_row_segments(file_row)
+        } else {
+            1
+        }
+    }
+
+    /// Screen line within its row the cursor is on
+    fn editor_cursor_segment(&self) -> usize {
+        if self.options.wrap {
+            self.editor_segment_at(self.buffer.cursor_y, self.render_cursor_x)
+                .0
+        } else {
+            0
+        }
+    }
+
+    /// Vertical scrolling when rows don't map one to one onto screen lines
+    /// (soft wrap or a line filter)
+    pub fn editor_scroll_layout(&mut self) {
+        if self.options.wrap {
+            self.buffer.column_offset = 0;
+        }
+        // Segments are counted from the cursor's row, which must be shown
+        self.editor_snap_to_visible_row();
+
+        if self.buffer.cursor_y < self.buffer.row_offset {
+            self.buffer.row_offset = self.buffer.cursor_y;
+        }
+
+        // Screen lines kept visible below the cursor, as far as the buffer goes
+        let margin = self.editor_scroll_margin();
+        let mut below = self
+            .editor_row_screen_lines(self.buffer.cursor_y)
+            .saturating_sub(self.editor_cursor_segment() + 1);
+        let mut file_row = self.buffer.cursor_y + 1;
+        while below < margin && file_row < self.get_num_rows() {
+            below += self.editor_row_screen_lines(file_row);
+            file_row += 1;
+        }
+
+        // Walk up from the cursor to find the lowest offset keeping it on screen
+        let mut used = self.editor_cursor_segment() + 1 + below.min(margin);
+        let mut first_row = self.buffer.cursor_y;
+        while first_row > self.buffer.row_offset {
+            let lines = self.editor_row_screen_lines(first_row - 1);
+            if used + lines > self.screen_num_rows {
+                break;
+            }
+            used += lines;
+            first_row -= 1;
+        }
+        self.buffer.row_offset = first_row;
+
+        if self.editor_layout_cursor_position().0 < margin {
+            self.editor_place_cursor_at_screen_line(margin);
+        }
+    }
+
+    /// Sets the row offset so the cursor ends up `screen_line` lines from the
+    /// top of the screen (or as close as the start of the buffer allows)
+    pub fn editor_place_cursor_at_screen_line(&mut self, screen_line: usize) {
+        let mut above = self.editor_cursor_segment();
+        let mut first_row = self.buffer.cursor_y;
+        while first_row > 0 {
+            let lines = self.editor_row_screen_lines(first_row - 1);
+            if above + lines > screen_line {
+                break;
+            }
+            above += lines;
+            first_row -= 1;
+        }
+        self.buffer.row_offset = first_row;
+    }
+
+    pub fn editor_layout_screen_lines(&self) -> Vec<(usize, usize, bool)> {
+        let mut lines = Vec::with_capacity(self.screen_num_rows);
+
+        let mut file_row = self.buffer.row_offset;
+        while lines.len() < self.screen_num_rows && file_row < self.get_num_rows() {
+            let starts = if !self.editor_row_visible(file_row) {
+                vec![]
+            } else if self.options.wrap {
+                self.editor_segment_starts(file_row)
+            } else {
+                vec![self.buffer.column_offset]
+            };
+            for (segment, start_column) in starts.into_iter().enumerate() {
+                if lines.len() == self.screen_num_rows {
+                    break;
+                }
+                lines.push((file_row, start_column, segment == 0));
+            }
+            file_row += 1;
+        }
+
+        lines
+    }
+
+    pub fn editor_layout_cursor_position(&self) -> (usize, usize) {
+        let lines_above: usize = (self.buffer.row_offset..self.buffer.cursor_y)
+            .map(|file_row| self.editor_row_screen_lines(file_row))
+            .sum();
+
+        let column = if self.options.wrap {
+            let (_, start, _) = self.editor_segment_at(self.buffer.cursor_y, self.render_cursor_x);
+            self.render_cursor_x - start
+        } else {
+            self.render_cursor_x - self.buffer.column_offset
+        };
+
+        (lines_above + self.editor_cursor_segment(), column)
+    }
+
+    /// Whether Up/Down (or their Alt alternates) move by display line
+    fn editor_uses_display_lines(&self, key: Key) -> bool {
+        let alternate = matches!(key, Key::Modified(Modifiers::ALT, _));
+        self.options.wrap && (self.options.display_line_movement != alternate)
+    }
+
+    fn editor_cursor_render_x(&self) -> usize {
+        match self.buffer.rows.get(self.buffer.cursor_y) {
+            Some(row) => {
+                row.cursor_x_to_render_cursor_x(self.options.tab_stop, self.buffer.cursor_x)
+            }
+            None => 0,
+        }
+    }
+
+    /// Moves the cursor up or down one display line if display line movement
+    /// applies to `key`. Returns false if the caller should move by logical line.
+    pub fn editor_move_by_display_line(&mut self, key: Key) -> bool {
+        if !self.editor_uses_display_lines(key) {
+            return false;
+        }
+
+        let render_x = self.editor_cursor_render_x();
+        let (segment, segment_start, _) = self.editor_segment_at(self.buffer.cursor_y, render_x);
+        let column = self.editor_goal_column(true, render_x - segment_start);
+
+        // The goal may be past the end of any line
+        let in_segment = |row: usize, segment: usize| {
+            let starts = self.editor_segment_starts(row);
+            let start = starts[segment.min(starts.len() - 1)];
+            let width = match starts.get(segment + 1) {
+                Some(next) => next - start,
+                None => self.editor_wrap_width(),
+            };
+            start + column.min(width - 1)
+        };
+        let target = if key.unmodified() == Key::Up {
+            if segment > 0 && self.buffer.cursor_y < self.get_num_rows() {
+                Some((
+                    self.buffer.cursor_y,
+                    in_segment(self.buffer.cursor_y, segment - 1),
+                ))
+            } else {
+                self.editor_next_visible_row(self.buffer.cursor_y, true)
+                    .map(|row| (row, in_segment(row, usize::MAX)))
+            }
+        } else if segment + 1 < self.editor_row_segments(self.buffer.cursor_y)
+            && self.buffer.cursor_y < self.get_num_rows()
+        {
+            Some((
+                self.buffer.cursor_y,
+                in_segment(self.buffer.cursor_y, segment + 1),
+            ))
+        } else {
+            self.editor_next_visible_row(self.buffer.cursor_y, false)
+                .map(|row| (row, in_segment(row, 0)))
+        };
+
+        if let Some((file_row, target_render_x)) = target {
+            self.buffer.cursor_y = file_row;
+            self.buffer.cursor_x = match self.buffer.rows.get(file_row) {
+                Some(row) => {
+                    row.render_cursor_x_to_cursor_x(self.options.tab_stop, target_render_x)
+                }
+                None => 0,
+            };
+        }
+        self.editor_keep_goal_column(true, column);
+
+        true
+    }
+
+    /// Column Up or Down aims for: the one the cursor was in before a run of
+    /// them, if it hasn't moved since, else `current`. Columns count within
+    /// the display line when `by_display_line`.
+    pub fn editor_goal_column(&self, by_display_line: bool, current: usize) -> usize {
+        match self.goal_column {
+            Some((display, column, at))
+                if display == by_display_line
+                    && at == (self.buffer.cursor_y, self.buffer.cursor_x) =>
+            {
+                column
+            }
+            _ => current,
+        }
+    }
+
+    /// Whether Up and Down move by display line and the column they aim for,
+    /// for moves that start elsewhere, like a page up or down
+    pub fn editor_vertical_goal(&self) -> (bool, usize) {
+        let by_display_line = self.editor_uses_display_lines(Key::Up);
+        let render_x = self.editor_cursor_render_x();
+        let current = if by_display_line {
+            render_x - self.editor_segment_at(self.buffer.cursor_y, render_x).1
+        } else {
+            render_x
+        };
+        (
+            by_display_line,
+            self.editor_goal_column(by_display_line, current),
+        )
+    }
+
+    /// After End: Up and Down go to the ends of lines from here, unless End
+    /// only went as far as the edge of the screen
+    pub fn editor_stick_to_line_end(&mut self) {
+        let by_display_line = self.editor_uses_display_lines(Key::Up);
+        let at_end = self
+            .buffer
+            .rows
+            .get(self.buffer.cursor_y)
+            .is_some_and(|row| self.buffer.cursor_x == row.len());
+        if by_display_line || at_end {
+            self.editor_keep_goal_column(by_display_line, usize::MAX);
+        }
+    }
+
+    /// Remembers `column` as the goal of the next Up or Down from here
+    pub fn editor_keep_goal_column(&mut self, by_display_line: bool, column: usize) {
+        self.goal_column = Some((
+            by_display_line,
+            column,
+            (self.buffer.cursor_y, self.buffer.cursor_x),
+        ));
+    }
+
+    /// Home/End within the current display line. Returns false if not wrapping.
+    pub fn editor_move_to_display_line_edge(&mut self, end: bool) -> bool {
+        if !self.editor_uses_display_lines(Key::Up) || self.buffer.cursor_y >= self.get_num_rows() {
+            return false;
+        }
+
+        let (_, segment_start, width) =
+            self.editor_segment_at(self.buffer.cursor_y, self.editor_cursor_render_x());
+        let row = &self.buffer.rows[self.buffer.cursor_y];
+        self.buffer.cursor_x = if !end {
+            row.render_cursor_x_to_cursor_x(self.options.tab_stop, segment_start)
+        } else if segment_start + width > row.render_columns(self.options.tab_stop) {
+            row.len()
+        } else {
+            row.render_cursor_x_to_cursor_x(self.options.tab_stop, segment_start + width - 1)
+        };
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_a_cursor_row_the_filter_hides() {
+        let mut editor = Editor::new_headless(24, 80);
+        let lines = ["foo", "bar", "foo2"].map(String::from).to_vec();
+        editor.editor_replace_rows(0, 0, lines);
+        editor.editor_execute_command("filter foo");
+        editor.buffer.cursor_y = 2;
+        // An edit outside the keys, as a script or front end makes it
+        editor.editor_replace_rows(2, 1, vec!["oo2".to_string()]);
+        editor.editor_frame();
+        assert!(editor.editor_row_visible(editor.buffer.cursor_y));
+    }
+
+    #[test]
+    fn wraps_long_rows_onto_screen_lines() {
+        let mut editor = Editor::new_headless(10, 20);
+        editor.options.wrap = true;
+        editor.options.change_marks = false;
+        let lines = vec!["x".repeat(50), "y".to_string()];
+        editor.editor_replace_rows(0, 0, lines);
+        assert_eq!(editor.editor_row_screen_lines(0), 3);
+
+        editor.buffer.cursor_y = 1;
+        let frame = editor.editor_frame();
+        assert_eq!(frame.cursor, Some((3, 0)));
+    }
+
+    #[test]
+    fn moves_up_and_down_by_display_line() {
+        let mut editor = Editor::new_headless(10, 20);
+        editor.options.wrap = true;
+        let lines = vec!["x".repeat(50), "y".to_string()];
+        editor.editor_replace_rows(0, 0, lines);
+        editor.editor_goto(0, 3);
+
+        editor.editor_handle_keys([Key::Down]);
+        assert_eq!(editor.buffer.cursor_y, 0);
+        // All x, so byte and screen columns are the same
+        assert_eq!(editor.editor_segment_at(0, editor.buffer.cursor_x).0, 1);
+        editor.editor_handle_keys([Key::Up]);
+        assert_eq!((editor.buffer.cursor_y, editor.buffer.cursor_x), (0, 3));
+
+        // Alt-Down is the other flavor, by file line
+        editor.editor_handle_keys([Key::Modified(Modifiers::ALT, &Key::Down)]);
+        assert_eq!(editor.buffer.cursor_y, 1);
+        editor.editor_execute_command("set nodisplaymove");
+        editor.editor_handle_keys([Key::Up]);
+        assert_eq!(editor.buffer.cursor_y, 0);
+        editor.editor_handle_keys([Key::Modified(Modifiers::ALT, &Key::Down)]);
+        assert_eq!(editor.buffer.cursor_y, 0);
+        assert_eq!(editor.editor_segment_at(0, editor.buffer.cursor_x).0, 1);
+    }
+
+    #[test]
+    fn keeps_the_goal_column_across_short_lines() {
+        let mut editor = Editor::new_headless(10, 40);
+        let lines = ["a long line", "ab", "another long line"].map(String::from);
+        editor.editor_replace_rows(0, 0, lines.to_vec());
+        editor.editor_goto(0, 8);
+
+        editor.editor_handle_keys([Key::Down]);
+        assert_eq!(editor.buffer.cursor_x, 2);
+        editor.editor_handle_keys([Key::Down]);
+        assert_eq!(editor.buffer.cursor_x, 8);
+
+        // After End, Up and Down keep to the ends of lines
+        editor.editor_handle_keys([Key::Up, Key::Up, Key::End, Key::Down, Key::Down]);
+        assert_eq!(editor.buffer.cursor_x, lines[2].len());
+    }
+}